@@ -1,21 +1,123 @@
 use log::{error, info};
-use std::io::{self, BufRead, BufReader, Write};
-use std::net::TcpStream;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread;
 use std::time::{Duration, Instant};
 
-#[cfg(windows)]
-use winapi::um::consoleapi::SetConsoleCtrlHandler;
+/// 到服务器的连接，TCP或Unix域套接字
+#[derive(Debug)]
+pub enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn try_clone(&self) -> io::Result<Connection> {
+        match self {
+            Connection::Tcp(stream) => stream.try_clone().map(Connection::Tcp),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.try_clone().map(Connection::Unix),
+        }
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.shutdown(how),
+        }
+    }
+
+    fn take_error(&self) -> io::Result<Option<io::Error>> {
+        match self {
+            Connection::Tcp(stream) => stream.take_error(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.take_error(),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// 客户端输出的详细程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    Normal,  // 显示欢迎信息、提示符和响应
+    Quiet,   // 只输出服务器响应，适合在脚本中处理
+    Verbose, // 额外显示每条命令的耗时以及收发的原始字节
+}
+
+/// 客户端的输出目标，接收线程和命令循环共用，默认为标准输出
+type SharedOutput = Arc<Mutex<Box<dyn Write + Send>>>;
+
+// 写入输出并立即刷新，忽略写入错误，不影响程序退出
+fn emit(output: &SharedOutput, text: &str) {
+    let mut output = output.lock().unwrap();
+    let _ = output.write_all(text.as_bytes());
+    let _ = output.flush();
+}
+
+/// 中断句柄，可以在信号处理线程中安全地关闭连接
+#[derive(Debug)]
+pub struct InterruptHandle {
+    connected: Arc<AtomicBool>,
+    stream: Option<Connection>,
+}
+
+impl InterruptHandle {
+    /// 标记连接已断开并关闭socket
+    pub fn interrupt(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+        if let Some(stream) = &self.stream {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
 
 pub struct Client {
     host: String,
     port: u16,
+    unix_socket: Option<PathBuf>, // 设置后通过Unix域套接字连接，忽略host和port
     connected: Arc<AtomicBool>,
-    stream: Option<TcpStream>,
+    stream: Option<Connection>,
     response_rx: Option<Receiver<String>>,
+    output_mode: OutputMode,
+    output: SharedOutput,
+    input: Mutex<Option<Box<dyn BufRead + Send>>>, // 设置后从这里读取命令，否则读取标准输入
 }
 
 impl Client {
@@ -23,22 +125,73 @@ impl Client {
         Client {
             host,
             port,
+            unix_socket: None,
             connected: Arc::new(AtomicBool::new(false)),
             stream: None,
             response_rx: None,
+            output_mode: OutputMode::Normal,
+            output: Arc::new(Mutex::new(Box::new(io::stdout()))),
+            input: Mutex::new(None),
         }
     }
 
-    // 连接到服务器
-    pub fn connect(&mut self) -> Result<(), String> {
+    /// 设置输出的详细程度
+    pub fn with_output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// 把提示和响应写到指定的输出，而不是标准输出
+    pub fn with_output(mut self, output: impl Write + Send + 'static) -> Self {
+        self.output = Arc::new(Mutex::new(Box::new(output)));
+        self
+    }
+
+    /// 从指定的输入读取命令，而不是标准输入
+    pub fn with_input(self, input: impl BufRead + Send + 'static) -> Self {
+        *self.input.lock().unwrap() = Some(Box::new(input));
+        self
+    }
+
+    // 输出提示信息，安静模式下不显示
+    fn print_info(&self, text: &str) {
+        if self.output_mode != OutputMode::Quiet {
+            emit(&self.output, text);
+        }
+    }
+
+    /// 通过Unix域套接字连接本机服务器
+    pub fn with_unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix_socket = Some(path.into());
+        self
+    }
+
+    // 建立TCP或Unix域套接字连接
+    fn open_connection(&self) -> Result<Connection, String> {
+        if let Some(path) = &self.unix_socket {
+            info!("尝试连接到服务器: unix:{}", path.display());
+            #[cfg(unix)]
+            let connection = UnixStream::connect(path)
+                .map(Connection::Unix)
+                .map_err(|e| format!("无法连接到服务器 unix:{}: {}", path.display(), e));
+            #[cfg(not(unix))]
+            let connection = Err(format!("当前平台不支持Unix域套接字: {}", path.display()));
+            return connection;
+        }
+
         let addr = format!("{}:{}", self.host, self.port);
         info!("尝试连接到服务器: {}", addr);
+        TcpStream::connect(&addr)
+            .map(Connection::Tcp)
+            .map_err(|e| format!("无法连接到服务器 {}: {}", addr, e))
+    }
 
+    // 连接到服务器
+    pub fn connect(&mut self) -> Result<(), String> {
         // 尝试建立连接
-        let stream = TcpStream::connect(&addr)
-            .map_err(|e| format!("无法连接到服务器 {}: {}", addr, e))?;
+        let stream = self.open_connection()?;
 
-        info!("已连接到服务器: {}", addr);
+        info!("已连接到服务器");
         self.connected.store(true, Ordering::SeqCst);
 
         // 创建一个通道来接收响应
@@ -49,24 +202,20 @@ impl Client {
         let mut stream_clone = stream.try_clone()
             .map_err(|e| format!("克隆流失败: {}", e))?;
         let connected = Arc::clone(&self.connected);
+        let (output_mode, output) = (self.output_mode, Arc::clone(&self.output));
 
         thread::spawn(move || {
             // 忽略接收线程中的错误，因为用户退出时可能会发生错误
-            let _ = Self::receive_responses(&mut stream_clone, connected, tx);
+            let _ = Self::receive_responses(&mut stream_clone, connected, tx, output_mode, output);
         });
 
         // 保存流用于后续命令
         self.stream = Some(stream);
         
-        // 设置Ctrl+C处理 - 使用单独的线程监听标准输入的中断
-        let ctrl_c_connected = Arc::clone(&self.connected);
-        thread::spawn(move || {
-            // 这个线程将持续检查是否收到Ctrl+C信号
-            // 当用户按下Ctrl+C时，Windows会发送一个中断，我们可以在这里捕获它
-            if let Err(e) = Self::handle_ctrl_c(ctrl_c_connected) {
-                error!("Ctrl+C 处理错误: {}", e);
-            }
-        });
+        // 设置Ctrl+C处理
+        if let Err(e) = Self::handle_ctrl_c(self.interrupt_handle()?) {
+            error!("Ctrl+C 处理错误: {}", e);
+        }
         
         // 获取流的克隆，用于发送命令
         let stream_for_commands = self.stream.as_ref().unwrap().try_clone()
@@ -79,16 +228,11 @@ impl Client {
     }
 
     // 专用于测试的连接方法，不会启动命令处理循环
-    #[allow(dead_code)]
     pub fn connect_for_test(&mut self) -> Result<(), String> {
-        let addr = format!("{}:{}", self.host, self.port);
-        info!("尝试连接到服务器: {}", addr);
-
         // 尝试建立连接
-        let stream = TcpStream::connect(&addr)
-            .map_err(|e| format!("无法连接到服务器 {}: {}", addr, e))?;
+        let stream = self.open_connection()?;
 
-        info!("已连接到服务器: {}", addr);
+        info!("已连接到服务器");
         self.connected.store(true, Ordering::SeqCst);
 
         // 创建一个通道来接收响应
@@ -99,10 +243,11 @@ impl Client {
         let mut stream_clone = stream.try_clone()
             .map_err(|e| format!("克隆流失败: {}", e))?;
         let connected = Arc::clone(&self.connected);
+        let (output_mode, output) = (self.output_mode, Arc::clone(&self.output));
 
         thread::spawn(move || {
             // 忽略接收线程中的错误，因为用户退出时可能会发生错误
-            let _ = Self::receive_responses(&mut stream_clone, connected, tx);
+            let _ = Self::receive_responses(&mut stream_clone, connected, tx, output_mode, output);
         });
 
         // 保存流用于后续命令
@@ -111,77 +256,33 @@ impl Client {
         Ok(())
     }
 
+    // 获取中断句柄，信号处理程序通过它关闭连接
+    pub fn interrupt_handle(&self) -> Result<InterruptHandle, String> {
+        let stream = match &self.stream {
+            Some(stream) => Some(stream.try_clone().map_err(|e| format!("克隆流失败: {}", e))?),
+            None => None,
+        };
+        Ok(InterruptHandle {
+            connected: Arc::clone(&self.connected),
+            stream,
+        })
+    }
+
     // 处理Ctrl+C信号
-    fn handle_ctrl_c(connected: Arc<AtomicBool>) -> Result<(), String> {
-        #[cfg(windows)]
-        {
-            // 创建一个全局状态用于共享 connected 变量
-            static mut CONNECTED_PTR: *mut Arc<AtomicBool> = std::ptr::null_mut();
-            unsafe {
-                CONNECTED_PTR = Box::into_raw(Box::new(connected));
-            }
-            
-            // 定义控制台事件处理函数
-            extern "system" fn handler(_: u32) -> i32 {
-                let connected = unsafe { &*CONNECTED_PTR };
-                println!("\n接收到 Ctrl+C 信号，正在关闭连接...");
-                connected.store(false, Ordering::SeqCst);
-                
-                // 在这里直接退出程序，但先给一点时间关闭连接
-                thread::spawn(move || {
-                    // 等待一小段时间，让其他线程有机会关闭连接
-                    thread::sleep(Duration::from_millis(200));
-                    std::process::exit(0);
-                });
-                
-                1 // 返回 true 表示我们处理了这个事件
-            }
-            
-            // 注册控制台事件处理函数
-            if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
-                return Err("无法设置控制台事件处理器".to_string());
-            }
-            
-            Ok(())
-        }
-        
-        #[cfg(not(windows))]
-        {
-            // 非Windows平台上的简单实现
-            // 使用单独的线程来监听标准输入，检测到 EOF 时表示 Ctrl+C
-            let (tx, rx) = mpsc::channel();
-            
-            thread::spawn(move || {
-                let stdin = io::stdin();
-                let mut buffer = String::new();
-                while stdin.read_line(&mut buffer).is_ok() {
-                    buffer.clear();
-                }
-                // 如果到达这里，说明输入流被中断
-                let _ = tx.send(());
-            });
-            
-            // 等待信号
-            thread::spawn(move || {
-                if rx.recv().is_ok() {
-                    println!("\n接收到中断信号，正在关闭连接...");
-                    connected.store(false, Ordering::SeqCst);
-                    
-                    // 在这里直接退出程序，但先给一点时间关闭连接
-                    thread::spawn(move || {
-                        // 等待一小段时间，让其他线程有机会关闭连接
-                        thread::sleep(Duration::from_millis(200));
-                        std::process::exit(0);
-                    });
-                }
-            });
-            
-            Ok(())
-        }
+    fn handle_ctrl_c(handle: InterruptHandle) -> Result<(), String> {
+        ctrlc::set_handler(move || {
+            println!("\n接收到 Ctrl+C 信号，正在关闭连接...");
+            // 命令循环会在100毫秒内发现连接已断开并退出
+            handle.interrupt();
+        }).map_err(|e| format!("无法设置信号处理程序: {}", e))
+    }
+
+    // 是否仍与服务器保持连接
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
     }
 
     // 用于测试的方法：发送单个命令并返回响应
-    #[allow(dead_code)]
     pub fn send_command_with_response(&mut self, command: &str) -> Result<String, String> {
         if !self.connected.load(Ordering::SeqCst) {
             return Err("未连接到服务器".to_string());
@@ -218,22 +319,26 @@ impl Client {
     }
 
     // 发送命令到服务器
-    fn send_commands(&self, mut stream: TcpStream) -> Result<(), String> {
-        let stdin = io::stdin();
-        let mut reader = stdin.lock();
-        let mut buffer = String::new();
+    fn send_commands(&self, mut stream: Connection) -> Result<(), String> {
+        // 在单独的线程中读取标准输入，使主循环在等待输入时也能及时发现连接已断开
+        let input_rx = Self::spawn_input_reader(self.input.lock().unwrap().take());
 
-        println!("已连接到服务器。输入命令或输入 'exit' 退出。");
-        println!("输入 'help' 获取可用命令列表。");
+        self.print_info("已连接到服务器。输入命令或输入 'exit' 退出。\n");
+        self.print_info("输入 'help' 获取可用命令列表。\n");
 
         while self.connected.load(Ordering::SeqCst) {
-            print!("> ");
-            io::stdout().flush().map_err(|e| format!("刷新标准输出失败: {}", e))?;
-
-            buffer.clear();
-            if reader.read_line(&mut buffer).map_err(|e| format!("读取输入失败: {}", e))? == 0 {
-                break;
-            }
+            self.print_info("> ");
+
+            let buffer = match self.wait_for_input(&input_rx) {
+                Some(line) => line,
+                None => {
+                    if !self.connected.load(Ordering::SeqCst) {
+                        self.print_info("正在关闭连接...\n");
+                        let _ = stream.shutdown(Shutdown::Both);
+                    }
+                    break;
+                }
+            };
 
             let command = buffer.trim();
             if command.is_empty() {
@@ -241,19 +346,19 @@ impl Client {
             }
 
             if command.eq_ignore_ascii_case("exit") {
-                println!("断开连接并退出...");
+                self.print_info("断开连接并退出...\n");
                 self.connected.store(false, Ordering::SeqCst);
                 // 在退出前关闭socket，防止产生错误
-                let _ = stream.shutdown(std::net::Shutdown::Both);
+                let _ = stream.shutdown(Shutdown::Both);
                 break;
             }
 
             // 每次命令循环开始时检查 connected 标志
             // 这样可以确保当 Ctrl+C 触发时，能够立即响应
             if !self.connected.load(Ordering::SeqCst) {
-                println!("正在关闭连接...");
+                self.print_info("正在关闭连接...\n");
                 // 在退出前关闭socket，防止产生错误
-                let _ = stream.shutdown(std::net::Shutdown::Both);
+                let _ = stream.shutdown(Shutdown::Both);
                 break;
             }
 
@@ -278,17 +383,25 @@ impl Client {
                             // 先等待一小段时间，确保接收线程已经打印了响应
                             thread::sleep(Duration::from_millis(50));
                             let elapsed = start_time.elapsed();
-                            println!("延迟: {} 毫秒", elapsed.as_millis());
+                            self.print_info(&format!("延迟: {} 毫秒\n", elapsed.as_millis()));
                         },
-                        Err(_) => println!("接收响应超时")
+                        Err(_) => self.print_info("接收响应超时\n")
                     }
                 }
                 
                 continue;
             }
 
-            // 发送命令到服务器
-            if let Err(e) = stream.write_all(format!("{}\n", command).as_bytes()) {
+            // 发送命令到服务器，详细模式下先清空旧的响应，以便统计这条命令的耗时
+            let request = format!("{}\n", command);
+            if self.output_mode == OutputMode::Verbose {
+                if let Some(rx) = &self.response_rx {
+                    while rx.try_recv().is_ok() {}
+                }
+                emit(&self.output, &format!("-> {:?}\n", request));
+            }
+            let start_time = Instant::now();
+            if let Err(e) = stream.write_all(request.as_bytes()) {
                 // 只有在非正常退出时才显示错误
                 if self.connected.load(Ordering::SeqCst) {
                     error!("发送命令时出错: {}", e);
@@ -298,23 +411,80 @@ impl Client {
                 break;
             }
 
-            // 等待一小段时间，让接收线程有时间处理响应
-            thread::sleep(Duration::from_millis(100));
+            if self.output_mode == OutputMode::Verbose {
+                self.print_elapsed(start_time);
+            } else {
+                // 等待一小段时间，让接收线程有时间处理响应
+                thread::sleep(Duration::from_millis(100));
+            }
         }
 
         // 确保在退出时关闭连接
         if !self.connected.load(Ordering::SeqCst) && stream.take_error().is_ok() {
-            let _ = stream.shutdown(std::net::Shutdown::Both);
+            let _ = stream.shutdown(Shutdown::Both);
         }
 
         Ok(())
     }
 
+    // 等待响应的第一行并输出命令的耗时
+    fn print_elapsed(&self, start_time: Instant) {
+        let Some(rx) = &self.response_rx else {
+            return;
+        };
+        match rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(_) => {
+                let elapsed = start_time.elapsed();
+                // 多行响应的其余部分由接收线程继续输出，稍等片刻再显示耗时
+                thread::sleep(Duration::from_millis(50));
+                emit(&self.output, &format!("(耗时 {} 毫秒)\n", elapsed.as_millis()));
+            }
+            Err(_) => emit(&self.output, "接收响应超时\n"),
+        }
+    }
+
+    // 启动读取输入的线程，未指定输入时读取标准输入，输入结束或出错时关闭通道
+    fn spawn_input_reader(input: Option<Box<dyn BufRead + Send>>) -> Receiver<String> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader: Box<dyn BufRead> = match input {
+                Some(input) => input,
+                None => Box::new(io::stdin().lock()),
+            };
+            loop {
+                let mut buffer = String::new();
+                match reader.read_line(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if tx.send(buffer).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        rx
+    }
+
+    // 等待下一行输入，输入结束或连接断开时返回None
+    fn wait_for_input(&self, input_rx: &Receiver<String>) -> Option<String> {
+        while self.connected.load(Ordering::SeqCst) {
+            match input_rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(line) => return Some(line),
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+        None
+    }
+
     // 接收并显示服务器响应
     fn receive_responses(
-        stream: &mut TcpStream, 
+        stream: &mut Connection,
         connected: Arc<AtomicBool>,
-        tx: Sender<String>
+        tx: Sender<String>,
+        output_mode: OutputMode,
+        output: SharedOutput,
     ) -> Result<(), String> {
         let mut reader = BufReader::new(stream);
         let mut response = String::new();
@@ -324,17 +494,19 @@ impl Client {
             match reader.read_line(&mut response) {
                 Ok(0) => {
                     // 服务器断开连接
-                    if connected.load(Ordering::SeqCst) {
-                        println!("服务器断开连接");
+                    if connected.load(Ordering::SeqCst) && output_mode != OutputMode::Quiet {
+                        emit(&output, "服务器断开连接\n");
                     }
                     connected.store(false, Ordering::SeqCst);
                     break;
                 }
                 Ok(_) => {
-                    // 打印响应，不包括末尾的换行符
-                    print!("{}", response);
-                    // 忽略刷新错误，不影响程序退出
-                    let _ = io::stdout().flush();
+                    // 打印响应，详细模式下显示包括换行符在内的原始字节
+                    if output_mode == OutputMode::Verbose {
+                        emit(&output, &format!("<- {:?}\n", response));
+                    } else {
+                        emit(&output, &response);
+                    }
                     
                     // 发送响应到通道，用于测试
                     let _ = tx.send(response.clone());
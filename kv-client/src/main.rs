@@ -1,10 +1,8 @@
-mod client;
-
-use clap::{Command,Arg};
+use clap::{Command,Arg,ArgAction};
 use kv_common::config::Settings;
 use kv_common::logger;
 use log::{error, info};
-use client::Client;
+use kv_client::client::{Client, OutputMode};
 use std::process;
 
 fn main() {
@@ -12,6 +10,14 @@ fn main() {
     let matches = Command::new("KV Store Client")
         .version("1.1")
         .about("A simple key-value store client")
+        // -h 用于主机地址，帮助信息只保留 --help
+        .disable_help_flag(true)
+        .arg(
+            Arg::new("help")
+                .long("help")
+                .help("打印帮助信息")
+                .action(ArgAction::Help)
+        )
         .arg(
             Arg::new("host")
                 .short('h')
@@ -26,12 +32,48 @@ fn main() {
                 .long("port")
                 .value_name("PORT")
                 .help("服务器端口")
+                .value_parser(clap::value_parser!(u16))
+                .num_args(1)
+        )
+        .arg(
+            Arg::new("socket")
+                .short('s')
+                .long("socket")
+                .value_name("PATH")
+                .help("通过Unix域套接字连接本机服务器，忽略主机和端口")
+                .num_args(1)
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("显示每条命令的耗时以及收发的原始字节")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("quiet")
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("不显示欢迎信息和提示符，只输出服务器响应")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("FILE")
+                .help("配置文件路径")
                 .num_args(1)
         )
         .get_matches();
 
     // 加载配置
-    let settings = match Settings::new() {
+    let settings = match matches.get_one::<String>("config") {
+        Some(path) => Settings::from_path(path),
+        None => Settings::new(),
+    };
+    let settings = match settings {
         Ok(s) => s,
         Err(e) => {
             eprintln!("加载配置失败: {}", e);
@@ -55,15 +97,28 @@ fn main() {
     let port = matches.get_one::<u16>("port")
         .unwrap_or(&settings.server.port);
     
+    let output_mode = if matches.get_flag("verbose") {
+        OutputMode::Verbose
+    } else if matches.get_flag("quiet") {
+        OutputMode::Quiet
+    } else {
+        OutputMode::Normal
+    };
+
     // 启动客户端
-    run_client(host, port);
+    run_client(host, port, matches.get_one::<String>("socket"), output_mode);
 }
 
 // 启动客户端
-fn run_client(host: &String, port: &u16) {
-    let mut client = Client::new(host.clone(), *port);
-    
-    info!("客户端配置: 主机={}, 端口={}", host, port);
+fn run_client(host: &String, port: &u16, socket: Option<&String>, output_mode: OutputMode) {
+    let mut client = Client::new(host.clone(), *port).with_output_mode(output_mode);
+    match socket {
+        Some(path) => {
+            client = client.with_unix_socket(path);
+            info!("客户端配置: Unix域套接字={}", path);
+        }
+        None => info!("客户端配置: 主机={}, 端口={}", host, port),
+    }
     
     match client.connect() {
         Ok(_) => info!("客户端正常关闭"),
@@ -610,4 +610,104 @@ fn test_client_various_commands() {
     println!("各种命令测试：等待服务器线程完成");
     let _ = server_thread.join();
     println!("各种命令测试：测试完成");
-}
\ No newline at end of file
+}
+// 测试中断句柄：模拟Ctrl+C信号处理，客户端应断开连接并关闭socket
+#[test]
+fn test_real_client_interrupt_handle() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server_thread = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut buffer = String::new();
+        // 客户端关闭socket后服务器会读到EOF
+        reader.read_line(&mut buffer).unwrap()
+    });
+
+    let mut client = RealClient::new("127.0.0.1".to_string(), port);
+    client.connect_for_test().unwrap();
+    let handle = client.interrupt_handle().unwrap();
+    assert!(client.is_connected());
+
+    // 信号处理程序运行在独立线程中
+    thread::spawn(move || handle.interrupt()).join().unwrap();
+    assert!(!client.is_connected());
+
+    assert_eq!(server_thread.join().unwrap(), 0);
+    assert_eq!(
+        client.send_command_with_response("ping").unwrap_err(),
+        "未连接到服务器"
+    );
+}
+
+// 测试用的共享输出缓冲区
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// 启动只处理一个连接的服务器，按命令返回固定的响应
+fn spawn_scripted_server() -> (u16, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server_thread = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        for line in reader.lines() {
+            let response = match line.unwrap().trim() {
+                "set name alice" => "OK\n",
+                "get name" => "alice\n",
+                _ => "ERROR: unknown command\n",
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (port, server_thread)
+}
+
+// 安静模式下只输出服务器响应，没有欢迎信息和提示符
+#[test]
+fn test_real_client_quiet_mode_prints_only_responses() {
+    use kv_client::client::OutputMode;
+
+    let (port, server_thread) = spawn_scripted_server();
+    let output = SharedBuffer::default();
+    let mut client = RealClient::new("127.0.0.1".to_string(), port)
+        .with_output_mode(OutputMode::Quiet)
+        .with_input(io::Cursor::new("set name alice\nget name\nexit\n"))
+        .with_output(output.clone());
+
+    client.connect().unwrap();
+    server_thread.join().unwrap();
+    assert_eq!(String::from_utf8(output.0.lock().unwrap().clone()).unwrap(), "OK\nalice\n");
+}
+
+// 详细模式下显示收发的原始字节和耗时
+#[test]
+fn test_real_client_verbose_mode_shows_raw_bytes_and_timing() {
+    use kv_client::client::OutputMode;
+
+    let (port, server_thread) = spawn_scripted_server();
+    let output = SharedBuffer::default();
+    let mut client = RealClient::new("127.0.0.1".to_string(), port)
+        .with_output_mode(OutputMode::Verbose)
+        .with_input(io::Cursor::new("get name\nexit\n"))
+        .with_output(output.clone());
+
+    client.connect().unwrap();
+    server_thread.join().unwrap();
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    assert!(output.contains("> -> \"get name\\n\"\n<- \"alice\\n\"\n(耗时 "), "{}", output);
+    assert!(output.starts_with("已连接到服务器"), "{}", output);
+}
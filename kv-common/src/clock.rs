@@ -0,0 +1,64 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 时间来源，测试中可以替换为手动推进的时钟
+pub trait Clock: Debug + Send + Sync {
+    /// 当前Unix时间戳(毫秒)
+    fn now_millis(&self) -> u64;
+
+    /// 当前Unix时间戳(秒)
+    fn now_secs(&self) -> u64 {
+        self.now_millis() / 1000
+    }
+}
+
+/// 使用系统时间的时钟
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+/// 手动控制的时钟，只有调用advance或set时才会前进
+#[derive(Debug, Default)]
+pub struct MockClock {
+    millis: AtomicU64,
+}
+
+impl MockClock {
+    /// 从指定的Unix时间戳(毫秒)开始
+    pub fn new(start_millis: u64) -> Self {
+        MockClock {
+            millis: AtomicU64::new(start_millis),
+        }
+    }
+
+    /// 推进时钟
+    pub fn advance(&self, duration: Duration) {
+        self.millis.fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// 设置为指定的Unix时间戳(毫秒)
+    pub fn set(&self, millis: u64) {
+        self.millis.store(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_millis(&self) -> u64 {
+        self.millis.load(Ordering::SeqCst)
+    }
+}
+
+/// 默认使用的系统时钟
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
@@ -1,14 +1,33 @@
-use crate::store::StoreManager;
-use log::{debug, error};
+use crate::config::{ArrayReplyFormat, DataPaths, Settings, UnknownCommandPolicy, RUNTIME_PARAMS};
+use crate::metrics::{CommandMetrics, SlowLog};
+use crate::result_cache::ResultCache;
+use crate::store::{
+    write_file_atomically, DataType, ExpiryOption, ListEnd, SaveJobState, StoreError, StoreManager, StoreOperation,
+    TransactionManager, WalCompactionState,
+};
+use crate::transaction_cmd::TransactionCommandHandler;
+use base64::prelude::*;
+use log::info;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use std::thread;
+use std::time::{Duration, Instant};
 
 // 表示解析后的命令
 #[derive(Debug, Clone)]
 pub enum Command {
     // 字符串命令
     Set(String, String),
+    SetEx(String, String, u64), // 设置值并指定过期秒数
+    SetBin(String, Vec<u8>),    // 设置二进制值(命令中以base64传输)
+    GetBin(String),             // 以base64获取值
     Get(String),
-    Del(String),
+    GetEx(String, ExpiryOption), // 读取值并设置或移除过期时间
+    Del(Vec<String>),
+    DelPattern(String),         // 删除所有匹配glob模式的键
+    Rename(String, String),     // 重命名键，覆盖已存在的目标键
 
     // 列表命令
     LPush(String, String),
@@ -18,76 +37,915 @@ pub enum Command {
     LPop(String),
     RPop(String),
     LDel(String),
+    LMPop(Vec<String>, ListEnd, Option<usize>), // 从第一个非空列表弹出元素
+    BLPop(Vec<String>, u64), // 从第一个非空列表左端弹出，都为空时最多阻塞指定的毫秒数，0表示一直等待
+    BRPop(Vec<String>, u64), // 从第一个非空列表右端弹出，都为空时最多阻塞指定的毫秒数，0表示一直等待
 
     // 哈希命令
     HSet(String, String, String),
     HGet(String, String),
     HDel(String, String),
     HDelKey(String),
+    HRandField(String, Option<isize>, bool), // 随机获取字段，count为负数时可重复，可同时返回值
+    HExpire(String, u64, Vec<String>), // 设置字段的过期时间(秒)
+    HTtl(String, Vec<String>), // 获取字段的剩余生存时间
 
     // 集合命令
     SAdd(String, Vec<String>),
+    SAddCh(String, Vec<String>), // 返回实际新增的成员
     SMembers(String),
     SIsMember(String, String),
-    SRem(String, String),
+    SRem(String, Vec<String>), // 移除一个或多个成员，集合变为空时删除键
+    SInter(Vec<String>),
+    SInterCard(Vec<String>, Option<usize>), // 交集大小，可指定LIMIT提前停止
 
     // 持久化
     Save,
     BgSave,
+    BgSaveStatus(u64), // 查询后台保存任务状态
+    LastSave,          // 最近一次成功保存的时间
     FlushDB,
+    FlushDBType(String), // 只删除指定类型的键
+    Wait(u64),         // 等待本连接之前的写入落盘，超时毫秒数为0时一直等待
+    Export(Option<String>), // 以JSON行导出当前数据库，可指定服务器上的输出文件
+    Import(String),         // 从服务器上的JSON行文件导入数据
 
     // 过期
     Expire(String, u64),
     DDL(String),
+    ExpiringSoon(u64),   // 在指定秒数内过期的键，按剩余生存时间排序
+    KeysWithTtl,         // 设置了过期时间的所有键
     
     // 事务命令
     Begin,               // 开始事务
     Commit,              // 提交事务
     Rollback,            // 回滚事务
     Checkpoint,          // 创建检查点
-    CompactWal,          // 压缩WAL日志
+    CompactWal(Option<u64>), // 压缩WAL日志，指定阈值时只在文件超过该字节数时压缩
+    BgCompactWal,        // 在后台压缩WAL日志
     ListTransactions,    // 列出所有活跃事务
+    TxInfo(u64),          // 查看事务的状态和操作数
+    Savepoint(String),    // 在当前事务中创建保存点
+    RollbackTo(String),   // 回滚到当前事务的保存点
+    WalDump(Option<u64>), // 输出WAL日志，可指定起始序列号
+    WalInfo,             // WAL文件信息
+    
+    // 监控命令
+    CommandStats,        // 各命令的调用次数和延迟统计
+    SlowLogGet(Option<usize>), // 获取最近的慢查询
+    SlowLogReset,        // 清空慢查询日志
+    MemoryUsage(String), // 单个键的估算内存占用
+    IdleKeys(u64),       // 闲置超过指定秒数的键
+    ResetStats(String),  // 重置键的访问统计
+    ResetCommandStats,   // 清空命令执行指标
+    ConfigGet(String),   // 读取配置项
+    ConfigDump,          // 输出实际生效的全部配置
+    ConfigSet(String, String), // 在运行时修改配置项
+    
+    // 调试命令
+    DebugSleep(u64),     // 阻塞指定的毫秒数
+    DebugSetActiveExpire(bool), // 开启或关闭过期键的主动清理
+    DebugObject(String),  // 查看键的内部信息
+    DebugList(String),    // 查看列表的元素统计
+    DebugReload,          // 保存后重新加载数据文件并校验数据一致
     
     // 其他命令
+    Select(usize),        // 切换当前连接使用的数据库
+    SwapDb(usize, usize), // 原子地交换两个数据库的内容
+    Scan(usize, Option<String>, Option<usize>, Option<String>), // 按游标遍历键，可按MATCH模式和TYPE类型过滤
     Ping,
+    Health,               // 就绪检查: 数据是否加载完成、持久化是否正常
+    CommandList,          // 以JSON行输出所有命令的名称、参数个数和读写类型
     Help,
     HelpCommand(String),
 
+    // 无法识别的命令名及其参数
+    Unknown(String, Vec<String>),
+
     // 无效命令
     Invalid(String),
 }
 
+/// 命令帮助表: (分组, [(命令名, 用法说明)])
+const HELP_GROUPS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "字符串类型命令",
+        &[
+            ("set", "set [key] [value] [EX seconds] - 存储key-value类型数据，值含空格时用引号包裹"),
+            ("get", "get [key] - 获取key对应的value"),
+            ("setbin", "setbin [key] [base64] - 存储base64编码的二进制value"),
+            ("getbin", "getbin [key] - 以base64编码获取value，可读取二进制value"),
+            ("del", "del [key1] [key2 ...] - 删除一个或多个key，返回删除的数量"),
+            ("rename", "rename [key] [newkey] - 重命名key，已存在的newkey会被覆盖"),
+        ],
+    ),
+    (
+        "双向链表类型命令",
+        &[
+            ("lpush", "lpush [key] [value] - 在链表左端添加数据"),
+            ("rpush", "rpush [key] [value] - 在链表右端添加数据"),
+            ("range", "range [key] [start] [end] - 获取start到end位置的数据"),
+            ("len", "len [key] - 获取链表长度"),
+            ("lpop", "lpop [key] - 获取并删除左端数据"),
+            ("rpop", "rpop [key] - 获取并删除右端数据"),
+            ("ldel", "ldel [key] - 删除整个链表"),
+            ("lmpop", "lmpop [numkeys] [key1] [key2 ...] [LEFT|RIGHT] [COUNT n] - 从第一个非空链表弹出元素，返回键名和弹出的数据"),
+            ("blpop", "blpop [key1] [key2 ...] [timeout] - 从第一个非空链表左端弹出元素，都为空时阻塞等待，超时返回(nil)，timeout为0时一直等待"),
+            ("brpop", "brpop [key1] [key2 ...] [timeout] - 从第一个非空链表右端弹出元素，都为空时阻塞等待，超时返回(nil)，timeout为0时一直等待"),
+        ],
+    ),
+    (
+        "哈希类型命令",
+        &[
+            ("hset", "hset [key] [field] [value] - 存储哈希表字段"),
+            ("hget", "hget [key] [field] - 获取哈希表字段值"),
+            ("hdel", "hdel [key] [field] - 删除哈希表字段"),
+            ("hdel", "hdel [key] - 删除整个哈希表"),
+            ("hrandfield", "hrandfield [key] [count] [WITHVALUES] - 随机获取字段，count为负数时字段可能重复"),
+            ("hexpire", "hexpire [key] [seconds] FIELDS [numfields] [field1] [field2 ...] - 设置字段的过期时间，过期后只删除该字段"),
+            ("httl", "httl [key] FIELDS [numfields] [field1] [field2 ...] - 获取字段的剩余生存时间(秒)"),
+        ],
+    ),
+    (
+        "集合类型命令",
+        &[
+            ("sadd", "sadd [key] [member1] [member2 ...] - 添加集合成员，返回新增成员数"),
+            ("sadd", "sadd [key] CH [member1] [member2 ...] - 添加集合成员，返回实际新增的成员"),
+            ("smembers", "smembers [key] - 获取集合所有成员"),
+            ("sismember", "sismember [key] [member] - 判断成员是否在集合中"),
+            ("srem", "srem [key] [member1] [member2 ...] - 删除集合成员，返回删除的数量，集合变为空时删除key"),
+            ("sinter", "sinter [key1] [key2 ...] - 获取多个集合的交集"),
+            ("sintercard", "sintercard [numkeys] [key1] [key2 ...] [LIMIT n] - 获取多个集合交集的大小"),
+        ],
+    ),
+    (
+        "过期命令",
+        &[
+            ("expire", "expire [key] [seconds] - 设置键的过期时间"),
+            ("getex", "getex [key] [EX seconds|PERSIST] - 读取值，同时重新设置或移除过期时间"),
+            ("ddl", "ddl [key] - 查看键的剩余生存时间"),
+            ("expiringsoon", "expiringsoon [seconds] - 列出在指定秒数内过期的键，剩余时间短的在前"),
+            ("keyswithttl", "keyswithttl - 列出设置了过期时间的所有键"),
+        ],
+    ),
+    (
+        "持久化命令",
+        &[
+            ("save", "save - 同步保存数据到文件，返回写入的字节数和耗时"),
+            ("bgsave", "bgsave - 在后台保存数据到文件，返回任务ID"),
+            ("bgsave", "bgsave status [job_id] - 查询后台保存任务状态"),
+            ("lastsave", "lastsave - 最近一次成功保存的Unix时间，从未保存时为0"),
+            ("flushdb", "flushdb - 清空所有数据"),
+            ("flushdb", "flushdb type [type] - 只删除指定类型(string、bytes、list、hash、set)的键，返回删除的数量"),
+            ("delpattern", "delpattern [pattern] - 删除所有匹配glob模式的键，返回删除的键数(需启用运维命令)"),
+            ("wait", "wait [timeout_ms] - 等待本连接之前的写入保存到磁盘，返回已确认的写入数，0表示一直等待"),
+            ("export", "export [path] - 以JSON行导出当前数据库的键、类型、值和TTL，指定路径时写入服务器上的文件(需启用运维命令)"),
+            ("import", "import [path] - 导入export生成的文件，覆盖同名键，返回导入的键数(需启用运维命令)"),
+        ],
+    ),
+    (
+        "事务命令",
+        &[
+            ("begin", "begin (multi) - 开始事务"),
+            ("commit", "commit (exec) - 提交事务"),
+            ("rollback", "rollback (discard) - 回滚事务"),
+            ("savepoint", "savepoint <name> - 在当前事务中创建保存点"),
+            ("rollback", "rollback to <name> - 回滚到保存点，丢弃保存点之后的操作"),
+            ("checkpoint", "checkpoint - 创建WAL检查点"),
+            ("compactwal", "compactwal [threshold_bytes] - 压缩WAL日志，指定阈值时只在文件超过该大小时压缩"),
+            ("bgcompactwal", "bgcompactwal - 在后台压缩WAL日志，结果通过wal info查看"),
+            ("transactions", "transactions (listtx) - 列出所有活跃事务"),
+            ("txinfo", "txinfo [id] - 查看事务的状态、开始时间、操作数和已运行时间"),
+        ],
+    ),
+    (
+        "监控命令",
+        &[
+            ("stats", "stats commands - 查看各命令的调用次数和延迟"),
+            ("slowlog", "slowlog get [count] - 查看最近的慢查询"),
+            ("slowlog", "slowlog reset - 清空慢查询日志"),
+            ("memory", "memory usage [key] - 查看键的估算内存占用(字节)"),
+            ("idlekeys", "idlekeys [seconds] - 列出闲置超过指定秒数的键，闲置最久的在前，用于预览低频数据转移"),
+            ("resetstats", "resetstats [key] - 清零键的访问次数，影响低频数据转移的判断(需启用运维命令)"),
+            ("resetstats", "resetstats - 清空所有命令的调用次数和延迟统计(需启用运维命令)"),
+            ("config", "config get [section.name] - 读取配置项，例如 config get logging.level，敏感值被隐藏(需启用运维命令)"),
+            ("config", "config set [section.name] [value] - 在运行时修改日志级别等可调整的配置项(需启用运维命令)"),
+            ("config", "config dump - 以 section.name=value 的形式输出实际生效的全部配置，密码等敏感值被隐藏"),
+            ("debug", "debug sleep [seconds] - 阻塞指定秒数(需启用DEBUG命令)"),
+            ("debug", "debug set-active-expire [0|1] - 关闭或开启过期键的主动清理(需启用DEBUG命令)"),
+            ("debug", "debug object [key] - 查看键的类型、元素数量、估算大小、访问次数及是否已转移到磁盘(需启用DEBUG命令)"),
+            ("debug", "debug list [key] - 查看列表的元素数量、总大小、平均元素大小和最大元素大小(需启用DEBUG命令)"),
+            ("debug", "debug reload - 保存数据文件后重新加载，校验数据、过期时间和磁盘键在往返后保持一致(需启用DEBUG命令)"),
+            ("wal", "wal dump [from_seq] - 输出WAL日志条目(需启用运维命令)"),
+            ("wal", "wal info - 查看WAL文件大小、最后序列号和未完成事务(需启用运维命令)"),
+        ],
+    ),
+    (
+        "其他命令",
+        &[
+            ("select", "select [index] - 切换当前连接使用的数据库"),
+            ("swapdb", "swapdb [index1] [index2] - 原子地交换两个数据库的内容"),
+            ("scan", "scan [cursor] [MATCH pattern] [COUNT n] [TYPE type] - 从游标开始遍历键，返回下一个游标和本批的键，游标为0时遍历结束"),
+            ("ping", "ping - 测试服务器连接"),
+            ("health", "health - 就绪检查，返回OK、DEGRADED或LOADING以及活跃事务数"),
+            ("command", "command docs - 以JSON行输出所有命令的名称、参数个数和读写类型"),
+            ("help", "help - 获取所有命令帮助"),
+            ("help", "help [command] - 获取特定命令帮助"),
+        ],
+    ),
+];
+
+/// 命令元数据表: (命令名, 最少参数个数, 最多参数个数, 示例)
+///
+/// 参数个数包含命令名本身，最多参数个数为None表示不限；读写类型由示例解析出的命令的is_write决定。
+const COMMAND_SPECS: &[(&str, usize, Option<usize>, &str)] = &[
+    ("set", 3, None, "set key value"),
+    ("get", 2, Some(2), "get key"),
+    ("setbin", 3, Some(3), "setbin key AA=="),
+    ("getbin", 2, Some(2), "getbin key"),
+    ("del", 2, None, "del key"),
+    ("rename", 3, Some(3), "rename key newkey"),
+    ("lpush", 3, None, "lpush key value"),
+    ("rpush", 3, None, "rpush key value"),
+    ("range", 4, Some(4), "range key 0 -1"),
+    ("len", 2, Some(2), "len key"),
+    ("lpop", 2, Some(2), "lpop key"),
+    ("rpop", 2, Some(2), "rpop key"),
+    ("ldel", 2, Some(2), "ldel key"),
+    ("lmpop", 4, None, "lmpop 1 key left"),
+    ("blpop", 3, None, "blpop queue 5"),
+    ("brpop", 3, None, "brpop queue 5"),
+    ("hset", 4, None, "hset key field value"),
+    ("hget", 3, Some(3), "hget key field"),
+    ("hdel", 2, Some(3), "hdel key field"),
+    ("hrandfield", 2, Some(4), "hrandfield key"),
+    ("hexpire", 6, None, "hexpire key 60 fields 1 field"),
+    ("httl", 5, None, "httl key fields 1 field"),
+    ("sadd", 3, None, "sadd key member"),
+    ("smembers", 2, Some(2), "smembers key"),
+    ("sismember", 3, Some(3), "sismember key member"),
+    ("srem", 3, None, "srem key member"),
+    ("sinter", 2, None, "sinter key"),
+    ("sintercard", 3, None, "sintercard 1 key"),
+    ("expire", 3, Some(3), "expire key 10"),
+    ("getex", 2, Some(4), "getex key ex 10"),
+    ("ddl", 2, Some(2), "ddl key"),
+    ("expiringsoon", 2, Some(2), "expiringsoon 60"),
+    ("keyswithttl", 1, Some(1), "keyswithttl"),
+    ("save", 1, Some(1), "save"),
+    ("bgsave", 1, Some(3), "bgsave"),
+    ("lastsave", 1, Some(1), "lastsave"),
+    ("flushdb", 1, Some(3), "flushdb"),
+    ("delpattern", 2, Some(2), "delpattern *"),
+    ("wait", 2, Some(2), "wait 0"),
+    ("export", 1, Some(2), "export"),
+    ("import", 2, Some(2), "import path"),
+    ("begin", 1, Some(1), "begin"),
+    ("commit", 1, Some(1), "commit"),
+    ("rollback", 1, Some(3), "rollback"),
+    ("savepoint", 2, Some(2), "savepoint name"),
+    ("checkpoint", 1, Some(1), "checkpoint"),
+    ("compactwal", 1, Some(2), "compactwal 1048576"),
+    ("bgcompactwal", 1, Some(1), "bgcompactwal"),
+    ("transactions", 1, Some(1), "transactions"),
+    ("txinfo", 2, Some(2), "txinfo 1"),
+    ("stats", 2, Some(2), "stats commands"),
+    ("slowlog", 2, Some(3), "slowlog get"),
+    ("memory", 3, Some(3), "memory usage key"),
+    ("idlekeys", 2, Some(2), "idlekeys 600"),
+    ("resetstats", 1, Some(2), "resetstats key"),
+    ("config", 2, Some(4), "config get logging.level"),
+    ("debug", 2, Some(3), "debug object key"),
+    ("wal", 2, Some(3), "wal info"),
+    ("select", 2, Some(2), "select 0"),
+    ("swapdb", 3, Some(3), "swapdb 0 1"),
+    ("scan", 2, Some(8), "scan 0"),
+    ("ping", 1, Some(1), "ping"),
+    ("health", 1, Some(1), "health"),
+    ("command", 2, Some(2), "command docs"),
+    ("help", 1, Some(2), "help"),
+];
+
+/// 内置的命令别名: (别名, 命令名)
+const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("multi", "begin"),
+    ("exec", "commit"),
+    ("discard", "rollback"),
+    ("listtx", "transactions"),
+    ("healthcheck", "health"),
+];
+
+/// 名称(小写)是否为内置命令或内置别名
+pub fn is_builtin_command(name: &str) -> bool {
+    COMMAND_SPECS.iter().any(|&(command, ..)| command == name)
+        || BUILTIN_ALIASES.iter().any(|&(alias, _)| alias == name)
+}
+
+/// COMMAND DOCS输出的单个命令元数据
+#[derive(Serialize)]
+struct CommandDoc<'a> {
+    name: &'a str,
+    min_arity: usize,
+    max_arity: Option<usize>,
+    kind: &'static str, // read或write
+}
+
+/// 命令行中的一个参数
+struct Token {
+    text: String,
+    quoted: bool, // 带引号的参数不会被当作选项关键字
+}
+
+/// 按空白切分命令行，支持单引号、双引号以及引号内的反斜杠转义
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars();
+    let mut current: Option<Token> = None;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let token = current.get_or_insert_with(|| Token { text: String::new(), quoted: false });
+                token.quoted = true;
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') if c == '"' => token.text.push('\n'),
+                            Some('t') if c == '"' => token.text.push('\t'),
+                            Some(escaped) => token.text.push(escaped),
+                            None => return Err("Unterminated quote".to_string()),
+                        },
+                        Some(ch) => token.text.push(ch),
+                        None => return Err("Unterminated quote".to_string()),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if let Some(token) = current.take() {
+                    tokens.push(token);
+                }
+            }
+            c => current
+                .get_or_insert_with(|| Token { text: String::new(), quoted: false })
+                .text
+                .push(c),
+        }
+    }
+    if let Some(token) = current {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// 命令的结构化执行结果，文本响应由它渲染得到
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandResult {
+    /// 整数，例如长度、计数以及0/1表示的布尔结果
+    Integer(i64),
+    /// 单个字符串，None表示键或字段不存在
+    Bulk(Option<String>),
+    /// 字符串列表，例如列表范围和集合成员
+    Array(Vec<String>),
+    /// 执行成功且没有返回值
+    Ok,
+    /// 错误信息，不含`ERROR: `前缀
+    Error(String),
+}
+
+impl CommandResult {
+    /// 是否为错误结果
+    pub fn is_error(&self) -> bool {
+        matches!(self, CommandResult::Error(_))
+    }
+
+    /// 按指定的多值响应格式渲染，counted格式下数组为`*元素数`，每个元素前一行为`$字节数`
+    pub fn render(&self, format: ArrayReplyFormat) -> String {
+        match (self, format) {
+            (CommandResult::Array(values), ArrayReplyFormat::Counted) => {
+                let mut reply = format!("*{}", values.len());
+                for value in values {
+                    reply.push_str(&format!("\n${}\n{}", value.len(), value));
+                }
+                reply
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CommandResult {
+    /// 渲染为服务器发送给客户端的文本响应
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandResult::Integer(value) => write!(f, "{}", value),
+            CommandResult::Bulk(Some(value)) => f.write_str(value),
+            CommandResult::Bulk(None) => f.write_str("(nil)"),
+            CommandResult::Array(values) if values.is_empty() => f.write_str("(empty list)"),
+            CommandResult::Array(values) => f.write_str(&values.join("\n")),
+            CommandResult::Ok => f.write_str("OK"),
+            CommandResult::Error(message) => write!(f, "ERROR: {}", message),
+        }
+    }
+}
+
+/// 将存储错误转换为带错误码的结果，渲染后例如 `ERROR: WRONGTYPE ...`
+fn error_reply(error: &StoreError) -> CommandResult {
+    CommandResult::Error(format!("{} {}", error.code(), error))
+}
+
+/// 解析从start开始的`FIELDS numfields field [field ...]`，字段数量必须与numfields一致
+fn parse_hash_fields(parts: &[&str], start: usize) -> Option<Vec<String>> {
+    match parts.get(start..)? {
+        [keyword, count, fields @ ..] if keyword.eq_ignore_ascii_case("FIELDS") => {
+            let count = count.parse::<usize>().ok()?;
+            (count > 0 && count == fields.len()).then(|| fields.iter().map(|field| field.to_string()).collect())
+        }
+        _ => None,
+    }
+}
+
+impl Command {
+    /// 命令名称，用于日志和指标统计
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Set(..) => "set",
+            Command::SetEx(..) => "set",
+            Command::SetBin(..) => "setbin",
+            Command::GetBin(..) => "getbin",
+            Command::Get(..) => "get",
+            Command::Del(..) => "del",
+            Command::Rename(..) => "rename",
+            Command::DelPattern(..) => "delpattern",
+            Command::LPush(..) => "lpush",
+            Command::RPush(..) => "rpush",
+            Command::Range(..) => "range",
+            Command::Len(..) => "len",
+            Command::LPop(..) => "lpop",
+            Command::RPop(..) => "rpop",
+            Command::LDel(..) => "ldel",
+            Command::LMPop(..) => "lmpop",
+            Command::BLPop(..) => "blpop",
+            Command::BRPop(..) => "brpop",
+            Command::HSet(..) => "hset",
+            Command::HGet(..) => "hget",
+            Command::HDel(..) => "hdel",
+            Command::HDelKey(..) => "hdel",
+            Command::HRandField(..) => "hrandfield",
+            Command::HExpire(..) => "hexpire",
+            Command::HTtl(..) => "httl",
+            Command::SAdd(..) => "sadd",
+            Command::SAddCh(..) => "sadd",
+            Command::SMembers(..) => "smembers",
+            Command::SIsMember(..) => "sismember",
+            Command::SRem(..) => "srem",
+            Command::SInter(..) => "sinter",
+            Command::SInterCard(..) => "sintercard",
+            Command::Save => "save",
+            Command::BgSave => "bgsave",
+            Command::BgSaveStatus(..) => "bgsave",
+            Command::LastSave => "lastsave",
+            Command::FlushDB => "flushdb",
+            Command::FlushDBType(..) => "flushdb",
+            Command::Wait(..) => "wait",
+            Command::Export(..) => "export",
+            Command::Import(..) => "import",
+            Command::Expire(..) => "expire",
+            Command::GetEx(..) => "getex",
+            Command::DDL(..) => "ddl",
+            Command::ExpiringSoon(..) => "expiringsoon",
+            Command::KeysWithTtl => "keyswithttl",
+            Command::Begin => "begin",
+            Command::Commit => "commit",
+            Command::Rollback => "rollback",
+            Command::Checkpoint => "checkpoint",
+            Command::CompactWal(..) => "compactwal",
+            Command::BgCompactWal => "bgcompactwal",
+            Command::ListTransactions => "transactions",
+            Command::TxInfo(..) => "txinfo",
+            Command::Savepoint(..) => "savepoint",
+            Command::RollbackTo(..) => "rollback",
+            Command::WalDump(..) => "wal",
+            Command::WalInfo => "wal",
+            Command::CommandStats => "stats",
+            Command::SlowLogGet(..) => "slowlog",
+            Command::SlowLogReset => "slowlog",
+            Command::MemoryUsage(..) => "memory",
+            Command::IdleKeys(..) => "idlekeys",
+            Command::ResetStats(..) => "resetstats",
+            Command::ResetCommandStats => "resetstats",
+            Command::ConfigGet(..) => "config",
+            Command::ConfigDump => "config",
+            Command::ConfigSet(..) => "config",
+            Command::DebugSleep(..) => "debug",
+            Command::DebugSetActiveExpire(..) => "debug",
+            Command::DebugObject(..) => "debug",
+            Command::DebugList(..) => "debug",
+            Command::DebugReload => "debug",
+            Command::Select(..) => "select",
+            Command::SwapDb(..) => "swapdb",
+            Command::Scan(..) => "scan",
+            Command::Ping => "ping",
+            Command::Health => "health",
+            Command::CommandList => "command",
+            Command::Help => "help",
+            Command::HelpCommand(..) => "help",
+            Command::Unknown(..) => "unknown",
+            Command::Invalid(..) => "invalid",
+        }
+    }
+
+    /// 是否会修改数据，只读模式和WAIT据此区分读写命令
+    ///
+    /// 这里不使用通配分支，新增命令时必须明确归类。
+    pub fn is_write(&self) -> bool {
+        match self {
+            Command::Set(..)
+            | Command::SetEx(..)
+            | Command::SetBin(..)
+            | Command::Del(..)
+            | Command::DelPattern(..)
+            | Command::Rename(..)
+            | Command::Import(..)
+            | Command::LPush(..)
+            | Command::RPush(..)
+            | Command::LPop(..)
+            | Command::RPop(..)
+            | Command::LDel(..)
+            | Command::LMPop(..)
+            | Command::BLPop(..)
+            | Command::BRPop(..)
+            | Command::HSet(..)
+            | Command::HDel(..)
+            | Command::HDelKey(..)
+            | Command::HExpire(..)
+            | Command::SAdd(..)
+            | Command::SAddCh(..)
+            | Command::SRem(..)
+            | Command::FlushDB
+            | Command::FlushDBType(..)
+            | Command::Expire(..)
+            | Command::GetEx(..)
+            | Command::SwapDb(..)
+            | Command::Commit => true,
+            Command::GetBin(..)
+            | Command::Get(..)
+            | Command::Range(..)
+            | Command::Len(..)
+            | Command::HGet(..)
+            | Command::HRandField(..)
+            | Command::HTtl(..)
+            | Command::SMembers(..)
+            | Command::SIsMember(..)
+            | Command::SInter(..)
+            | Command::SInterCard(..)
+            | Command::Save
+            | Command::BgSave
+            | Command::BgSaveStatus(..)
+            | Command::LastSave
+            | Command::Wait(..)
+            | Command::Export(..)
+            | Command::DDL(..)
+            | Command::ExpiringSoon(..)
+            | Command::KeysWithTtl
+            | Command::Begin
+            | Command::Rollback
+            | Command::Checkpoint
+            | Command::CompactWal(..)
+            | Command::BgCompactWal
+            | Command::ListTransactions
+            | Command::TxInfo(..)
+            | Command::Savepoint(..)
+            | Command::RollbackTo(..)
+            | Command::WalDump(..)
+            | Command::WalInfo
+            | Command::CommandStats
+            | Command::SlowLogGet(..)
+            | Command::SlowLogReset
+            | Command::MemoryUsage(..)
+            | Command::IdleKeys(..)
+            | Command::ResetStats(..)
+            | Command::ResetCommandStats
+            | Command::ConfigGet(..)
+            | Command::ConfigDump
+            | Command::ConfigSet(..)
+            | Command::DebugSleep(..)
+            | Command::DebugSetActiveExpire(..)
+            | Command::DebugObject(..)
+            | Command::DebugList(..)
+            | Command::DebugReload
+            | Command::Scan(..)
+            | Command::Select(..)
+            | Command::Ping
+            | Command::Health
+            | Command::CommandList
+            | Command::Help
+            | Command::HelpCommand(..)
+            | Command::Unknown(..)
+            | Command::Invalid(..) => false,
+        }
+    }
+
+    /// 写命令可能修改的当前数据库中的键，None表示可能修改任意键，用于把修改记录到WAL
+    ///
+    /// COMMIT的操作由事务管理器记录，这里返回空列表；SWAPDB涉及两个数据库，由调用方单独处理。
+    pub fn written_keys(&self) -> Option<Vec<String>> {
+        let keys = match self {
+            Command::Set(key, _)
+            | Command::SetEx(key, ..)
+            | Command::SetBin(key, _)
+            | Command::GetEx(key, _)
+            | Command::LPush(key, _)
+            | Command::RPush(key, _)
+            | Command::LPop(key)
+            | Command::RPop(key)
+            | Command::LDel(key)
+            | Command::HSet(key, ..)
+            | Command::HDel(key, _)
+            | Command::HDelKey(key)
+            | Command::HExpire(key, ..)
+            | Command::SAdd(key, _)
+            | Command::SAddCh(key, _)
+            | Command::SRem(key, _)
+            | Command::Expire(key, _) => vec![key.clone()],
+            Command::Rename(from, to) => vec![from.clone(), to.clone()],
+            Command::Del(keys) | Command::LMPop(keys, ..) | Command::BLPop(keys, _) | Command::BRPop(keys, _) => {
+                keys.clone()
+            }
+            Command::DelPattern(_) | Command::Import(_) | Command::FlushDB | Command::FlushDBType(_) => return None,
+            _ => Vec::new(),
+        };
+        Some(keys)
+    }
+
+    /// 写命令在事务中对应的存储操作，不能在事务中执行的命令返回None
+    pub fn transaction_operations(&self) -> Option<Vec<StoreOperation>> {
+        let operations = match self {
+            Command::Set(key, value) => vec![StoreOperation::Set(key.clone(), value.clone())],
+            Command::Del(keys) => keys.iter().map(|key| StoreOperation::Delete(key.clone())).collect(),
+            Command::LPush(key, value) => vec![StoreOperation::LPush(key.clone(), value.clone())],
+            Command::RPush(key, value) => vec![StoreOperation::RPush(key.clone(), value.clone())],
+            Command::LPop(key) => vec![StoreOperation::LPop(key.clone())],
+            Command::RPop(key) => vec![StoreOperation::RPop(key.clone())],
+            Command::LDel(key) => vec![StoreOperation::LDel(key.clone())],
+            Command::HSet(key, field, value) => vec![StoreOperation::HSet(key.clone(), field.clone(), value.clone())],
+            Command::HDel(key, field) => vec![StoreOperation::HDel(key.clone(), field.clone())],
+            Command::HDelKey(key) => vec![StoreOperation::HDelKey(key.clone())],
+            Command::SAdd(key, members) | Command::SAddCh(key, members) => members
+                .iter()
+                .map(|member| StoreOperation::SAdd(key.clone(), member.clone()))
+                .collect(),
+            Command::SRem(key, members) => members
+                .iter()
+                .map(|member| StoreOperation::SRem(key.clone(), member.clone()))
+                .collect(),
+            _ => return None,
+        };
+        Some(operations)
+    }
+
+    /// 结果可以缓存的只读命令涉及的键，其他命令返回None
+    pub fn cacheable_keys(&self) -> Option<&[String]> {
+        match self {
+            Command::SInter(keys) | Command::SInterCard(keys, _) => Some(keys),
+            _ => None,
+        }
+    }
+}
+
 // 命令处理器
 pub struct CommandHandler {
     store_manager: StoreManager,
     data_file: String,
+    paths: DataPaths, // WAL和检查点等数据路径
+    connection_id: Option<u64>, // 设置后每条命令都会带连接ID记录日志
+    metrics: Option<Arc<CommandMetrics>>,
+    slowlog: Option<Arc<SlowLog>>,
+    result_cache: Option<Arc<ResultCache>>, // 所有连接共享的只读命令结果缓存
+    settings: Option<Arc<Settings>>,
+    runtime_config: Option<Arc<RwLock<Settings>>>, // 所有连接共享、CONFIG SET可修改的配置
+    pending_writes: Mutex<Vec<u64>>, // 本连接尚未被WAIT确认持久化的写入序号
+    transaction_manager: Option<Arc<TransactionManager>>, // 所有连接共享的事务管理器和WAL日志
+    transaction_handler: OnceLock<TransactionCommandHandler>, // 首次使用时创建，事务状态在同一连接的命令之间保留
 }
 
 impl CommandHandler {
     pub fn new(store_manager: StoreManager, data_file: String) -> Self {
         CommandHandler {
             store_manager,
+            paths: DataPaths::from_data_file(&data_file),
             data_file,
+            connection_id: None,
+            metrics: None,
+            slowlog: None,
+            result_cache: None,
+            settings: None,
+            runtime_config: None,
+            pending_writes: Mutex::new(Vec::new()),
+            transaction_manager: None,
+            transaction_handler: OnceLock::new(),
+        }
+    }
+
+    /// 使用配置构建
+    pub fn with_settings(mut self, settings: Arc<Settings>) -> Self {
+        self.paths = settings.data_paths();
+        self.settings = Some(settings);
+        self
+    }
+
+    /// 启用慢查询日志
+    pub fn with_slowlog(mut self, slowlog: Arc<SlowLog>) -> Self {
+        self.slowlog = Some(slowlog);
+        self
+    }
+
+    /// 是否允许DEBUG命令，未提供配置时默认允许
+    fn debug_commands_enabled(&self) -> bool {
+        self.settings
+            .as_ref()
+            .is_none_or(|settings| settings.server.enable_debug_commands)
+    }
+
+    /// 是否允许运维命令，未提供配置时默认允许
+    fn admin_commands_enabled(&self) -> bool {
+        self.settings
+            .as_ref()
+            .is_none_or(|settings| settings.server.enable_admin_commands)
+    }
+
+    /// 是否处于只读模式，未提供配置时默认可写
+    fn read_only(&self) -> bool {
+        self.settings
+            .as_ref()
+            .is_some_and(|settings| settings.server.read_only)
+    }
+
+    /// 启用CONFIG GET/SET，修改作用于所有共享该配置的连接
+    pub fn with_runtime_config(mut self, runtime_config: Arc<RwLock<Settings>>) -> Self {
+        self.runtime_config = Some(runtime_config);
+        self
+    }
+
+    /// 启用只读命令结果缓存
+    pub fn with_result_cache(mut self, result_cache: Arc<ResultCache>) -> Self {
+        self.result_cache = Some(result_cache);
+        self
+    }
+
+    /// 启用命令执行指标统计
+    pub fn with_metrics(mut self, metrics: Arc<CommandMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// 使用共享的事务管理器，事务ID和WAL日志在所有连接之间共享
+    pub fn with_transaction_manager(mut self, transaction_manager: Arc<TransactionManager>) -> Self {
+        self.transaction_manager = Some(transaction_manager);
+        self
+    }
+
+    /// 设置连接ID，用于在日志中追踪单个客户端的会话
+    pub fn with_connection_id(mut self, connection_id: u64) -> Self {
+        self.connection_id = Some(connection_id);
+        self
+    }
+
+    /// 获取连接ID
+    pub fn connection_id(&self) -> Option<u64> {
+        self.connection_id
+    }
+
+    /// 把命令名(小写)解析为内置命令名，配置的别名优先于内置别名
+    ///
+    /// 配置加载时已拒绝与内置命令重名的别名，除非设置了server.allow_alias_override。
+    fn resolve_alias<'a>(&'a self, name: &'a str) -> &'a str {
+        if let Some(target) = self
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.server.command_aliases.get(name))
+        {
+            return target;
         }
+        BUILTIN_ALIASES
+            .iter()
+            .find(|&&(alias, _)| alias == name)
+            .map_or(name, |&(_, command)| command)
     }
 
     // 解析命令字符串
     pub fn parse_command(&self, input: &str) -> Command {
-        let input = input.trim();
-        let parts: Vec<&str> = input.split_whitespace().collect();
+        let tokens = match tokenize(input) {
+            Ok(tokens) => tokens,
+            Err(e) => return Command::Invalid(e),
+        };
+        let parts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
 
         if parts.is_empty() {
             return Command::Invalid("Empty command".to_string());
         }
 
-        match parts[0].to_lowercase().as_str() {
+        match self.resolve_alias(&parts[0].to_lowercase()) {
             // 事务命令
-            "begin" | "multi" => Command::Begin,
-            "commit" | "exec" => Command::Commit,
-            "rollback" | "discard" => Command::Rollback,
+            "begin" => Command::Begin,
+            "commit" => Command::Commit,
+            "rollback" if parts.len() == 3 && parts[1].eq_ignore_ascii_case("to") => {
+                Command::RollbackTo(parts[2].to_string())
+            }
+            "rollback" => Command::Rollback,
+            "savepoint" if parts.len() == 2 => Command::Savepoint(parts[1].to_string()),
+            "savepoint" => Command::Invalid("Usage: SAVEPOINT name".to_string()),
             "checkpoint" => Command::Checkpoint,
-            "compactwal" => Command::CompactWal,
-            "transactions" | "listtx" => Command::ListTransactions,
+            "compactwal" => match parts.get(1).map(|threshold| threshold.parse::<u64>()) {
+                None => Command::CompactWal(None),
+                Some(Ok(threshold)) => Command::CompactWal(Some(threshold)),
+                Some(Err(_)) => Command::Invalid("Threshold must be a non-negative integer".to_string()),
+            },
+            "bgcompactwal" => Command::BgCompactWal,
+            "transactions" => Command::ListTransactions,
+            "txinfo" => match parts.get(1).map(|id| id.parse::<u64>()) {
+                Some(Ok(id)) if parts.len() == 2 => Command::TxInfo(id),
+                Some(Err(_)) => Command::Invalid("Transaction id must be a non-negative integer".to_string()),
+                _ => Command::Invalid("Usage: TXINFO id".to_string()),
+            },
+            "wal" => {
+                match parts.get(1).map(|s| s.to_lowercase()).as_deref() {
+                    Some("dump") if parts.len() == 2 => Command::WalDump(None),
+                    Some("dump") if parts.len() == 3 => match parts[2].parse::<u64>() {
+                        Ok(seq) => Command::WalDump(Some(seq)),
+                        Err(_) => Command::Invalid("Sequence number must be a positive integer".to_string()),
+                    },
+                    Some("info") if parts.len() == 2 => Command::WalInfo,
+                    _ => Command::Invalid("Usage: WAL DUMP [from_seq] | WAL INFO".to_string()),
+                }
+            }
+            
+            // 监控命令
+            "stats" => {
+                if parts.len() == 2 && parts[1].eq_ignore_ascii_case("commands") {
+                    Command::CommandStats
+                } else {
+                    Command::Invalid("Usage: STATS COMMANDS".to_string())
+                }
+            }
+            "slowlog" => {
+                match parts.get(1).map(|s| s.to_lowercase()).as_deref() {
+                    Some("get") if parts.len() == 2 => Command::SlowLogGet(None),
+                    Some("get") if parts.len() == 3 => match parts[2].parse::<usize>() {
+                        Ok(count) => Command::SlowLogGet(Some(count)),
+                        Err(_) => Command::Invalid("Count must be a positive integer".to_string()),
+                    },
+                    Some("reset") if parts.len() == 2 => Command::SlowLogReset,
+                    _ => Command::Invalid("Usage: SLOWLOG GET [count] | SLOWLOG RESET".to_string()),
+                }
+            }
+            "memory" => {
+                if parts.len() == 3 && parts[1].eq_ignore_ascii_case("usage") {
+                    Command::MemoryUsage(parts[2].to_string())
+                } else {
+                    Command::Invalid("Usage: MEMORY USAGE key".to_string())
+                }
+            }
+            "idlekeys" => match parts.get(1).map(|seconds| seconds.parse::<u64>()) {
+                Some(Ok(seconds)) if parts.len() == 2 => Command::IdleKeys(seconds),
+                Some(Err(_)) => Command::Invalid("Seconds must be a positive integer".to_string()),
+                _ => Command::Invalid("Usage: IDLEKEYS seconds".to_string()),
+            },
+            "config" => match parts.get(1).map(|s| s.to_lowercase()).as_deref() {
+                Some("get") if parts.len() == 3 => Command::ConfigGet(parts[2].to_lowercase()),
+                Some("dump") if parts.len() == 2 => Command::ConfigDump,
+                Some("set") if parts.len() == 4 => Command::ConfigSet(parts[2].to_lowercase(), parts[3].to_string()),
+                _ => Command::Invalid("Usage: CONFIG GET name | CONFIG SET name value | CONFIG DUMP".to_string()),
+            },
+            "resetstats" => match &parts[1..] {
+                [] => Command::ResetCommandStats,
+                [key] => Command::ResetStats(key.to_string()),
+                _ => Command::Invalid("Usage: RESETSTATS [key]".to_string()),
+            },
+            
+            // 调试命令
+            "debug" => {
+                match parts.get(1).map(|s| s.to_lowercase()).as_deref() {
+                    Some("sleep") if parts.len() == 3 => match parts[2].parse::<f64>() {
+                        Ok(seconds) if seconds >= 0.0 => {
+                            Command::DebugSleep((seconds * 1000.0) as u64)
+                        }
+                        _ => Command::Invalid("Seconds must be a non-negative number".to_string()),
+                    },
+                    Some("set-active-expire") if parts.len() == 3 => match parts[2] {
+                        "0" => Command::DebugSetActiveExpire(false),
+                        "1" => Command::DebugSetActiveExpire(true),
+                        _ => Command::Invalid("Value must be 0 or 1".to_string()),
+                    },
+                    Some("object") if parts.len() == 3 => Command::DebugObject(parts[2].to_string()),
+                    Some("list") if parts.len() == 3 => Command::DebugList(parts[2].to_string()),
+                    Some("reload") if parts.len() == 2 => Command::DebugReload,
+                    _ => Command::Invalid(
+                        "Usage: DEBUG SLEEP seconds | DEBUG SET-ACTIVE-EXPIRE 0|1 | DEBUG OBJECT key | DEBUG LIST key | DEBUG RELOAD"
+                            .to_string(),
+                    ),
+                }
+            }
             
             // 字符串命令
             "set" => {
@@ -96,18 +954,16 @@ impl CommandHandler {
                 } else {
                     let key = parts[1].to_string();
 
-                    // 检查是否有EX选项
-                    if parts.len() >= 5 && parts[parts.len() - 2].to_uppercase() == "EX" {
-                        if let Ok(seconds) = parts[parts.len() - 1].parse::<u64>() {
-                            // 如果有EX选项，value是除了key、EX和seconds之外的所有部分
-                            let value = parts[2..parts.len() - 2].join(" ");
-                            return Command::Set(key, value + " EX " + &seconds.to_string());
-                        }
+                    // 检查是否有EX选项，带引号的EX视为普通值
+                    let ex_index = parts.len() - 2;
+                    if parts.len() >= 5 && !tokens[ex_index].quoted && parts[ex_index].eq_ignore_ascii_case("EX") {
+                        return match parts[parts.len() - 1].parse::<u64>() {
+                            Ok(seconds) => Command::SetEx(key, parts[2..ex_index].join(" "), seconds),
+                            Err(_) => Command::Invalid("Seconds must be a positive integer".to_string()),
+                        };
                     }
 
-                    // 没有EX选项或EX选项无效
-                    let value = parts[2..].join(" ");
-                    Command::Set(key, value)
+                    Command::Set(key, parts[2..].join(" "))
                 }
             }
             "get" => {
@@ -117,11 +973,35 @@ impl CommandHandler {
                     Command::Get(parts[1].to_string())
                 }
             }
-            "del" => {
+            "setbin" => {
+                if parts.len() != 3 {
+                    Command::Invalid("Usage: SETBIN key base64_value".to_string())
+                } else {
+                    match BASE64_STANDARD.decode(parts[2]) {
+                        Ok(value) => Command::SetBin(parts[1].to_string(), value),
+                        Err(_) => Command::Invalid("Value must be valid base64".to_string()),
+                    }
+                }
+            }
+            "getbin" => {
                 if parts.len() != 2 {
-                    Command::Invalid("Usage: DEL key".to_string())
+                    Command::Invalid("Usage: GETBIN key".to_string())
+                } else {
+                    Command::GetBin(parts[1].to_string())
+                }
+            }
+            "del" => {
+                if parts.len() < 2 {
+                    Command::Invalid("Usage: DEL key [key ...]".to_string())
                 } else {
-                    Command::Del(parts[1].to_string())
+                    Command::Del(parts[1..].iter().map(|key| key.to_string()).collect())
+                }
+            }
+            "rename" => {
+                if parts.len() != 3 {
+                    Command::Invalid("Usage: RENAME key newkey".to_string())
+                } else {
+                    Command::Rename(parts[1].to_string(), parts[2].to_string())
                 }
             }
 
@@ -202,6 +1082,28 @@ impl CommandHandler {
                     Command::HGet(parts[1].to_string(), parts[2].to_string())
                 }
             }
+            "hrandfield" => {
+                let key = parts.get(1).map(|key| key.to_string());
+                let count = parts.get(2).map(|count| count.parse::<isize>());
+                match (key, count, &parts[parts.len().min(3)..]) {
+                    (Some(key), None, _) => Command::HRandField(key, None, false),
+                    (Some(key), Some(Ok(count)), []) => Command::HRandField(key, Some(count), false),
+                    (Some(key), Some(Ok(count)), [option]) if option.eq_ignore_ascii_case("WITHVALUES") => {
+                        Command::HRandField(key, Some(count), true)
+                    }
+                    (Some(_), Some(Err(_)), _) => Command::Invalid("Count must be an integer".to_string()),
+                    _ => Command::Invalid("Usage: HRANDFIELD key [count [WITHVALUES]]".to_string()),
+                }
+            }
+            "hexpire" => match (parts.get(2).map(|seconds| seconds.parse::<u64>()), parse_hash_fields(&parts, 3)) {
+                (Some(Ok(seconds)), Some(fields)) => Command::HExpire(parts[1].to_string(), seconds, fields),
+                (Some(Err(_)), Some(_)) => Command::Invalid("Seconds must be a non-negative integer".to_string()),
+                _ => Command::Invalid("Usage: HEXPIRE key seconds FIELDS numfields field [field ...]".to_string()),
+            },
+            "httl" => match parse_hash_fields(&parts, 2) {
+                Some(fields) => Command::HTtl(parts[1].to_string(), fields),
+                None => Command::Invalid("Usage: HTTL key FIELDS numfields field [field ...]".to_string()),
+            },
             "hdel" => {
                 if parts.len() == 2 {
                     Command::HDelKey(parts[1].to_string())
@@ -213,11 +1115,17 @@ impl CommandHandler {
             }
             "sadd"=>{
                 if parts.len() < 3 {
-                    Command::Invalid("Usage: SADD key value1 [value2 ...]".to_string())
+                    Command::Invalid("Usage: SADD key [CH] value1 [value2 ...]".to_string())
                 } else {
                     let key = parts[1].to_string();
-                    let values = parts[2..].iter().map(|s| s.to_string()).collect();
-                    Command::SAdd(key, values)
+                    // 带引号的CH视为普通成员
+                    if parts.len() >= 4 && !tokens[2].quoted && parts[2].eq_ignore_ascii_case("CH") {
+                        let values = parts[3..].iter().map(|s| s.to_string()).collect();
+                        Command::SAddCh(key, values)
+                    } else {
+                        let values = parts[2..].iter().map(|s| s.to_string()).collect();
+                        Command::SAdd(key, values)
+                    }
                 }
             }
             "smembers" => {
@@ -235,15 +1143,137 @@ impl CommandHandler {
                 }
             }
             "srem" => {
-                if parts.len() != 3 {
-                    Command::Invalid("Usage: SREM key value".to_string())
+                if parts.len() < 3 {
+                    Command::Invalid("Usage: SREM key member [member ...]".to_string())
                 } else {
-                    Command::SRem(parts[1].to_string(), parts[2].to_string())
+                    Command::SRem(parts[1].to_string(), parts[2..].iter().map(|member| member.to_string()).collect())
+                }
+            }
+            "lmpop" => {
+                let usage = "Usage: LMPOP numkeys key [key ...] LEFT|RIGHT [COUNT count]";
+                let Some(numkeys) = parts.get(1).and_then(|n| n.parse::<usize>().ok()).filter(|n| *n > 0) else {
+                    return Command::Invalid("numkeys must be a positive integer".to_string());
+                };
+                let keys_end = 2 + numkeys;
+                if parts.len() <= keys_end {
+                    return Command::Invalid(usage.to_string());
+                }
+                let keys: Vec<String> = parts[2..keys_end].iter().map(|s| s.to_string()).collect();
+                let end = match parts[keys_end].to_lowercase().as_str() {
+                    "left" => ListEnd::Left,
+                    "right" => ListEnd::Right,
+                    _ => return Command::Invalid(usage.to_string()),
+                };
+                match &parts[keys_end + 1..] {
+                    [] => Command::LMPop(keys, end, None),
+                    [option, count] if option.eq_ignore_ascii_case("COUNT") => {
+                        match count.parse::<usize>().ok().filter(|count| *count > 0) {
+                            Some(count) => Command::LMPop(keys, end, Some(count)),
+                            None => Command::Invalid("Count must be a positive integer".to_string()),
+                        }
+                    }
+                    _ => Command::Invalid(usage.to_string()),
+                }
+            }
+            name @ ("blpop" | "brpop") => {
+                if parts.len() < 3 {
+                    return Command::Invalid(format!("Usage: {} key [key ...] timeout", name.to_uppercase()));
+                }
+                let keys: Vec<String> = parts[1..parts.len() - 1].iter().map(|s| s.to_string()).collect();
+                match parts[parts.len() - 1].parse::<f64>() {
+                    Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+                        let millis = (seconds * 1000.0) as u64;
+                        if name == "blpop" {
+                            Command::BLPop(keys, millis)
+                        } else {
+                            Command::BRPop(keys, millis)
+                        }
+                    }
+                    _ => Command::Invalid("Timeout must be a non-negative number of seconds".to_string()),
+                }
+            }
+            "sinter" => {
+                if parts.len() < 2 {
+                    Command::Invalid("Usage: SINTER key [key ...]".to_string())
+                } else {
+                    Command::SInter(parts[1..].iter().map(|s| s.to_string()).collect())
+                }
+            }
+            "sintercard" => {
+                let usage = "Usage: SINTERCARD numkeys key [key ...] [LIMIT limit]";
+                let Some(numkeys) = parts.get(1).and_then(|n| n.parse::<usize>().ok()).filter(|n| *n > 0) else {
+                    return Command::Invalid("numkeys must be a positive integer".to_string());
+                };
+                let keys_end = 2 + numkeys;
+                if parts.len() < keys_end {
+                    return Command::Invalid(usage.to_string());
+                }
+                let keys: Vec<String> = parts[2..keys_end].iter().map(|s| s.to_string()).collect();
+                match &parts[keys_end..] {
+                    [] => Command::SInterCard(keys, None),
+                    [option, limit] if option.eq_ignore_ascii_case("LIMIT") => match limit.parse::<usize>() {
+                        Ok(limit) => Command::SInterCard(keys, Some(limit)),
+                        Err(_) => Command::Invalid("Limit must be a non-negative integer".to_string()),
+                    },
+                    _ => Command::Invalid(usage.to_string()),
                 }
             }
             "save" => Command::Save,
-            "bgsave" => Command::BgSave,
-            "flushdb" => Command::FlushDB,
+            "lastsave" => Command::LastSave,
+            "bgsave" => {
+                match parts.get(1).map(|s| s.to_lowercase()).as_deref() {
+                    None => Command::BgSave,
+                    Some("status") if parts.len() == 3 => match parts[2].parse::<u64>() {
+                        Ok(job_id) => Command::BgSaveStatus(job_id),
+                        Err(_) => Command::Invalid("Job id must be a positive integer".to_string()),
+                    },
+                    _ => Command::Invalid("Usage: BGSAVE | BGSAVE STATUS job_id".to_string()),
+                }
+            }
+            "flushdb" => match (parts.get(1), parts.get(2)) {
+                (None, _) => Command::FlushDB,
+                (Some(option), Some(key_type)) if option.eq_ignore_ascii_case("type") && parts.len() == 3 => {
+                    let key_type = key_type.to_lowercase();
+                    if !DataType::TYPE_NAMES.contains(&key_type.as_str()) {
+                        return Command::Invalid(format!(
+                            "Unknown type {}, expected one of {}",
+                            key_type,
+                            DataType::TYPE_NAMES.join(", ")
+                        ));
+                    }
+                    Command::FlushDBType(key_type)
+                }
+                _ => Command::Invalid("Usage: FLUSHDB [TYPE type]".to_string()),
+            },
+            "delpattern" => {
+                if parts.len() != 2 {
+                    Command::Invalid("Usage: DELPATTERN pattern".to_string())
+                } else {
+                    Command::DelPattern(parts[1].to_string())
+                }
+            }
+            "wait" => {
+                if parts.len() != 2 {
+                    Command::Invalid("Usage: WAIT timeout_ms".to_string())
+                } else {
+                    match parts[1].parse::<u64>() {
+                        Ok(timeout_ms) => Command::Wait(timeout_ms),
+                        Err(_) => Command::Invalid("Timeout must be a non-negative integer".to_string()),
+                    }
+                }
+            }
+            "export" => match parts.len() {
+                1 => Command::Export(None),
+                2 => Command::Export(Some(parts[1].to_string())),
+                _ => Command::Invalid("Usage: EXPORT [path]".to_string()),
+            },
+            "import" => {
+                if parts.len() != 2 {
+                    Command::Invalid("Usage: IMPORT path".to_string())
+                } else {
+                    Command::Import(parts[1].to_string())
+                }
+            }
             "expire" => {
                 if parts.len() != 3 {
                     Command::Invalid("Usage: EXPIRE key seconds".to_string())
@@ -257,6 +1287,21 @@ impl CommandHandler {
                     }
                 }
             }
+            "getex" => match (parts.get(1), parts.get(2).map(|s| s.to_lowercase()).as_deref(), parts.get(3)) {
+                (Some(key), None, None) => Command::GetEx(key.to_string(), ExpiryOption::Keep),
+                (Some(key), Some("persist"), None) => Command::GetEx(key.to_string(), ExpiryOption::Persist),
+                (Some(key), Some("ex"), Some(seconds)) if parts.len() == 4 => match seconds.parse::<u64>() {
+                    Ok(seconds) => Command::GetEx(key.to_string(), ExpiryOption::Ex(seconds)),
+                    Err(_) => Command::Invalid("Seconds must be a positive integer".to_string()),
+                },
+                _ => Command::Invalid("Usage: GETEX key [EX seconds | PERSIST]".to_string()),
+            },
+            "expiringsoon" => match parts.get(1).map(|seconds| seconds.parse::<u64>()) {
+                Some(Ok(seconds)) if parts.len() == 2 => Command::ExpiringSoon(seconds),
+                Some(Err(_)) => Command::Invalid("Seconds must be a positive integer".to_string()),
+                _ => Command::Invalid("Usage: EXPIRINGSOON seconds".to_string()),
+            },
+            "keyswithttl" => Command::KeysWithTtl,
             "ddl" => {
                 if parts.len() != 2 {
                     Command::Invalid("Usage: DDL key".to_string())
@@ -265,7 +1310,45 @@ impl CommandHandler {
                 }
             }
             // 其他命令
+            "select" => match parts.get(1).map(|index| index.parse::<usize>()) {
+                Some(Ok(index)) if parts.len() == 2 => Command::Select(index),
+                Some(Err(_)) => Command::Invalid("DB index must be a non-negative integer".to_string()),
+                _ => Command::Invalid("Usage: SELECT index".to_string()),
+            },
+            "scan" => {
+                let usage = "Usage: SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]";
+                let Some(cursor) = parts.get(1).and_then(|cursor| cursor.parse::<usize>().ok()) else {
+                    return Command::Invalid(usage.to_string());
+                };
+                let (mut pattern, mut count, mut key_type) = (None, None, None);
+                for option in parts[2..].chunks(2) {
+                    match option {
+                        [name, value] if name.eq_ignore_ascii_case("MATCH") => pattern = Some(value.to_string()),
+                        [name, value] if name.eq_ignore_ascii_case("COUNT") => match value.parse::<usize>() {
+                            Ok(n) if n > 0 => count = Some(n),
+                            _ => return Command::Invalid("Count must be a positive integer".to_string()),
+                        },
+                        [name, value] if name.eq_ignore_ascii_case("TYPE") => key_type = Some(value.to_lowercase()),
+                        _ => return Command::Invalid(usage.to_string()),
+                    }
+                }
+                Command::Scan(cursor, pattern, count, key_type)
+            }
+            "swapdb" => {
+                if parts.len() != 3 {
+                    return Command::Invalid("Usage: SWAPDB index1 index2".to_string());
+                }
+                match (parts[1].parse::<usize>(), parts[2].parse::<usize>()) {
+                    (Ok(first), Ok(second)) => Command::SwapDb(first, second),
+                    _ => Command::Invalid("DB index must be a non-negative integer".to_string()),
+                }
+            }
             "ping" => Command::Ping,
+            "health" => Command::Health,
+            "command" => match parts.get(1).map(|s| s.to_lowercase()).as_deref() {
+                Some("docs") | Some("list") if parts.len() == 2 => Command::CommandList,
+                _ => Command::Invalid("Usage: COMMAND DOCS".to_string()),
+            },
             "help" => {
                 if parts.len() == 1 {
                     Command::Help
@@ -273,285 +1356,858 @@ impl CommandHandler {
                     Command::HelpCommand(parts[1].to_string())
                 }
             }
-            _ => Command::Invalid(format!("Unknown command: {}", parts[0])),
+            _ => Command::Unknown(parts[0].to_string(), parts[1..].iter().map(|arg| arg.to_string()).collect()),
         }
     }
 
-    // 执行命令
+    // 执行命令，返回文本响应
     pub fn execute_command(&self, command: Command) -> String {
-        // 确定WAL日志路径
-        let wal_path = std::path::Path::new(&self.data_file)
-            .parent()
-            .unwrap_or_else(|| std::path::Path::new("."))
-            .join("wal.log");
-            
+        // 集合命令的空结果沿用"(empty set)"的文本
+        let returns_set = matches!(command, Command::SMembers(_) | Command::SInter(_));
+        let format = self.array_reply_format();
+        match self.execute_typed(command) {
+            CommandResult::Array(members)
+                if returns_set && members.is_empty() && format == ArrayReplyFormat::Joined =>
+            {
+                "(empty set)".to_string()
+            }
+            result => result.render(format),
+        }
+    }
+
+    /// 执行BLPOP/BRPOP，timeout为0时一直等待，返回键名和弹出的元素，超时返回(nil)
+    fn blocking_pop(&self, keys: &[String], end: ListEnd, timeout_millis: u64) -> CommandResult {
+        let timeout = (timeout_millis > 0).then(|| Duration::from_millis(timeout_millis));
+        match self.store_manager.blocking_pop(keys, end, timeout) {
+            Ok(Some((key, value))) => CommandResult::Array(vec![key, value]),
+            Ok(None) => CommandResult::Bulk(None),
+            Err(e) => error_reply(&e),
+        }
+    }
+
+    /// 多值响应的格式，未提供配置时每个元素一行
+    fn array_reply_format(&self) -> ArrayReplyFormat {
+        self.settings
+            .as_ref()
+            .map_or(ArrayReplyFormat::Joined, |settings| settings.server.array_replies)
+    }
+
+    /// 执行命令，返回结构化的结果，适合在进程内直接使用
+    pub fn execute_typed(&self, command: Command) -> CommandResult {
+        // 恢复数据期间只响应就绪检查，其他命令看到的数据还不完整
+        if self.store_manager.is_loading() && !matches!(command, Command::Ping | Command::Health | Command::Help) {
+            return CommandResult::Error("LOADING server is loading the dataset".to_string());
+        }
+        let is_write = command.is_write();
+        if is_write && self.read_only() {
+            return CommandResult::Error("READONLY server is read-only".to_string());
+        }
+        // 事务中的写命令只加入事务，不修改数据
+        let logged_writes = (is_write && !self.in_transaction()).then(|| self.written_databases(&command));
+        let mut response = self.execute_and_record(command);
+        if let Some(writes) = logged_writes {
+            let writes: Vec<(usize, Option<&[String]>)> =
+                writes.iter().map(|(db, keys)| (*db, keys.as_deref())).collect();
+            if let Err(e) = self.store_manager.log_key_images(&writes) {
+                log::error!("记录写入到WAL失败: {}", e);
+                response = error_reply(&e);
+            }
+        }
+        // 登记成功的写入，供WAIT等待其落盘
+        if is_write && !response.is_error() {
+            self.pending_writes.lock().unwrap().push(self.store_manager.record_write());
+        }
+        response
+    }
+
+    // 执行命令并记录日志、指标和慢查询
+    fn execute_and_record(&self, command: Command) -> CommandResult {
+        // 既不记录日志也不统计指标时不做计时
+        if self.connection_id.is_none() && self.metrics.is_none() && self.slowlog.is_none() {
+            return self.dispatch_cached(command);
+        }
+
+        let name = command.name();
+        let description = self.connection_id.map(|_| format!("{:?}", command));
+        let slowlog_candidate = self.slowlog.as_ref().map(|_| command.clone());
+        let start = Instant::now();
+        let response = self.dispatch_cached(command);
+        let elapsed = start.elapsed();
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record(name, elapsed);
+        }
+        if let (Some(slowlog), Some(command)) = (&self.slowlog, slowlog_candidate) {
+            if elapsed >= slowlog.threshold() {
+                slowlog.record(format!("{:?}", command), elapsed);
+            }
+        }
+        if let (Some(connection_id), Some(description)) = (self.connection_id, description) {
+            info!(
+                "[conn {}] 执行命令: {}, 响应 {} 字节, 耗时 {:?}",
+                connection_id,
+                description,
+                response.to_string().len(),
+                elapsed
+            );
+        }
+        response
+    }
+
+    /// 本连接是否在事务中
+    fn in_transaction(&self) -> bool {
+        self.transaction_handler.get().is_some_and(|handler| handler.in_transaction())
+    }
+
+    /// 写命令修改的数据库和键，键为None时记录整个数据库
+    fn written_databases(&self, command: &Command) -> Vec<(usize, Option<Vec<String>>)> {
+        match command {
+            Command::SwapDb(first, second) if first != second => vec![(*first, None), (*second, None)],
+            Command::SwapDb(..) => Vec::new(),
+            command => match command.written_keys() {
+                Some(keys) if keys.is_empty() => Vec::new(),
+                keys => vec![(self.store_manager.selected_db(), keys)],
+            },
+        }
+    }
+
+    /// 本连接的事务处理器，首次使用时创建
+    ///
+    /// 设置了共享的事务管理器时使用它，否则按配置为本连接单独打开WAL日志，只适合单个连接独占数据目录的场景。
+    fn transaction_handler(&self) -> &TransactionCommandHandler {
+        self.transaction_handler.get_or_init(|| {
+            if let Some(manager) = &self.transaction_manager {
+                return TransactionCommandHandler::from_manager(Arc::clone(manager));
+            }
+            let handler = TransactionCommandHandler::new(&self.paths.wal_file)
+                .with_checkpoint_dir(&self.paths.checkpoint_dir);
+            match &self.settings {
+                Some(settings) => handler
+                    .with_checkpoint_retention(settings.persistence.checkpoint_retention)
+                    .with_sync_policy(settings.persistence.wal_sync, settings.persistence.wal_batch_entries),
+                None => handler,
+            }
+        })
+    }
+
+    // 分发命令到具体的处理逻辑
+    // 可缓存的命令先查结果缓存，相关键的版本号在执行前读取，执行期间发生的修改会使结果在下次查找时失效
+    fn dispatch_cached(&self, command: Command) -> CommandResult {
+        if let Some(response) = self.dispatch_in_transaction(&command) {
+            return response;
+        }
+        let Some(cache) = &self.result_cache else {
+            return self.dispatch(command);
+        };
+        let Some(versions) = command
+            .cacheable_keys()
+            .and_then(|keys| self.store_manager.key_versions(keys))
+        else {
+            return self.dispatch(command);
+        };
+
+        let cache_key = format!("{}:{:?}", self.store_manager.selected_db(), command);
+        if let Some(response) = cache.get(&cache_key, &versions) {
+            return response;
+        }
+        let response = self.dispatch(command);
+        if !response.is_error() {
+            cache.insert(cache_key, versions, response.clone());
+        }
+        response
+    }
+
+    /// 连接在事务中时，写命令加入事务而不是直接修改数据，提交时才生效；GET先读取事务自己的修改
+    ///
+    /// 不在事务中或命令不受事务影响时返回None，按普通命令执行。
+    fn dispatch_in_transaction(&self, command: &Command) -> Option<CommandResult> {
+        let handler = self.transaction_handler.get().filter(|handler| handler.in_transaction())?;
+        match command {
+            Command::Get(key) => handler.read_local(key).map(CommandResult::Bulk),
+            Command::Commit => None,
+            command if command.is_write() => {
+                let Some(operations) = command.transaction_operations() else {
+                    return Some(CommandResult::Error(format!(
+                        "ERR {} is not allowed inside a transaction",
+                        command.name()
+                    )));
+                };
+                for operation in operations {
+                    if let Err(e) = handler.queue_operation(operation) {
+                        return Some(CommandResult::Error(e));
+                    }
+                }
+                Some(CommandResult::Bulk(Some("QUEUED".to_string())))
+            }
+            _ => None,
+        }
+    }
+
+    fn dispatch(&self, command: Command) -> CommandResult {
         // 尝试使用事务处理器
-        let use_transaction_handler = |f: fn(&crate::transaction_cmd::TransactionCommandHandler) -> Result<String, String>| -> String {
-            // 创建事务处理器
-            let handler = crate::transaction_cmd::TransactionCommandHandler::new(&wal_path);
-            match f(&handler) {
-                Ok(result) => result,
-                Err(e) => format!("ERROR: {}", e)
+        let use_transaction_handler = |f: &dyn Fn(&TransactionCommandHandler) -> Result<String, String>| -> CommandResult {
+            match f(self.transaction_handler()) {
+                Ok(result) => CommandResult::Bulk(Some(result)),
+                Err(e) => CommandResult::Error(e)
             }
         };
         
         match command {
             // 事务命令
-            Command::Begin => use_transaction_handler(|h| h.begin()),
-            Command::Commit => use_transaction_handler(|h| h.commit()),
-            Command::Rollback => use_transaction_handler(|h| h.rollback()),
-            Command::Checkpoint => use_transaction_handler(|h| h.checkpoint()),
-            Command::CompactWal => use_transaction_handler(|h| h.compact()),
-            Command::ListTransactions => use_transaction_handler(|h| h.list_transactions()),
+            Command::Begin => use_transaction_handler(&|h| h.begin()),
+            Command::Commit => use_transaction_handler(&|h| {
+                h.commit_with(|txn_manager, txn_id| {
+                    self.store_manager.commit_transaction(txn_manager, txn_id).map_err(|e| e.to_string())
+                })
+            }),
+            Command::Rollback => use_transaction_handler(&|h| h.rollback()),
+            Command::Checkpoint => use_transaction_handler(&|h| h.checkpoint()),
+            Command::CompactWal(None) => use_transaction_handler(&|h| h.compact()),
+            Command::CompactWal(Some(threshold)) => use_transaction_handler(&|h| h.compact_if_needed(threshold)),
+            Command::BgCompactWal => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                let txn_manager = self.transaction_handler().get_transaction_manager();
+                if self.store_manager.bg_compact_wal(txn_manager) {
+                    CommandResult::Bulk(Some("Background WAL compaction started".to_string()))
+                } else {
+                    CommandResult::Error("BUSY background WAL compaction already in progress".to_string())
+                }
+            }
+            Command::ListTransactions => use_transaction_handler(&|h| h.list_transactions()),
+            Command::TxInfo(txn_id) => use_transaction_handler(&|h| h.txinfo(txn_id)),
+            Command::Savepoint(name) => use_transaction_handler(&|h| h.savepoint(&name)),
+            Command::RollbackTo(name) => use_transaction_handler(&|h| h.rollback_to(&name)),
+            Command::WalDump(from_seq) => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                match self.transaction_handler().wal_dump(from_seq) {
+                    Ok(result) => CommandResult::Bulk(Some(result)),
+                    Err(e) => CommandResult::Error(e)
+                }
+            }
+            Command::WalInfo => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                let compaction = match self.store_manager.wal_compaction_state() {
+                    None => "(none)".to_string(),
+                    Some(WalCompactionState::Running) => "running".to_string(),
+                    Some(WalCompactionState::Completed { before, after, elapsed }) => {
+                        format!("completed: {} -> {} bytes in {}ms", before, after, elapsed.as_millis())
+                    }
+                    Some(WalCompactionState::Failed(e)) => format!("failed: {}", e),
+                };
+                match self.transaction_handler().wal_info() {
+                    Ok(info) => CommandResult::Bulk(Some(format!("{}\nlast_bg_compaction: {}", info, compaction))),
+                    Err(e) => CommandResult::Error(e),
+                }
+            }
             
             // 字符串命令 - 使用新的StoreManager API
             Command::Set(key, value) => {
                 match self.store_manager.set_string(key, value) {
-                    Ok(result) => result,
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(_) => CommandResult::Ok,
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::SetEx(key, value, seconds) => {
+                let result = self
+                    .store_manager
+                    .set_string(key.clone(), value)
+                    .and_then(|_| self.store_manager.expire(&key, seconds));
+                match result {
+                    Ok(_) => CommandResult::Ok,
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::Get(key) => {
                 match self.store_manager.get_string(&key) {
-                    Ok(Some(value)) => value,
-                    Ok(None) => "(nil)".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(value) => CommandResult::Bulk(value),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::GetEx(key, option) => {
+                match self.store_manager.getex(&key, option) {
+                    Ok(value) => CommandResult::Bulk(value),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::SetBin(key, value) => {
+                match self.store_manager.set_bytes(key, value) {
+                    Ok(()) => CommandResult::Ok,
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::GetBin(key) => {
+                match self.store_manager.get_bytes(&key) {
+                    Ok(value) => CommandResult::Bulk(value.map(|value| BASE64_STANDARD.encode(value))),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::Del(keys) => {
+                match self.store_manager.del_keys(&keys) {
+                    Ok(count) => CommandResult::Integer(count as i64),
+                    Err(e) => error_reply(&e)
                 }
             }
-            Command::Del(key) => {
-                match self.store_manager.del_key(&key) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+            Command::Rename(key, new_key) => {
+                match self.store_manager.rename(&key, &new_key) {
+                    Ok(()) => CommandResult::Ok,
+                    Err(e) => error_reply(&e)
                 }
             }
 
             // 列表命令 - 使用新的StoreManager API
             Command::LPush(key, value) => {
                 match self.store_manager.lpush(key, value) {
-                    Ok(len) => len.to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(len) => CommandResult::Integer(len as i64),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::RPush(key, value) => {
                 match self.store_manager.rpush(key, value) {
-                    Ok(len) => len.to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(len) => CommandResult::Integer(len as i64),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::Range(key, start, end) => {
                 match self.store_manager.range(&key, start, end) {
-                    Ok(values) => {
-                        if values.is_empty() {
-                            "(empty list)".to_string()
-                        } else {
-                            values.join("\n")
-                        }
-                    },
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(values) => CommandResult::Array(values),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::LMPop(keys, end, count) => {
+                match self.store_manager.lmpop(&keys, end, count.unwrap_or(1)) {
+                    Ok(Some((key, values))) => CommandResult::Array(std::iter::once(key).chain(values).collect()),
+                    Ok(None) => CommandResult::Bulk(None),
+                    Err(e) => error_reply(&e)
                 }
             }
+            Command::BLPop(keys, timeout) => self.blocking_pop(&keys, ListEnd::Left, timeout),
+            Command::BRPop(keys, timeout) => self.blocking_pop(&keys, ListEnd::Right, timeout),
             Command::Len(key) => {
                 match self.store_manager.llen(&key) {
-                    Ok(len) => len.to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(len) => CommandResult::Integer(len as i64),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::LPop(key) => {
                 match self.store_manager.lpop(&key) {
-                    Ok(Some(value)) => value,
-                    Ok(None) => "(nil)".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(value) => CommandResult::Bulk(value),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::RPop(key) => {
                 match self.store_manager.rpop(&key) {
-                    Ok(Some(value)) => value,
-                    Ok(None) => "(nil)".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(value) => CommandResult::Bulk(value),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::LDel(key) => {
                 match self.store_manager.ldel(&key) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(flag) => CommandResult::Integer(i64::from(flag)),
+                    Err(e) => error_reply(&e)
                 }
             }
 
             // 哈希命令 - 使用新的StoreManager API
             Command::HSet(key, field, value) => {
                 match self.store_manager.hset(key, field, value) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(flag) => CommandResult::Integer(i64::from(flag)),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::HGet(key, field) => {
                 match self.store_manager.hget(&key, &field) {
-                    Ok(Some(value)) => value,
-                    Ok(None) => "(nil)".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(value) => CommandResult::Bulk(value),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::HRandField(key, count, with_values) => {
+                match self.store_manager.hrandfield(&key, count) {
+                    Ok(entries) if entries.is_empty() && count.is_none() => CommandResult::Bulk(None),
+                    Ok(entries) => CommandResult::Array(
+                        entries
+                            .into_iter()
+                            .flat_map(|(field, value)| if with_values { vec![field, value] } else { vec![field] })
+                            .collect(),
+                    ),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::HExpire(key, seconds, fields) => {
+                match self.store_manager.hexpire(&key, seconds, &fields) {
+                    Ok(results) => CommandResult::Array(results.iter().map(i64::to_string).collect()),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::HTtl(key, fields) => {
+                match self.store_manager.httl(&key, &fields) {
+                    Ok(results) => CommandResult::Array(results.iter().map(i64::to_string).collect()),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::HDel(key, field) => {
                 match self.store_manager.hdel_field(&key, &field) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(flag) => CommandResult::Integer(i64::from(flag)),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::HDelKey(key) => {
                 match self.store_manager.hdel_key(&key) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(flag) => CommandResult::Integer(i64::from(flag)),
+                    Err(e) => error_reply(&e)
                 }
             }
             
             // 集合命令 - 使用新的StoreManager API
             Command::SAdd(key, value) => {
                 match self.store_manager.sadd(key, value) {
-                    Ok(count) => count.to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(count) => CommandResult::Integer(count as i64),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::SAddCh(key, values) => {
+                match self.store_manager.sadd_members(key, values) {
+                    Ok(added) => CommandResult::Array(added),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::SMembers(key) => {
                 match self.store_manager.smembers(&key) {
-                    Ok(members) if !members.is_empty() => {
-                        members.into_iter().collect::<Vec<String>>().join("\n")
-                    },
-                    Ok(_) => "(empty set)".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(members) => CommandResult::Array(members.into_iter().collect()),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::SIsMember(key, value) => {
                 match self.store_manager.smember_query(&key, &value) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(flag) => CommandResult::Integer(i64::from(flag)),
+                    Err(e) => error_reply(&e)
                 }
             }
-            Command::SRem(key, value) => {
-                match self.store_manager.srem(&key, &value) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+            Command::SRem(key, members) => {
+                match self.store_manager.srem_members(&key, &members) {
+                    Ok(count) => CommandResult::Integer(count as i64),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::SInter(keys) => {
+                match self.store_manager.sinter(&keys) {
+                    Ok(mut members) => {
+                        members.sort();
+                        CommandResult::Array(members)
+                    },
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::SInterCard(keys, limit) => {
+                match self.store_manager.sintercard(&keys, limit.unwrap_or(0)) {
+                    Ok(count) => CommandResult::Integer(count as i64),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::Save => {
+                let start = Instant::now();
                 match self.store_manager.save_to_file(&self.data_file) {
-                    Ok(_) => "Saved".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(bytes) => CommandResult::Bulk(Some(format!("Saved {} bytes in {}ms", bytes, start.elapsed().as_millis()))),
+                    Err(e) => error_reply(&e)
                 }
             }
             Command::BgSave => {
-                let data_file = self.data_file.clone();
-                let store_manager = self.store_manager.clone();
-                thread::spawn(move || {
-                    if let Err(e) = store_manager.save_to_file(&data_file) {
-                        error!("Background save failed: {}", e);
-                    } else {
-                        debug!("Background save completed");
+                let job_id = self.store_manager.bg_save(self.data_file.clone());
+                CommandResult::Bulk(Some(format!("Background save started, job {}", job_id)))
+            }
+            Command::BgSaveStatus(job_id) => {
+                let status = match self.store_manager.save_job(job_id) {
+                    Some(SaveJobState::Running) => "running".to_string(),
+                    Some(SaveJobState::Completed { bytes, elapsed }) => {
+                        format!("completed: saved {} bytes in {}ms", bytes, elapsed.as_millis())
                     }
-                });
-                "Background save started".to_string()
+                    Some(SaveJobState::Failed(e)) => format!("failed: {}", e),
+                    None => return CommandResult::Error(format!("ERR unknown background save job {}", job_id)),
+                };
+                CommandResult::Bulk(Some(status))
+            }
+            Command::LastSave => {
+                let mut reply = self.store_manager.last_save_time().unwrap_or(0).to_string();
+                if self.store_manager.last_bgsave_failed() {
+                    reply.push_str(" (last background save failed)");
+                }
+                CommandResult::Bulk(Some(reply))
             }
             Command::FlushDB => {
-                // 创建新的空Store并替换现有的
-                let store_guard = self.store_manager.get_store();
-                let mut store = store_guard.lock().unwrap();
-                *store = crate::store::Store::new();
+                // 只清空连接当前选择的数据库，flush_db返回时已释放锁，保存时会锁定所有数据库
+                self.store_manager.flush_db();
                 
                 // 保存空状态
                 match self.store_manager.save_to_file(&self.data_file) {
-                    Ok(_) => "OK".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(_) => CommandResult::Ok,
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::FlushDBType(key_type) => match self.store_manager.flush_type(&key_type) {
+                Ok(count) => CommandResult::Integer(count as i64),
+                Err(e) => error_reply(&e),
+            },
+            Command::DelPattern(pattern) => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                match self.store_manager.delete_pattern(&pattern) {
+                    Ok(count) => CommandResult::Integer(count as i64),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            Command::Wait(timeout_ms) => {
+                let Some(last_write) = self.pending_writes.lock().unwrap().last().copied() else {
+                    return CommandResult::Integer(0);
+                };
+                // 等待期间不持有pending_writes的锁
+                let timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms));
+                let durable = self.store_manager.wait_durable(last_write, timeout);
+                let mut pending = self.pending_writes.lock().unwrap();
+                let acknowledged = pending.iter().take_while(|seq| **seq <= durable).count();
+                pending.drain(..acknowledged);
+                CommandResult::Integer(acknowledged as i64)
+            }
+            Command::Export(path) => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                let exported = match self.store_manager.export_json() {
+                    Ok(exported) => exported,
+                    Err(e) => return error_reply(&e),
+                };
+                match path {
+                    Some(path) => match write_file_atomically(Path::new(&path), exported.as_bytes()) {
+                        Ok(()) => CommandResult::Integer(exported.lines().count() as i64),
+                        Err(e) => error_reply(&e.into()),
+                    },
+                    None if exported.is_empty() => CommandResult::Bulk(Some("(empty)".to_string())),
+                    None => CommandResult::Bulk(Some(exported.trim_end().to_string())),
+                }
+            }
+            Command::Import(path) => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                match std::fs::read_to_string(&path)
+                    .map_err(StoreError::from)
+                    .and_then(|content| self.store_manager.import_json(&content))
+                {
+                    Ok(count) => CommandResult::Integer(count as i64),
+                    Err(e) => error_reply(&e),
                 }
             }
             Command::Expire(key, seconds) => {
                 match self.store_manager.expire(&key, seconds) {
-                    Ok(true) => "1".to_string(),
-                    Ok(false) => "0".to_string(),
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(flag) => CommandResult::Integer(i64::from(flag)),
+                    Err(e) => error_reply(&e)
                 }
             }
+            Command::ExpiringSoon(seconds) => CommandResult::Array(self.store_manager.expiring_soon(seconds)),
+            Command::KeysWithTtl => CommandResult::Array(self.store_manager.keys_with_ttl()),
             Command::DDL(key) => {
                 match self.store_manager.ttl(&key) {
-                    Ok(ttl) => {
-                        if ttl == -2 {
-                            "Key does not exist".to_string()
-                        } else if ttl == -1 {
-                            "No expiration".to_string()
-                        } else {
-                            format!("TTL: {} seconds", ttl)
-                        }
-                    },
-                    Err(e) => format!("ERROR: {}", e)
+                    Ok(ttl) => CommandResult::Bulk(Some(if ttl == -2 {
+                        "Key does not exist".to_string()
+                    } else if ttl == -1 {
+                        "No expiration".to_string()
+                    } else {
+                        format!("TTL: {} seconds", ttl)
+                    })),
+                    Err(e) => error_reply(&e)
+                }
+            }
+            // 监控命令
+            Command::CommandStats => match &self.metrics {
+                Some(metrics) => CommandResult::Bulk(Some(metrics.render())),
+                None => CommandResult::Error("DISABLED command metrics are disabled".to_string()),
+            },
+            Command::SlowLogGet(count) => match &self.slowlog {
+                Some(slowlog) => CommandResult::Array(
+                    slowlog
+                        .get(count.unwrap_or(10))
+                        .iter()
+                        .map(|entry| {
+                            format!(
+                                "{} {} {}us {}",
+                                entry.id, entry.timestamp, entry.duration_micros, entry.command
+                            )
+                        })
+                        .collect(),
+                ),
+                None => CommandResult::Error("DISABLED slowlog is disabled".to_string()),
+            },
+            Command::SlowLogReset => match &self.slowlog {
+                Some(slowlog) => {
+                    slowlog.reset();
+                    CommandResult::Ok
+                }
+                None => CommandResult::Error("DISABLED slowlog is disabled".to_string()),
+            },
+            Command::MemoryUsage(key) => match self.store_manager.memory_usage_of(&key) {
+                Ok(Some(bytes)) => CommandResult::Integer(bytes as i64),
+                Ok(None) => CommandResult::Bulk(None),
+                Err(e) => error_reply(&e),
+            },
+            Command::IdleKeys(seconds) => CommandResult::Array(self.store_manager.idle_keys(seconds)),
+            Command::ResetStats(key) => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                match self.store_manager.reset_access_stats(&key) {
+                    Ok(flag) => CommandResult::Integer(i64::from(flag)),
+                    Err(e) => error_reply(&e),
+                }
+            }
+            Command::ConfigGet(name) => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                let Some(runtime_config) = &self.runtime_config else {
+                    return CommandResult::Error("DISABLED runtime config is not available".to_string());
+                };
+                match runtime_config.read().unwrap().get_param(&name) {
+                    Some(value) => CommandResult::Bulk(Some(value)),
+                    None => CommandResult::Error(format!("ERR unknown config parameter {}", name)),
+                }
+            }
+            Command::ConfigDump => {
+                let Some(runtime_config) = &self.runtime_config else {
+                    return CommandResult::Error("DISABLED runtime config is not available".to_string());
+                };
+                CommandResult::Array(runtime_config.read().unwrap().dump())
+            }
+            Command::ConfigSet(name, value) => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                let Some(runtime_config) = &self.runtime_config else {
+                    return CommandResult::Error("DISABLED runtime config is not available".to_string());
+                };
+                let mut settings = runtime_config.write().unwrap();
+                if let Err(e) = settings.set_param(&name, &value) {
+                    return CommandResult::Error(format!("ERR {}", e));
+                }
+                self.apply_runtime_config(&name, &settings);
+                CommandResult::Ok
+            }
+            Command::ResetCommandStats => {
+                if !self.admin_commands_enabled() {
+                    return CommandResult::Error("NOPERM admin commands are disabled".to_string());
+                }
+                match &self.metrics {
+                    Some(metrics) => {
+                        metrics.reset();
+                        CommandResult::Ok
+                    }
+                    None => CommandResult::Error("DISABLED command metrics are disabled".to_string()),
                 }
             }
+
+            // 调试命令
+            Command::DebugSleep(millis) => {
+                if !self.debug_commands_enabled() {
+                    return CommandResult::Error("DEBUG commands are disabled".to_string());
+                }
+                thread::sleep(std::time::Duration::from_millis(millis));
+                CommandResult::Ok
+            }
+            Command::DebugSetActiveExpire(enabled) => {
+                if !self.debug_commands_enabled() {
+                    return CommandResult::Error("DEBUG commands are disabled".to_string());
+                }
+                self.store_manager.set_active_expire(enabled);
+                CommandResult::Ok
+            }
+            Command::DebugObject(key) => {
+                if !self.debug_commands_enabled() {
+                    return CommandResult::Error("DEBUG commands are disabled".to_string());
+                }
+                match self.store_manager.debug_object(&key) {
+                    Ok(Some(info)) => CommandResult::Bulk(Some(info.to_string())),
+                    Ok(None) => error_reply(&StoreError::KeyNotFound(key)),
+                    Err(e) => error_reply(&e),
+                }
+            }
+            Command::DebugList(key) => {
+                if !self.debug_commands_enabled() {
+                    return CommandResult::Error("DEBUG commands are disabled".to_string());
+                }
+                match self.store_manager.debug_list(&key) {
+                    Ok(Some(info)) => CommandResult::Bulk(Some(info.to_string())),
+                    Ok(None) => error_reply(&StoreError::KeyNotFound(key)),
+                    Err(e) => error_reply(&e),
+                }
+            }
+            Command::DebugReload => {
+                if !self.debug_commands_enabled() {
+                    return CommandResult::Error("DEBUG commands are disabled".to_string());
+                }
+                match self.store_manager.debug_reload(&self.data_file) {
+                    Ok(None) => CommandResult::Ok,
+                    Ok(Some(mismatch)) => CommandResult::Error(format!("DEBUG RELOAD mismatch: {}", mismatch)),
+                    Err(e) => error_reply(&e),
+                }
+            }
+
             // 其他命令
-            Command::Ping => "PONG".to_string(),
-            Command::Help => self.get_help(),
-            Command::HelpCommand(cmd) => self.get_command_help(&cmd),
-            Command::Invalid(msg) => format!("ERROR: {}", msg),
+            Command::Select(index) => match self.store_manager.select(index) {
+                Ok(()) => CommandResult::Ok,
+                Err(e) => error_reply(&e),
+            },
+            Command::SwapDb(first, second) => match self.store_manager.swap_db(first, second) {
+                Ok(()) => CommandResult::Ok,
+                Err(e) => error_reply(&e),
+            },
+            Command::Scan(cursor, pattern, count, key_type) => {
+                match self.store_manager.scan(cursor, pattern.as_deref(), count.unwrap_or(10), key_type.as_deref()) {
+                    Ok((next, keys)) => CommandResult::Array(std::iter::once(next.to_string()).chain(keys).collect()),
+                    Err(e) => error_reply(&e),
+                }
+            }
+            Command::Ping => CommandResult::Bulk(Some("PONG".to_string())),
+            Command::Health => CommandResult::Bulk(Some(self.health())),
+            Command::CommandList => CommandResult::Bulk(Some(self.command_docs())),
+            Command::Help => CommandResult::Bulk(Some(self.get_help())),
+            Command::HelpCommand(cmd) => CommandResult::Bulk(Some(self.get_command_help(&cmd))),
+            Command::Unknown(name, args) => {
+                let policy = self.settings.as_ref()
+                    .map_or(UnknownCommandPolicy::Strict, |settings| settings.server.unknown_commands);
+                // 与Redis的错误格式一致，参数逐个加引号
+                let args: String = args.iter().map(|arg| format!(" '{}'", arg)).collect();
+                let message = format!("unknown command '{}', with args beginning with:{}", name, args);
+                match policy {
+                    UnknownCommandPolicy::Strict => CommandResult::Error(format!("ERR {}", message)),
+                    UnknownCommandPolicy::Lenient => {
+                        log::warn!("忽略{}", message);
+                        CommandResult::Ok
+                    }
+                }
+            }
+            Command::Invalid(msg) => CommandResult::Error(msg),
+        }
+    }
+
+    /// 按重新加载的配置修改运行时可调整的配置项并立即生效，返回值有变化的配置项
+    ///
+    /// 其他配置项需要重启生效，与当前配置不同时只记录警告。
+    pub fn reload_config(&self, reloaded: &Settings) -> Result<Vec<String>, String> {
+        let Some(runtime_config) = &self.runtime_config else {
+            return Err("runtime config is not available".to_string());
+        };
+        let mut settings = runtime_config.write().unwrap();
+        let mut changed = Vec::new();
+        for &name in RUNTIME_PARAMS {
+            let value = reloaded.get_param(name).unwrap_or_default();
+            if settings.get_param(name).as_deref() != Some(value.as_str()) {
+                settings.set_param(name, &value)?;
+                self.apply_runtime_config(name, &settings);
+                changed.push(name.to_string());
+            }
+        }
+        let current = settings.dump();
+        let restart_required: Vec<String> = reloaded
+            .dump()
+            .into_iter()
+            .filter(|line| !current.contains(line))
+            .collect();
+        if !restart_required.is_empty() {
+            log::warn!("以下配置项需要重启生效，已忽略: {}", restart_required.join(", "));
+        }
+        Ok(changed)
+    }
+
+    /// 让修改后的配置项立即生效
+    fn apply_runtime_config(&self, name: &str, settings: &Settings) {
+        match name {
+            "logging.level" => {
+                if let Err(e) = crate::logger::set_level(&settings.logging.level) {
+                    log::warn!("调整日志级别失败: {}", e);
+                }
+            }
+            "memory.low_frequency_check_interval" => {
+                self.store_manager.set_optimization_interval(settings.memory.low_frequency_check_interval);
+            }
+            _ => self.store_manager.update_settings(Arc::new(settings.clone())),
         }
+        info!("配置项 {} 已修改为 {}", name, settings.get_param(name).unwrap_or_default());
     }
 
     // 持久化数据方法已经被移除，改为直接调用 store_manager 的 save_to_file 方法
 
+    /// 就绪状态: 正在恢复数据时为LOADING，后台保存失败或低频数据无法写入磁盘时为DEGRADED
+    fn health(&self) -> String {
+        let persistence_ok = !self.store_manager.last_bgsave_failed() && self.store_manager.offload_enabled();
+        let status = if self.store_manager.is_loading() {
+            "LOADING"
+        } else if !persistence_ok {
+            "DEGRADED"
+        } else {
+            "OK"
+        };
+        format!(
+            "{}\nloading: {}\npersistence_ok: {}\nactive_transactions: {}",
+            status,
+            u8::from(self.store_manager.is_loading()),
+            u8::from(persistence_ok),
+            self.transaction_handler().active_transaction_count()
+        )
+    }
+
+    /// 每个命令一行JSON: 名称、参数个数范围(包含命令名)和读写类型
+    fn command_docs(&self) -> String {
+        COMMAND_SPECS
+            .iter()
+            .map(|&(name, min_arity, max_arity, example)| {
+                let doc = CommandDoc {
+                    name,
+                    min_arity,
+                    max_arity,
+                    kind: if self.parse_command(example).is_write() { "write" } else { "read" },
+                };
+                serde_json::to_string(&doc).unwrap_or_default()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
     // 获取帮助信息
     fn get_help(&self) -> String {
-        let help = r"可用命令:
-字符串类型命令:
-  set [key] [value] - 存储key-value类型数据
-  get [key] - 获取key对应的value
-  del [key] - 删除key对应的value
-
-双向链表类型命令:
-  lpush [key] [value] - 在链表左端添加数据
-  rpush [key] [value] - 在链表右端添加数据
-  range [key] [start] [end] - 获取start到end位置的数据
-  len [key] - 获取链表长度
-  lpop [key] - 获取并删除左端数据
-  rpop [key] - 获取并删除右端数据
-  ldel [key] - 删除整个链表
-
-哈希类型命令:
-  hset [key] [field] [value] - 存储哈希表字段
-  hget [key] [field] - 获取哈希表字段值
-  hdel [key] [field] - 删除哈希表字段
-  hdel [key] - 删除整个哈希表
-
-其他命令:
-  ping - 测试服务器连接
-  help - 获取所有命令帮助
-  help [command] - 获取特定命令帮助";
-
-        help.to_string()
+        let groups: Vec<String> = HELP_GROUPS
+            .iter()
+            .map(|(group, commands)| {
+                let lines: Vec<String> = commands
+                    .iter()
+                    .map(|(_, usage)| format!("  {}", usage))
+                    .collect();
+                format!("{}:\n{}", group, lines.join("\n"))
+            })
+            .collect();
+
+        format!("可用命令:\n{}", groups.join("\n\n"))
     }
 
     // 获取特定命令的帮助信息
     fn get_command_help(&self, command: &str) -> String {
-        match command.to_lowercase().as_str() {
-            "set" => "set [key] [value] - 存储key-value类型数据".to_string(),
-            "get" => "get [key] - 获取key对应的value".to_string(),
-            "del" => "del [key] - 删除key对应的value".to_string(),
-            "lpush" => "lpush [key] [value] - 在链表左端添加数据".to_string(),
-            "rpush" => "rpush [key] [value] - 在链表右端添加数据".to_string(),
-            "range" => "range [key] [start] [end] - 获取start到end位置的数据".to_string(),
-            "len" => "len [key] - 获取链表长度".to_string(),
-            "lpop" => "lpop [key] - 获取并删除左端数据".to_string(),
-            "rpop" => "rpop [key] - 获取并删除右端数据".to_string(),
-            "ldel" => "ldel [key] - 删除整个链表".to_string(),
-            "hset" => "hset [key] [field] [value] - 存储哈希表字段".to_string(),
-            "hget" => "hget [key] [field] - 获取哈希表字段值".to_string(),
-            "hdel" => {
-                "hdel [key] [field] - 删除哈希表字段\nhdel [key] - 删除整个哈希表".to_string()
-            }
-            "ping" => "ping - 测试服务器连接".to_string(),
-            "help" => "help - 获取所有命令帮助\nhelp [command] - 获取特定命令帮助".to_string(),
-            _ => format!("Unknown command: {}", command),
+        let name = command.to_lowercase();
+        let name = self.resolve_alias(&name);
+
+        let usages: Vec<&str> = HELP_GROUPS
+            .iter()
+            .flat_map(|(_, commands)| commands.iter())
+            .filter(|(command_name, _)| *command_name == name)
+            .map(|(_, usage)| *usage)
+            .collect();
+
+        if usages.is_empty() {
+            format!("Unknown command: {}", command)
+        } else {
+            usages.join("\n")
         }
     }
 }
@@ -1,92 +1,260 @@
-use config::{Config, ConfigError, File};
-use serde::Deserialize;
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    pub listen: Vec<String>,                  // 监听地址列表，非空时代替host:port，unix:前缀表示Unix域套接字
+    pub unix_socket: Option<String>,          // 额外监听的Unix域套接字路径
+    pub enable_debug_commands: bool,          // 是否允许DEBUG命令
+    pub enable_admin_commands: bool,          // 是否允许WAL DUMP等运维命令
+    pub protocol: WireProtocol,               // 客户端连接使用的协议
+    pub rate_limit_per_second: u64,           // 每个连接每秒允许的命令数，0表示不限制
+    pub read_only: bool,                      // 只读模式，拒绝所有修改数据的命令
+    pub databases: usize,                     // 数据库数量，SELECT可选择0到databases-1
+    pub command_aliases: HashMap<String, String>, // 命令别名 -> 内置命令名
+    pub allow_alias_override: bool,           // 是否允许别名覆盖同名的内置命令
+    pub unknown_commands: UnknownCommandPolicy, // 收到无法识别的命令时的处理方式
+    pub array_replies: ArrayReplyFormat,      // 多值响应的格式
+    pub max_command_length: usize,            // 按行协议中单条命令行的最大字节数
 }
 
-#[derive(Debug, Deserialize)]
+/// 多值响应（列表范围、集合成员、哈希表字段等）的文本格式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArrayReplyFormat {
+    Joined,  // 每个元素一行，元素中含有换行时无法区分边界
+    Counted, // 首行为*元素数，每个元素前一行为$字节数，元素中可以含有换行
+}
+
+/// 收到无法识别的命令时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownCommandPolicy {
+    Strict,  // 返回错误
+    Lenient, // 记录警告后返回OK，兼容会发送较新命令的客户端
+}
+
+/// 客户端连接的传输协议
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireProtocol {
+    Line,   // 每行一条命令，响应带时间戳并以换行结尾
+    Binary, // 命令和响应都以4字节大端长度为前缀，可携带换行和任意字节
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PersistenceMode {
     None,
     OnChange,
     Interval,
 }
-#[derive(Debug, Deserialize)]
+/// 数据文件损坏时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorruptDataFilePolicy {
+    Fail,       // 拒绝启动
+    Quarantine, // 将损坏的文件改名隔离，继续使用空存储(或已从WAL恢复的数据)
+}
+
+/// 内存数据超过max_memory_bytes时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaxMemoryPolicy {
+    Reject,  // 拒绝会增加数据的写入，删除和读取照常执行
+    Offload, // 将访问最少的键转移到磁盘，无法转移时拒绝写入
+    AllKeysLfu,  // 删除访问次数最少的键
+    VolatileLfu, // 只在设置了过期时间的键中删除访问次数最少的键，没有这样的键时拒绝写入
+}
+
+/// WAL日志同步到磁盘的策略
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalSyncPolicy {
+    Always,   // 每条日志写入后立即同步
+    EverySec, // 批量写入，距上次同步超过1秒时同步
+    No,       // 批量写入，由操作系统决定何时落盘
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PersistenceConfig {
+    pub data_dir: Option<String>,             // 数据根目录，未设置时使用数据文件所在目录
     pub data_file: String,
     pub mode: PersistenceMode,
     pub interval_seconds: u64,
+    pub checkpoint_retention: usize,          // 保留最近的检查点文件数，0表示全部保留
+    pub on_corrupt_data_file: CorruptDataFilePolicy, // 数据文件损坏时的处理方式
+    pub per_database_files: bool,             // 每个数据库保存到单独的数据文件
+    pub wal_file: Option<String>,             // 覆盖默认的WAL日志路径
+    pub wal_sync: WalSyncPolicy,              // WAL日志同步到磁盘的策略
+    pub wal_batch_entries: usize,             // 批量写入时每缓冲多少条日志写入一次文件
+    pub wal_compact_threshold_bytes: u64,     // WAL日志超过该字节数时由后台定期压缩，0表示不自动压缩
+    pub wal_compact_check_seconds: u64,       // 检查WAL日志大小的时间间隔(秒)
+    pub checkpoint_dir: Option<String>,       // 覆盖默认的检查点目录
+    pub offload_dir: Option<String>,          // 覆盖默认的低频数据目录
+}
+
+/// 由数据根目录推导出的所有数据路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataPaths {
+    pub data_dir: PathBuf,
+    pub data_file: PathBuf,
+    pub wal_file: PathBuf,
+    pub checkpoint_dir: PathBuf,
+    pub offload_dir: PathBuf,
+}
+
+impl DataPaths {
+    /// 以数据根目录推导默认路径，相对的数据文件路径位于根目录下
+    pub fn new<P: AsRef<Path>, F: AsRef<Path>>(data_dir: P, data_file: F) -> Self {
+        let data_dir = data_dir.as_ref().to_path_buf();
+        DataPaths {
+            data_file: data_dir.join(data_file),
+            wal_file: data_dir.join("wal.log"),
+            checkpoint_dir: data_dir.join("checkpoints"),
+            offload_dir: data_dir.join("low_freq"),
+            data_dir,
+        }
+    }
+
+    /// 没有数据根目录时，以数据文件所在目录作为根目录
+    pub fn from_data_file<P: AsRef<Path>>(data_file: P) -> Self {
+        let data_file = data_file.as_ref();
+        let data_dir = data_file
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut paths = Self::new(data_dir, "");
+        paths.data_file = data_file.to_path_buf();
+        paths
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub enable_default_expiry: bool,
     pub default_expiry_seconds: i64,
+    pub ttl_jitter_percent: u64,              // 过期时间随机抖动的百分比，0表示关闭
+    pub default_expiry_exempt_prefixes: Vec<String>, // 不应用默认过期时间的键前缀
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryConfig {
     pub enable_memory_optimization: bool,
     pub low_frequency_check_interval: u64,     // 秒
     pub access_threshold: u64,                // 访问次数阈值
     pub idle_time_threshold: u64,             // 闲置时间阈值(秒)
     pub max_memory_keys: usize,               // 内存中保留的最大键数
+    pub compress_offload: bool,               // 转移到磁盘的低频数据是否使用gzip压缩
+    pub intern_values: bool,                  // 相同的字符串值是否在键之间共享
+    pub result_cache_entries: usize,          // 只读命令结果缓存的最大条目数，0表示关闭
+    pub max_memory_bytes: usize,              // 内存中数据的字节数上限，0表示不限制
+    pub max_memory_policy: MaxMemoryPolicy,   // 超过字节数上限时的处理方式
+    pub lfu_decay_seconds: u64,               // 每经过这么多秒所有键的访问次数减半，0表示不衰减
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
     pub log_file: String,
     pub level: String,
+    pub log_commands: bool,                   // 记录每个连接执行的命令(带连接ID)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitoringConfig {
+    pub enable_metrics: bool,                 // 是否统计每种命令的调用次数和延迟
+    pub slowlog_threshold_ms: u64,            // 慢查询阈值(毫秒)
+    pub slowlog_max_len: usize,               // 慢查询日志最多保留的条数，0表示关闭
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub server: ServerConfig,
     pub persistence: PersistenceConfig,
     pub storage: StorageConfig,
     pub memory: MemoryConfig,
     pub logging: LoggingConfig,
+    pub monitoring: MonitoringConfig,
 }
 
-impl Settings {
-    pub fn new() -> Result<Self, ConfigError> {
-        let config_dir = "config";
-        let default_config_path = Path::new(config_dir).join("default.toml");
-
-        // 确保配置目录存在
-        if !Path::new(config_dir).exists() {
-            fs::create_dir_all(config_dir).map_err(|e| {
-                ConfigError::Message(format!("无法创建配置目录: {}", e))
-            })?;
-        }
-
-        // 检查配置文件是否存在，如果不存在则创建默认配置
-        if !default_config_path.exists() {
-            let default_config_content = r#"[server]
+/// 内置默认配置，作为所有配置来源的最底层
+pub const DEFAULT_CONFIG: &str = r#"[server]
 # 服务器监听端口
 port = 6379
 # 服务器IP地址
 host = "127.0.0.1"
+# 监听地址列表，非空时代替host和port，可同时监听IPv4、IPv6和Unix域套接字(unix:前缀)
+# 例如 listen = ["127.0.0.1:6379", "[::1]:6379", "unix:/tmp/kv.sock"]
+listen = []
+# 额外监听的Unix域套接字路径，供本机客户端低延迟访问，访问权限由套接字文件和所在目录的权限控制
+# unix_socket = "/tmp/kv.sock"
+# 是否允许DEBUG命令(如 DEBUG SLEEP)，仅用于调试
+enable_debug_commands = false
+# 是否允许运维命令(如 WAL DUMP、WAL INFO、CONFIG GET)，默认关闭
+enable_admin_commands = false
+# 连接协议: "line"(按行文本) 或 "binary"(4字节大端长度前缀分帧)
+protocol = "line"
+# 每个连接每秒允许执行的命令数，超出后返回错误直到令牌恢复；0表示不限制
+rate_limit_per_second = 0
+# 只读模式，所有修改数据的命令返回错误，可用于热备或调试控制台
+read_only = false
+# 数据库数量，连接通过SELECT选择0到databases-1号数据库；未启用persistence.per_database_files时所有数据库保存在同一个数据文件中
+databases = 16
+# 命令别名，键为别名、值为内置命令名，例如 command_aliases = { put = "set", fetch = "get" }
+command_aliases = {}
+# 是否允许别名覆盖同名的内置命令，默认与内置命令重名的别名会导致配置加载失败
+allow_alias_override = false
+# 无法识别的命令的处理方式: "strict" 返回错误, "lenient" 记录警告后返回OK(兼容会发送较新命令的客户端)
+unknown_commands = "strict"
+# 多值响应格式: "joined" 每个元素一行, "counted" 首行为*元素数、每个元素前一行为$字节数，元素中含有换行时也能准确解析
+array_replies = "joined"
+# 按行协议中单条命令行的最大字节数，超过时返回 ERROR: command too long 并丢弃该行，连接保持可用
+max_command_length = 67108864
 
 [persistence]
-# 数据持久化文件路径
+# 数据根目录，WAL、检查点和低频数据默认都位于该目录下；未设置时使用数据文件所在目录
+# data_dir = "data"
+# 数据持久化文件路径，设置data_dir时相对路径位于data_dir下
 data_file = "data/storage.dat"
 # 持久化方式: "none", "on_change", "interval"
 mode = "on_change"
 # 定时持久化的时间间隔(秒)，仅当mode为interval时有效
 interval_seconds = 300
+# 保留最近的检查点文件数，0表示全部保留
+checkpoint_retention = 5
+# 数据文件无法解析时的处理方式: "fail" 拒绝启动, "quarantine" 改名隔离后继续启动
+on_corrupt_data_file = "fail"
+# 每个数据库保存到单独的文件(storage.0.dat、storage.1.dat...)，便于单独备份和恢复某个数据库；
+# 关闭时只有0号数据库保存到data_file
+per_database_files = false
+# WAL同步策略: "always" 每条日志都同步, "every_sec" 批量写入且最多每秒同步一次, "no" 批量写入且不主动同步
+wal_sync = "always"
+# every_sec和no策略下，每缓冲多少条日志写入一次文件(缓冲区写满时也会写入)
+wal_batch_entries = 64
+# WAL日志超过该字节数时服务器定期自动压缩，0表示不自动压缩(仍可手动执行COMPACTWAL)
+wal_compact_threshold_bytes = 0
+# 检查WAL日志大小的时间间隔(秒)
+wal_compact_check_seconds = 60
+# 以下路径默认由数据根目录推导，可单独覆盖
+# wal_file = "data/wal.log"
+# checkpoint_dir = "data/checkpoints"
+# offload_dir = "data/low_freq"
 
 [storage]
 # 是否默认启用键过期
 enable_default_expiry = false
 # 默认键过期时间(秒)
 default_expiry_seconds = 3600
+# 过期时间随机抖动百分比(0-100)，实际生存时间在设定值的±该比例内随机分布，避免大量键同时过期；0表示关闭
+ttl_jitter_percent = 0
+# 不应用默认过期时间的键前缀，例如 ["config:", "permanent:"]
+default_expiry_exempt_prefixes = []
 
 [memory]
 # 是否启用内存优化
@@ -99,26 +267,216 @@ access_threshold = 100
 idle_time_threshold = 600
 # 内存中保留的最大键数
 max_memory_keys = 1000
+# 转移到磁盘的低频数据是否使用gzip压缩(文件扩展名为.json.gz)，目录中可以同时存在压缩和未压缩的文件
+compress_offload = false
+# 相同的字符串值是否在键之间共享同一份内存，适合大量键保存相同值的场景
+intern_values = false
+# 缓存SINTER等开销较大的只读命令结果的最大条目数，相关键被修改后缓存自动失效，0表示关闭
+result_cache_entries = 0
+# 内存中数据(键和值)的字节数上限，0表示不限制；按键数限制的max_memory_keys无法约束少量很大的值
+max_memory_bytes = 0
+# 超过字节数上限时的处理方式: "reject"(拒绝写入)、"offload"(将访问最少的键转移到磁盘)、
+# "all_keys_lfu"(删除访问最少的键) 或 "volatile_lfu"(只删除设置了过期时间的键中访问最少的)
+max_memory_policy = "reject"
+# 访问次数的衰减周期(秒)，每个周期所有键的访问次数减半，使很久以前的访问不再影响淘汰顺序，0表示不衰减
+lfu_decay_seconds = 0
 
 [logging]
 # 日志文件路径
 log_file = "logs/server.log"
 # 日志级别: "error", "warn", "info", "debug", "trace"
 level = "info"
+# 是否记录每个连接执行的命令、响应大小和耗时(日志行带连接ID)
+log_commands = false
+
+[monitoring]
+# 是否统计每种命令的调用次数和延迟(STATS COMMANDS)
+enable_metrics = true
+# 执行时间超过该阈值(毫秒)的命令记录到慢查询日志(SLOWLOG GET)
+slowlog_threshold_ms = 10
+# 慢查询日志最多保留的条数，0表示关闭
+slowlog_max_len = 128
 "#;
+
+/// 环境变量前缀，例如 KV_SERVER__PORT=7000
+pub const ENV_PREFIX: &str = "KV";
+
+/// 可以通过CONFIG SET在运行时修改的配置项，其余配置项需要重启生效
+pub const RUNTIME_PARAMS: &[&str] = &[
+    "logging.level",
+    "storage.enable_default_expiry",
+    "storage.default_expiry_seconds",
+    "memory.low_frequency_check_interval",
+];
+
+/// CONFIG GET和CONFIG DUMP中值被隐藏的配置项，名称包含这些词时视为密码等敏感信息
+const SENSITIVE_PARAM_WORDS: &[&str] = &["pass", "secret", "token"];
+
+/// 配置项的值转换为显示的文本，密码等敏感的值显示为`******`
+fn displayed_param_text(name: &str, value: &serde_json::Value) -> String {
+    let sensitive = SENSITIVE_PARAM_WORDS.iter().any(|word| name.to_lowercase().contains(word));
+    if sensitive && !value.is_null() { "******".to_string() } else { param_text(value) }
+}
+
+/// 配置项的值转换为文本，字符串不带引号，未设置的值为空
+fn param_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// 把各段配置渲染为按名称排序的`段.名称=值`行，密码等敏感的值显示为`******`
+pub fn render_params(settings: &serde_json::Value) -> Vec<String> {
+    let Some(sections) = settings.as_object() else {
+        return vec![];
+    };
+    let mut lines = Vec::new();
+    for (section, params) in sections {
+        let Some(params) = params.as_object() else {
+            continue;
+        };
+        for (name, value) in params {
+            lines.push(format!("{}.{}={}", section, name, displayed_param_text(name, value)));
+        }
+    }
+    lines
+}
+
+impl Settings {
+    /// 按`段.名称`读取配置项的值，例如`logging.level`，不存在时返回None；密码等敏感的值显示为`******`
+    pub fn get_param(&self, name: &str) -> Option<String> {
+        let settings = serde_json::to_value(self).ok()?;
+        settings
+            .pointer(&format!("/{}", name.replace('.', "/")))
+            .map(|value| displayed_param_text(name, value))
+    }
+
+    /// 实际生效的全部配置(包括环境变量和运行时的修改)，每行一个`段.名称=值`
+    pub fn dump(&self) -> Vec<String> {
+        serde_json::to_value(self).map(|settings| render_params(&settings)).unwrap_or_default()
+    }
+
+    /// 修改运行时可调整的配置项，值按配置项原有的类型解析
+    pub fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let current = self
+            .get_param(name)
+            .ok_or_else(|| format!("unknown config parameter {}", name))?;
+        if !RUNTIME_PARAMS.contains(&name) {
+            return Err(format!("config parameter {} cannot be changed at runtime", name));
+        }
+
+        let mut settings = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        let slot = settings
+            .pointer_mut(&format!("/{}", name.replace('.', "/")))
+            .ok_or_else(|| format!("unknown config parameter {}", name))?;
+        *slot = match slot {
+            serde_json::Value::String(_) => serde_json::Value::String(value.to_string()),
+            _ => serde_json::from_str(value).map_err(|_| {
+                format!("invalid value {} for config parameter {} (current value {})", value, name, current)
+            })?,
+        };
+        let updated: Settings = serde_json::from_value(settings)
+            .map_err(|e| format!("invalid value {} for config parameter {}: {}", value, name, e))?;
+        if crate::logger::parse_level(&updated.logging.level).is_none() {
+            return Err(format!("invalid log level {}", updated.logging.level));
+        }
+        *self = updated;
+        Ok(())
+    }
+
+    /// 数据文件、WAL、检查点和低频数据的路径
+    pub fn data_paths(&self) -> DataPaths {
+        let persistence = &self.persistence;
+        let mut paths = match &persistence.data_dir {
+            Some(data_dir) => DataPaths::new(data_dir, &persistence.data_file),
+            None => DataPaths::from_data_file(&persistence.data_file),
+        };
+        if let Some(wal_file) = &persistence.wal_file {
+            paths.wal_file = PathBuf::from(wal_file);
+        }
+        if let Some(checkpoint_dir) = &persistence.checkpoint_dir {
+            paths.checkpoint_dir = PathBuf::from(checkpoint_dir);
+        }
+        if let Some(offload_dir) = &persistence.offload_dir {
+            paths.offload_dir = PathBuf::from(offload_dir);
+        }
+        paths
+    }
+
+    pub fn new() -> Result<Self, ConfigError> {
+        let config_dir = "config";
+        let default_config_path = Path::new(config_dir).join("default.toml");
+
+        // 确保配置目录存在
+        if !Path::new(config_dir).exists() {
+            fs::create_dir_all(config_dir).map_err(|e| {
+                ConfigError::Message(format!("无法创建配置目录: {}", e))
+            })?;
+        }
+
+        // 检查配置文件是否存在，如果不存在则创建默认配置
+        if !default_config_path.exists() {
             let mut file = fs::File::create(&default_config_path).map_err(|e| {
                 ConfigError::Message(format!("无法创建配置文件: {}", e))
             })?;
             
-            file.write_all(default_config_content.as_bytes()).map_err(|e| {
+            file.write_all(DEFAULT_CONFIG.as_bytes()).map_err(|e| {
                 ConfigError::Message(format!("无法写入配置文件: {}", e))
             })?;
         }
 
+        Self::from_path(&default_config_path)
+    }
+
+    /// 从指定配置文件加载
+    ///
+    /// 优先级从低到高: 内置默认值 < 配置文件 < 环境变量(KV_前缀)
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(ConfigError::Message(format!(
+                "配置文件不存在: {}",
+                path.display()
+            )));
+        }
+
         let settings = Config::builder()
-            .add_source(File::from(default_config_path))
+            .add_source(File::from_str(DEFAULT_CONFIG, FileFormat::Toml))
+            .add_source(File::from(path))
+            .add_source(
+                Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true),
+            )
             .build()?;
 
-        settings.try_deserialize()
+        let mut settings: Settings = settings.try_deserialize()?;
+        settings.check_command_aliases()?;
+        Ok(settings)
     }
-}
\ No newline at end of file
+
+    /// 统一别名为小写并校验：目标必须是内置命令，未允许覆盖时别名不能与内置命令重名
+    fn check_command_aliases(&mut self) -> Result<(), ConfigError> {
+        let aliases = std::mem::take(&mut self.server.command_aliases);
+        for (alias, target) in aliases {
+            let (alias, target) = (alias.to_lowercase(), target.to_lowercase());
+            if !crate::command::is_builtin_command(&target) {
+                return Err(ConfigError::Message(format!(
+                    "命令别名 {} 的目标 {} 不是内置命令",
+                    alias, target
+                )));
+            }
+            if crate::command::is_builtin_command(&alias) && !self.server.allow_alias_override {
+                return Err(ConfigError::Message(format!(
+                    "命令别名 {} 与内置命令重名，需要设置 server.allow_alias_override = true",
+                    alias
+                )));
+            }
+            self.server.command_aliases.insert(alias, target);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use crate::command::{CommandHandler, CommandResult};
+use crate::config::Settings;
+use crate::store::{StoreManager, StoreResult};
+use std::sync::{Arc, RwLock};
+
+/// 进程内嵌入使用的存储，不需要启动服务器，直接执行与网络协议相同的命令行
+pub struct EmbeddedStore {
+    store_manager: StoreManager,
+    data_file: String,
+    command_handler: CommandHandler,
+}
+
+impl EmbeddedStore {
+    /// 创建空的存储，SAVE等命令和save方法把数据写入data_file
+    pub fn new(data_file: impl Into<String>) -> Self {
+        let data_file = data_file.into();
+        let store_manager = StoreManager::new();
+        let command_handler = CommandHandler::new(store_manager.clone(), data_file.clone());
+        EmbeddedStore { store_manager, data_file, command_handler }
+    }
+
+    /// 使用配置构建，数据文件改为配置中的路径，应在写入数据之前调用
+    pub fn with_settings(mut self, settings: Arc<Settings>) -> Self {
+        self.data_file = settings.data_paths().data_file.to_string_lossy().to_string();
+        self.store_manager = StoreManager::new().with_settings(Arc::clone(&settings));
+        self.command_handler = CommandHandler::new(self.store_manager.clone(), self.data_file.clone())
+            .with_runtime_config(Arc::new(RwLock::new((*settings).clone())))
+            .with_settings(settings);
+        self
+    }
+
+    /// 解析并执行一条命令，返回与服务器相同的响应文本（不含时间戳）
+    pub fn execute(&self, command_line: &str) -> String {
+        let command = self.command_handler.parse_command(command_line);
+        self.command_handler.execute_command(command)
+    }
+
+    /// 解析并执行一条命令，返回结构化的结果
+    pub fn execute_typed(&self, command_line: &str) -> CommandResult {
+        let command = self.command_handler.parse_command(command_line);
+        self.command_handler.execute_typed(command)
+    }
+
+    /// 从数据文件加载数据，文件不存在时创建空文件
+    pub fn load(&self) -> StoreResult<()> {
+        self.store_manager.load_from_file(&self.data_file)
+    }
+
+    /// 把数据保存到数据文件，返回写入的字节数
+    pub fn save(&self) -> StoreResult<usize> {
+        self.store_manager.save_to_file(&self.data_file)
+    }
+
+    /// 数据文件路径
+    pub fn data_file(&self) -> &str {
+        &self.data_file
+    }
+
+    /// 底层的存储管理器，可以直接调用类型化的接口
+    pub fn store_manager(&self) -> &StoreManager {
+        &self.store_manager
+    }
+}
@@ -1,12 +1,19 @@
 pub mod store;
+pub mod clock;
 pub mod config;
 pub mod command;
+pub mod embedded;
 pub mod logger;
+pub mod metrics;
+pub mod protocol;
+pub mod result_cache;
 pub mod transaction_cmd;
 
 // 重新导出一些常用的类型，使其他crate更容易使用
 pub use store::{Store, StoreManager};
-pub use command::{Command, CommandHandler};
+pub use command::{Command, CommandHandler, CommandResult};
+pub use embedded::EmbeddedStore;
 pub use config::Settings;
+pub use metrics::CommandMetrics;
 pub use store::{TransactionManager, Transaction, TransactionState, StoreOperation};
 pub use transaction_cmd::TransactionCommandHandler;
\ No newline at end of file
@@ -24,25 +24,39 @@ pub fn init_logger(log_file: &str, level: &str) -> Result<(), SetLoggerError> {
         });
 
     // 设置日志级别
-    let level_filter = match level.to_lowercase().as_str() {
-        "error" => LevelFilter::Error,
-        "warn" => LevelFilter::Warn,
-        "info" => LevelFilter::Info,
-        "debug" => LevelFilter::Debug,
-        "trace" => LevelFilter::Trace,
-        _ => LevelFilter::Info,
-    };
+    let level_filter = parse_level(level).unwrap_or(LevelFilter::Info);
 
-    // 同时初始化终端日志和文件日志
+    // 同时初始化终端日志和文件日志，各日志器不过滤级别，由全局最大级别控制，以便运行时调整
     CombinedLogger::init(vec![
         // 输出到终端的日志
         TermLogger::new(
-            level_filter,
+            LevelFilter::Trace,
             Config::default(),
             TerminalMode::Mixed,
             ColorChoice::Auto,
         ),
         // 输出到文件的日志
-        WriteLogger::new(level_filter, Config::default(), file),
-    ])
+        WriteLogger::new(LevelFilter::Trace, Config::default(), file),
+    ])?;
+    log::set_max_level(level_filter);
+    Ok(())
+}
+
+/// 解析日志级别名称，不区分大小写
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.to_lowercase().as_str() {
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+/// 运行时调整日志级别
+pub fn set_level(level: &str) -> Result<(), String> {
+    let level_filter = parse_level(level).ok_or_else(|| format!("invalid log level {}", level))?;
+    log::set_max_level(level_filter);
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 延迟直方图的桶上界(微秒)，最后一个桶收纳所有更慢的命令
+const LATENCY_BUCKETS_MICROS: [u64; 16] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000,
+    1_000_000, u64::MAX,
+];
+
+/// 单个命令类型的执行统计
+#[derive(Debug, Clone, Default)]
+pub struct CommandStat {
+    pub count: u64,
+    pub total_micros: u64,
+    pub max_micros: u64,
+    buckets: [u64; LATENCY_BUCKETS_MICROS.len()],
+}
+
+impl CommandStat {
+    /// 记录一次执行耗时
+    fn record(&mut self, micros: u64) {
+        self.count += 1;
+        self.total_micros = self.total_micros.saturating_add(micros);
+        self.max_micros = self.max_micros.max(micros);
+        let bucket = LATENCY_BUCKETS_MICROS
+            .iter()
+            .position(|&bound| micros <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MICROS.len() - 1);
+        self.buckets[bucket] += 1;
+    }
+
+    /// 平均耗时(微秒)
+    pub fn average_micros(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_micros as f64 / self.count as f64
+        }
+    }
+
+    /// 估算的p99耗时(微秒)，取p99所在桶的上界，不超过观测到的最大值
+    pub fn p99_micros(&self) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let target = self.count.saturating_mul(99).div_ceil(100);
+        let mut seen = 0;
+        for (i, &bucket_count) in self.buckets.iter().enumerate() {
+            seen += bucket_count;
+            if seen >= target {
+                return LATENCY_BUCKETS_MICROS[i].min(self.max_micros);
+            }
+        }
+        self.max_micros
+    }
+}
+
+/// 命令执行指标注册表，按命令类型统计调用次数和延迟
+#[derive(Debug, Default)]
+pub struct CommandMetrics {
+    stats: Mutex<HashMap<&'static str, CommandStat>>,
+}
+
+impl CommandMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次命令执行
+    pub fn record(&self, command: &'static str, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.stats
+            .lock()
+            .unwrap()
+            .entry(command)
+            .or_default()
+            .record(micros);
+    }
+
+    /// 获取指定命令的统计
+    pub fn get(&self, command: &str) -> Option<CommandStat> {
+        self.stats.lock().unwrap().get(command).cloned()
+    }
+
+    /// 获取所有命令的统计，按命令名排序
+    pub fn snapshot(&self) -> Vec<(&'static str, CommandStat)> {
+        let mut stats: Vec<(&'static str, CommandStat)> = self
+            .stats
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stat)| (*name, stat.clone()))
+            .collect();
+        stats.sort_by_key(|(name, _)| *name);
+        stats
+    }
+
+    /// 清空所有命令的统计
+    pub fn reset(&self) {
+        self.stats.lock().unwrap().clear();
+    }
+
+    /// 渲染为每个命令一行的文本
+    pub fn render(&self) -> String {
+        let stats = self.snapshot();
+        if stats.is_empty() {
+            return "(no commands recorded)".to_string();
+        }
+        stats
+            .iter()
+            .map(|(name, stat)| {
+                format!(
+                    "{}: calls={} avg={:.2}us p99={}us",
+                    name,
+                    stat.count,
+                    stat.average_micros(),
+                    stat.p99_micros()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// 慢查询日志条目
+#[derive(Debug, Clone)]
+pub struct SlowLogEntry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub duration_micros: u64,
+    pub command: String,
+}
+
+#[derive(Debug, Default)]
+struct SlowLogInner {
+    entries: VecDeque<SlowLogEntry>,
+    next_id: u64,
+}
+
+/// 慢查询日志，使用有界环形缓冲区保存最近的慢命令
+#[derive(Debug)]
+pub struct SlowLog {
+    threshold: Duration,
+    max_len: usize,
+    inner: Mutex<SlowLogInner>,
+}
+
+impl SlowLog {
+    pub fn new(threshold: Duration, max_len: usize) -> Self {
+        Self {
+            threshold,
+            max_len,
+            inner: Mutex::new(SlowLogInner::default()),
+        }
+    }
+
+    /// 慢查询阈值
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// 如果耗时超过阈值则记录命令，返回是否记录
+    pub fn record(&self, command: String, elapsed: Duration) -> bool {
+        if elapsed < self.threshold || self.max_len == 0 {
+            return false;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.entries.push_front(SlowLogEntry {
+            id,
+            timestamp,
+            duration_micros: u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX),
+            command,
+        });
+        inner.entries.truncate(self.max_len);
+        true
+    }
+
+    /// 获取最近的n条慢查询，最新的在前
+    pub fn get(&self, count: usize) -> Vec<SlowLogEntry> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.iter().take(count).cloned().collect()
+    }
+
+    /// 当前记录的条数
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 清空慢查询日志
+    pub fn reset(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+}
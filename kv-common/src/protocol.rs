@@ -0,0 +1,139 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+/// 单帧最大长度，防止恶意的长度前缀导致分配过多内存
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// 写入一帧：4字节大端长度前缀 + 负载
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("帧长度 {} 超过上限 {}", payload.len(), MAX_FRAME_LEN),
+        ));
+    }
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// 读取一帧，连接在帧边界处关闭时返回None
+///
+/// 尚未读到任何字节时的读取超时会原样返回给调用方；帧读取到一半时的超时会继续等待，避免丢失已读取的字节。
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 4];
+    if !read_full(reader, &mut header, true)? {
+        return Ok(None);
+    }
+
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("帧长度 {} 超过上限 {}", len, MAX_FRAME_LEN),
+        ));
+    }
+
+    let mut payload = vec![0u8; len];
+    read_full(reader, &mut payload, false)?;
+    Ok(Some(payload))
+}
+
+/// 读满缓冲区，at_boundary为true时在首个字节前遇到EOF返回false
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8], at_boundary: bool) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 && at_boundary => return Ok(false),
+            Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "连接在帧中途关闭")),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e)
+                if (filled > 0 || !at_boundary)
+                    && matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                continue
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// 文本协议的行缓冲区，命令行以`\n`或`\r\n`结尾，一次读取中的多条命令依次取出，不完整的行留到下次读取
+///
+/// 缓冲的是原始字节，被拆分到多次读取中的多字节UTF-8字符会在整行到达后再解码。
+/// 超过长度上限的行不会继续缓冲，该行剩余的字节在到达时直接丢弃，直到下一个换行。
+#[derive(Debug)]
+pub struct LineBuffer {
+    pending: Vec<u8>,
+    max_line_len: usize,
+    discarding: bool, // 正在丢弃超长行的剩余部分
+    overflowed: bool, // 未结束的行超过上限，尚未报告
+}
+
+impl Default for LineBuffer {
+    fn default() -> Self {
+        LineBuffer {
+            pending: Vec::new(),
+            max_line_len: MAX_FRAME_LEN,
+            discarding: false,
+            overflowed: false,
+        }
+    }
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置单条命令行的最大字节数（不含行尾），默认为MAX_FRAME_LEN
+    pub fn with_max_line_len(mut self, max_line_len: usize) -> Self {
+        self.max_line_len = max_line_len;
+        self
+    }
+
+    /// 追加读取到的字节，未结束的行超过上限时丢弃该行，由next_line报告错误
+    pub fn extend(&mut self, mut bytes: &[u8]) {
+        if self.discarding {
+            match bytes.iter().position(|&byte| byte == b'\n') {
+                Some(end) => {
+                    self.discarding = false;
+                    bytes = &bytes[end + 1..];
+                }
+                None => return,
+            }
+        }
+
+        self.pending.extend_from_slice(bytes);
+        let line_start = self.pending.iter().rposition(|&byte| byte == b'\n').map_or(0, |end| end + 1);
+        if self.pending.len() - line_start > self.max_line_len {
+            self.pending.truncate(line_start);
+            self.discarding = true;
+            self.overflowed = true;
+        }
+    }
+
+    /// 取出下一条完整命令行的原始字节，去掉行尾的`\n`或`\r\n`；超过长度上限的行返回InvalidData错误
+    pub fn next_line(&mut self) -> Option<io::Result<Vec<u8>>> {
+        let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') else {
+            // 完整的行都取出后再报告被丢弃的超长行，保持响应顺序
+            return std::mem::take(&mut self.overflowed).then(Self::too_long);
+        };
+        let mut line: Vec<u8> = self.pending.drain(..=end).collect();
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if line.len() > self.max_line_len {
+            return Some(Self::too_long());
+        }
+        Some(Ok(line))
+    }
+
+    fn too_long() -> io::Result<Vec<u8>> {
+        Err(io::Error::new(ErrorKind::InvalidData, "command too long"))
+    }
+}
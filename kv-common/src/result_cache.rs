@@ -0,0 +1,92 @@
+use crate::command::CommandResult;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// 缓存的命令结果，以及计算结果时各个相关键的版本号
+#[derive(Debug)]
+struct CachedResult {
+    versions: Vec<u64>,
+    response: CommandResult,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    results: HashMap<String, CachedResult>,
+    order: VecDeque<String>, // 写入顺序，超过容量时淘汰最早写入的条目
+}
+
+/// 有界的命令结果缓存，按命令和参数缓存开销较大的只读命令的结果
+///
+/// 每个条目记录计算结果时相关键的版本号，任何一个键被修改后版本号改变，条目随之失效。
+#[derive(Debug)]
+pub struct ResultCache {
+    capacity: usize,
+    entries: Mutex<CacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResultCache {
+    /// 创建最多保存capacity个结果的缓存
+    pub fn new(capacity: usize) -> Self {
+        ResultCache {
+            capacity,
+            entries: Mutex::new(CacheEntries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 查找缓存的结果，相关键的版本号与缓存时不一致时视为未命中
+    pub fn get(&self, key: &str, versions: &[u64]) -> Option<CommandResult> {
+        let entries = self.entries.lock().unwrap();
+        match entries.results.get(key) {
+            Some(cached) if cached.versions == versions => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(cached.response.clone())
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// 保存结果，覆盖同一命令的旧结果，超过容量时淘汰最早写入的条目
+    pub fn insert(&self, key: String, versions: Vec<u64>, response: CommandResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let cached = CachedResult { versions, response };
+        if entries.results.insert(key.clone(), cached).is_none() {
+            entries.order.push_back(key);
+            while entries.order.len() > self.capacity {
+                if let Some(oldest) = entries.order.pop_front() {
+                    entries.results.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    /// 缓存中的结果数量
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().results.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 命中次数
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// 未命中次数
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
@@ -1,11 +1,14 @@
 use std::collections::{HashMap, VecDeque, HashSet};
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 /// 存储系统中支持的数据类型
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DataType {
     /// 字符串类型
-    String(String),
+    String(Arc<str>),
+    /// 字节串类型，保存不是合法UTF-8的二进制值，序列化为base64
+    Bytes(#[serde(with = "base64_bytes")] Vec<u8>),
     /// 列表类型（双向队列实现）
     List(VecDeque<String>),
     /// 哈希表类型
@@ -15,25 +18,55 @@ pub enum DataType {
 }
 
 impl DataType {
+    /// 所有类型的名称，与type_name的返回值一致
+    pub const TYPE_NAMES: &'static [&'static str] = &["string", "bytes", "list", "hash", "set"];
+
     /// 获取数据类型名称
     pub fn type_name(&self) -> &'static str {
         match self {
             DataType::String(_) => "string",
+            DataType::Bytes(_) => "bytes",
             DataType::List(_) => "list",
             DataType::Hash(_) => "hash",
             DataType::Set(_) => "set",
         }
     }
 
+    /// 从序列化结果的开头(形如 `{"Hash":`)识别类型，不需要反序列化整个值
+    pub fn type_name_from_serialized(prefix: &[u8]) -> Option<&'static str> {
+        let tag = prefix.strip_prefix(b"{\"")?;
+        let end = tag.iter().position(|&byte| byte == b'"')?;
+        match &tag[..end] {
+            b"String" => Some("string"),
+            b"Bytes" => Some("bytes"),
+            b"List" => Some("list"),
+            b"Hash" => Some("hash"),
+            b"Set" => Some("set"),
+            _ => None,
+        }
+    }
+
     /// 检查是否为指定类型
     pub fn is_type(&self, type_name: &str) -> bool {
         self.type_name() == type_name
     }
 
+    /// 获取元素数量，字符串和字节串返回字节长度
+    pub fn element_count(&self) -> usize {
+        match self {
+            DataType::String(s) => s.len(),
+            DataType::Bytes(bytes) => bytes.len(),
+            DataType::List(list) => list.len(),
+            DataType::Hash(map) => map.len(),
+            DataType::Set(set) => set.len(),
+        }
+    }
+
     /// 获取数据的字节大小估算
     pub fn estimated_size(&self) -> usize {
         match self {
             DataType::String(s) => s.len(),
+            DataType::Bytes(bytes) => bytes.len(),
             DataType::List(list) => list.iter().map(|s| s.len()).sum::<usize>() + list.len() * 8,
             DataType::Hash(map) => {
                 map.iter().map(|(k, v)| k.len() + v.len()).sum::<usize>() + map.len() * 16
@@ -47,6 +80,51 @@ impl DataType {
 
 impl Default for DataType {
     fn default() -> Self {
-        DataType::String(String::new())
+        DataType::String(Arc::from(""))
+    }
+}
+
+/// 导出数据中的一个键，每个键序列化为一行JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub key: String,
+    pub value: DataType,
+    /// 剩余生存时间(秒)，没有过期时间时省略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+/// 一个键的完整状态，WAL记录键的修改和检查点保存数据时使用
+///
+/// 过期时间都是Unix时间戳(秒)，恢复时不受记录和恢复之间经过的时间影响。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeySnapshot {
+    pub value: DataType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire_at: Option<u64>,
+    /// 哈希字段的过期时间，没有时省略
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub field_expire_at: HashMap<String, u64>,
+}
+
+impl KeySnapshot {
+    /// 没有过期时间的快照
+    pub fn new(value: DataType) -> Self {
+        KeySnapshot { value, expire_at: None, field_expire_at: HashMap::new() }
+    }
+}
+
+/// 以base64字符串序列化字节串
+mod base64_bytes {
+    use base64::prelude::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(encoded).map_err(serde::de::Error::custom)
     }
 }
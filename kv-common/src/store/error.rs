@@ -47,6 +47,24 @@ impl fmt::Display for StoreError {
     }
 }
 
+impl StoreError {
+    /// 机器可读的错误码，与Redis的错误前缀约定一致
+    pub fn code(&self) -> &'static str {
+        match self {
+            StoreError::KeyNotFound(_) => "NOKEY",
+            StoreError::TypeMismatch { .. } => "WRONGTYPE",
+            StoreError::SerializationError(_) | StoreError::DeserializationError(_) => "SERIALIZATION",
+            StoreError::IoError(_) => "IO",
+            StoreError::OutOfMemory => "OOM",
+            StoreError::KeyExpired(_) => "EXPIRED",
+            StoreError::TransactionError(_) => "TXN",
+            StoreError::WalError(_) => "WAL",
+            StoreError::ConfigError(_) => "CONFIG",
+            StoreError::General(_) => "ERR",
+        }
+    }
+}
+
 impl std::error::Error for StoreError {}
 
 /// 存储操作结果类型
@@ -1,40 +1,86 @@
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use rand::Rng;
+use crate::clock::{system_clock, Clock};
 use super::error::StoreResult;
 
+/// GETEX等命令读取值时对过期时间的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryOption {
+    Keep,    // 保持原有的过期时间
+    Ex(u64), // 重新设置过期秒数
+    Persist, // 移除过期时间
+}
+
 /// 过期时间管理器
 #[derive(Debug, Clone)]
 pub struct ExpiryManager {
     expire_times: HashMap<String, u64>, // 键过期时间 (Unix时间戳)
+    field_expire_times: HashMap<String, HashMap<String, u64>>, // 哈希字段过期时间 (键 -> 字段 -> Unix时间戳)
+    jitter_percent: u64, // 过期时间随机抖动的百分比，0表示不抖动
+    clock: Arc<dyn Clock>, // 判断过期所用的时间来源
 }
 
 impl ExpiryManager {
     pub fn new() -> Self {
         Self {
             expire_times: HashMap::new(),
+            field_expire_times: HashMap::new(),
+            jitter_percent: 0,
+            clock: system_clock(),
         }
     }
 
     /// 从现有的过期时间映射创建管理器
     pub fn from_map(expire_times: HashMap<String, u64>) -> Self {
-        Self { expire_times }
+        Self { expire_times, field_expire_times: HashMap::new(), jitter_percent: 0, clock: system_clock() }
+    }
+
+    /// 使用指定的时钟判断过期，测试中可注入手动推进的时钟
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置过期时间抖动，实际生存时间在请求值的±percent%内随机分布
+    pub fn with_jitter(mut self, percent: u64) -> Self {
+        self.jitter_percent = percent.min(100);
+        self
+    }
+
+    /// 对生存时间应用随机抖动，结果至少为1秒
+    fn jittered_seconds(&self, seconds: u64) -> u64 {
+        let max_offset = seconds.saturating_mul(self.jitter_percent) / 100;
+        if max_offset == 0 {
+            return seconds;
+        }
+        let offset = rand::rng().random_range(0..=max_offset * 2);
+        (seconds - max_offset + offset).max(1)
+    }
+
+    /// 当前时间(Unix时间戳)，使用注入的时钟
+    pub fn now_secs(&self) -> u64 {
+        self.current_timestamp()
     }
 
     /// 获取当前时间戳
-    fn current_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs()
+    fn current_timestamp(&self) -> u64 {
+        self.clock.now_secs()
     }
 
     /// 设置键的过期时间
     pub fn set_expire(&mut self, key: &str, seconds: u64) -> StoreResult<()> {
-        let expire_time = Self::current_timestamp() + seconds;
+        let expire_time = self.current_timestamp() + self.jittered_seconds(seconds);
         self.expire_times.insert(key.to_string(), expire_time);
         Ok(())
     }
 
+    /// 设置键的过期时间，不添加随机偏移
+    pub fn set_expire_exact(&mut self, key: &str, seconds: u64) -> StoreResult<()> {
+        let expire_time = self.current_timestamp() + seconds;
+        self.set_expire_at(key, expire_time)
+    }
+
     /// 设置键的绝对过期时间
     pub fn set_expire_at(&mut self, key: &str, timestamp: u64) -> StoreResult<()> {
         self.expire_times.insert(key.to_string(), timestamp);
@@ -44,7 +90,7 @@ impl ExpiryManager {
     /// 检查键是否已过期
     pub fn is_expired(&self, key: &str) -> bool {
         if let Some(expire_time) = self.expire_times.get(key) {
-            Self::current_timestamp() >= *expire_time
+            self.current_timestamp() >= *expire_time
         } else {
             false
         }
@@ -53,7 +99,7 @@ impl ExpiryManager {
     /// 获取键的剩余生存时间（秒）
     pub fn get_ttl(&self, key: &str) -> i64 {
         if let Some(expire_time) = self.expire_times.get(key) {
-            let current_time = Self::current_timestamp();
+            let current_time = self.current_timestamp();
             if current_time >= *expire_time {
                 -2 // 已过期
             } else {
@@ -76,7 +122,7 @@ impl ExpiryManager {
 
     /// 清理所有过期的键，返回过期的键列表
     pub fn find_expired_keys(&self) -> Vec<String> {
-        let current_time = Self::current_timestamp();
+        let current_time = self.current_timestamp();
         
         self.expire_times
             .iter()
@@ -99,7 +145,7 @@ impl ExpiryManager {
 
     /// 检查并返回需要清理的过期键数量
     pub fn count_expired_keys(&self) -> usize {
-        let current_time = Self::current_timestamp();
+        let current_time = self.current_timestamp();
         
         self.expire_times
             .values()
@@ -122,26 +168,23 @@ impl ExpiryManager {
         self.expire_times.keys().cloned().collect()
     }
 
-    /// 获取即将过期的键（在指定秒数内过期）
+    /// 获取即将过期的键（在指定秒数内过期），按剩余生存时间从短到长排序
     pub fn get_expiring_soon(&self, within_seconds: u64) -> Vec<String> {
-        let current_time = Self::current_timestamp();
+        let current_time = self.current_timestamp();
         let threshold = current_time + within_seconds;
         
-        self.expire_times
+        let mut expiring: Vec<(u64, &String)> = self.expire_times
             .iter()
-            .filter_map(|(key, expire_time)| {
-                if *expire_time <= threshold && *expire_time > current_time {
-                    Some(key.clone())
-                } else {
-                    None
-                }
-            })
-            .collect()
+            .filter(|(_, expire_time)| **expire_time <= threshold && **expire_time > current_time)
+            .map(|(key, expire_time)| (*expire_time, key))
+            .collect();
+        expiring.sort();
+        expiring.into_iter().map(|(_, key)| key.clone()).collect()
     }
 
     /// 获取过期时间统计信息
     pub fn get_expiry_stats(&self) -> ExpiryStats {
-        let current_time = Self::current_timestamp();
+        let current_time = self.current_timestamp();
         let mut expired_count = 0;
         let mut expiring_soon_count = 0; // 1小时内过期
         let total_with_expiry = self.expire_times.len();
@@ -177,10 +220,111 @@ impl ExpiryManager {
     /// 清空所有过期时间设置
     pub fn clear(&mut self) {
         self.expire_times.clear();
+        self.field_expire_times.clear();
+    }
+
+    /// 设置哈希字段的过期时间，不添加随机偏移
+    pub fn set_field_expire(&mut self, key: &str, field: &str, seconds: u64) {
+        let expire_time = self.current_timestamp() + seconds;
+        self.field_expire_times
+            .entry(key.to_string())
+            .or_default()
+            .insert(field.to_string(), expire_time);
+    }
+
+    /// 设置哈希字段的绝对过期时间
+    pub fn set_field_expire_at(&mut self, key: &str, field: &str, timestamp: u64) {
+        self.field_expire_times
+            .entry(key.to_string())
+            .or_default()
+            .insert(field.to_string(), timestamp);
+    }
+
+    /// 检查哈希字段是否已过期
+    pub fn is_field_expired(&self, key: &str, field: &str) -> bool {
+        self.field_expire_times
+            .get(key)
+            .and_then(|fields| fields.get(field))
+            .is_some_and(|&expire_time| self.current_timestamp() >= expire_time)
+    }
+
+    /// 获取哈希字段的剩余生存时间（秒），-1表示没有过期时间，-2表示已过期
+    pub fn get_field_ttl(&self, key: &str, field: &str) -> i64 {
+        match self.field_expire_times.get(key).and_then(|fields| fields.get(field)) {
+            Some(&expire_time) => {
+                let current_time = self.current_timestamp();
+                if current_time >= expire_time {
+                    -2
+                } else {
+                    (expire_time - current_time) as i64
+                }
+            }
+            None => -1,
+        }
+    }
+
+    /// 移除哈希字段的过期时间
+    pub fn remove_field_expire(&mut self, key: &str, field: &str) -> bool {
+        let Some(fields) = self.field_expire_times.get_mut(key) else {
+            return false;
+        };
+        let removed = fields.remove(field).is_some();
+        if fields.is_empty() {
+            self.field_expire_times.remove(key);
+        }
+        removed
+    }
+
+    /// 移除键所有字段的过期时间
+    pub fn remove_field_expires(&mut self, key: &str) {
+        self.field_expire_times.remove(key);
+    }
+
+    /// 键是否有设置了过期时间的字段
+    pub fn has_field_expiry(&self, key: &str) -> bool {
+        self.field_expire_times.contains_key(key)
     }
 
-    /// 重命名键的过期时间设置
+    /// 键中已过期的字段
+    pub fn expired_fields(&self, key: &str) -> Vec<String> {
+        let current_time = self.current_timestamp();
+        self.field_expire_times
+            .get(key)
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter(|(_, &expire_time)| current_time >= expire_time)
+                    .map(|(field, _)| field.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 有已过期字段的键
+    pub fn keys_with_expired_fields(&self) -> Vec<String> {
+        let current_time = self.current_timestamp();
+        self.field_expire_times
+            .iter()
+            .filter(|(_, fields)| fields.values().any(|&expire_time| current_time >= expire_time))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// 导出哈希字段过期时间映射（用于序列化）
+    pub fn export_field_expire_times(&self) -> &HashMap<String, HashMap<String, u64>> {
+        &self.field_expire_times
+    }
+
+    /// 导入哈希字段过期时间映射（用于反序列化）
+    pub fn import_field_expire_times(&mut self, field_expire_times: HashMap<String, HashMap<String, u64>>) {
+        self.field_expire_times = field_expire_times;
+    }
+
+    /// 重命名键的过期时间设置，哈希字段的过期时间一起转移
     pub fn rename_key(&mut self, old_key: &str, new_key: &str) -> bool {
+        if let Some(fields) = self.field_expire_times.remove(old_key) {
+            self.field_expire_times.insert(new_key.to_string(), fields);
+        }
         if let Some(expire_time) = self.expire_times.remove(old_key) {
             self.expire_times.insert(new_key.to_string(), expire_time);
             true
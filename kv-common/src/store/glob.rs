@@ -0,0 +1,75 @@
+/// 按Redis风格的glob模式匹配字符串
+///
+/// 支持 `*`(任意个字符)、`?`(单个字符)、`[abc]`/`[a-z]`/`[^a]`(字符集合) 和 `\` 转义。
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // 最近一个*的位置及其当前匹配到的文本位置，失配时从这里回溯
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if pattern.get(p) == Some(&'*') {
+            backtrack = Some((p, t));
+            p += 1;
+            continue;
+        }
+        if let Some(next) = match_one(&pattern, p, text[t]) {
+            p = next;
+            t += 1;
+            continue;
+        }
+        match backtrack {
+            Some((star, matched)) => {
+                // 让*多匹配一个字符
+                backtrack = Some((star, matched + 1));
+                p = star + 1;
+                t = matched + 1;
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// 匹配模式中位置p处的单个字符元素，成功时返回下一个元素的位置
+fn match_one(pattern: &[char], p: usize, c: char) -> Option<usize> {
+    match *pattern.get(p)? {
+        '?' => Some(p + 1),
+        '\\' if p + 1 < pattern.len() => (pattern[p + 1] == c).then_some(p + 2),
+        '[' => match_class(pattern, p, c),
+        literal => (literal == c).then_some(p + 1),
+    }
+}
+
+/// 匹配以位置p处的 `[` 开始的字符集合，没有闭合的 `]` 时把 `[` 当作普通字符
+fn match_class(pattern: &[char], p: usize, c: char) -> Option<usize> {
+    let mut i = p + 1;
+    let negate = pattern.get(i) == Some(&'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    while i < pattern.len() && pattern[i] != ']' {
+        let mut low = pattern[i];
+        if low == '\\' && i + 1 < pattern.len() {
+            i += 1;
+            low = pattern[i];
+        }
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            let high = pattern[i + 2];
+            let (low, high) = if low <= high { (low, high) } else { (high, low) };
+            matched |= low <= c && c <= high;
+            i += 3;
+        } else {
+            matched |= low == c;
+            i += 1;
+        }
+    }
+
+    if i >= pattern.len() {
+        return ('[' == c).then_some(p + 1);
+    }
+    (matched != negate).then_some(i + 1)
+}
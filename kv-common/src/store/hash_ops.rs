@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use super::data_types::DataType;
 use super::error::{StoreError, StoreResult};
+use rand::seq::SliceRandom;
+use rand::prelude::*;
 
 pub struct HashHandler;
 
@@ -35,23 +37,6 @@ impl HashHandler {
         }
     }
 
-    /// 获取哈希字段值的内部实现
-    pub fn hget_internal(
-        data: &HashMap<String, DataType>,
-        key: &str,
-        field: &str,
-    ) -> StoreResult<Option<String>> {
-        match data.get(key) {
-            Some(DataType::Hash(hash)) => Ok(hash.get(field).cloned()),
-            Some(_) => Err(StoreError::TypeMismatch {
-                key: key.to_string(),
-                expected: "hash".to_string(),
-                found: data.get(key).unwrap().type_name().to_string(),
-            }),
-            None => Ok(None),
-        }
-    }
-
     /// 删除哈希字段的内部实现
     pub fn hdel_internal(
         data: &mut HashMap<String, DataType>,
@@ -86,124 +71,28 @@ impl HashHandler {
         }
     }
 
-    /// 获取所有哈希字段的内部实现
-    pub fn hkeys_internal(
-        data: &HashMap<String, DataType>,
-        key: &str,
-    ) -> StoreResult<Vec<String>> {
-        match data.get(key) {
-            Some(DataType::Hash(hash)) => Ok(hash.keys().cloned().collect()),
-            Some(_) => Err(StoreError::TypeMismatch {
-                key: key.to_string(),
-                expected: "hash".to_string(),
-                found: data.get(key).unwrap().type_name().to_string(),
-            }),
-            None => Ok(vec![]),
-        }
-    }
-
-    /// 获取所有哈希值的内部实现
-    pub fn hvals_internal(
-        data: &HashMap<String, DataType>,
-        key: &str,
-    ) -> StoreResult<Vec<String>> {
-        match data.get(key) {
-            Some(DataType::Hash(hash)) => Ok(hash.values().cloned().collect()),
-            Some(_) => Err(StoreError::TypeMismatch {
-                key: key.to_string(),
-                expected: "hash".to_string(),
-                found: data.get(key).unwrap().type_name().to_string(),
-            }),
-            None => Ok(vec![]),
-        }
-    }
-
-    /// 获取哈希字段数量的内部实现
-    pub fn hlen_internal(
-        data: &HashMap<String, DataType>,
-        key: &str,
-    ) -> StoreResult<usize> {
-        match data.get(key) {
-            Some(DataType::Hash(hash)) => Ok(hash.len()),
-            Some(_) => Err(StoreError::TypeMismatch {
-                key: key.to_string(),
-                expected: "hash".to_string(),
-                found: data.get(key).unwrap().type_name().to_string(),
-            }),
-            None => Ok(0),
-        }
-    }
-
-    /// 获取所有哈希字段和值的内部实现
-    pub fn hgetall_internal(
-        data: &HashMap<String, DataType>,
-        key: &str,
-    ) -> StoreResult<HashMap<String, String>> {
-        match data.get(key) {
-            Some(DataType::Hash(hash)) => Ok(hash.clone()),
-            Some(_) => Err(StoreError::TypeMismatch {
-                key: key.to_string(),
-                expected: "hash".to_string(),
-                found: data.get(key).unwrap().type_name().to_string(),
-            }),
-            None => Ok(HashMap::new()),
-        }
-    }
-
-    /// 批量设置哈希字段的内部实现
-    pub fn hmset_internal(
-        data: &mut HashMap<String, DataType>,
-        key: String,
-        field_values: Vec<(String, String)>,
-    ) -> StoreResult<()> {
-        match data.get_mut(&key) {
-            Some(DataType::Hash(hash)) => {
-                for (field, value) in field_values {
-                    hash.insert(field, value);
-                }
-                Ok(())
-            }
-            Some(_) => {
-                // 类型不匹配，替换为哈希类型
-                let mut new_hash = HashMap::new();
-                for (field, value) in field_values {
-                    new_hash.insert(field, value);
-                }
-                data.insert(key, DataType::Hash(new_hash));
-                Ok(())
+    /// 按HRANDFIELD的count语义从字段列表中随机选取
+    pub fn random_entries(entries: Vec<(String, String)>, count: Option<isize>) -> Vec<(String, String)> {
+        let mut rng = rand::rng();
+        match count {
+            // 返回一个随机字段
+            None => entries.choose(&mut rng).cloned().into_iter().collect(),
+            Some(n) if n >= 0 => {
+                // 返回最多 n 个不重复的随机字段
+                let mut selected = entries;
+                selected.shuffle(&mut rng);
+                selected.truncate(n as usize);
+                selected
             }
-            None => {
-                // 新键
-                let mut new_hash = HashMap::new();
-                for (field, value) in field_values {
-                    new_hash.insert(field, value);
+            Some(n) => {
+                // 返回 |n| 个可能重复的随机字段
+                if entries.is_empty() {
+                    return vec![];
                 }
-                data.insert(key, DataType::Hash(new_hash));
-                Ok(())
+                (0..n.unsigned_abs())
+                    .filter_map(|_| entries.choose(&mut rng).cloned())
+                    .collect()
             }
         }
     }
-
-    /// 批量获取哈希字段值的内部实现
-    pub fn hmget_internal(
-        data: &HashMap<String, DataType>,
-        key: &str,
-        fields: &[String],
-    ) -> StoreResult<Vec<Option<String>>> {
-        match data.get(key) {
-            Some(DataType::Hash(hash)) => {
-                let values = fields
-                    .iter()
-                    .map(|field| hash.get(field).cloned())
-                    .collect();
-                Ok(values)
-            }
-            Some(_) => Err(StoreError::TypeMismatch {
-                key: key.to_string(),
-                expected: "hash".to_string(),
-                found: data.get(key).unwrap().type_name().to_string(),
-            }),
-            None => Ok(vec![None; fields.len()]),
-        }
-    }
 }
@@ -2,6 +2,13 @@ use std::collections::{HashMap, VecDeque};
 use super::data_types::DataType;
 use super::error::{StoreError, StoreResult};
 
+/// 列表的一端
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
 pub struct ListHandler;
 
 impl ListHandler {
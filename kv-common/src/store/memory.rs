@@ -27,27 +27,23 @@ impl MemoryManager {
         }
     }
 
-    /// 获取低频访问的键
+    /// 获取低频访问的键，current_time为当前Unix时间戳
     pub fn get_low_frequency_keys(
         &self,
         data: &HashMap<String, DataType>,
         metadata: &HashMap<String, DataMetadata>,
+        current_time: u64,
     ) -> Vec<String> {
         if !self.enable_optimization || data.len() <= self.max_memory_keys {
             return vec![];
         }
 
-        let current_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-
         let mut candidates: Vec<(String, &DataMetadata)> = metadata
             .iter()
             .filter(|(key, meta)| {
                 data.contains_key(*key)
                     && (meta.access_count < self.access_threshold
-                        || (current_time - meta.last_access_time) > self.idle_time_threshold)
+                        || meta.idle_time(current_time) > self.idle_time_threshold)
             })
             .map(|(key, meta)| (key.clone(), meta))
             .collect();
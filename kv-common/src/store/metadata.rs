@@ -1,4 +1,3 @@
-use std::time::{SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
 
 /// 数据项元信息
@@ -16,31 +15,9 @@ pub struct DataMetadata {
     pub size: usize,
 }
 
-impl Default for DataMetadata {
-    fn default() -> Self {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        
-        Self {
-            access_count: 0,
-            last_access_time: now,
-            created_time: now,
-            modified_time: now,
-            size: 0,
-        }
-    }
-}
-
 impl DataMetadata {
-    /// 创建新的元数据
-    pub fn new(size: usize) -> Self {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        
+    /// 创建新的元数据，now为当前Unix时间戳，由存储注入的时钟提供
+    pub fn new(size: usize, now: u64) -> Self {
         Self {
             access_count: 1,
             last_access_time: now,
@@ -51,34 +28,73 @@ impl DataMetadata {
     }
 
     /// 记录访问
-    pub fn access(&mut self) {
+    pub fn access(&mut self, now: u64) {
         self.access_count += 1;
-        self.last_access_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        self.last_access_time = now;
+    }
+
+    /// 清零访问次数并把上次访问时间设为当前时间
+    pub fn reset_access(&mut self, now: u64) {
+        self.access_count = 0;
+        self.last_access_time = now;
     }
 
     /// 记录修改
-    pub fn modify(&mut self, new_size: usize) {
-        self.modified_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+    pub fn modify(&mut self, new_size: usize, now: u64) {
+        self.modified_time = now;
         self.size = new_size;
-        self.access();
+        self.access(now);
     }
 
     /// 获取闲置时间（秒）
-    pub fn idle_time(&self) -> u64 {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+    pub fn idle_time(&self, now: u64) -> u64 {
         now.saturating_sub(self.last_access_time)
     }
 }
 
+/// DEBUG OBJECT返回的键内部信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    pub type_name: &'static str, // 数据类型
+    pub length: usize,           // 元素数量，字符串为字节长度
+    pub serialized_size: usize,  // 估算的序列化大小（字节）
+    pub access_count: u64,       // 访问次数
+    pub offloaded: bool,         // 是否已转移到磁盘
+}
+
+impl std::fmt::Display for ObjectInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "type:{} length:{} serializedlength:{} access_count:{} offloaded:{}",
+            self.type_name,
+            self.length,
+            self.serialized_size,
+            self.access_count,
+            u8::from(self.offloaded)
+        )
+    }
+}
+
+/// DEBUG LIST返回的列表内部统计，用于发现需要裁剪的大列表
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListInfo {
+    pub length: usize,                // 元素数量
+    pub total_bytes: usize,           // 估算的总大小（字节），与DEBUG OBJECT的serializedlength一致
+    pub average_element_bytes: f64,   // 元素的平均字节长度
+    pub largest_element_bytes: usize, // 最大元素的字节长度
+}
+
+impl std::fmt::Display for ListInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "length:{} total_bytes:{} avg_element_bytes:{:.2} largest_element_bytes:{}",
+            self.length, self.total_bytes, self.average_element_bytes, self.largest_element_bytes
+        )
+    }
+}
+
 /// 内存压力监控结构
 #[derive(Debug, Clone, Default)]
 pub struct MemoryPressure {
@@ -17,11 +17,12 @@ mod hash_ops;
 mod set_ops;
 mod store_core;
 mod store_manager;
+mod glob;
 
 // Export WAL and transaction types (existing)
 pub use wal::{
-    WriteAheadLog, LogEntry, LogCommand, Checkpoint, 
-    WalError, WalResult
+    WriteAheadLog, LogEntry, LogCommand, Checkpoint, CheckpointInfo, CheckpointRecord, KeyImage, RecoveredDatabases,
+    SyncProgress, WalError, WalResult, WalAnomaly, WalValidationReport
 };
 
 pub use transaction::{
@@ -29,17 +30,19 @@ pub use transaction::{
 };
 
 pub use self::store_transaction::StoreTransactionExt;
-pub use self::store_transaction::TransactionStoreManager;
 
 // Export new modular types
 pub use error::{StoreError, StoreResult};
-pub use data_types::DataType;
-pub use metadata::DataMetadata;
+pub use data_types::{DataType, ExportRecord, KeySnapshot};
+pub use metadata::{DataMetadata, ListInfo, ObjectInfo};
 pub use memory::{MemoryManager, OptimizationStrategy};
-pub use expiry::ExpiryManager;
+pub use expiry::{ExpiryManager, ExpiryOption};
 pub use traits::{
     StoreOperations, StringOperations, ListOperations, 
     HashOperations, SetOperations
 };
 pub use store_core::Store;
-pub use store_manager::StoreManager;
\ No newline at end of file
+pub use glob::glob_match;
+pub use list_ops::ListEnd;
+pub use store_manager::{SaveJobState, SaveStatus, StoreManager, WalCompactionState};
+pub(crate) use store_manager::write_file_atomically;
\ No newline at end of file
@@ -8,41 +8,28 @@ pub struct SetHandler;
 
 #[allow(dead_code)]
 impl SetHandler {
-    /// 添加集合成员的内部实现
+    /// 添加集合成员的内部实现，返回新增成员数和按添加顺序排列的新增成员
     pub fn sadd_internal(
         data: &mut HashMap<String, DataType>,
         key: String,
         members: Vec<String>,
-    ) -> StoreResult<usize> {
-        match data.get_mut(&key) {
-            Some(DataType::Set(set)) => {
-                let initial_size = set.len();
-                for member in members {
-                    set.insert(member);
-                }
-                Ok(set.len() - initial_size)
-            }
-            Some(_) => {
-                // 类型不匹配，替换为集合类型
-                let mut new_set = HashSet::new();
-                let added_count = members.len();
-                for member in members {
-                    new_set.insert(member);
-                }
-                data.insert(key, DataType::Set(new_set));
-                Ok(added_count)
-            }
-            None => {
-                // 新键
-                let mut new_set = HashSet::new();
-                let added_count = members.len();
-                for member in members {
-                    new_set.insert(member);
-                }
-                data.insert(key, DataType::Set(new_set));
-                Ok(added_count)
+    ) -> StoreResult<(usize, Vec<String>)> {
+        let set = match data.entry(key.clone()).or_insert_with(|| DataType::Set(HashSet::new())) {
+            DataType::Set(set) => set,
+            other => {
+                // 已有其他类型的值时不覆盖
+                return Err(StoreError::TypeMismatch {
+                    key,
+                    expected: "set".to_string(),
+                    found: other.type_name().to_string(),
+                });
             }
-        }
+        };
+        let added: Vec<String> = members
+            .into_iter()
+            .filter(|member| set.insert(member.clone()))
+            .collect();
+        Ok((added.len(), added))
     }
 
     /// 移除集合成员的内部实现
@@ -175,28 +162,19 @@ impl SetHandler {
     ) -> StoreResult<Vec<String>> {
         match data.get_mut(key) {
             Some(DataType::Set(set)) => {
-                if set.is_empty() {
-                    return Ok(vec![]);
-                }
-
-                let count = count.unwrap_or(1).min(set.len());
-                let mut result = Vec::new();
                 let mut rng = rand::rng();
-
-                // 将集合转换为向量以便随机选择
-                let members: Vec<String> = set.iter().cloned().collect();
-                let mut selected_members = members.clone();
-                selected_members.shuffle(&mut rng);
-
-                for _ in 0..count {
-                    if let Some(member) = selected_members.pop() {
-                        if set.remove(&member) {
-                            result.push(member);
-                        }
-                    }
+                // 未指定数量时弹出一个成员，数量为0时不弹出，数量不小于集合大小时清空集合；
+                // 只克隆被选中的成员，不再复制整个集合
+                let popped: Vec<String> = match count {
+                    None => set.iter().choose(&mut rng).cloned().into_iter().collect(),
+                    Some(0) => return Ok(vec![]),
+                    Some(count) if count >= set.len() => return Ok(set.drain().collect()),
+                    Some(count) => set.iter().choose_multiple(&mut rng, count).into_iter().cloned().collect(),
+                };
+                for member in &popped {
+                    set.remove(member);
                 }
-
-                Ok(result)
+                Ok(popped)
             }
             Some(_) => Err(StoreError::TypeMismatch {
                 key: key.to_string(),
@@ -249,6 +227,48 @@ impl SetHandler {
         }
     }
 
+    /// 计算集合交集大小的内部实现，只计数不构建交集，limit大于0时达到limit即停止
+    pub fn sintercard_internal(
+        data: &HashMap<String, DataType>,
+        keys: &[String],
+        limit: usize,
+    ) -> StoreResult<usize> {
+        let mut sets = Vec::with_capacity(keys.len());
+        for key in keys {
+            match data.get(key) {
+                Some(DataType::Set(set)) => sets.push(set),
+                Some(_) => {
+                    return Err(StoreError::TypeMismatch {
+                        key: key.to_string(),
+                        expected: "set".to_string(),
+                        found: data.get(key).unwrap().type_name().to_string(),
+                    })
+                }
+                None => {
+                    // 如果任何一个键不存在，交集为空
+                    return Ok(0);
+                }
+            }
+        }
+
+        // 遍历最小的集合，在其余集合中查找
+        sets.sort_by_key(|set| set.len());
+        let Some((smallest, rest)) = sets.split_first() else {
+            return Ok(0);
+        };
+
+        let mut count = 0;
+        for item in smallest.iter() {
+            if rest.iter().all(|set| set.contains(item)) {
+                count += 1;
+                if count == limit {
+                    break;
+                }
+            }
+        }
+        Ok(count)
+    }
+
     /// 计算集合并集的内部实现
     pub fn sunion_internal(
         data: &HashMap<String, DataType>,
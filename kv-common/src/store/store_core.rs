@@ -1,19 +1,57 @@
-use std::collections::{HashMap, BTreeMap};
+use std::collections::{HashMap, BTreeMap, BTreeSet, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
+use base64::prelude::*;
 
+use crate::clock::Clock;
 use crate::config::Settings;
-use super::data_types::DataType;
-use super::metadata::{DataMetadata, MemoryPressure};
+use super::data_types::{DataType, ExportRecord, KeySnapshot};
+use super::metadata::{DataMetadata, ListInfo, MemoryPressure, ObjectInfo};
 use super::memory::{MemoryManager, OptimizationStats, OptimizationStrategy};
 use super::expiry::{ExpiryManager, ExpiryStats};
 use super::error::{StoreError, StoreResult};
 use super::traits::*;
 use super::string_ops::StringHandler;
-use super::list_ops::ListHandler;
+use super::list_ops::{ListEnd, ListHandler};
 use super::hash_ops::HashHandler;
 use super::set_ops::SetHandler;
 
+/// 键版本号计数器，所有数据库共享，保证不同存储中的版本号不会重复
+static NEXT_KEY_VERSION: AtomicU64 = AtomicU64::new(1);
+
+/// 写入数据文件的内容：数据、过期时间(Unix时间戳)和已转移到磁盘的键
+///
+/// 旧版本的数据文件只有data字段，缺少的字段按空处理。所有数据库保存在同一个数据文件时，
+/// 0号数据库的内容位于顶层，其他非空数据库按编号保存在databases字段中。
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    data: &'a HashMap<String, DataType>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    expire_times: HashMap<String, u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    disk_keys: Vec<&'a String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    field_expire_times: &'a HashMap<String, HashMap<String, u64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    databases: BTreeMap<usize, SnapshotRef<'a>>,
+}
+
+/// 从数据文件读取的内容，字段与SnapshotRef对应
+#[derive(Deserialize)]
+struct Snapshot {
+    data: HashMap<String, DataType>,
+    #[serde(default)]
+    expire_times: HashMap<String, u64>,
+    #[serde(default)]
+    disk_keys: Vec<String>,
+    #[serde(default)]
+    field_expire_times: HashMap<String, HashMap<String, u64>>,
+    #[serde(default)]
+    databases: BTreeMap<usize, Snapshot>,
+}
+
 /// 重构后的核心存储结构
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Store {
@@ -30,8 +68,29 @@ pub struct Store {
     memory_manager: Option<MemoryManager>, // 内存管理器
     #[serde(skip)]
     settings: Option<Arc<Settings>>, // 配置引用
+    #[serde(skip)]
+    interned: HashSet<Arc<str>>, // 共享的字符串值，开启memory.intern_values时使用
+    #[serde(skip)]
+    versions: HashMap<String, u64>, // 键的版本号，键每次被修改都会更新
+    #[serde(skip)]
+    key_sizes: HashMap<String, usize>, // 内存中每个键上次统计的字节数，共享的字符串值不计入
+    #[serde(skip)]
+    used_bytes: usize, // key_sizes之和，随写入和删除增量更新
+    #[serde(skip)]
+    interned_bytes: usize, // 共享字符串池中所有值的字节数之和
+    #[serde(skip)]
+    last_lfu_decay: u64, // 上次衰减访问次数的时间(Unix时间戳)，0表示尚未开始计时
+    #[serde(skip)]
+    eviction_order: BTreeSet<(EvictionRank, String)>, // 内存中的键按淘汰顺序排列，随访问和写入增量更新
+    #[serde(skip)]
+    eviction_ranks: HashMap<String, EvictionRank>, // 每个键在eviction_order中的位置
+    #[serde(skip)]
+    scan_order: BTreeSet<(usize, String)>, // 所有键(包括磁盘上的)按游标位置排列，SCAN按此顺序遍历
 }
 
+/// 键的淘汰顺序: (访问次数, 上次访问时间)，越小越先被转移或删除
+pub type EvictionRank = (u64, u64);
+
 impl Store {
     pub fn new() -> Self {
         Self {
@@ -42,38 +101,380 @@ impl Store {
             expiry_manager: ExpiryManager::new(),
             memory_manager: None,
             settings: None,
+            interned: HashSet::new(),
+            versions: HashMap::new(),
+            key_sizes: HashMap::new(),
+            used_bytes: 0,
+            interned_bytes: 0,
+            last_lfu_decay: 0,
+            eviction_order: BTreeSet::new(),
+            eviction_ranks: HashMap::new(),
+            scan_order: BTreeSet::new(),
+        }
+    }
+
+    /// 创建配置相同但不含数据的存储，用于新建数据库
+    pub fn empty_like(&self) -> Self {
+        let mut expiry_manager = self.expiry_manager.clone();
+        expiry_manager.clear();
+        Self {
+            expiry_manager,
+            memory_manager: self.memory_manager.clone(),
+            settings: self.settings.clone(),
+            ..Self::new()
         }
     }
 
     /// 设置配置
     pub fn with_settings(mut self, settings: Arc<Settings>) -> Self {
+        self.expiry_manager = self.expiry_manager.with_jitter(settings.storage.ttl_jitter_percent);
         self.settings = Some(settings);
         self
     }
 
+    /// 替换配置，已有数据和过期时间保持不变
+    pub fn update_settings(&mut self, settings: Arc<Settings>) {
+        self.settings = Some(settings);
+    }
+
+    /// 设置判断过期所用的时钟
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.expiry_manager = self.expiry_manager.with_clock(clock);
+        self
+    }
+
     /// 设置内存管理器
     pub fn with_memory_manager(mut self, memory_manager: MemoryManager) -> Self {
         self.memory_manager = Some(memory_manager);
         self
     }
 
-    /// 应用默认过期时间
+    /// 开启值共享时返回池中相同的字符串，否则直接包装传入的值
+    fn intern(&mut self, value: String) -> Arc<str> {
+        let enabled = self.settings.as_ref().is_some_and(|s| s.memory.intern_values);
+        if !enabled {
+            return Arc::from(value);
+        }
+        if let Some(shared) = self.interned.get(value.as_str()) {
+            return Arc::clone(shared);
+        }
+        let shared: Arc<str> = Arc::from(value);
+        self.interned_bytes += shared.len();
+        self.interned.insert(Arc::clone(&shared));
+        shared
+    }
+
+    /// 对从文件加载的字符串值执行共享
+    fn intern_value(&mut self, value: DataType) -> DataType {
+        match value {
+            DataType::String(s) => DataType::String(self.intern(s.to_string())),
+            other => other,
+        }
+    }
+
+    /// 移除池中已经没有键引用的字符串
+    fn prune_interned(&mut self) {
+        let mut released = 0;
+        self.interned.retain(|value| {
+            let referenced = Arc::strong_count(value) > 1;
+            if !referenced {
+                released += value.len();
+            }
+            referenced
+        });
+        self.interned_bytes -= released;
+    }
+
+    /// 值是否是共享池中的字符串(同一份内存，而不仅是内容相同)
+    fn is_interned(&self, value: &Arc<str>) -> bool {
+        self.interned.get(value.as_ref()).is_some_and(|shared| Arc::ptr_eq(shared, value))
+    }
+
+    /// 键在整体内存统计中的字节数，共享的字符串值由池统一计算，这里只计算键名
+    fn key_usage(&self, key: &str, value: &DataType) -> usize {
+        match value {
+            DataType::String(s) if self.is_interned(s) => key.len(),
+            other => key.len() + other.estimated_size(),
+        }
+    }
+
+    /// 值占用的内存估算，共享的字符串按引用它的键数平摊
+    fn value_size(&self, value: &DataType) -> usize {
+        match value {
+            DataType::String(s) if self.is_interned(s) => {
+                // 池本身持有一个引用
+                let holders = Arc::strong_count(s).saturating_sub(1).max(1);
+                s.len().div_ceil(holders)
+            }
+            other => other.estimated_size(),
+        }
+    }
+
+    /// 为新建的键应用默认过期时间，已有键被修改时不会重置其过期时间
     fn apply_default_expiry(&mut self, key: &str) {
         if let Some(settings) = &self.settings {
-            if settings.storage.enable_default_expiry {
+            // 匹配豁免前缀的键(如配置类键)不设置默认过期时间
+            let exempt = settings
+                .storage
+                .default_expiry_exempt_prefixes
+                .iter()
+                .any(|prefix| key.starts_with(prefix.as_str()));
+            if settings.storage.enable_default_expiry && !exempt {
                 let default_ttl = settings.storage.default_expiry_seconds as u64;
                 let _ = self.expiry_manager.set_expire(key, default_ttl);
             }
         }
     }
 
+    /// 执行可能新建键的写操作
+    ///
+    /// 所有可能创建键的写入都经过这里：已过期的键先被删除，默认过期时间只在写入后键才存在时应用一次，
+    /// 修改已有的键不会改变其过期时间。
+    fn write_key<T>(
+        &mut self,
+        key: &str,
+        write: impl FnOnce(&mut HashMap<String, DataType>) -> StoreResult<T>,
+    ) -> StoreResult<T> {
+        if self.expiry_manager.is_expired(key) {
+            self.delete(key)?;
+        }
+        
+        let is_new = !self.data.contains_key(key);
+        self.record_write(key);
+        let result = write(&mut self.data);
+        self.refresh_usage(key);
+        if !matches!(self.data.get(key), Some(DataType::Hash(_))) {
+            self.expiry_manager.remove_field_expires(key);
+        }
+        let result = result?;
+        if is_new && self.data.contains_key(key) {
+            self.apply_default_expiry(key);
+        }
+        Ok(result)
+    }
+
+    /// 修改已有键的值后更新字节数统计
+    fn modify_key<T>(
+        &mut self,
+        key: &str,
+        modify: impl FnOnce(&mut HashMap<String, DataType>) -> StoreResult<T>,
+    ) -> StoreResult<T> {
+        self.record_write(key);
+        let result = modify(&mut self.data);
+        self.refresh_usage(key);
+        result
+    }
+
+    /// 删除哈希中已过期的字段，字段全部过期时删除整个键，返回键是否被删除
+    fn purge_expired_fields(&mut self, key: &str) -> StoreResult<bool> {
+        let expired = self.expiry_manager.expired_fields(key);
+        if expired.is_empty() {
+            return Ok(false);
+        }
+        for field in &expired {
+            self.expiry_manager.remove_field_expire(key, field);
+        }
+        self.modify_key(key, |data| {
+            if let Some(DataType::Hash(hash)) = data.get_mut(key) {
+                for field in &expired {
+                    hash.remove(field);
+                }
+            }
+            Ok(())
+        })?;
+        self.delete_if_empty(key)?;
+        Ok(!self.data.contains_key(key))
+    }
+
+    /// 哈希中未过期的字段，读取时逐个跳过已过期的字段，不复制整个哈希
+    ///
+    /// 读操作不能修改存储，过期字段由写操作或定期清理真正删除。
+    fn live_hash_fields<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> StoreResult<Option<impl Iterator<Item = (&'a String, &'a String)> + 'a>> {
+        match self.data.get(key) {
+            Some(DataType::Hash(hash)) => {
+                let has_field_expiry = self.expiry_manager.has_field_expiry(key);
+                Ok(Some(hash.iter().filter(move |(field, _)| {
+                    !has_field_expiry || !self.expiry_manager.is_field_expired(key, field)
+                })))
+            }
+            Some(other) => Err(StoreError::TypeMismatch {
+                key: key.to_string(),
+                expected: "hash".to_string(),
+                found: other.type_name().to_string(),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// 哈希字段是否存在且未过期
+    fn live_hash_field(&self, key: &str, field: &str) -> StoreResult<Option<&String>> {
+        match self.data.get(key) {
+            Some(DataType::Hash(_)) if self.expiry_manager.is_field_expired(key, field) => Ok(None),
+            Some(DataType::Hash(hash)) => Ok(hash.get(field)),
+            Some(other) => Err(StoreError::TypeMismatch {
+                key: key.to_string(),
+                expected: "hash".to_string(),
+                found: other.type_name().to_string(),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// 键是否在内存中且未过期，所有字段都已过期的哈希视为不存在
+    fn is_live_in_memory(&self, key: &str) -> bool {
+        if self.expiry_manager.is_expired(key) {
+            return false;
+        }
+        match self.data.get(key) {
+            Some(DataType::Hash(_)) => self
+                .live_hash_fields(key)
+                .is_ok_and(|fields| fields.is_some_and(|mut fields| fields.next().is_some())),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// 列表、哈希或集合的最后一个元素被移除后删除整个键，与Redis一致，空容器不会留在存储中
+    fn delete_if_empty(&mut self, key: &str) -> StoreResult<()> {
+        let is_empty = match self.data.get(key) {
+            Some(DataType::List(list)) => list.is_empty(),
+            Some(DataType::Hash(hash)) => hash.is_empty(),
+            Some(DataType::Set(set)) => set.is_empty(),
+            _ => false,
+        };
+        if is_empty {
+            self.delete(key)?;
+        }
+        Ok(())
+    }
+
+    /// 重新计算键占用的字节数并更新总数，键已不在内存中时移除统计
+    ///
+    /// 只计算被修改的这一个键，整体内存统计因此不需要遍历所有数据。
+    fn refresh_usage(&mut self, key: &str) {
+        let current = self.data.get(key).map(|value| self.key_usage(key, value));
+        let previous = match current {
+            Some(size) => self.key_sizes.insert(key.to_string(), size),
+            None => self.key_sizes.remove(key),
+        };
+        self.used_bytes = self.used_bytes - previous.unwrap_or(0) + current.unwrap_or(0);
+        self.refresh_eviction_rank(key);
+        self.refresh_scan_order(key);
+    }
+
+    /// 键在SCAN游标空间中的位置，由键名哈希决定，不随其他键的增删移动；0留作遍历的开始和结束
+    fn scan_position(key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() as usize).max(1)
+    }
+
+    /// 按键当前是否存在(内存或磁盘)更新SCAN的遍历顺序
+    fn refresh_scan_order(&mut self, key: &str) {
+        let entry = (Self::scan_position(key), key.to_string());
+        if self.data.contains_key(key) || self.disk_keys.contains_key(key) {
+            self.scan_order.insert(entry);
+        } else {
+            self.scan_order.remove(&entry);
+        }
+    }
+
+    /// 从游标开始按游标位置返回最多count个键(包括磁盘上的和已过期的键)及下一个游标，0表示遍历结束
+    ///
+    /// 游标是键名哈希决定的位置，不会因为其他键的增删而改变，遍历期间一直存在的键都会被返回；
+    /// 位置相同的键在批次边界上可能被重复返回。
+    pub fn scan_keys(&self, cursor: usize, count: usize) -> (usize, Vec<String>) {
+        let mut entries = self.scan_order.range((cursor, String::new())..);
+        let keys = entries.by_ref().take(count).map(|(_, key)| key.clone()).collect();
+        let next = entries.next().map_or(0, |(position, _)| *position);
+        (next, keys)
+    }
+
+    /// 按键当前的访问统计更新它在淘汰顺序中的位置，键已不在内存中时移除
+    fn refresh_eviction_rank(&mut self, key: &str) {
+        let current = self.data.contains_key(key).then(|| {
+            let metadata = self.metadata.get(key);
+            (metadata.map_or(0, |m| m.access_count), metadata.map_or(0, |m| m.last_access_time))
+        });
+        let previous = self.eviction_ranks.get(key).copied();
+        if previous == current {
+            return;
+        }
+        if let Some(rank) = previous {
+            self.eviction_order.remove(&(rank, key.to_string()));
+            self.eviction_ranks.remove(key);
+        }
+        if let Some(rank) = current {
+            self.eviction_order.insert((rank, key.to_string()));
+            self.eviction_ranks.insert(key.to_string(), rank);
+        }
+    }
+
+    /// 最先被淘汰的内存中的键及其淘汰顺序：访问次数最少的，次数相同时最久未访问的
+    ///
+    /// volatile为true时只考虑设置了过期时间的键。淘汰顺序随访问和写入增量维护，不需要排序整个键空间。
+    pub fn eviction_candidate(&self, volatile: bool) -> Option<(EvictionRank, String)> {
+        self.eviction_order
+            .iter()
+            .find(|(_, key)| !volatile || self.expiry_manager.get_ttl(key) >= 0)
+            .cloned()
+    }
+
+    /// 内存中闲置超过min_idle_seconds秒的键，闲置最久的在前，已过期的键不包含在内
+    pub fn idle_keys(&self, min_idle_seconds: u64) -> Vec<String> {
+        let now = self.expiry_manager.now_secs();
+        let mut idle: Vec<(u64, &String)> = self
+            .data
+            .keys()
+            .filter(|key| !self.expiry_manager.is_expired(key))
+            .filter_map(|key| {
+                let metadata = self.metadata.get(key)?;
+                (metadata.idle_time(now) > min_idle_seconds).then_some((metadata.last_access_time, key))
+            })
+            .collect();
+        idle.sort();
+        idle.into_iter().map(|(_, key)| key.clone()).collect()
+    }
+
+    /// 每经过decay_seconds秒把所有键的访问次数减半，返回减半的次数
+    ///
+    /// 第一次调用只开始计时；多个周期过去后一次减半多次。
+    pub fn decay_access_counts(&mut self, decay_seconds: u64) -> u32 {
+        let now = self.expiry_manager.now_secs();
+        if decay_seconds == 0 || self.last_lfu_decay == 0 {
+            self.last_lfu_decay = now;
+            return 0;
+        }
+        let periods = now.saturating_sub(self.last_lfu_decay) / decay_seconds;
+        if periods == 0 {
+            return 0;
+        }
+        self.last_lfu_decay += periods * decay_seconds;
+        let shift = periods.min(u64::BITS as u64 - 1) as u32;
+        for metadata in self.metadata.values_mut() {
+            metadata.access_count >>= shift;
+        }
+        self.eviction_order.clear();
+        self.eviction_ranks.clear();
+        let keys: Vec<String> = self.data.keys().cloned().collect();
+        for key in keys {
+            self.refresh_eviction_rank(&key);
+        }
+        shift
+    }
+
     /// 记录访问统计
     fn record_access(&mut self, key: &str) {
         // 更新元数据
+        let now = self.expiry_manager.now_secs();
         self.metadata
             .entry(key.to_string())
-            .or_insert_with(|| DataMetadata::new(0))
-            .access();
+            .or_insert_with(|| DataMetadata::new(0, now))
+            .access(now);
+        self.refresh_eviction_rank(key);
 
         // 更新内存压力统计
         if self.data.contains_key(key) {
@@ -83,26 +484,62 @@ impl Store {
         }
     }
 
+    /// 记录写入：更新访问统计并为键分配新的版本号
+    fn record_write(&mut self, key: &str) {
+        self.record_access(key);
+        self.bump_version(key);
+    }
+
+    /// 为键分配新的版本号
+    fn bump_version(&mut self, key: &str) {
+        let version = NEXT_KEY_VERSION.fetch_add(1, Ordering::Relaxed);
+        self.versions.insert(key.to_string(), version);
+    }
+
+    /// 键的当前版本号，从未写入或已删除的键为0，已过期的键返回None
+    ///
+    /// 版本号在进程内全局唯一，版本号相同说明键的内容没有变化。
+    pub fn key_version(&self, key: &str) -> Option<u64> {
+        if self.expiry_manager.is_expired(key) {
+            return None;
+        }
+        Some(self.versions.get(key).copied().unwrap_or(0))
+    }
+
     /// 记录数据修改
     fn record_modification(&mut self, key: &str, new_size: usize) {
+        let now = self.expiry_manager.now_secs();
         self.metadata
             .entry(key.to_string())
-            .or_insert_with(|| DataMetadata::new(new_size))
-            .modify(new_size);
+            .or_insert_with(|| DataMetadata::new(new_size, now))
+            .modify(new_size, now);
+        self.refresh_eviction_rank(key);
     }
 
-    /// 清理过期键
+    /// 清理过期键和哈希中过期的字段，返回删除的键数量（包括字段全部过期的哈希）
     pub fn clean_expired_keys(&mut self) -> usize {
         let expired_keys = self.expiry_manager.find_expired_keys();
-        let count = expired_keys.len();
+        let mut count = expired_keys.len();
 
         for key in &expired_keys {
             self.data.remove(key);
             self.metadata.remove(key);
             self.disk_keys.remove(key);
+            self.versions.remove(key);
+            self.refresh_usage(key);
         }
 
         self.expiry_manager.remove_expired_keys(&expired_keys);
+        for key in &expired_keys {
+            self.expiry_manager.remove_field_expires(key);
+        }
+
+        for key in self.expiry_manager.keys_with_expired_fields() {
+            if self.purge_expired_fields(&key).unwrap_or(false) {
+                count += 1;
+            }
+        }
+        self.prune_interned();
         count
     }
 
@@ -118,7 +555,7 @@ impl Store {
     /// 执行内存优化
     pub fn optimize_memory(&mut self) -> StoreResult<usize> {
         if let Some(memory_manager) = &self.memory_manager {
-            let low_freq_keys = memory_manager.get_low_frequency_keys(&self.data, &self.metadata);
+            let low_freq_keys = memory_manager.get_low_frequency_keys(&self.data, &self.metadata, self.expiry_manager.now_secs());
             let count = low_freq_keys.len();
             
             for key in &low_freq_keys {
@@ -136,14 +573,13 @@ impl Store {
         if self.data.contains_key(key) {
             self.disk_keys.insert(key.to_string(), true);
             self.data.remove(key);
+            self.refresh_usage(key);
             self.memory_pressure.record_offload();
         }
     }
 
     /// 获取优化统计信息
     pub fn get_optimization_stats(&self) -> OptimizationStats {
-        let memory_usage = MemoryManager::calculate_memory_usage(&self.data);
-        
         let (strategy, max_memory_keys, access_threshold, idle_time_threshold) = 
             if let Some(memory_manager) = &self.memory_manager {
                 let pressure_level = self.memory_pressure.calculate_pressure_level(
@@ -169,7 +605,7 @@ impl Store {
             idle_time_threshold,
             memory_pressure_level: self.memory_pressure.last_pressure_level,
             cache_hit_ratio: self.memory_pressure.cache_hit_ratio(),
-            memory_usage_bytes: memory_usage,
+            memory_usage_bytes: self.memory_usage(),
             optimization_strategy: strategy,
         }
     }
@@ -197,31 +633,157 @@ impl Store {
     /// 反序列化单个键的数据
     pub fn deserialize_key(&mut self, key: &str, data: &str) -> StoreResult<()> {
         let value: DataType = serde_json::from_str(data)?;
+        let value = self.intern_value(value);
         let size = value.estimated_size();
         
         self.data.insert(key.to_string(), value);
         self.disk_keys.remove(key);
+        self.refresh_usage(key);
         self.record_modification(key, size);
         self.memory_pressure.record_load();
         
         Ok(())
     }
 
-    /// 序列化整个存储
+    /// 序列化整个存储，包括过期时间和已转移到磁盘的键(值保存在各自的磁盘文件中)
     pub fn serialize(&self) -> StoreResult<String> {
-        let serialized = serde_json::to_string(self)?;
+        self.serialize_databases(&[])
+    }
+
+    /// 序列化存储，并把其他数据库中非空的保存到databases字段，用于所有数据库共用一个数据文件
+    pub fn serialize_databases(&self, others: &[(usize, &Store)]) -> StoreResult<String> {
+        let mut snapshot = self.snapshot_ref();
+        for (db, store) in others {
+            if !store.data.is_empty() || !store.disk_keys.is_empty() {
+                snapshot.databases.insert(*db, store.snapshot_ref());
+            }
+        }
+        let serialized = serde_json::to_string(&snapshot)?;
         Ok(serialized)
     }
+
+    fn snapshot_ref(&self) -> SnapshotRef<'_> {
+        SnapshotRef {
+            data: &self.data,
+            expire_times: self.expiry_manager.export_expire_times().clone(),
+            disk_keys: self.disk_keys.keys().filter(|key| !self.data.contains_key(*key)).collect(),
+            field_expire_times: self.expiry_manager.export_field_expire_times(),
+            databases: BTreeMap::new(),
+        }
+    }
     
-    /// 反序列化整个存储
+    /// 反序列化整个存储，替换已有的数据、过期时间和磁盘键记录
+    ///
+    /// 只加载顶层的数据，databases字段中的其他数据库被忽略。
     pub fn deserialize(&mut self, data: &str) -> StoreResult<()> {
-        let store: Store = serde_json::from_str(data)?;
-        self.data = store.data;
-        // 重新构建元数据
-        for (key, value) in &self.data {
-            let metadata = DataMetadata::new(value.estimated_size());
+        let snapshot: Snapshot = serde_json::from_str(data)?;
+        self.load_snapshot(snapshot);
+        Ok(())
+    }
+
+    /// 反序列化所有数据库共用的数据文件，顶层数据加载到当前存储，
+    /// 返回databases字段中的其他数据库，它们使用与当前存储相同的配置
+    pub fn deserialize_databases(&mut self, data: &str) -> StoreResult<BTreeMap<usize, Store>> {
+        let mut snapshot: Snapshot = serde_json::from_str(data)?;
+        let others = std::mem::take(&mut snapshot.databases)
+            .into_iter()
+            .map(|(db, snapshot)| {
+                let mut store = self.empty_like();
+                store.load_snapshot(snapshot);
+                (db, store)
+            })
+            .collect();
+        self.load_snapshot(snapshot);
+        Ok(others)
+    }
+
+    fn load_snapshot(&mut self, snapshot: Snapshot) {
+        self.data = snapshot
+            .data
+            .into_iter()
+            .map(|(key, value)| (key, self.intern_value(value)))
+            .collect();
+        self.expiry_manager.import_expire_times(snapshot.expire_times);
+        self.expiry_manager.import_field_expire_times(snapshot.field_expire_times);
+        self.disk_keys = snapshot
+            .disk_keys
+            .into_iter()
+            .filter(|key| !self.data.contains_key(key))
+            .map(|key| (key, true))
+            .collect();
+        // 重新构建元数据和字节数统计，加载的键都视为被修改过
+        self.key_sizes.clear();
+        self.used_bytes = 0;
+        self.eviction_order.clear();
+        self.eviction_ranks.clear();
+        self.scan_order.clear();
+        let now = self.expiry_manager.now_secs();
+        let keys: Vec<String> = self.data.keys().cloned().collect();
+        for key in keys {
+            let metadata = DataMetadata::new(self.data[&key].estimated_size(), now);
             self.metadata.insert(key.clone(), metadata);
+            self.bump_version(&key);
+            self.refresh_usage(&key);
+        }
+        let disk_keys: Vec<String> = self.disk_keys.keys().cloned().collect();
+        for key in disk_keys {
+            self.refresh_scan_order(&key);
+        }
+    }
+
+    /// 与另一个存储比较数据、过期时间和磁盘键，返回第一处不一致的描述
+    pub fn diff(&self, other: &Store) -> Option<String> {
+        let mut keys: Vec<&String> = self.data.keys().chain(other.data.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            if self.data.get(key) != other.data.get(key) {
+                return Some(format!("value of key '{}' differs", key));
+            }
+        }
+
+        let (expected, actual) = (self.expiry_manager.export_expire_times(), other.expiry_manager.export_expire_times());
+        if let Some(key) = expected.keys().chain(actual.keys()).find(|key| expected.get(*key) != actual.get(*key)) {
+            return Some(format!("expire time of key '{}' differs", key));
+        }
+
+        let (expected, actual) = (
+            self.expiry_manager.export_field_expire_times(),
+            other.expiry_manager.export_field_expire_times(),
+        );
+        if let Some(key) = expected.keys().chain(actual.keys()).find(|key| expected.get(*key) != actual.get(*key)) {
+            return Some(format!("field expire times of key '{}' differ", key));
+        }
+
+        let (expected, actual) = (self.get_disk_keys(), other.get_disk_keys());
+        if expected != actual {
+            return Some(format!("offloaded keys differ: {:?} != {:?}", expected, actual));
+        }
+        None
+    }
+
+    /// 重命名内存中的键，值、过期时间和访问统计随键转移，已存在的目标键被覆盖
+    pub fn rename(&mut self, key: &str, new_key: &str) -> StoreResult<()> {
+        if !self.exists(key) {
+            return Err(StoreError::KeyNotFound(key.to_string()));
+        }
+        if key == new_key {
+            return Ok(());
+        }
+
+        self.delete(new_key)?;
+        if let Some(value) = self.data.remove(key) {
+            self.data.insert(new_key.to_string(), value);
         }
+        if let Some(metadata) = self.metadata.remove(key) {
+            self.metadata.insert(new_key.to_string(), metadata);
+        }
+        self.expiry_manager.rename_key(key, new_key);
+        self.disk_keys.remove(key);
+        self.versions.remove(key);
+        self.bump_version(new_key);
+        self.refresh_usage(key);
+        self.refresh_usage(new_key);
         Ok(())
     }
 
@@ -246,24 +808,28 @@ impl Store {
 // 实现存储操作 trait
 impl StoreOperations for Store {
     fn exists(&self, key: &str) -> bool {
-        !self.expiry_manager.is_expired(key) && self.data.contains_key(key)
+        self.is_live_in_memory(key)
     }
     
     fn delete(&mut self, key: &str) -> StoreResult<bool> {
         let existed = self.data.remove(key).is_some();
-        self.metadata.remove(key);
         self.disk_keys.remove(key);
+        self.refresh_usage(key);
+        self.metadata.remove(key);
+        self.versions.remove(key);
         self.expiry_manager.remove_expire(key);
+        self.expiry_manager.remove_field_expires(key);
         Ok(existed)
     }
     
     fn get_type(&self, key: &str) -> StoreResult<String> {
-        if self.expiry_manager.is_expired(key) {
+        if !self.is_live_in_memory(key) {
             return Err(StoreError::KeyNotFound(key.to_string()));
         }
         
         match self.data.get(key) {
             Some(DataType::String(_)) => Ok("string".to_string()),
+            Some(DataType::Bytes(_)) => Ok("bytes".to_string()),
             Some(DataType::List(_)) => Ok("list".to_string()),
             Some(DataType::Hash(_)) => Ok("hash".to_string()),
             Some(DataType::Set(_)) => Ok("set".to_string()),
@@ -307,18 +873,21 @@ impl StringOperations for Store {
     }
     
     fn get(&self, key: &str) -> StoreResult<Option<String>> {
-        Ok(self.get_string(key))
+        // 键存在但不是字符串时返回类型错误；二进制值无法作为文本返回，需要通过字节接口读取
+        match self.data.get(key) {
+            Some(value) if !self.expiry_manager.is_expired(key) && !matches!(value, DataType::String(_)) => {
+                Err(StoreError::TypeMismatch {
+                    key: key.to_string(),
+                    expected: "string".to_string(),
+                    found: value.type_name().to_string(),
+                })
+            }
+            _ => Ok(self.get_string(key)),
+        }
     }
     
     fn append(&mut self, key: &str, value: &str) -> StoreResult<usize> {
-        if self.expiry_manager.is_expired(key) {
-            self.delete(key)?;
-        }
-        
-        self.record_access(key);
-        let result = StringHandler::append_internal(&mut self.data, key, value)?;
-        self.apply_default_expiry(key);
-        Ok(result)
+        self.write_key(key, |data| StringHandler::append_internal(data, key, value))
     }
     
     fn strlen(&self, key: &str) -> StoreResult<usize> {
@@ -332,25 +901,11 @@ impl StringOperations for Store {
 // 实现列表操作 trait  
 impl ListOperations for Store {
     fn lpush(&mut self, key: String, value: String) -> StoreResult<usize> {
-        if self.expiry_manager.is_expired(&key) {
-            self.delete(&key)?;
-        }
-        
-        self.record_access(&key);
-        let result = ListHandler::lpush_internal(&mut self.data, key.clone(), value)?;
-        self.apply_default_expiry(&key);
-        Ok(result)
+        self.write_key(&key, |data| ListHandler::lpush_internal(data, key.clone(), value))
     }
     
     fn rpush(&mut self, key: String, value: String) -> StoreResult<usize> {
-        if self.expiry_manager.is_expired(&key) {
-            self.delete(&key)?;
-        }
-        
-        self.record_access(&key);
-        let result = ListHandler::rpush_internal(&mut self.data, key.clone(), value)?;
-        self.apply_default_expiry(&key);
-        Ok(result)
+        self.write_key(&key, |data| ListHandler::rpush_internal(data, key.clone(), value))
     }
     
     fn lpop(&mut self, key: &str) -> StoreResult<Option<String>> {
@@ -359,8 +914,9 @@ impl ListOperations for Store {
             return Ok(None);
         }
         
-        self.record_access(key);
-        ListHandler::lpop_internal(&mut self.data, key)
+        let value = self.modify_key(key, |data| ListHandler::lpop_internal(data, key))?;
+        self.delete_if_empty(key)?;
+        Ok(value)
     }
     
     fn rpop(&mut self, key: &str) -> StoreResult<Option<String>> {
@@ -369,8 +925,9 @@ impl ListOperations for Store {
             return Ok(None);
         }
         
-        self.record_access(key);
-        ListHandler::rpop_internal(&mut self.data, key)
+        let value = self.modify_key(key, |data| ListHandler::rpop_internal(data, key))?;
+        self.delete_if_empty(key)?;
+        Ok(value)
     }
     
     fn lrange(&self, key: &str, start: isize, stop: isize) -> StoreResult<Vec<String>> {
@@ -402,22 +959,46 @@ impl ListOperations for Store {
             return Ok(false);
         }
         
-        self.record_access(key);
-        ListHandler::lset_internal(&mut self.data, key, index, value)
+        self.modify_key(key, |data| ListHandler::lset_internal(data, key, index, value))
+    }
+    
+    fn lmpop(&mut self, keys: &[String], end: ListEnd, count: usize) -> StoreResult<Option<(String, Vec<String>)>> {
+        for key in keys {
+            if self.expiry_manager.is_expired(key) {
+                self.delete(key)?;
+                continue;
+            }
+            if ListHandler::llen_internal(&self.data, key)? == 0 {
+                continue;
+            }
+            
+            self.record_write(key);
+            let mut popped = Vec::new();
+            while popped.len() < count {
+                let value = match end {
+                    ListEnd::Left => ListHandler::lpop_internal(&mut self.data, key)?,
+                    ListEnd::Right => ListHandler::rpop_internal(&mut self.data, key)?,
+                };
+                match value {
+                    Some(value) => popped.push(value),
+                    None => break,
+                }
+            }
+            self.refresh_usage(key);
+            self.delete_if_empty(key)?;
+            return Ok(Some((key.clone(), popped)));
+        }
+        Ok(None)
     }
 }
 
 // 实现哈希操作 trait
 impl HashOperations for Store {
     fn hset(&mut self, key: String, field: String, value: String) -> StoreResult<bool> {
-        if self.expiry_manager.is_expired(&key) {
-            self.delete(&key)?;
-        }
-        
-        self.record_access(&key);
-        let result = HashHandler::hset_internal(&mut self.data, key.clone(), field.clone(), value)?;
-        self.apply_default_expiry(&key);
-        Ok(result)
+        self.purge_expired_fields(&key)?;
+        // 重新设置的字段不再过期
+        self.expiry_manager.remove_field_expire(&key, &field);
+        self.write_key(&key, |data| HashHandler::hset_internal(data, key.clone(), field, value))
     }
     
     fn hget(&self, key: &str, field: &str) -> StoreResult<Option<String>> {
@@ -425,7 +1006,7 @@ impl HashOperations for Store {
             return Ok(None);
         }
         
-        HashHandler::hget_internal(&self.data, key, field)
+        Ok(self.live_hash_field(key, field)?.cloned())
     }
     
     fn hdel(&mut self, key: &str, field: &str) -> StoreResult<bool> {
@@ -434,8 +1015,11 @@ impl HashOperations for Store {
             return Ok(false);
         }
         
-        self.record_access(key);
-        HashHandler::hdel_internal(&mut self.data, key, field)
+        self.purge_expired_fields(key)?;
+        self.expiry_manager.remove_field_expire(key, field);
+        let removed = self.modify_key(key, |data| HashHandler::hdel_internal(data, key, field))?;
+        self.delete_if_empty(key)?;
+        Ok(removed)
     }
     
     fn hkeys(&self, key: &str) -> StoreResult<Vec<String>> {
@@ -443,7 +1027,9 @@ impl HashOperations for Store {
             return Ok(vec![]);
         }
         
-        HashHandler::hkeys_internal(&self.data, key)
+        Ok(self.live_hash_fields(key)?
+            .map(|fields| fields.map(|(field, _)| field.clone()).collect())
+            .unwrap_or_default())
     }
     
     fn hvals(&self, key: &str) -> StoreResult<Vec<String>> {
@@ -451,7 +1037,9 @@ impl HashOperations for Store {
             return Ok(vec![]);
         }
         
-        HashHandler::hvals_internal(&self.data, key)
+        Ok(self.live_hash_fields(key)?
+            .map(|fields| fields.map(|(_, value)| value.clone()).collect())
+            .unwrap_or_default())
     }
     
     fn hgetall(&self, key: &str) -> StoreResult<Vec<String>> {
@@ -459,21 +1047,32 @@ impl HashOperations for Store {
             return Ok(vec![]);
         }
         
-        let hash_map = HashHandler::hgetall_internal(&self.data, key)?;
         let mut result = Vec::new();
-        for (field, value) in hash_map {
-            result.push(field);
-            result.push(value);
+        for (field, value) in self.live_hash_fields(key)?.into_iter().flatten() {
+            result.push(field.clone());
+            result.push(value.clone());
         }
         Ok(result)
     }
     
+    fn hrandfield(&self, key: &str, count: Option<isize>) -> StoreResult<Vec<(String, String)>> {
+        if self.expiry_manager.is_expired(key) {
+            return Ok(vec![]);
+        }
+        
+        let entries = self
+            .live_hash_fields(key)?
+            .map(|fields| fields.map(|(field, value)| (field.clone(), value.clone())).collect())
+            .unwrap_or_default();
+        Ok(HashHandler::random_entries(entries, count))
+    }
+    
     fn hexists(&self, key: &str, field: &str) -> StoreResult<bool> {
         if self.expiry_manager.is_expired(key) {
             return Ok(false);
         }
         
-        HashHandler::hexists_internal(&self.data, key, field)
+        Ok(self.live_hash_field(key, field)?.is_some())
     }
     
     fn hlen(&self, key: &str) -> StoreResult<usize> {
@@ -481,31 +1080,83 @@ impl HashOperations for Store {
             return Ok(0);
         }
         
-        HashHandler::hlen_internal(&self.data, key)
+        Ok(self.live_hash_fields(key)?.map_or(0, |fields| fields.count()))
+    }
+
+    fn hexpire(&mut self, key: &str, seconds: u64, fields: &[String]) -> StoreResult<Vec<i64>> {
+        if self.expiry_manager.is_expired(key) {
+            self.delete(key)?;
+        }
+        self.purge_expired_fields(key)?;
+
+        let mut results = Vec::with_capacity(fields.len());
+        for field in fields {
+            if !HashHandler::hexists_internal(&self.data, key, field)? {
+                results.push(-2);
+            } else if seconds == 0 {
+                self.hdel(key, field)?;
+                results.push(2);
+            } else {
+                self.expiry_manager.set_field_expire(key, field, seconds);
+                self.bump_version(key);
+                results.push(1);
+            }
+        }
+        Ok(results)
+    }
+
+    fn httl(&self, key: &str, fields: &[String]) -> StoreResult<Vec<i64>> {
+        if self.expiry_manager.is_expired(key) {
+            return Ok(vec![-2; fields.len()]);
+        }
+
+        fields
+            .iter()
+            .map(|field| {
+                if self.live_hash_field(key, field)?.is_some() {
+                    Ok(self.expiry_manager.get_field_ttl(key, field))
+                } else {
+                    Ok(-2)
+                }
+            })
+            .collect()
     }
 }
 
 // 实现集合操作 trait
 impl SetOperations for Store {
     fn sadd(&mut self, key: String, values: Vec<String>) -> StoreResult<usize> {
-        if self.expiry_manager.is_expired(&key) {
-            self.delete(&key)?;
-        }
-        
-        self.record_access(&key);
-        let result = SetHandler::sadd_internal(&mut self.data, key.clone(), values)?;
-        self.apply_default_expiry(&key);
-        Ok(result)
+        Ok(self.sadd_members(key, values)?.len())
+    }
+
+    fn sadd_members(&mut self, key: String, values: Vec<String>) -> StoreResult<Vec<String>> {
+        self.write_key(&key, |data| {
+            let (_, added) = SetHandler::sadd_internal(data, key.clone(), values)?;
+            Ok(added)
+        })
     }
     
     fn srem(&mut self, key: &str, value: &str) -> StoreResult<bool> {
+        Ok(self.srem_members(key, &[value.to_string()])? > 0)
+    }
+
+    fn srem_members(&mut self, key: &str, members: &[String]) -> StoreResult<usize> {
         if self.expiry_manager.is_expired(key) {
             self.delete(key)?;
-            return Ok(false);
+            return Ok(0);
         }
-        
-        self.record_access(key);
-        SetHandler::srem_internal(&mut self.data, key, value)
+
+        let removed = self.modify_key(key, |data| {
+            let mut removed = 0;
+            for member in members {
+                if SetHandler::srem_internal(data, key, member)? {
+                    removed += 1;
+                }
+            }
+            Ok(removed)
+        })?;
+        self.delete_if_empty(key)?;
+        Ok(removed)
     }
     
     fn smembers(&self, key: &str) -> StoreResult<Vec<String>> {
@@ -532,6 +1183,24 @@ impl SetOperations for Store {
         SetHandler::scard_internal(&self.data, key)
     }
     
+    fn sinter(&self, keys: &[String]) -> StoreResult<Vec<String>> {
+        // 已过期的键视为不存在
+        if keys.iter().any(|key| self.expiry_manager.is_expired(key)) {
+            return Ok(vec![]);
+        }
+        
+        SetHandler::sinter_internal(&self.data, keys)
+    }
+    
+    fn sintercard(&self, keys: &[String], limit: usize) -> StoreResult<usize> {
+        // 已过期的键视为不存在
+        if keys.iter().any(|key| self.expiry_manager.is_expired(key)) {
+            return Ok(0);
+        }
+        
+        SetHandler::sintercard_internal(&self.data, keys, limit)
+    }
+    
     fn srandmember(&self, key: &str, count: Option<isize>) -> StoreResult<Vec<String>> {
         if self.expiry_manager.is_expired(key) {
             return Ok(vec![]);
@@ -546,8 +1215,9 @@ impl SetOperations for Store {
             return Ok(vec![]);
         }
         
-        self.record_access(key);
-        SetHandler::spop_internal(&mut self.data, key, count)
+        let popped = self.modify_key(key, |data| SetHandler::spop_internal(data, key, count))?;
+        self.delete_if_empty(key)?;
+        Ok(popped)
     }
 }
 
@@ -555,10 +1225,45 @@ impl SetOperations for Store {
 impl Store {
     /// 设置字符串值
     pub fn set_string(&mut self, key: String, value: String) {
-        self.record_access(&key);
-        self.data.insert(key.clone(), DataType::String(value.clone()));
-        self.record_modification(&key, value.len());
-        self.apply_default_expiry(&key);
+        let size = value.len();
+        let value = self.intern(value);
+        let _ = self.write_key(&key, |data| {
+            data.insert(key.clone(), DataType::String(value));
+            Ok(())
+        });
+        self.record_modification(&key, size);
+    }
+    
+    /// 设置字节串值，合法的UTF-8数据仍按字符串保存
+    pub fn set_bytes(&mut self, key: String, value: Vec<u8>) {
+        let size = value.len();
+        let value = match String::from_utf8(value) {
+            Ok(text) => DataType::String(self.intern(text)),
+            Err(e) => DataType::Bytes(e.into_bytes()),
+        };
+        let _ = self.write_key(&key, |data| {
+            data.insert(key.clone(), value);
+            Ok(())
+        });
+        self.record_modification(&key, size);
+    }
+    
+    /// 以字节形式获取字符串或字节串值
+    pub fn get_bytes(&self, key: &str) -> StoreResult<Option<Vec<u8>>> {
+        if self.expiry_manager.is_expired(key) {
+            return Ok(None);
+        }
+        
+        match self.data.get(key) {
+            Some(DataType::String(value)) => Ok(Some(value.as_bytes().to_vec())),
+            Some(DataType::Bytes(value)) => Ok(Some(value.clone())),
+            Some(other) => Err(StoreError::TypeMismatch {
+                key: key.to_string(),
+                expected: "string".to_string(),
+                found: other.type_name().to_string(),
+            }),
+            None => Ok(None),
+        }
     }
     
     /// 获取字符串值
@@ -568,7 +1273,7 @@ impl Store {
         }
         
         match self.data.get(key) {
-            Some(DataType::String(value)) => Some(value.clone()),
+            Some(DataType::String(value)) => Some(value.to_string()),
             _ => None,
         }
     }
@@ -603,6 +1308,85 @@ impl Store {
         self.sismember(key, value).unwrap_or(false)
     }
     
+    /// 导出内存中所有未过期的键，保留数据类型和剩余生存时间
+    pub fn export_records(&self) -> Vec<ExportRecord> {
+        let mut records: Vec<ExportRecord> = self.data
+            .iter()
+            .filter(|(key, _)| !self.expiry_manager.is_expired(key))
+            .map(|(key, value)| ExportRecord {
+                key: key.clone(),
+                value: value.clone(),
+                ttl: self.remaining_ttl(key),
+            })
+            .collect();
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+        records
+    }
+    
+    /// 剩余生存时间(秒)，没有过期时间或已过期时返回None，也适用于已转移到磁盘的键
+    pub(crate) fn remaining_ttl(&self, key: &str) -> Option<u64> {
+        u64::try_from(self.expiry_manager.get_ttl(key)).ok()
+    }
+    
+    /// 导入一个键，覆盖已有的值和过期时间
+    pub fn import_record(&mut self, record: ExportRecord) {
+        let ExportRecord { key, value, ttl } = record;
+        let size = value.estimated_size();
+        self.disk_keys.remove(&key);
+        self.data.insert(key.clone(), value);
+        self.refresh_usage(&key);
+        self.record_modification(&key, size);
+        self.bump_version(&key);
+        match ttl {
+            Some(seconds) => {
+                let _ = self.expiry_manager.set_expire_exact(&key, seconds);
+            }
+            None => {
+                self.expiry_manager.remove_expire(&key);
+            }
+        }
+    }
+    
+    /// 内存中键的完整状态，包括已过期但尚未清理的键，键不在内存中时返回None
+    pub fn key_snapshot(&self, key: &str) -> Option<KeySnapshot> {
+        let value = self.data.get(key)?;
+        Some(self.snapshot_with_value(key, value.clone()))
+    }
+    
+    /// 以value作为键的值、加上键的过期时间组成完整状态，用于已转移到磁盘的键
+    pub fn snapshot_with_value(&self, key: &str, value: DataType) -> KeySnapshot {
+        KeySnapshot {
+            value,
+            expire_at: self.expiry_manager.export_expire_times().get(key).copied(),
+            field_expire_at: self
+                .expiry_manager
+                .export_field_expire_times()
+                .get(key)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+    
+    /// 按快照替换键的值和过期时间，快照为None时删除键
+    pub fn restore_key(&mut self, key: &str, snapshot: Option<KeySnapshot>) {
+        let _ = self.delete(key);
+        let Some(KeySnapshot { value, expire_at, field_expire_at }) = snapshot else {
+            return;
+        };
+        let value = self.intern_value(value);
+        let size = value.estimated_size();
+        self.data.insert(key.to_string(), value);
+        self.refresh_usage(key);
+        self.record_modification(key, size);
+        self.bump_version(key);
+        if let Some(timestamp) = expire_at {
+            let _ = self.expiry_manager.set_expire_at(key, timestamp);
+        }
+        for (field, timestamp) in field_expire_at {
+            self.expiry_manager.set_field_expire_at(key, &field, timestamp);
+        }
+    }
+    
     /// 获取所有键值对
     pub fn get_all_key_values(&self) -> std::collections::HashMap<String, String> {
         let mut result = std::collections::HashMap::new();
@@ -611,7 +1395,10 @@ impl Store {
             if !self.expiry_manager.is_expired(key) {
                 match value {
                     DataType::String(s) => {
-                        result.insert(key.clone(), s.clone());
+                        result.insert(key.clone(), s.to_string());
+                    },
+                    DataType::Bytes(bytes) => {
+                        result.insert(key.clone(), BASE64_STANDARD.encode(bytes));
                     },
                     DataType::List(list) => {
                         let serialized = serde_json::to_string(list).unwrap_or_default();
@@ -632,15 +1419,103 @@ impl Store {
         result
     }
     
-    /// 获取内存使用情况
+    /// 获取内存使用情况，共享的字符串只计算一次
+    ///
+    /// 总数随每次写入和删除增量维护，查询是O(1)的。
     pub fn memory_usage(&self) -> usize {
-        MemoryManager::calculate_memory_usage(&self.data)
+        self.used_bytes + self.interned_bytes
+    }
+
+    /// 遍历所有数据重新计算内存使用情况，结果应与memory_usage一致，用于校验增量统计
+    pub fn recalculate_memory_usage(&self) -> usize {
+        if self.interned.is_empty() {
+            return MemoryManager::calculate_memory_usage(&self.data);
+        }
+        let shared: usize = self.interned.iter().map(|value| value.len()).sum();
+        let unshared: usize = self.data.iter().map(|(key, value)| self.key_usage(key, value)).sum();
+        shared + unshared
+    }
+
+    /// 估算单个键占用的内存：键长度 + 值大小 + 元数据开销，键不存在时返回None
+    pub fn memory_usage_of(&self, key: &str) -> Option<usize> {
+        if self.expiry_manager.is_expired(key) {
+            return None;
+        }
+        self.data
+            .get(key)
+            .map(|value| key.len() + self.value_size(value) + std::mem::size_of::<DataMetadata>())
+    }
+    
+    /// 获取键的内部信息，已转移到磁盘的键需要传入从磁盘读取的值
+    /// 记录一次读取，只统计内存中未过期的键
+    pub fn touch(&mut self, key: &str) {
+        if !self.expiry_manager.is_expired(key) && self.data.contains_key(key) {
+            self.record_access(key);
+        }
+    }
+
+    /// 重置内存中键的访问统计，键不存在、已过期或已转移到磁盘时返回false
+    pub fn reset_access_stats(&mut self, key: &str) -> bool {
+        if self.expiry_manager.is_expired(key) || !self.data.contains_key(key) {
+            return false;
+        }
+        let now = self.expiry_manager.now_secs();
+        self.metadata
+            .entry(key.to_string())
+            .or_insert_with(|| DataMetadata::new(0, now))
+            .reset_access(now);
+        self.refresh_eviction_rank(key);
+        true
+    }
+
+    pub fn object_info(&self, key: &str, disk_value: Option<&DataType>) -> Option<ObjectInfo> {
+        if self.expiry_manager.is_expired(key) {
+            return None;
+        }
+        let (value, offloaded) = match (self.data.get(key), disk_value) {
+            (Some(value), _) => (value, false),
+            (None, Some(value)) if self.disk_keys.contains_key(key) => (value, true),
+            _ => return None,
+        };
+        Some(ObjectInfo {
+            type_name: value.type_name(),
+            length: value.element_count(),
+            serialized_size: value.estimated_size(),
+            access_count: self.metadata.get(key).map_or(0, |metadata| metadata.access_count),
+            offloaded,
+        })
+    }
+    
+    /// 列表的元素统计，键不存在或已过期时返回None，已转移到磁盘的键使用disk_value
+    pub fn list_info(&self, key: &str, disk_value: Option<&DataType>) -> StoreResult<Option<ListInfo>> {
+        if self.expiry_manager.is_expired(key) {
+            return Ok(None);
+        }
+        let value = match (self.data.get(key), disk_value) {
+            (Some(value), _) => value,
+            (None, Some(value)) if self.disk_keys.contains_key(key) => value,
+            _ => return Ok(None),
+        };
+        let DataType::List(list) = value else {
+            return Err(StoreError::TypeMismatch {
+                key: key.to_string(),
+                expected: "list".to_string(),
+                found: value.type_name().to_string(),
+            });
+        };
+        let element_bytes: usize = list.iter().map(|element| element.len()).sum();
+        Ok(Some(ListInfo {
+            length: list.len(),
+            total_bytes: value.estimated_size(),
+            average_element_bytes: if list.is_empty() { 0.0 } else { element_bytes as f64 / list.len() as f64 },
+            largest_element_bytes: list.iter().map(|element| element.len()).max().unwrap_or(0),
+        }))
     }
     
     /// 获取低频访问键
     pub fn get_low_frequency_keys(&self, count: usize) -> Vec<String> {
         if let Some(memory_manager) = &self.memory_manager {
-            memory_manager.get_low_frequency_keys(&self.data, &self.metadata)
+            memory_manager.get_low_frequency_keys(&self.data, &self.metadata, self.expiry_manager.now_secs())
         } else {
             // 简单实现：按访问计数排序，返回访问次数最少的键
             let mut key_counts: Vec<(String, u64)> = self.metadata
@@ -672,6 +1547,22 @@ impl Store {
         self.get_ttl(key).unwrap_or(-1)
     }
     
+    /// 在指定秒数内过期的键，按剩余生存时间从短到长排序
+    pub fn expiring_soon(&self, within_seconds: u64) -> Vec<String> {
+        self.expiry_manager.get_expiring_soon(within_seconds)
+    }
+    
+    /// 设置了过期时间且尚未过期的键，按名称排序
+    pub fn keys_with_ttl(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.expiry_manager
+            .get_keys_with_expiry()
+            .into_iter()
+            .filter(|key| !self.expiry_manager.is_expired(key))
+            .collect();
+        keys.sort();
+        keys
+    }
+    
     /// 兼容性方法：移除过期时间 (与测试兼容)
     pub fn persist(&mut self, key: &str) -> bool {
         self.persist_key(key).unwrap_or(false)
@@ -1,26 +1,292 @@
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use std::io::{Read, Write};
 use std::path::Path;
 use base64::prelude::*;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 
-use crate::config::Settings;
+use crate::clock::Clock;
+use crate::config::{CorruptDataFilePolicy, MaxMemoryPolicy, Settings};
 use super::store_core::Store;
+use super::data_types::{DataType, ExportRecord, KeySnapshot};
 use super::memory::{MemoryManager, OptimizationStats};
+use super::metadata::{ListInfo, ObjectInfo};
 use super::error::{StoreError, StoreResult};
-use super::store_transaction::TransactionStoreManager;
+use super::transaction::{StoreOperation, TransactionManager};
+use super::wal::{CheckpointRecord, KeyImage};
 use super::traits::*;
+use super::glob::glob_match;
+use super::list_ops::ListEnd;
+use super::expiry::ExpiryOption;
+
+/// 原子写入的临时文件名序号，同时进行的保存和导出各自使用不同的临时文件
+static NEXT_TEMP_FILE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// 先写入同目录下的临时文件并同步到磁盘，再重命名覆盖目标文件，
+/// 崩溃时目标文件要么是旧的完整版本，要么是新的完整版本
+///
+/// 临时文件名带有进程号和序号(`<文件名>.<进程号>.<序号>.tmp`)，同时写入同一个文件时不会互相覆盖临时文件；
+/// 写入失败时删除临时文件。
+pub(crate) fn write_file_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        NEXT_TEMP_FILE_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = path.with_file_name(temp_name);
+
+    let written = std::fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    // 同步目录，确保重命名本身已持久化
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// 最多保留的后台保存任务记录数
+const MAX_SAVE_JOBS: usize = 16;
+
+/// 后台保存任务的状态
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveJobState {
+    Running,
+    Completed { bytes: usize, elapsed: std::time::Duration },
+    Failed(String),
+}
+
+/// 后台保存任务记录
+#[derive(Debug, Default)]
+pub struct SaveStatus {
+    next_job_id: u64,
+    jobs: std::collections::VecDeque<(u64, SaveJobState)>,
+    last_save_time: Option<u64>, // 最近一次成功保存的Unix时间(秒)
+    last_bgsave_failed: bool,    // 最近一次后台保存是否失败
+}
+
+impl SaveStatus {
+    /// 最近一次成功保存的Unix时间(秒)
+    pub fn last_save_time(&self) -> Option<u64> {
+        self.last_save_time
+    }
+
+    /// 最近一次后台保存是否失败
+    pub fn last_bgsave_failed(&self) -> bool {
+        self.last_bgsave_failed
+    }
+
+    /// 登记新的后台保存任务，只保留最近的任务记录
+    fn start_job(&mut self) -> u64 {
+        self.next_job_id += 1;
+        self.jobs.push_back((self.next_job_id, SaveJobState::Running));
+        while self.jobs.len() > MAX_SAVE_JOBS {
+            self.jobs.pop_front();
+        }
+        self.next_job_id
+    }
+
+    fn finish_job(&mut self, job_id: u64, state: SaveJobState) {
+        self.last_bgsave_failed = matches!(state, SaveJobState::Failed(_));
+        if let Some((_, job)) = self.jobs.iter_mut().find(|(id, _)| *id == job_id) {
+            *job = state;
+        }
+    }
+
+    /// 查询任务状态
+    pub fn job(&self, job_id: u64) -> Option<SaveJobState> {
+        self.jobs.iter().find(|(id, _)| *id == job_id).map(|(_, state)| state.clone())
+    }
+}
+
+/// 后台WAL压缩的状态
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalCompactionState {
+    Running,
+    Completed { before: u64, after: u64, elapsed: std::time::Duration }, // 压缩前后的文件大小(字节)
+    Failed(String),
+}
+
+/// 写入序号与已持久化的序号，未记录WAL时WAIT命令据此等待写入保存到数据文件
+#[derive(Debug, Default)]
+struct Durability {
+    progress: Mutex<(u64, u64)>, // (最近一次写入的序号, 已保存到数据文件的最大序号)
+    persisted: Condvar,
+}
+
+impl Durability {
+    fn record_write(&self) -> u64 {
+        let mut progress = self.progress.lock().unwrap();
+        progress.0 += 1;
+        progress.0
+    }
+
+    fn last_write(&self) -> u64 {
+        self.progress.lock().unwrap().0
+    }
+
+    fn mark_durable(&self, seq: u64) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.1 = progress.1.max(seq);
+        self.persisted.notify_all();
+    }
+
+    fn wait_durable(&self, seq: u64, timeout: Option<Duration>) -> u64 {
+        let progress = self.progress.lock().unwrap();
+        let progress = match timeout {
+            Some(timeout) => {
+                self.persisted
+                    .wait_timeout_while(progress, timeout, |progress| progress.1 < seq)
+                    .unwrap()
+                    .0
+            }
+            None => self.persisted.wait_while(progress, |progress| progress.1 < seq).unwrap(),
+        };
+        progress.1
+    }
+}
+
+/// 一个阻塞在BLPOP/BRPOP上的连接，有元素推入它等待的列表时被唤醒
+#[derive(Debug, Default)]
+struct ListWaiter {
+    ready: Mutex<bool>,
+    condvar: Condvar,
+}
+
+/// 被等待的列表: (数据库编号, 键)
+type WaitedList = (usize, String);
+
+/// 登记等待列表元素的连接，推入元素时唤醒等待该列表的连接
+#[derive(Debug, Default)]
+struct ListWaiters {
+    waiters: Mutex<HashMap<WaitedList, Vec<Arc<ListWaiter>>>>,
+    closed: AtomicBool, // 服务器关闭后不再等待
+}
+
+impl ListWaiters {
+    fn register(&self, db: usize, keys: &[String]) -> Arc<ListWaiter> {
+        let waiter = Arc::new(ListWaiter::default());
+        let mut waiters = self.waiters.lock().unwrap();
+        for key in keys {
+            waiters.entry((db, key.clone())).or_default().push(Arc::clone(&waiter));
+        }
+        waiter
+    }
+
+    fn unregister(&self, db: usize, keys: &[String], waiter: &Arc<ListWaiter>) {
+        let mut waiters = self.waiters.lock().unwrap();
+        for key in keys {
+            let entry = (db, key.clone());
+            if let Some(list) = waiters.get_mut(&entry) {
+                list.retain(|other| !Arc::ptr_eq(other, waiter));
+                if list.is_empty() {
+                    waiters.remove(&entry);
+                }
+            }
+        }
+    }
+
+    fn notify(&self, db: usize, key: &str) {
+        let waiters = self.waiters.lock().unwrap();
+        for waiter in waiters.get(&(db, key.to_string())).into_iter().flatten() {
+            Self::wake(waiter);
+        }
+    }
+
+    /// 唤醒等待指定数据库中任意列表的连接，用于整个数据库的内容被替换时
+    fn notify_database(&self, db: usize) {
+        let waiters = self.waiters.lock().unwrap();
+        for waiter in waiters.iter().filter(|((waited_db, _), _)| *waited_db == db).flat_map(|(_, list)| list) {
+            Self::wake(waiter);
+        }
+    }
+
+    /// 标记为已关闭并唤醒所有等待的连接
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let waiters = self.waiters.lock().unwrap();
+        for waiter in waiters.values().flatten() {
+            Self::wake(waiter);
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    fn wake(waiter: &ListWaiter) {
+        *waiter.ready.lock().unwrap() = true;
+        waiter.condvar.notify_one();
+    }
+}
+
+/// 默认的数据库数量
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// 当前选择的数据库编号，克隆时复制当前值，各连接的选择互不影响
+#[derive(Debug, Default)]
+struct SelectedDb(AtomicUsize);
+
+impl Clone for SelectedDb {
+    fn clone(&self) -> Self {
+        SelectedDb(AtomicUsize::new(self.0.load(Ordering::SeqCst)))
+    }
+}
+
+/// 低频数据目录不可写后第一次重新检查的间隔
+const OFFLOAD_RETRY_MIN: Duration = Duration::from_secs(5);
+/// 重新检查的最长间隔
+const OFFLOAD_RETRY_MAX: Duration = Duration::from_secs(300);
+
+/// 停止转移后下一次检查目录的时间和当前的退避间隔
+#[derive(Debug)]
+struct OffloadRetry {
+    next_at: Instant,
+    backoff: Duration,
+}
+
+impl OffloadRetry {
+    fn new() -> Self {
+        OffloadRetry {
+            next_at: Instant::now() + OFFLOAD_RETRY_MIN,
+            backoff: OFFLOAD_RETRY_MIN,
+        }
+    }
+}
 
 /// 重构后的线程安全存储管理器
 #[derive(Debug, Clone)]
 pub struct StoreManager {
-    store: Arc<Mutex<Store>>,
+    databases: Arc<Vec<Arc<Mutex<Store>>>>, // 所有数据库，编号即下标
+    selected_db: SelectedDb,                // 当前选择的数据库
     disk_base_path: String,
     last_check_time: Arc<Mutex<Instant>>,
     settings: Option<Arc<Settings>>,
-    transaction_manager: Option<Arc<TransactionStoreManager>>,
-    use_wal: bool,
+    transaction_manager: Option<Arc<TransactionManager>>, // 设置后写命令修改的键记录到WAL
     background_optimization_enabled: bool,
-    optimization_interval: u64,
+    optimization_interval: Arc<AtomicU64>, // 所有克隆共享，可在运行时调整
+    save_status: Arc<Mutex<SaveStatus>>,
+    active_expire: Arc<AtomicBool>, // 后台检查时是否主动清理过期键
+    offload_disabled: Arc<AtomicBool>, // 低频数据目录不可写时停止转移，数据保留在内存中
+    offload_retry: Arc<Mutex<OffloadRetry>>, // 停止转移后按退避间隔重新检查目录是否可写
+    loading: Arc<AtomicBool>,          // 是否正在从WAL和数据文件恢复数据
+    durability: Arc<Durability>,
+    list_waiters: Arc<ListWaiters>, // 阻塞在BLPOP/BRPOP上的连接，所有克隆共享
+    wal_compaction: Arc<Mutex<Option<WalCompactionState>>>, // 最近一次后台WAL压缩的状态，所有连接共享
 }
 
 impl Default for StoreManager {
@@ -32,24 +298,46 @@ impl Default for StoreManager {
 impl StoreManager {
     pub fn new() -> Self {
         StoreManager {
-            store: Arc::new(Mutex::new(Store::new())),
+            databases: Arc::new(
+                (0..DEFAULT_DATABASES)
+                    .map(|_| Arc::new(Mutex::new(Store::new())))
+                    .collect(),
+            ),
+            selected_db: SelectedDb::default(),
             disk_base_path: "data/low_freq".to_string(),
             last_check_time: Arc::new(Mutex::new(Instant::now())),
             settings: None,
             transaction_manager: None,
-            use_wal: false,
             background_optimization_enabled: false,
-            optimization_interval: 300, // 5分钟
+            optimization_interval: Arc::new(AtomicU64::new(300)), // 5分钟
+            save_status: Arc::new(Mutex::new(SaveStatus::default())),
+            active_expire: Arc::new(AtomicBool::new(true)),
+            offload_disabled: Arc::new(AtomicBool::new(false)),
+            offload_retry: Arc::new(Mutex::new(OffloadRetry::new())),
+            loading: Arc::new(AtomicBool::new(false)),
+            durability: Arc::new(Durability::default()),
+            list_waiters: Arc::new(ListWaiters::default()),
+            wal_compaction: Arc::new(Mutex::new(None)),
         }
     }
 
     /// 使用配置构建
     pub fn with_settings(mut self, settings: Arc<Settings>) -> Self {
         // 将设置传递给 Store
-        {
-            let mut store = self.store.lock().unwrap();
+        for store in self.databases.iter() {
+            let mut store = store.lock().unwrap();
             *store = store.clone().with_settings(Arc::clone(&settings));
         }
+
+        // 按配置调整数据库数量，新数据库沿用0号数据库的配置
+        let count = settings.server.databases.max(1);
+        let databases = Arc::make_mut(&mut self.databases);
+        databases.truncate(count);
+        while databases.len() < count {
+            let store = databases[0].lock().unwrap().empty_like();
+            databases.push(Arc::new(Mutex::new(store)));
+        }
+        self.disk_base_path = settings.data_paths().offload_dir.to_string_lossy().to_string();
         self.settings = Some(settings);
         self
     }
@@ -72,51 +360,260 @@ impl StoreManager {
             );
 
             // 设置存储的内存管理器
-            {
-                let mut store = self.store.lock().unwrap();
-                *store = store.clone().with_memory_manager(memory_manager);
+            for store in self.databases.iter() {
+                let mut store = store.lock().unwrap();
+                *store = store.clone().with_memory_manager(memory_manager.clone());
             }
 
             self.disk_base_path = disk_base_path.to_string();
             
             // 创建磁盘目录
-            if std::fs::create_dir_all(&self.disk_base_path).is_err() {
-                eprintln!("警告: 无法创建低频数据目录: {}", &self.disk_base_path);
+            if let Err(e) = std::fs::create_dir_all(&self.disk_base_path) {
+                self.disable_offload(&e);
             }
         }
         
         self
     }
 
-    /// 启用 WAL 功能
-    pub fn with_wal(mut self, _wal_path: &Path) -> Self {
-        let txn_manager = TransactionStoreManager::new();
-        self.transaction_manager = Some(Arc::new(txn_manager));
-        self.use_wal = true;
+    /// 使用指定的时钟判断键是否过期
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        for store in self.databases.iter() {
+            let mut store = store.lock().unwrap();
+            *store = store.clone().with_clock(Arc::clone(&clock));
+        }
+        self
+    }
+
+    /// 开启或关闭过期键的主动清理，关闭后过期键只在访问时惰性判断
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.active_expire.store(enabled, Ordering::SeqCst);
+    }
+
+    /// 是否主动清理过期键
+    pub fn active_expire_enabled(&self) -> bool {
+        self.active_expire.load(Ordering::SeqCst)
+    }
+
+    /// 是否允许将低频数据转移到磁盘
+    pub fn offload_enabled(&self) -> bool {
+        !self.offload_disabled.load(Ordering::SeqCst)
+    }
+
+    /// 标记是否正在恢复数据，服务器启动时在WAL恢复和加载数据文件期间设置
+    pub fn set_loading(&self, loading: bool) {
+        self.loading.store(loading, Ordering::SeqCst);
+    }
+
+    /// 是否正在恢复数据
+    pub fn is_loading(&self) -> bool {
+        self.loading.load(Ordering::SeqCst)
+    }
+
+    /// 低频数据目录不可写时停止转移，只在第一次失败时记录日志
+    fn disable_offload(&self, err: &dyn std::fmt::Display) {
+        if !self.offload_disabled.swap(true, Ordering::SeqCst) {
+            *self.offload_retry.lock().unwrap() = OffloadRetry::new();
+            log::error!(
+                "低频数据目录 {} 不可写({})，已停止转移数据到磁盘，数据将保留在内存中",
+                self.disk_base_path, err
+            );
+        }
+    }
+
+    /// 立即检查低频数据目录是否重新可写，可写时恢复转移并返回 true
+    ///
+    /// 检查失败时下一次自动检查的间隔翻倍，最长为 `OFFLOAD_RETRY_MAX`。
+    pub fn retry_offload(&self) -> bool {
+        if self.offload_enabled() {
+            return true;
+        }
+        let probe = Path::new(&self.disk_base_path).join(".probe");
+        let writable = std::fs::create_dir_all(&self.disk_base_path)
+            .and_then(|_| std::fs::write(&probe, b""))
+            .and_then(|_| std::fs::remove_file(&probe));
+        let mut retry = self.offload_retry.lock().unwrap();
+        match writable {
+            Ok(()) => {
+                *retry = OffloadRetry::new();
+                self.offload_disabled.store(false, Ordering::SeqCst);
+                log::info!("低频数据目录 {} 已恢复可写，重新开始转移数据到磁盘", self.disk_base_path);
+                true
+            }
+            Err(e) => {
+                retry.backoff = (retry.backoff * 2).min(OFFLOAD_RETRY_MAX);
+                retry.next_at = Instant::now() + retry.backoff;
+                log::debug!("低频数据目录 {} 仍不可写({})，{:?} 后重试", self.disk_base_path, e, retry.backoff);
+                false
+            }
+        }
+    }
+
+    /// 停止转移后到了重试时间时重新检查目录
+    fn retry_offload_if_due(&self) {
+        if !self.offload_enabled() && Instant::now() >= self.offload_retry.lock().unwrap().next_at {
+            self.retry_offload();
+        }
+    }
+
+    /// 使用所有连接共享的事务管理器记录写入，崩溃后启动时通过 `recover_from_wal` 重放
+    pub fn with_transaction_manager(mut self, txn_manager: Arc<TransactionManager>) -> Self {
+        self.transaction_manager = Some(txn_manager);
         self
     }
 
     /// 启用后台优化
     pub fn with_background_optimization(mut self, enabled: bool, interval_seconds: u64) -> Self {
         self.background_optimization_enabled = enabled;
-        self.optimization_interval = interval_seconds;
+        self.optimization_interval.store(interval_seconds, Ordering::Relaxed);
         self
     }
 
-    /// 获取存储的引用
+    /// 运行时调整后台优化的检查间隔，下一次检查起生效
+    pub fn set_optimization_interval(&self, interval_seconds: u64) {
+        self.optimization_interval.store(interval_seconds, Ordering::Relaxed);
+    }
+
+    /// 运行时替换所有数据库使用的配置，之后的写入按新配置应用默认过期时间等设置
+    pub fn update_settings(&self, settings: Arc<Settings>) {
+        for store in self.databases.iter() {
+            store.lock().unwrap().update_settings(Arc::clone(&settings));
+        }
+    }
+
+    /// 获取当前数据库存储的引用
     pub fn get_store(&self) -> Arc<Mutex<Store>> {
-        Arc::clone(&self.store)
+        Arc::clone(self.store())
+    }
+
+    /// 当前选择的数据库
+    fn store(&self) -> &Arc<Mutex<Store>> {
+        &self.databases[self.selected_db()]
+    }
+
+    /// 数据库数量
+    pub fn database_count(&self) -> usize {
+        self.databases.len()
+    }
+
+    /// 当前选择的数据库编号
+    pub fn selected_db(&self) -> usize {
+        self.selected_db.0.load(Ordering::SeqCst)
+    }
+
+    /// 切换当前连接使用的数据库
+    pub fn select(&self, index: usize) -> StoreResult<()> {
+        self.check_db_index(index)?;
+        self.selected_db.0.store(index, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// 原子地交换两个数据库的内容，已选择这两个数据库的连接立即看到交换后的数据
+    pub fn swap_db(&self, first: usize, second: usize) -> StoreResult<()> {
+        self.check_db_index(first)?;
+        self.check_db_index(second)?;
+        if first == second {
+            return Ok(());
+        }
+
+        // 按编号顺序加锁，避免两个方向相反的SWAPDB互相等待
+        let (low, high) = (first.min(second), first.max(second));
+        let mut low_store = self.databases[low].lock().unwrap();
+        let mut high_store = self.databases[high].lock().unwrap();
+
+        // 转移到磁盘的键保存在各数据库自己的目录下，交换前先加载回内存
+        self.load_disk_keys(low, &mut low_store)?;
+        self.load_disk_keys(high, &mut high_store)?;
+        std::mem::swap(&mut *low_store, &mut *high_store);
+        drop((low_store, high_store));
+        self.list_waiters.notify_database(low);
+        self.list_waiters.notify_database(high);
+        Ok(())
+    }
+
+    /// 检查数据库编号是否有效
+    fn check_db_index(&self, index: usize) -> StoreResult<()> {
+        if index < self.databases.len() {
+            Ok(())
+        } else {
+            Err(StoreError::General("DB index is out of range".to_string()))
+        }
+    }
+
+    /// 将数据库中所有已转移到磁盘的键加载回内存
+    fn load_disk_keys(&self, db: usize, store: &mut Store) -> StoreResult<()> {
+        for key in store.get_disk_keys() {
+            if !store.data.contains_key(&key) {
+                let content = self.read_key_file(db, &key)?;
+                store.deserialize_key(&key, &content)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取键的磁盘文件路径，0号以外的数据库使用各自的子目录，压缩文件的扩展名为.json.gz
+    fn get_key_file_path(&self, db: usize, key: &str, compressed: bool) -> String {
+        let extension = if compressed { "json.gz" } else { "json" };
+        format!("{}/{}.{}", self.db_offload_dir(db), BASE64_STANDARD.encode(key), extension)
+    }
+
+    /// 数据库的低频数据目录
+    fn db_offload_dir(&self, db: usize) -> String {
+        if db == 0 {
+            self.disk_base_path.clone()
+        } else {
+            format!("{}/db{}", self.disk_base_path, db)
+        }
     }
 
-    /// 获取键的磁盘文件路径
-    fn get_key_file_path(&self, key: &str) -> String {
-        format!("{}/{}.json", self.disk_base_path, BASE64_STANDARD.encode(key))
+    /// 转移到磁盘的数据是否压缩
+    fn compress_offload(&self) -> bool {
+        self.settings
+            .as_ref()
+            .is_some_and(|settings| settings.memory.compress_offload)
+    }
+
+    /// 打开键的磁盘文件，压缩的文件读取时自动解压
+    fn open_key_file(&self, db: usize, key: &str) -> StoreResult<Box<dyn Read>> {
+        match std::fs::File::open(self.get_key_file_path(db, key, true)) {
+            Ok(file) => Ok(Box::new(GzDecoder::new(file))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Box::new(std::fs::File::open(self.get_key_file_path(db, key, false))?))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 读取键的磁盘文件，根据扩展名决定是否解压
+    fn read_key_file(&self, db: usize, key: &str) -> StoreResult<String> {
+        let mut content = String::new();
+        self.open_key_file(db, key)?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    /// 只读取磁盘文件开头的类型标记得到键的类型，不反序列化整个值
+    fn read_key_type(&self, db: usize, key: &str) -> StoreResult<Option<&'static str>> {
+        let mut prefix = Vec::new();
+        self.open_key_file(db, key)?.take(16).read_to_end(&mut prefix)?;
+        Ok(DataType::type_name_from_serialized(&prefix))
+    }
+
+    /// 删除键的磁盘文件(包括压缩和未压缩两种)
+    fn remove_key_files(&self, key: &str) {
+        self.remove_key_files_in(self.selected_db(), key);
+    }
+
+    /// 删除指定数据库中键的磁盘文件
+    fn remove_key_files_in(&self, db: usize, key: &str) {
+        for compressed in [false, true] {
+            let _ = std::fs::remove_file(self.get_key_file_path(db, key, compressed));
+        }
     }
 
     /// 检查是否应该执行低频数据检查
     pub fn should_check_low_frequency(&self) -> bool {
         let elapsed = self.last_check_time.lock().unwrap().elapsed().as_secs();
-        elapsed >= self.optimization_interval
+        elapsed >= self.optimization_interval.load(Ordering::Relaxed)
     }
 
     /// 执行低频数据转移
@@ -126,30 +623,45 @@ impl StoreManager {
         let mut offloaded_count = 0;
         
         // 首先清理过期键
-        {
-            let mut store = self.store.lock().unwrap();
-            let expired_count = store.clean_expired_keys();
+        if self.active_expire_enabled() {
+            let expired_count: usize = self
+                .databases
+                .iter()
+                .map(|store| store.lock().unwrap().clean_expired_keys())
+                .sum();
             if expired_count > 0 {
                 log::info!("清理了 {} 个过期键", expired_count);
             }
         }
 
+        // 按周期衰减访问次数，很久以前的访问不再影响淘汰顺序
+        let decay_seconds = self.settings.as_ref().map_or(0, |settings| settings.memory.lfu_decay_seconds);
+        for store in self.databases.iter() {
+            store.lock().unwrap().decay_access_counts(decay_seconds);
+        }
+
         // 检查是否需要内存优化
         let should_optimize = {
-            let store = self.store.lock().unwrap();
+            let store = self.store().lock().unwrap();
             store.should_optimize_memory()
         };
 
         if should_optimize {
+            self.retry_offload_if_due();
+        }
+        if should_optimize && self.offload_enabled() {
             // 获取需要转移的键
             let low_freq_keys = {
-                let store = self.store.lock().unwrap();
+                let store = self.store().lock().unwrap();
                 store.get_low_frequency_keys(100) // 一次最多转移100个键
             };
 
             // 转移键到磁盘
             for key in &low_freq_keys {
                 if let Err(err) = self.offload_key_to_disk(key) {
+                    if !self.offload_enabled() {
+                        break;
+                    }
                     log::error!("将键 '{}' 转移到磁盘时出错: {}", key, err);
                     continue;
                 }
@@ -167,18 +679,35 @@ impl StoreManager {
     /// 将键转移到磁盘
     fn offload_key_to_disk(&self, key: &str) -> StoreResult<()> {
         let serialized_data = {
-            let store = self.store.lock().unwrap();
+            let store = self.store().lock().unwrap();
             match store.serialize_key(key)? {
                 Some(data) => data,
                 None => return Ok(()),
             }
         };
 
-        let file_path = self.get_key_file_path(key);
-        std::fs::write(&file_path, serialized_data)?;
+        let compressed = self.compress_offload();
+        let file_data = if compressed {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(serialized_data.as_bytes())?;
+            encoder.finish()?
+        } else {
+            serialized_data.into_bytes()
+        };
+
+        let db = self.selected_db();
+        let file_path = self.get_key_file_path(db, key, compressed);
+        let written = std::fs::create_dir_all(self.db_offload_dir(db))
+            .and_then(|_| std::fs::write(&file_path, file_data));
+        if let Err(e) = written {
+            self.disable_offload(&e);
+            return Err(e.into());
+        }
+        // 删除另一种格式的旧文件，避免加载时读到过期的数据
+        let _ = std::fs::remove_file(self.get_key_file_path(db, key, !compressed));
 
         {
-            let mut store = self.store.lock().unwrap();
+            let mut store = self.store().lock().unwrap();
             store.mark_as_disk_stored(key);
         }
 
@@ -188,7 +717,7 @@ impl StoreManager {
     /// 从磁盘加载键
     pub fn load_key_from_disk(&self, key: &str) -> StoreResult<bool> {
         let needs_loading = {
-            let store = self.store.lock().unwrap();
+            let store = self.store().lock().unwrap();
             !store.data.contains_key(key) && store.disk_keys.contains_key(key)
         };
 
@@ -196,11 +725,10 @@ impl StoreManager {
             return Ok(false);
         }
 
-        let file_path = self.get_key_file_path(key);
-        let content = std::fs::read_to_string(&file_path)?;
+        let content = self.read_key_file(self.selected_db(), key)?;
 
         {
-            let mut store = self.store.lock().unwrap();
+            let mut store = self.store().lock().unwrap();
             store.deserialize_key(key, &content)?;
         }
 
@@ -213,13 +741,27 @@ impl StoreManager {
     }
 
     /// 从文件加载整个存储
+    ///
+    /// 启用按数据库分文件保存时，依次加载每个数据库自己的数据文件，文件不存在的数据库保持为空。
     pub fn load_from_file(&self, file_path: &str) -> StoreResult<()> {
-        match std::fs::read_to_string(file_path) {
-            Ok(content) if !content.is_empty() => {
-                let mut store = self.store.lock().unwrap();
-                store.deserialize(&content)
+        if self.per_database_files() {
+            if let Some(parent) = Path::new(file_path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            for db in 0..self.databases.len() {
+                let db_file = Self::database_file_path(file_path, db);
+                match std::fs::read_to_string(&db_file) {
+                    Ok(content) => self.load_database(db, &db_file, &content)?,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(StoreError::IoError(e.to_string())),
+                }
             }
-            Ok(_) => Ok(()),
+            return Ok(());
+        }
+
+        match std::fs::read_to_string(file_path) {
+            // 0号数据库保存在顶层，其他数据库保存在databases字段
+            Ok(content) => self.load_database(0, file_path, &content),
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::NotFound {
                     // 创建目录和文件
@@ -235,46 +777,417 @@ impl StoreManager {
         }
     }
 
-    /// 保存到文件
-    pub fn save_to_file(&self, file_path: &str) -> StoreResult<()> {
+    /// 将数据文件的内容加载到指定数据库，文件损坏时按配置隔离
+    fn load_database(&self, db: usize, file_path: &str, content: &str) -> StoreResult<()> {
+        if content.is_empty() {
+            return Ok(());
+        }
+        let result = if self.per_database_files() {
+            self.databases[db].lock().unwrap().deserialize(content)
+        } else {
+            self.load_all_databases(content)
+        };
+        match result {
+            Err(StoreError::SerializationError(e)) if self.quarantine_corrupt_data_file() => {
+                let quarantined = Self::quarantine_file(file_path)?;
+                log::error!(
+                    "数据文件 {} 已损坏({})，已隔离为 {}，继续使用当前数据启动",
+                    file_path, e, quarantined
+                );
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// 加载所有数据库共用的数据文件，编号超出数据库数量的内容被忽略
+    fn load_all_databases(&self, content: &str) -> StoreResult<()> {
+        let others = self.databases[0].lock().unwrap().deserialize_databases(content)?;
+        for (db, store) in others {
+            match self.databases.get(db) {
+                Some(database) => *database.lock().unwrap() = store,
+                None => log::warn!("数据文件包含{}号数据库，超出了配置的数据库数量，已忽略", db),
+            }
+        }
+        Ok(())
+    }
+
+    /// 是否每个数据库保存到单独的数据文件
+    fn per_database_files(&self) -> bool {
+        self.settings
+            .as_ref()
+            .is_some_and(|settings| settings.persistence.per_database_files)
+    }
+
+    /// 数据库单独保存时的文件路径，编号插入在扩展名之前，如storage.dat对应storage.1.dat
+    pub fn database_file_path(file_path: &str, db: usize) -> String {
+        let path = Path::new(file_path);
+        match (path.file_stem(), path.extension()) {
+            (Some(stem), Some(extension)) => path
+                .with_file_name(format!("{}.{}.{}", stem.to_string_lossy(), db, extension.to_string_lossy()))
+                .to_string_lossy()
+                .to_string(),
+            _ => format!("{}.{}", file_path, db),
+        }
+    }
+
+    /// 数据文件损坏时是否隔离，默认拒绝启动
+    fn quarantine_corrupt_data_file(&self) -> bool {
+        self.settings.as_ref().is_some_and(|settings| {
+            settings.persistence.on_corrupt_data_file == CorruptDataFilePolicy::Quarantine
+        })
+    }
+
+    /// 将文件改名为 `<file>.corrupt-<时间戳>`，返回新路径
+    fn quarantine_file(file_path: &str) -> StoreResult<String> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let quarantined = format!("{}.corrupt-{}", file_path, timestamp);
+        std::fs::rename(file_path, &quarantined)?;
+        Ok(quarantined)
+    }
+
+    /// 保存到文件，返回写入的字节数
+    pub fn save_to_file(&self, file_path: &str) -> StoreResult<usize> {
         // 如果启用了内存优化，先执行优化
         if self.background_optimization_enabled {
             let _ = self.check_and_offload_low_frequency_data();
         }
 
-        // 如果使用WAL，创建检查点
-        if self.use_wal {
-            if let Some(txn_manager) = &self.transaction_manager {
-                txn_manager
-                    .create_checkpoint()
-                    .map_err(|e| StoreError::WalError(format!("创建检查点失败: {}", e)))?;
+        let stores: Vec<_> = self.databases.iter().map(|store| store.lock().unwrap()).collect();
+        // 写入在修改完成后才登记序号，持有所有数据库的锁时读到的序号之前的写入都已包含在快照中
+        let write_seq = self.durability.last_write();
+        let bytes = self.write_data_files(file_path, &stores)?;
+        drop(stores);
+        self.mark_saved(write_seq);
+        Ok(bytes)
+    }
+
+    /// 把所有数据库写入数据文件，返回写入的字节数
+    ///
+    /// 未启用按数据库分文件保存时，所有数据库保存在同一个数据文件中。
+    fn write_data_files(&self, file_path: &str, stores: &[MutexGuard<'_, Store>]) -> StoreResult<usize> {
+        if self.per_database_files() {
+            let mut bytes = 0;
+            for (db, store) in stores.iter().enumerate() {
+                let data = store.serialize()?;
+                write_file_atomically(Path::new(&Self::database_file_path(file_path, db)), data.as_bytes())?;
+                bytes += data.len();
+            }
+            return Ok(bytes);
+        }
+
+        let others: Vec<(usize, &Store)> = stores.iter().enumerate().skip(1).map(|(db, store)| (db, &**store)).collect();
+        let data = stores[0].serialize_databases(&others)?;
+        write_file_atomically(Path::new(file_path), data.as_bytes())?;
+        Ok(data.len())
+    }
+
+    /// 数据文件写入成功后记录持久化进度和保存时间
+    fn mark_saved(&self, write_seq: u64) {
+        self.durability.mark_durable(write_seq);
+        // 同步WAL，等待WAL落盘的WAIT在保存后同样返回
+        if let Some(txn_manager) = &self.transaction_manager {
+            if let Err(e) = txn_manager.flush_wal() {
+                log::error!("保存后同步WAL失败: {}", e);
+            }
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.save_status.lock().unwrap().last_save_time = Some(now);
+    }
+
+    /// 保存到数据文件后重新加载到新的存储中并与当前数据比较，用于验证持久化的正确性
+    ///
+    /// 覆盖所有数据库：一致时用重新加载的存储替换各个数据库并返回None；不一致时保留当前数据，
+    /// 返回第一处差异的描述，非0号数据库的差异带有数据库编号。整个过程持有所有数据库的锁，期间的写入不会造成误报。
+    pub fn debug_reload(&self, file_path: &str) -> StoreResult<Option<String>> {
+        let mut stores: Vec<_> = self.databases.iter().map(|store| store.lock().unwrap()).collect();
+        let write_seq = self.durability.last_write();
+        self.write_data_files(file_path, &stores)?;
+        self.mark_saved(write_seq);
+
+        let reloaded = self.read_data_files(file_path, &stores)?;
+        for (db, (store, database)) in stores.iter().zip(&reloaded).enumerate() {
+            if let Some(mismatch) = store.diff(database) {
+                if db == 0 {
+                    return Ok(Some(mismatch));
+                }
+                return Ok(Some(format!("db {}: {}", db, mismatch)));
             }
         }
+        for (store, database) in stores.iter_mut().zip(reloaded) {
+            **store = database;
+        }
+        Ok(None)
+    }
+
+    /// 把数据文件中的所有数据库读取到新的存储中，不修改当前数据
+    fn read_data_files(&self, file_path: &str, stores: &[MutexGuard<'_, Store>]) -> StoreResult<Vec<Store>> {
+        if self.per_database_files() {
+            let mut reloaded = Vec::with_capacity(stores.len());
+            for (db, store) in stores.iter().enumerate() {
+                let mut database = store.empty_like();
+                database.deserialize(&std::fs::read_to_string(Self::database_file_path(file_path, db))?)?;
+                reloaded.push(database);
+            }
+            return Ok(reloaded);
+        }
 
-        let store = self.store.lock().unwrap();
-        let data = store.serialize()?;
-        std::fs::write(file_path, data)?;
+        let mut first = stores[0].empty_like();
+        let mut others = first.deserialize_databases(&std::fs::read_to_string(file_path)?)?;
+        let mut reloaded = vec![first];
+        for (db, store) in stores.iter().enumerate().skip(1) {
+            reloaded.push(others.remove(&db).unwrap_or_else(|| store.empty_like()));
+        }
+        Ok(reloaded)
+    }
+
+    /// 在后台线程中保存到文件，返回可用于查询状态的任务ID
+    pub fn bg_save(&self, file_path: String) -> u64 {
+        let job_id = self.save_status.lock().unwrap().start_job();
+        let store_manager = self.clone();
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            let state = match store_manager.save_to_file(&file_path) {
+                Ok(bytes) => {
+                    log::debug!("Background save {} completed", job_id);
+                    SaveJobState::Completed { bytes, elapsed: start.elapsed() }
+                }
+                Err(e) => {
+                    log::error!("Background save {} failed: {}", job_id, e);
+                    SaveJobState::Failed(e.to_string())
+                }
+            };
+            store_manager.save_status.lock().unwrap().finish_job(job_id, state);
+        });
+        job_id
+    }
+
+    /// 提交事务，把事务的操作应用到当前选择的数据库
+    ///
+    /// 事务涉及的已转移到磁盘的键先载入内存，校验、写入WAL和应用操作期间持有数据库的锁。
+    pub fn commit_transaction(&self, txn_manager: &TransactionManager, txn_id: u64) -> StoreResult<()> {
+        let operations = txn_manager
+            .get_transaction(txn_id)
+            .map_err(|e| StoreError::WalError(e.to_string()))?
+            .operations;
+        self.enforce_memory_limit(operations.iter().map(StoreOperation::added_bytes).sum())?;
+        for operation in &operations {
+            self.ensure_key_loaded(operation.key())?;
+        }
+
+        let db = self.selected_db();
+        {
+            let mut store = self.store().lock().unwrap();
+            txn_manager
+                .commit_transaction_in(txn_id, db, &mut store)
+                .map_err(|e| StoreError::WalError(e.to_string()))?;
+        }
+        for operation in &operations {
+            if let StoreOperation::LPush(key, _) | StoreOperation::RPush(key, _) = operation {
+                self.list_waiters.notify(db, key);
+            }
+        }
         Ok(())
     }
 
-    /// 从WAL恢复数据
-    pub fn recover_from_wal(&self) -> StoreResult<()> {
-        if !self.use_wal {
+    /// 在后台线程中压缩WAL日志，已有后台压缩正在进行时不启动并返回false
+    ///
+    /// txn_manager应是所有连接共享的事务管理器，压缩期间持有它的WAL锁，
+    /// 与COMPACTWAL、定期压缩和事务写入依次进行。
+    pub fn bg_compact_wal(&self, txn_manager: Arc<TransactionManager>) -> bool {
+        {
+            let mut state = self.wal_compaction.lock().unwrap();
+            if *state == Some(WalCompactionState::Running) {
+                return false;
+            }
+            *state = Some(WalCompactionState::Running);
+        }
+        let wal_compaction = Arc::clone(&self.wal_compaction);
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            let result = {
+                let mut wal = txn_manager.get_wal_manager();
+                wal.get_file_size().and_then(|before| {
+                    wal.compact()?;
+                    Ok((before, wal.get_file_size()?))
+                })
+            };
+            let state = match result {
+                Ok((before, after)) => {
+                    log::info!("Background WAL compaction completed: {} -> {} bytes", before, after);
+                    WalCompactionState::Completed { before, after, elapsed: start.elapsed() }
+                }
+                Err(e) => {
+                    log::error!("Background WAL compaction failed: {}", e);
+                    WalCompactionState::Failed(e.to_string())
+                }
+            };
+            *wal_compaction.lock().unwrap() = Some(state);
+        });
+        true
+    }
+
+    /// 最近一次后台WAL压缩的状态，从未执行时返回None
+    pub fn wal_compaction_state(&self) -> Option<WalCompactionState> {
+        self.wal_compaction.lock().unwrap().clone()
+    }
+
+    /// 查询后台保存任务状态
+    pub fn save_job(&self, job_id: u64) -> Option<SaveJobState> {
+        self.save_status.lock().unwrap().job(job_id)
+    }
+
+    /// 最近一次成功保存的Unix时间(秒)
+    pub fn last_save_time(&self) -> Option<u64> {
+        self.save_status.lock().unwrap().last_save_time()
+    }
+
+    /// 最近一次后台保存是否失败
+    pub fn last_bgsave_failed(&self) -> bool {
+        self.save_status.lock().unwrap().last_bgsave_failed()
+    }
+
+    /// 登记一次已完成的写入，返回写入序号
+    ///
+    /// 设置了事务管理器时写入已记录到WAL，序号是WAL已追加的位置，WAL同步到磁盘后即为已持久化。
+    pub fn record_write(&self) -> u64 {
+        match &self.transaction_manager {
+            Some(txn_manager) => txn_manager.wal_position(),
+            None => self.durability.record_write(),
+        }
+    }
+
+    /// 等待序号不大于seq的写入都持久化，返回已持久化的最大写入序号
+    ///
+    /// 设置了事务管理器时等待WAL同步到磁盘，否则等待保存到数据文件。
+    /// timeout为None时一直等待；超时返回时结果可能小于seq。
+    pub fn wait_durable(&self, seq: u64, timeout: Option<Duration>) -> u64 {
+        match &self.transaction_manager {
+            Some(txn_manager) => txn_manager.wait_wal_synced(seq, timeout),
+            None => self.durability.wait_durable(seq, timeout),
+        }
+    }
+
+    /// 在从数据文件加载的数据上重放WAL，返回恢复后的键数量
+    ///
+    /// WAL中有检查点时以检查点替换所有数据库，再应用之后的条目。恢复完成后以全部数据
+    /// 创建检查点并压缩WAL，之后的日志只依赖这个检查点，不再依赖数据文件。
+    pub fn recover_from_wal(&self) -> StoreResult<usize> {
+        let Some(txn_manager) = &self.transaction_manager else {
+            return Ok(0);
+        };
+        let wal_error = |e: super::wal::WalError| StoreError::WalError(e.to_string());
+
+        let mut stores: Vec<_> = self.databases.iter().map(|store| store.lock().unwrap()).collect();
+        let mut databases = std::collections::BTreeMap::new();
+        for (db, store) in stores.iter_mut().enumerate() {
+            // 转移到磁盘的键先加载回内存，日志条目可能修改它们
+            self.load_disk_keys(db, store)?;
+            let empty = store.empty_like();
+            databases.insert(db, std::mem::replace(&mut **store, empty));
+        }
+
+        let recovered = txn_manager.recover_databases(databases).map_err(wal_error)?;
+        let records = recovered.records();
+        for (db, store) in recovered.into_databases() {
+            match stores.get_mut(db) {
+                Some(target) => **target = store,
+                None => log::warn!("WAL中的数据库{}超出数据库数量，已忽略", db),
+            }
+        }
+        let keys = records.len();
+        txn_manager.compact_wal_with(records).map_err(wal_error)?;
+        Ok(keys)
+    }
+
+    /// 以所有数据库的当前数据创建WAL检查点并压缩WAL，返回检查点中的键数量，未设置事务管理器时不做任何事
+    ///
+    /// 已转移到磁盘的键从磁盘文件读取。整个过程持有所有数据库的锁，检查点不会遗漏期间的写入。
+    pub fn checkpoint_wal(&self) -> StoreResult<usize> {
+        let Some(txn_manager) = &self.transaction_manager else {
+            return Ok(0);
+        };
+        let stores: Vec<_> = self.databases.iter().map(|store| store.lock().unwrap()).collect();
+        let mut records = Vec::new();
+        for (db, store) in stores.iter().enumerate() {
+            let mut keys = store.get_all_keys();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                if let Some(snapshot) = self.key_image(db, store, &key)? {
+                    records.push(CheckpointRecord { db, key, snapshot });
+                }
+            }
+        }
+        let keys = records.len();
+        txn_manager
+            .compact_wal_with(records)
+            .map_err(|e| StoreError::WalError(e.to_string()))?;
+        Ok(keys)
+    }
+
+    /// 把写命令修改过的键的当前状态记录到WAL，未设置事务管理器时不做任何事
+    ///
+    /// writes中每一项为数据库编号和键，键为None时记录整个数据库。读取状态和写入日志期间
+    /// 持有这些数据库的锁，同一个键最后写入日志的状态总是它在内存中的最新状态。
+    pub fn log_key_images(&self, writes: &[(usize, Option<&[String]>)]) -> StoreResult<()> {
+        let Some(txn_manager) = &self.transaction_manager else {
             return Ok(());
+        };
+
+        // 编号无效的数据库没有被修改；按编号顺序加锁，与SWAPDB一致
+        let writes: Vec<_> = writes.iter().filter(|(db, _)| *db < self.databases.len()).collect();
+        let mut dbs: Vec<usize> = writes.iter().map(|(db, _)| *db).collect();
+        dbs.sort_unstable();
+        dbs.dedup();
+        let stores: HashMap<usize, _> = dbs
+            .iter()
+            .map(|&db| (db, self.databases[db].lock().unwrap()))
+            .collect();
+
+        let mut images = Vec::new();
+        for (db, keys) in writes {
+            let store = &stores[db];
+            let keys = match keys {
+                Some(keys) => keys.to_vec(),
+                None => {
+                    images.push(KeyImage::ClearDatabase(*db));
+                    let mut keys = store.get_all_keys();
+                    keys.sort();
+                    keys
+                }
+            };
+            for key in keys {
+                let snapshot = self.key_image(*db, store, &key)?;
+                images.push(KeyImage::Key { db: *db, key, snapshot });
+            }
         }
+        txn_manager
+            .log_images(&images)
+            .map_err(|e| StoreError::WalError(e.to_string()))
+    }
 
-        if let Some(txn_manager) = &self.transaction_manager {
-            txn_manager
-                .recover_from_wal()
-                .map_err(StoreError::WalError)
-        } else {
-            Err(StoreError::WalError("事务管理器未初始化".to_string()))
+    /// 键的完整状态，已转移到磁盘的键从磁盘文件读取值
+    fn key_image(&self, db: usize, store: &Store, key: &str) -> StoreResult<Option<KeySnapshot>> {
+        if let Some(snapshot) = store.key_snapshot(key) {
+            return Ok(Some(snapshot));
+        }
+        if !store.disk_keys.contains_key(key) {
+            return Ok(None);
         }
+        let value: DataType = serde_json::from_str(&self.read_key_file(db, key)?)?;
+        Ok(Some(store.snapshot_with_value(key, value)))
     }
 
     /// 获取优化统计信息
     pub fn get_optimization_stats(&self) -> OptimizationStats {
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.get_optimization_stats()
     }
 
@@ -287,7 +1200,9 @@ impl StoreManager {
         let store_manager = self.clone();
         Some(std::thread::spawn(move || {
             loop {
-                std::thread::sleep(std::time::Duration::from_secs(store_manager.optimization_interval));
+                std::thread::sleep(std::time::Duration::from_secs(
+                    store_manager.optimization_interval.load(Ordering::Relaxed),
+                ));
 
                 if let Err(e) = store_manager.check_and_offload_low_frequency_data() {
                     log::error!("后台内存优化检查失败: {}", e);
@@ -311,6 +1226,9 @@ impl StoreManager {
     pub fn offload_keys_to_disk(&self, keys: &[String]) -> StoreResult<usize> {
         let mut offloaded_count = 0;
         for key in keys {
+            if !self.offload_enabled() {
+                break;
+            }
             if self.offload_key_to_disk(key).is_ok() {
                 offloaded_count += 1;
             }
@@ -320,31 +1238,216 @@ impl StoreManager {
 
     /// 执行内存优化
     pub fn optimize_memory(&self) -> StoreResult<usize> {
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.optimize_memory()
     }
 
     /// 获取内存使用统计
     pub fn get_memory_usage(&self) -> usize {
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.memory_usage()
     }
 
+    /// 所有数据库内存中数据的字节数之和，与max_memory_bytes比较
+    pub fn used_memory_bytes(&self) -> usize {
+        self.databases.iter().map(|store| store.lock().unwrap().memory_usage()).sum()
+    }
+
+    /// 写入前检查memory.max_memory_bytes，incoming为这次写入预计增加的字节数，未设置上限时直接返回
+    ///
+    /// 按策略腾出空间后仍然放不下这次写入(reject策略或没有可转移、可删除的键)时返回OutOfMemory。
+    fn enforce_memory_limit(&self, incoming: usize) -> StoreResult<()> {
+        if self.make_room(incoming) {
+            Ok(())
+        } else {
+            Err(StoreError::OutOfMemory)
+        }
+    }
+
+    /// 只减少或不改变数据量的写入(弹出、移除成员、修改过期时间等)执行前按策略腾出空间，但不拒绝写入
+    fn evict_if_needed(&self) {
+        self.make_room(0);
+    }
+
+    /// 按memory.max_memory_policy腾出空间，直到已用字节数加上incoming不超过上限，返回是否已不超过上限
+    ///
+    /// offload策略下按访问次数从少到多把当前数据库的键转移到磁盘，LFU策略下按同样的顺序删除所有数据库中的键。
+    /// 每次只取各数据库淘汰顺序中的第一个键，不需要排序整个键空间。
+    fn make_room(&self, incoming: usize) -> bool {
+        let Some(settings) = &self.settings else {
+            return true;
+        };
+        let limit = settings.memory.max_memory_bytes;
+        let fits = || self.used_memory_bytes().saturating_add(incoming) <= limit;
+        if limit == 0 || fits() {
+            return true;
+        }
+
+        match settings.memory.max_memory_policy {
+            MaxMemoryPolicy::Reject => {}
+            MaxMemoryPolicy::Offload => {
+                self.retry_offload_if_due();
+                while self.offload_enabled() && !fits() {
+                    let Some((_, key)) = self.store().lock().unwrap().eviction_candidate(false) else {
+                        break;
+                    };
+                    if let Err(err) = self.offload_key_to_disk(&key) {
+                        log::error!("超过内存上限时将键 '{}' 转移到磁盘出错: {}", key, err);
+                        break;
+                    }
+                    // 没有转移出去的键(如已过期)会一直排在最前面
+                    if self.store().lock().unwrap().data.contains_key(&key) {
+                        break;
+                    }
+                }
+            }
+            policy @ (MaxMemoryPolicy::AllKeysLfu | MaxMemoryPolicy::VolatileLfu) => {
+                let volatile = policy == MaxMemoryPolicy::VolatileLfu;
+                // 先按周期衰减访问次数，很久以前的访问不再影响淘汰顺序
+                for store in self.databases.iter() {
+                    store.lock().unwrap().decay_access_counts(settings.memory.lfu_decay_seconds);
+                }
+                while !fits() {
+                    // 所有数据库中访问最少的键
+                    let victim = self
+                        .databases
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(db, store)| {
+                            let (rank, key) = store.lock().unwrap().eviction_candidate(volatile)?;
+                            Some((rank, db, key))
+                        })
+                        .min();
+                    let Some((_, db, key)) = victim else {
+                        break;
+                    };
+                    let _ = self.databases[db].lock().unwrap().delete(&key);
+                    self.remove_key_files_in(db, &key);
+                    // 删除也写入WAL，否则重启时检查点和日志重放会恢复被淘汰的键
+                    if let Err(err) = self.log_key_images(&[(db, Some(std::slice::from_ref(&key)))]) {
+                        log::error!("记录淘汰键 '{}' 到WAL失败: {}", key, err);
+                    }
+                    log::debug!("超过内存上限，删除{}号数据库中访问最少的键 '{}'", db, key);
+                }
+            }
+        }
+        fits()
+    }
+
+    /// 获取单个键的估算内存占用(字节)，键不存在时返回None
+    pub fn memory_usage_of(&self, key: &str) -> StoreResult<Option<usize>> {
+        self.ensure_key_loaded(key)?;
+        let store = self.store().lock().unwrap();
+        Ok(store.memory_usage_of(key))
+    }
+
+    /// 闲置超过min_idle_seconds秒的键，闲置最久的在前，用于预览内存优化会转移哪些键，不会改变访问时间
+    pub fn idle_keys(&self, min_idle_seconds: u64) -> Vec<String> {
+        self.store().lock().unwrap().idle_keys(min_idle_seconds)
+    }
+
+    /// 获取键的内部调试信息，已转移到磁盘的键不会被重新加载到内存
+    pub fn debug_object(&self, key: &str) -> StoreResult<Option<ObjectInfo>> {
+        let disk_value = self.read_offloaded_value(key)?;
+        let store = self.store().lock().unwrap();
+        Ok(store.object_info(key, disk_value.as_ref()))
+    }
+
+    /// 列表的元素数量、总大小、平均和最大元素大小，不会把磁盘上的键重新加载到内存
+    pub fn debug_list(&self, key: &str) -> StoreResult<Option<ListInfo>> {
+        let disk_value = self.read_offloaded_value(key)?;
+        let store = self.store().lock().unwrap();
+        store.list_info(key, disk_value.as_ref())
+    }
+
+    /// 读取已转移到磁盘的键的值，键在内存中或不存在时返回None
+    fn read_offloaded_value(&self, key: &str) -> StoreResult<Option<DataType>> {
+        let offloaded = {
+            let store = self.store().lock().unwrap();
+            !store.data.contains_key(key) && store.disk_keys.contains_key(key)
+        };
+        if !offloaded {
+            return Ok(None);
+        }
+        let content = self.read_key_file(self.selected_db(), key)?;
+        Ok(Some(serde_json::from_str::<DataType>(&content)?))
+    }
+
+    /// 重置键的访问次数，使低频数据转移按新的访问模式重新判断
+    pub fn reset_access_stats(&self, key: &str) -> StoreResult<bool> {
+        let mut store = self.store().lock().unwrap();
+        Ok(store.reset_access_stats(key))
+    }
+
+    /// 将当前数据库导出为按键排序的JSON行，已转移到磁盘的键直接从文件读取
+    pub fn export_json(&self) -> StoreResult<String> {
+        let (mut records, disk_keys) = {
+            let store = self.store().lock().unwrap();
+            let disk_keys: Vec<(String, Option<u64>)> = store.disk_keys.keys()
+                .filter(|key| !store.data.contains_key(*key) && !store.is_expired(key))
+                .map(|key| (key.clone(), store.remaining_ttl(key)))
+                .collect();
+            (store.export_records(), disk_keys)
+        };
+        for (key, ttl) in disk_keys {
+            let value = serde_json::from_str::<DataType>(&self.read_key_file(self.selected_db(), &key)?)?;
+            records.push(ExportRecord { key, value, ttl });
+        }
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+
+        let mut output = String::new();
+        for record in &records {
+            output.push_str(&serde_json::to_string(record)?);
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
+    /// 导入export_json生成的JSON行到当前数据库，已存在的键被覆盖，返回导入的键数
+    ///
+    /// 所有行都解析成功后才写入，任意一行格式错误时不修改数据。
+    pub fn import_json(&self, content: &str) -> StoreResult<usize> {
+        self.enforce_memory_limit(content.len())?;
+        let records = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str::<ExportRecord>)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let keys: Vec<String> = records.iter().map(|record| record.key.clone()).collect();
+        {
+            let mut store = self.store().lock().unwrap();
+            for record in &records {
+                if store.disk_keys.contains_key(&record.key) {
+                    self.remove_key_files(&record.key);
+                }
+            }
+            for record in records {
+                store.import_record(record);
+            }
+        }
+        let db = self.selected_db();
+        for key in &keys {
+            self.list_waiters.notify(db, key);
+        }
+        Ok(keys.len())
+    }
+
     /// 获取所有键
     pub fn get_all_keys(&self) -> Vec<String> {
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.get_all_keys()
     }
 
     /// 获取磁盘键
     pub fn get_disk_keys(&self) -> Vec<String> {
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.get_disk_keys()
     }
 
     /// 获取内存键
     pub fn get_memory_keys(&self) -> Vec<String> {
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.get_memory_keys()
     }
 }
@@ -353,128 +1456,442 @@ impl StoreManager {
 impl StoreManager {
     /// 字符串操作
     pub fn set_string(&self, key: String, value: String) -> StoreResult<String> {
+        self.enforce_memory_limit(key.len() + value.len())?;
         self.ensure_key_loaded(&key)?;
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.set(key, value)
     }
 
     pub fn get_string(&self, key: &str) -> StoreResult<Option<String>> {
         self.ensure_key_loaded(key)?;
-        let store = self.store.lock().unwrap();
-        store.get(key)
+        let mut store = self.store().lock().unwrap();
+        let value = store.get(key)?;
+        // 读取计入访问次数，低频数据转移据此判断
+        if value.is_some() {
+            store.touch(key);
+        }
+        Ok(value)
+    }
+
+    /// 读取字符串并在同一次加锁中按选项设置或移除过期时间，键不存在时不做任何修改
+    pub fn getex(&self, key: &str, option: ExpiryOption) -> StoreResult<Option<String>> {
+        self.evict_if_needed();
+        self.ensure_key_loaded(key)?;
+        let mut store = self.store().lock().unwrap();
+        let value = store.get(key)?;
+        if value.is_some() {
+            store.touch(key);
+            match option {
+                ExpiryOption::Keep => {}
+                ExpiryOption::Ex(seconds) => {
+                    store.set_expire(key, seconds)?;
+                }
+                ExpiryOption::Persist => {
+                    store.persist_key(key)?;
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// 追加字符串，键不存在时新建，返回追加后的长度
+    pub fn append(&self, key: &str, value: &str) -> StoreResult<usize> {
+        self.enforce_memory_limit(value.len())?;
+        self.ensure_key_loaded(key)?;
+        let mut store = self.store().lock().unwrap();
+        store.append(key, value)
+    }
+
+    /// 设置任意字节值，合法的UTF-8数据仍按字符串保存
+    pub fn set_bytes(&self, key: String, value: Vec<u8>) -> StoreResult<()> {
+        self.enforce_memory_limit(key.len() + value.len())?;
+        self.ensure_key_loaded(&key)?;
+        let mut store = self.store().lock().unwrap();
+        store.set_bytes(key, value);
+        Ok(())
+    }
+
+    /// 以字节形式获取字符串值
+    pub fn get_bytes(&self, key: &str) -> StoreResult<Option<Vec<u8>>> {
+        self.ensure_key_loaded(key)?;
+        let store = self.store().lock().unwrap();
+        store.get_bytes(key)
     }
 
     /// 列表操作
     pub fn lpush(&self, key: String, value: String) -> StoreResult<usize> {
+        self.enforce_memory_limit(key.len() + value.len())?;
         self.ensure_key_loaded(&key)?;
-        let mut store = self.store.lock().unwrap();
-        store.lpush(key, value)
+        let len = self.store().lock().unwrap().lpush(key.clone(), value)?;
+        self.list_waiters.notify(self.selected_db(), &key);
+        Ok(len)
     }
 
     pub fn rpush(&self, key: String, value: String) -> StoreResult<usize> {
+        self.enforce_memory_limit(key.len() + value.len())?;
         self.ensure_key_loaded(&key)?;
-        let mut store = self.store.lock().unwrap();
-        store.rpush(key, value)
+        let len = self.store().lock().unwrap().rpush(key.clone(), value)?;
+        self.list_waiters.notify(self.selected_db(), &key);
+        Ok(len)
     }
 
     pub fn lpop(&self, key: &str) -> StoreResult<Option<String>> {
+        self.evict_if_needed();
         self.ensure_key_loaded(key)?;
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.lpop(key)
     }
 
     pub fn rpop(&self, key: &str) -> StoreResult<Option<String>> {
+        self.evict_if_needed();
         self.ensure_key_loaded(key)?;
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.rpop(key)
     }
 
+    /// 从第一个非空列表弹出最多count个元素
+    pub fn lmpop(&self, keys: &[String], end: ListEnd, count: usize) -> StoreResult<Option<(String, Vec<String>)>> {
+        self.evict_if_needed();
+        for key in keys {
+            self.ensure_key_loaded(key)?;
+        }
+        let mut store = self.store().lock().unwrap();
+        store.lmpop(keys, end, count)
+    }
+
+    /// 从第一个非空列表弹出一个元素，所有列表都为空时阻塞到列表中出现元素或超时
+    ///
+    /// timeout为None时一直等待；超时或服务器关闭时返回None。
+    pub fn blocking_pop(&self, keys: &[String], end: ListEnd, timeout: Option<Duration>) -> StoreResult<Option<(String, String)>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let db = self.selected_db();
+        // 先登记再检查列表，检查之后推入的元素一定会唤醒等待
+        let waiter = self.list_waiters.register(db, keys);
+        let result = loop {
+            match self.lmpop(keys, end, 1) {
+                Ok(Some((key, mut values))) => break Ok(values.pop().map(|value| (key, value))),
+                Ok(None) if self.list_waiters.is_closed() => break Ok(None),
+                Ok(None) => {}
+                Err(e) => break Err(e),
+            }
+            let ready = waiter.ready.lock().unwrap();
+            let mut ready = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        break Ok(None);
+                    }
+                    waiter.condvar.wait_timeout_while(ready, remaining, |ready| !*ready).unwrap().0
+                }
+                None => waiter.condvar.wait_while(ready, |ready| !*ready).unwrap(),
+            };
+            *ready = false;
+        };
+        self.list_waiters.unregister(db, keys, &waiter);
+        result
+    }
+
+    /// 唤醒所有阻塞在BLPOP/BRPOP上的连接并让之后的阻塞弹出立即返回，服务器关闭时调用
+    pub fn wake_blocked_pops(&self) {
+        self.list_waiters.close();
+    }
+
     pub fn lrange(&self, key: &str, start: isize, end: isize) -> StoreResult<Vec<String>> {
         self.ensure_key_loaded(key)?;
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.lrange(key, start, end)
     }
 
     pub fn llen(&self, key: &str) -> StoreResult<usize> {
         self.ensure_key_loaded(key)?;
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.llen(key)
     }
 
     /// 哈希表操作
     pub fn hset(&self, key: String, field: String, value: String) -> StoreResult<bool> {
+        self.enforce_memory_limit(key.len() + field.len() + value.len())?;
         self.ensure_key_loaded(&key)?;
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.hset(key, field, value)
     }
 
     pub fn hget(&self, key: &str, field: &str) -> StoreResult<Option<String>> {
         self.ensure_key_loaded(key)?;
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.hget(key, field)
     }
 
     pub fn hdel(&self, key: &str, field: &str) -> StoreResult<bool> {
+        self.evict_if_needed();
         self.ensure_key_loaded(key)?;
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.hdel(key, field)
     }
 
+    pub fn hrandfield(&self, key: &str, count: Option<isize>) -> StoreResult<Vec<(String, String)>> {
+        self.ensure_key_loaded(key)?;
+        let store = self.store().lock().unwrap();
+        store.hrandfield(key, count)
+    }
+
+    pub fn hexpire(&self, key: &str, seconds: u64, fields: &[String]) -> StoreResult<Vec<i64>> {
+        self.evict_if_needed();
+        self.ensure_key_loaded(key)?;
+        let mut store = self.store().lock().unwrap();
+        store.hexpire(key, seconds, fields)
+    }
+
+    pub fn httl(&self, key: &str, fields: &[String]) -> StoreResult<Vec<i64>> {
+        self.ensure_key_loaded(key)?;
+        let store = self.store().lock().unwrap();
+        store.httl(key, fields)
+    }
+
     /// 集合操作
     pub fn sadd(&self, key: String, members: Vec<String>) -> StoreResult<usize> {
+        self.enforce_memory_limit(key.len() + members.iter().map(String::len).sum::<usize>())?;
         self.ensure_key_loaded(&key)?;
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.sadd(key, members)
     }
 
+    /// 添加集合成员并返回实际新增的成员
+    pub fn sadd_members(&self, key: String, members: Vec<String>) -> StoreResult<Vec<String>> {
+        self.enforce_memory_limit(key.len() + members.iter().map(String::len).sum::<usize>())?;
+        self.ensure_key_loaded(&key)?;
+        let mut store = self.store().lock().unwrap();
+        store.sadd_members(key, members)
+    }
+
     pub fn smembers(&self, key: &str) -> StoreResult<Vec<String>> {
         self.ensure_key_loaded(key)?;
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.smembers(key)
     }
 
+    /// 计算多个集合的交集
+    pub fn sinter(&self, keys: &[String]) -> StoreResult<Vec<String>> {
+        for key in keys {
+            self.ensure_key_loaded(key)?;
+        }
+        let store = self.store().lock().unwrap();
+        store.sinter(keys)
+    }
+
+    /// 计算多个集合交集的大小，limit为0表示不限制
+    pub fn sintercard(&self, keys: &[String], limit: usize) -> StoreResult<usize> {
+        for key in keys {
+            self.ensure_key_loaded(key)?;
+        }
+        let store = self.store().lock().unwrap();
+        store.sintercard(keys, limit)
+    }
+
     pub fn sismember(&self, key: &str, member: &str) -> StoreResult<bool> {
         self.ensure_key_loaded(key)?;
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.sismember(key, member)
     }
 
     pub fn srem(&self, key: &str, member: &str) -> StoreResult<bool> {
+        self.evict_if_needed();
         self.ensure_key_loaded(key)?;
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.srem(key, member)
     }
 
+    /// 移除多个集合成员，返回实际移除的数量，集合变为空时删除键
+    pub fn srem_members(&self, key: &str, members: &[String]) -> StoreResult<usize> {
+        self.evict_if_needed();
+        self.ensure_key_loaded(key)?;
+        let mut store = self.store().lock().unwrap();
+        store.srem_members(key, members)
+    }
+
+    /// 当前数据库中多个键的版本号，任意一个键已过期时返回None
+    pub fn key_versions(&self, keys: &[String]) -> Option<Vec<u64>> {
+        let store = self.store().lock().unwrap();
+        keys.iter().map(|key| store.key_version(key)).collect()
+    }
+
     /// 通用操作
     pub fn exists(&self, key: &str) -> bool {
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.exists(key)
     }
 
     pub fn delete_key(&self, key: &str) -> StoreResult<bool> {
         // 删除磁盘文件（如果存在）
-        let file_path = self.get_key_file_path(key);
-        let _ = std::fs::remove_file(file_path);
+        self.remove_key_files(key);
         
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.store().lock().unwrap();
         store.delete(key)
     }
 
+    /// 重命名键，已转移到磁盘的源键和目标键先加载回内存，完成后删除它们的磁盘文件
+    pub fn rename(&self, key: &str, new_key: &str) -> StoreResult<()> {
+        self.evict_if_needed();
+        self.ensure_key_loaded(key)?;
+        self.ensure_key_loaded(new_key)?;
+        self.store().lock().unwrap().rename(key, new_key)?;
+        self.remove_key_files(key);
+        self.remove_key_files(new_key);
+        self.list_waiters.notify(self.selected_db(), new_key);
+        Ok(())
+    }
+
+    /// 删除多个键(包括磁盘文件和过期时间)，返回删除的未过期键数，重复的键只计算一次
+    pub fn del_keys(&self, keys: &[String]) -> StoreResult<usize> {
+        let mut store = self.store().lock().unwrap();
+        let mut deleted = 0;
+        for key in keys {
+            if self.delete_live_key(&mut store, key)? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// 删除键及其磁盘文件，返回删除前键是否存在且未过期
+    fn delete_live_key(&self, store: &mut Store, key: &str) -> StoreResult<bool> {
+        let live = !store.is_expired(key)
+            && (store.data.contains_key(key) || store.disk_keys.contains_key(key));
+        self.remove_key_files(key);
+        store.delete(key)?;
+        Ok(live)
+    }
+
+    /// 从游标开始检查count个键，返回下一个游标(0表示遍历结束)和其中匹配的键
+    ///
+    /// 已过期的键不返回；指定类型时已转移到磁盘的键只读取磁盘文件开头的类型标记，不加载回内存。
+    /// 游标是键在有序索引中的位置，由键名决定，遍历期间一直存在的键都会被返回，新增或删除的键可能返回也可能不返回。
+    pub fn scan(
+        &self,
+        cursor: usize,
+        pattern: Option<&str>,
+        count: usize,
+        key_type: Option<&str>,
+    ) -> StoreResult<(usize, Vec<String>)> {
+        let (next, keys) = self.store().lock().unwrap().scan_keys(cursor, count);
+
+        let mut matched = Vec::new();
+        for key in keys {
+            if pattern.is_some_and(|pattern| !glob_match(pattern, &key)) {
+                continue;
+            }
+            let on_disk = {
+                let store = self.store().lock().unwrap();
+                if store.is_expired(&key) {
+                    continue;
+                }
+                if let Some(key_type) = key_type {
+                    if store.data.contains_key(&key) && !store.get_type(&key).is_ok_and(|actual| actual == key_type) {
+                        continue;
+                    }
+                }
+                !store.data.contains_key(&key) && store.disk_keys.contains_key(&key)
+            };
+            if let Some(key_type) = key_type {
+                if on_disk && self.read_key_type(self.selected_db(), &key)? != Some(key_type) {
+                    continue;
+                }
+            }
+            matched.push(key);
+        }
+        // 同一批的键按名称排序输出
+        matched.sort();
+
+        Ok((next, matched))
+    }
+
+    /// 删除所有匹配glob模式的键(包括已转移到磁盘的键)，返回删除的未过期键数
+    pub fn delete_pattern(&self, pattern: &str) -> StoreResult<usize> {
+        let mut store = self.store().lock().unwrap();
+        // 先收集匹配的键再逐个删除，避免遍历时修改存储
+        let mut keys: Vec<String> = store
+            .get_all_keys()
+            .into_iter()
+            .filter(|key| glob_match(pattern, key))
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut deleted = 0;
+        for key in &keys {
+            if self.delete_live_key(&mut store, key)? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// 删除当前数据库中指定类型的所有键，包括已转移到磁盘的键，返回删除的数量
+    ///
+    /// 已转移到磁盘的键直接读取文件判断类型，不会加载回内存；过期时间和磁盘文件随键一起删除。
+    /// 清空当前数据库并删除已转移到磁盘的键的文件，数据库的配置和内存管理器保持不变
+    pub fn flush_db(&self) {
+        let db = self.selected_db();
+        let mut store = self.store().lock().unwrap();
+        for key in store.disk_keys.keys() {
+            self.remove_key_files_in(db, key);
+        }
+        *store = store.empty_like();
+    }
+
+    pub fn flush_type(&self, key_type: &str) -> StoreResult<usize> {
+        let db = self.selected_db();
+        let mut store = self.store().lock().unwrap();
+        let mut keys = store.get_all_keys();
+        keys.sort();
+        keys.dedup();
+
+        let mut deleted = 0;
+        for key in &keys {
+            if store.is_expired(key) {
+                continue;
+            }
+            let matches = match store.data.get(key) {
+                Some(value) => value.type_name() == key_type,
+                None => {
+                    let content = self.read_key_file(db, key)?;
+                    serde_json::from_str::<DataType>(&content)?.type_name() == key_type
+                }
+            };
+            if matches && self.delete_live_key(&mut store, key)? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
     pub fn set_expire(&self, key: &str, seconds: u64) -> StoreResult<bool> {
-        let mut store = self.store.lock().unwrap();
+        self.evict_if_needed();
+        let mut store = self.store().lock().unwrap();
         store.set_expire(key, seconds)
     }
 
     pub fn get_ttl(&self, key: &str) -> StoreResult<i64> {
-        let store = self.store.lock().unwrap();
+        let store = self.store().lock().unwrap();
         store.get_ttl(key)
     }
 
     pub fn persist_key(&self, key: &str) -> StoreResult<bool> {
-        let mut store = self.store.lock().unwrap();
+        self.evict_if_needed();
+        let mut store = self.store().lock().unwrap();
         store.persist_key(key)
     }
 
+    /// 在指定秒数内过期的键，按剩余生存时间从短到长排序
+    pub fn expiring_soon(&self, within_seconds: u64) -> Vec<String> {
+        self.store().lock().unwrap().expiring_soon(within_seconds)
+    }
+
+    /// 设置了过期时间的所有键，按名称排序
+    pub fn keys_with_ttl(&self) -> Vec<String> {
+        self.store().lock().unwrap().keys_with_ttl()
+    }
+
     // 命令处理器需要的额外方法别名
     
     /// 删除键（别名方法）
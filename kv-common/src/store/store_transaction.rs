@@ -1,43 +1,15 @@
 use super::{Transaction, StoreOperation, Store};
+use super::error::StoreResult;
 use super::traits::{ListOperations, HashOperations, SetOperations};
 
-/// 事务相关的存储管理器
-#[derive(Debug)]
-pub struct TransactionStoreManager {
-    /// 内部存储实例
-    pub store: Store,
-}
-
-impl TransactionStoreManager {
-    /// 创建新的事务存储管理器
-    pub fn new() -> Self {
-        TransactionStoreManager {
-            store: Store::new(),
-        }
-    }
-    
-    /// 创建检查点（简化实现）
-    pub fn create_checkpoint(&self) -> Result<u64, String> {
-        // 简化的检查点实现
-        Ok(0)
-    }
-    
-    /// 从WAL恢复数据（简化实现）
-    pub fn recover_from_wal(&self) -> Result<(), String> {
-        // 简化的WAL恢复实现
-        Ok(())
-    }
-}
-impl Default for TransactionStoreManager {
-    fn default() -> Self {
-        TransactionStoreManager::new()
-    }
-}
 /// StoreManager 的事务扩展实现
 pub trait StoreTransactionExt {
     /// 应用单个事务操作到存储
     fn apply_transaction_operation(&mut self, operation: &StoreOperation) -> bool;
     
+    /// 应用单个事务操作，类型冲突等错误原样返回，用于提交前校验
+    fn try_apply_transaction_operation(&mut self, operation: &StoreOperation) -> StoreResult<()>;
+    
     /// 应用整个事务的所有操作到存储
     fn apply_transaction(&mut self, transaction: &Transaction) -> bool;
     
@@ -88,6 +60,46 @@ impl StoreTransactionExt for Store {
         }
     }
     
+    fn try_apply_transaction_operation(&mut self, operation: &StoreOperation) -> StoreResult<()> {
+        match operation {
+            StoreOperation::Set(key, value) => self.set_string(key.clone(), value.clone()),
+            StoreOperation::Delete(key) => {
+                self.del_key(key);
+            }
+            StoreOperation::LPush(key, value) => {
+                self.lpush(key.clone(), value.clone())?;
+            }
+            StoreOperation::RPush(key, value) => {
+                self.rpush(key.clone(), value.clone())?;
+            }
+            StoreOperation::LPop(key) => {
+                self.lpop(key)?;
+            }
+            StoreOperation::RPop(key) => {
+                self.rpop(key)?;
+            }
+            StoreOperation::LDel(key) => {
+                self.ldel(key);
+            }
+            StoreOperation::HSet(key, field, value) => {
+                self.hset(key.clone(), field.clone(), value.clone())?;
+            }
+            StoreOperation::HDel(key, field) => {
+                self.hdel_field(key, field);
+            }
+            StoreOperation::HDelKey(key) => {
+                self.hdel_key(key);
+            }
+            StoreOperation::SAdd(key, value) => {
+                self.sadd(key.clone(), vec![value.clone()])?;
+            }
+            StoreOperation::SRem(key, value) => {
+                self.srem(key, value)?;
+            }
+        }
+        Ok(())
+    }
+    
     fn apply_transaction(&mut self, transaction: &Transaction) -> bool {
         if transaction.state != super::TransactionState::Committed {
             return false;
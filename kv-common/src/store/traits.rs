@@ -1,4 +1,5 @@
 use super::error::StoreResult;
+use super::list_ops::ListEnd;
 
 /// 存储操作的通用 trait
 pub trait StoreOperations {
@@ -64,6 +65,9 @@ pub trait ListOperations {
     
     /// 根据索引设置元素
     fn lset(&mut self, key: &str, index: isize, value: String) -> StoreResult<bool>;
+    
+    /// 从第一个非空列表的指定一端弹出最多count个元素，返回该列表的键和弹出的元素
+    fn lmpop(&mut self, keys: &[String], end: ListEnd, count: usize) -> StoreResult<Option<(String, Vec<String>)>>;
 }
 
 /// 哈希表操作 trait
@@ -91,15 +95,30 @@ pub trait HashOperations {
     
     /// 获取所有哈希字段和值
     fn hgetall(&self, key: &str) -> StoreResult<Vec<String>>;
+    
+    /// 随机获取哈希字段及其值，count为负数时字段可能重复
+    fn hrandfield(&self, key: &str, count: Option<isize>) -> StoreResult<Vec<(String, String)>>;
+
+    /// 设置哈希字段的过期时间，每个字段返回：-2字段不存在，1已设置，2秒数为0时字段被立即删除
+    fn hexpire(&mut self, key: &str, seconds: u64, fields: &[String]) -> StoreResult<Vec<i64>>;
+
+    /// 获取哈希字段的剩余生存时间，每个字段返回：-2字段不存在，-1没有过期时间，否则为剩余秒数
+    fn httl(&self, key: &str, fields: &[String]) -> StoreResult<Vec<i64>>;
 }
 
 /// 集合操作 trait
 pub trait SetOperations {
     /// 添加集合成员
     fn sadd(&mut self, key: String, members: Vec<String>) -> StoreResult<usize>;
+
+    /// 添加集合成员，返回实际新增的成员
+    fn sadd_members(&mut self, key: String, members: Vec<String>) -> StoreResult<Vec<String>>;
     
     /// 移除集合成员
     fn srem(&mut self, key: &str, member: &str) -> StoreResult<bool>;
+
+    /// 移除多个集合成员，返回实际移除的数量，集合变为空时删除键
+    fn srem_members(&mut self, key: &str, members: &[String]) -> StoreResult<usize>;
     
     /// 检查成员是否存在
     fn sismember(&self, key: &str, member: &str) -> StoreResult<bool>;
@@ -110,24 +129,15 @@ pub trait SetOperations {
     /// 获取集合大小
     fn scard(&self, key: &str) -> StoreResult<usize>;
     
+    /// 计算多个集合的交集
+    fn sinter(&self, keys: &[String]) -> StoreResult<Vec<String>>;
+    
+    /// 计算多个集合交集的大小，limit大于0时最多计数到limit
+    fn sintercard(&self, keys: &[String], limit: usize) -> StoreResult<usize>;
+    
     /// 随机获取集合成员
     fn srandmember(&self, key: &str, count: Option<isize>) -> StoreResult<Vec<String>>;
     
-    /// 随机弹出集合成员
+    /// 随机弹出集合成员，count为None时弹出一个，为0时不弹出，超过集合大小时弹出全部成员
     fn spop(&mut self, key: &str, count: Option<usize>) -> StoreResult<Vec<String>>;
 }
-
-/// 内存管理 trait
-pub trait MemoryManager {
-    /// 获取内存使用统计
-    fn memory_usage(&self) -> usize;
-    
-    /// 执行内存优化
-    fn optimize_memory(&mut self) -> StoreResult<usize>;
-    
-    /// 检查是否需要内存优化
-    fn should_optimize(&self) -> bool;
-    
-    /// 获取低频访问的键
-    fn get_low_frequency_keys(&self, count: usize) -> Vec<String>;
-}
@@ -1,8 +1,10 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
-use std::time::{SystemTime, UNIX_EPOCH};
-use crate::store::{WriteAheadLog, LogCommand, LogEntry, WalResult, WalError, Checkpoint};
+use std::time::Duration;
+use crate::clock::{system_clock, Clock, SystemClock};
+use crate::config::WalSyncPolicy;
+use crate::store::{KeySnapshot, Store, StoreTransactionExt, WriteAheadLog, LogCommand, LogEntry, WalResult, WalError, Checkpoint, CheckpointRecord, KeyImage, RecoveredDatabases};
 
 /// 事务状态
 #[derive(Debug, Clone, PartialEq)]
@@ -18,7 +20,7 @@ pub enum TransactionState {
 }
 
 /// 表示存储操作
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum StoreOperation {
     /// 设置键值对
     Set(String, String),
@@ -46,6 +48,79 @@ pub enum StoreOperation {
     SRem(String, String),
 }
 
+impl StoreOperation {
+    /// 操作的键
+    pub fn key(&self) -> &str {
+        match self {
+            StoreOperation::Set(key, _)
+            | StoreOperation::Delete(key)
+            | StoreOperation::LPush(key, _)
+            | StoreOperation::RPush(key, _)
+            | StoreOperation::LPop(key)
+            | StoreOperation::RPop(key)
+            | StoreOperation::LDel(key)
+            | StoreOperation::HSet(key, _, _)
+            | StoreOperation::HDel(key, _)
+            | StoreOperation::HDelKey(key)
+            | StoreOperation::SAdd(key, _)
+            | StoreOperation::SRem(key, _) => key,
+        }
+    }
+
+    /// 操作最多新增的字节数，用于提交前检查内存上限
+    pub fn added_bytes(&self) -> usize {
+        match self {
+            StoreOperation::Set(key, value)
+            | StoreOperation::LPush(key, value)
+            | StoreOperation::RPush(key, value)
+            | StoreOperation::SAdd(key, value) => key.len() + value.len(),
+            StoreOperation::HSet(key, field, value) => key.len() + field.len() + value.len(),
+            _ => 0,
+        }
+    }
+    
+    /// 根据WAL条目中的元数据还原操作，元数据缺失时（旧版本日志）按字符串操作处理
+    pub(crate) fn from_log_entry(entry: &LogEntry) -> Option<StoreOperation> {
+        let key = entry.key.clone()?;
+        let value = || entry.value.clone().unwrap_or_default();
+        // 哈希字段和集合元素编码在键的最后一个冒号之后，键本身可以包含冒号
+        let split_member = |prefix: &str| -> Option<(String, String)> {
+            let (key, member) = key.strip_prefix(prefix)?.rsplit_once(':')?;
+            Some((key.to_string(), member.to_string()))
+        };
+        let list_key = || key.strip_prefix("list:").map(str::to_string);
+
+        let operation = match (entry.metadata.as_deref(), &entry.command) {
+            (Some("list:lpush"), _) => StoreOperation::LPush(list_key()?, value()),
+            (Some("list:rpush"), _) => StoreOperation::RPush(list_key()?, value()),
+            (Some("list:lpop"), _) => StoreOperation::LPop(list_key()?),
+            (Some("list:rpop"), _) => StoreOperation::RPop(list_key()?),
+            (Some("list:ldel"), _) => StoreOperation::LDel(list_key()?),
+            (Some("hash:hset"), _) => {
+                let (key, field) = split_member("hash:")?;
+                StoreOperation::HSet(key, field, value())
+            }
+            (Some("hash:hdel"), _) => {
+                let (key, field) = split_member("hash:")?;
+                StoreOperation::HDel(key, field)
+            }
+            (Some("hash:hdelkey"), _) => StoreOperation::HDelKey(key.strip_prefix("hash:")?.to_string()),
+            (Some("set:sadd"), _) => {
+                let (key, member) = split_member("set:")?;
+                StoreOperation::SAdd(key, member)
+            }
+            (Some("set:srem"), _) => {
+                let (key, member) = split_member("set:")?;
+                StoreOperation::SRem(key, member)
+            }
+            (_, LogCommand::Put) => StoreOperation::Set(key, value()),
+            (_, LogCommand::Delete) => StoreOperation::Delete(key),
+            _ => return None,
+        };
+        Some(operation)
+    }
+}
+
 /// 事务结构
 #[derive(Debug, Clone)]
 pub struct Transaction {
@@ -59,18 +134,20 @@ pub struct Transaction {
     pub start_time: u64,
     /// 提交或回滚时间戳
     pub end_time: Option<u64>,
-    /// 本地缓存修改数据
-    pub local_data: HashMap<String, String>,
+    /// 本地缓存修改数据，None表示键在事务中被删除（墓碑）
+    pub local_data: HashMap<String, Option<String>>,
+    /// 保存点名称到操作列表长度的映射
+    pub savepoints: HashMap<String, usize>,
 }
 
 impl Transaction {
     /// 创建新事务
     pub fn new(id: u64) -> Self {
-        let start_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-            
+        Self::new_at(id, SystemClock.now_secs())
+    }
+    
+    /// 创建指定开始时间的新事务
+    pub fn new_at(id: u64, start_time: u64) -> Self {
         Transaction {
             id,
             state: TransactionState::Active,
@@ -78,6 +155,7 @@ impl Transaction {
             start_time,
             end_time: None,
             local_data: HashMap::new(),
+            savepoints: HashMap::new(),
         }
     }
     
@@ -86,30 +164,76 @@ impl Transaction {
         if self.state != TransactionState::Active {
             return Err(format!("事务 {} 不再活跃，状态为: {:?}", self.id, self.state));
         }
+        Self::cache_operation(&mut self.local_data, &operation);
         self.operations.push(operation);
         Ok(())
     }
     
+    /// 将字符串操作写入本地缓存，删除整个键的操作记为墓碑，使之后的读取看到键已不存在
+    fn cache_operation(local_data: &mut HashMap<String, Option<String>>, operation: &StoreOperation) {
+        match operation {
+            StoreOperation::Set(key, value) => {
+                local_data.insert(key.clone(), Some(value.clone()));
+            }
+            StoreOperation::Delete(key) | StoreOperation::LDel(key) | StoreOperation::HDelKey(key) => {
+                local_data.insert(key.clone(), None);
+            }
+            _ => {}
+        }
+    }
+    
+    /// 读取事务自己的修改：外层None表示事务没有修改该键，需要读取存储；Some(None)表示键已在事务中删除
+    pub fn read_local(&self, key: &str) -> Option<Option<&str>> {
+        self.local_data.get(key).map(|value| value.as_deref())
+    }
+    
+    /// 在当前位置创建保存点，同名保存点会被覆盖
+    pub fn savepoint(&mut self, name: &str) -> Result<(), String> {
+        if self.state != TransactionState::Active {
+            return Err(format!("事务 {} 不再活跃，状态为: {:?}", self.id, self.state));
+        }
+        self.savepoints.insert(name.to_string(), self.operations.len());
+        Ok(())
+    }
+    
+    /// 回滚到保存点，丢弃保存点之后的操作和保存点
+    pub fn rollback_to_savepoint(&mut self, name: &str) -> Result<(), String> {
+        if self.state != TransactionState::Active {
+            return Err(format!("事务 {} 不再活跃，状态为: {:?}", self.id, self.state));
+        }
+        let offset = *self.savepoints.get(name)
+            .ok_or_else(|| format!("事务 {} 中不存在保存点 {}", self.id, name))?;
+        self.operations.truncate(offset);
+        self.savepoints.retain(|_, saved| *saved <= offset);
+        
+        // 本地缓存按剩余操作重建
+        self.local_data.clear();
+        for operation in &self.operations {
+            Self::cache_operation(&mut self.local_data, operation);
+        }
+        Ok(())
+    }
+    
     /// 标记为已提交
     pub fn commit(&mut self) {
+        self.commit_at(SystemClock.now_secs());
+    }
+    
+    /// 在指定时间标记为已提交
+    pub fn commit_at(&mut self, end_time: u64) {
         self.state = TransactionState::Committed;
-        self.end_time = Some(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-        );
+        self.end_time = Some(end_time);
     }
     
     /// 标记为已回滚
     pub fn rollback(&mut self) {
+        self.rollback_at(SystemClock.now_secs());
+    }
+    
+    /// 在指定时间标记为已回滚
+    pub fn rollback_at(&mut self, end_time: u64) {
         self.state = TransactionState::RolledBack;
-        self.end_time = Some(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-        );
+        self.end_time = Some(end_time);
     }
     
     /// 标记为已准备
@@ -124,8 +248,10 @@ pub struct TransactionManager {
     active_transactions: Arc<RwLock<HashMap<u64, Arc<Mutex<Transaction>>>>>,
     /// WAL日志
     wal: Arc<Mutex<WriteAheadLog>>,
-    /// 下一个事务ID
+    /// 最近分配的事务ID，下一个事务使用它加一
     next_txn_id: Arc<Mutex<u64>>,
+    /// 尚未写入WAL的事务日志条目，在准备或提交时批量写入
+    pending_entries: Arc<Mutex<HashMap<u64, Vec<LogEntry>>>>,
     /// WAL日志路径
     wal_path: PathBuf,
     /// 是否启用自动检查点
@@ -136,6 +262,16 @@ pub struct TransactionManager {
     checkpoint_threshold: u64,
     /// 存储引用，可选，用于获取操作前的数据
     store: Option<Arc<Mutex<super::Store>>>,
+    /// 事务和日志时间戳的来源
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for TransactionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransactionManager")
+            .field("wal_path", &self.wal_path)
+            .finish_non_exhaustive()
+    }
 }
 
 impl TransactionManager {
@@ -158,11 +294,8 @@ impl TransactionManager {
         let mut active_txns = HashMap::new();
         let entries = wal.load_entries()?;
         
-        // 初始ID使用当前时间戳，保证唯一性和较大的初始值，以避免ID冲突
-        let mut last_id = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        // 事务ID只由日志中出现过的最大ID推导，不依赖时钟，重启后继续单调递增
+        let mut last_id = 0;
         
         for entry in entries {
             if entry.id > last_id {
@@ -171,9 +304,15 @@ impl TransactionManager {
             
             match entry.command {
                 LogCommand::Begin => {
-                    let txn = Transaction::new(entry.id);
+                    let txn = Transaction::new_at(entry.id, entry.timestamp);
                     active_txns.insert(entry.id, Arc::new(Mutex::new(txn)));
                 }
+                LogCommand::Prepare => {
+                    // 已准备但未提交的事务在重启后保持准备状态，等待提交或回滚
+                    if let Some(txn) = active_txns.get(&entry.id) {
+                        txn.lock().unwrap().prepare();
+                    }
+                }
                 LogCommand::Commit | LogCommand::Rollback => {
                     active_txns.remove(&entry.id);
                 }
@@ -184,15 +323,24 @@ impl TransactionManager {
         Ok(TransactionManager {
             active_transactions: Arc::new(RwLock::new(active_txns)),
             wal: Arc::new(Mutex::new(wal)),
-            next_txn_id: Arc::new(Mutex::new(last_id + 1)),
+            next_txn_id: Arc::new(Mutex::new(last_id)),
+            pending_entries: Arc::new(Mutex::new(HashMap::new())),
             wal_path: wal_path.to_path_buf(), // 保存WAL日志路径，用于故障恢复和重新初始化
             auto_checkpoint: true,
             operation_count: Arc::new(Mutex::new(0)),
             checkpoint_threshold: 1000,
             store: None, // 初始化时没有存储引用
+            clock: system_clock(),
         })
     }
     
+    /// 使用指定的时钟记录事务时间和日志时间戳，测试中可注入手动推进的时钟
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.wal.lock().unwrap().set_clock(Arc::clone(&clock));
+        self.clock = clock;
+        self
+    }
+    
     /// 设置是否启用自动检查点
     pub fn with_auto_checkpoint(mut self, enabled: bool, threshold: u64) -> Self {
         self.auto_checkpoint = enabled;
@@ -200,23 +348,23 @@ impl TransactionManager {
         self
     }
     
+    /// 分配新的事务或日志条目ID，为已分配的最大ID加一
+    fn next_id(&self) -> u64 {
+        let mut id = self.next_txn_id.lock().unwrap();
+        *id += 1;
+        *id
+    }
+    
     /// 开始新事务
     pub fn begin_transaction(&self) -> WalResult<u64> {
-        // 使用递增ID（性能最优）
-        let txn_id = {
-            let mut id = self.next_txn_id.lock().unwrap();
-            *id += 1;
-            *id
-        };
+        let txn_id = self.next_id();
         
-        // 记录到WAL
-        {
-            let mut wal = self.wal.lock().unwrap();
-            wal.begin(txn_id)?;
-        }
+        // BEGIN记录先缓冲，与事务的操作一起写入WAL
+        let start_time = self.clock.now_secs();
+        self.buffer_entry(LogEntry::new(LogCommand::Begin, None, None, txn_id).with_timestamp(start_time));
         
         // 创建事务对象
-        let txn = Transaction::new(txn_id);
+        let txn = Transaction::new_at(txn_id, start_time);
         
         // 添加到活跃事务表
         {
@@ -239,28 +387,102 @@ impl TransactionManager {
         }
     }
     
-    /// 提交事务
-    pub fn commit_transaction(&self, txn_id: u64) -> WalResult<bool> {
-        // 检查事务是否存在
-        {
-            let txns = self.active_transactions.read().unwrap();
-            if !txns.contains_key(&txn_id) {
-                return Err(WalError::TransactionNotFound(txn_id));
-            }
+    /// 准备事务(两阶段提交的第一阶段)
+    ///
+    /// 对照存储中的当前数据依次应用事务的所有操作，确认没有类型冲突后写入PREPARE记录，
+    /// 事务进入准备状态后不能再添加操作，只能提交或回滚。
+    pub fn prepare_transaction(&self, txn_id: u64) -> WalResult<()> {
+        let txn_arc = self.active_transaction(txn_id)?;
+        let mut txn = txn_arc.lock().unwrap();
+        let store = self.store.as_ref().map(|store| store.lock().unwrap());
+        let batch = self.prepare_entries(&txn, store.as_deref())?;
+        
+        self.wal.lock().unwrap().append_batch(&batch)?;
+        self.pending_entries.lock().unwrap().remove(&txn_id);
+        txn.prepare();
+        Ok(())
+    }
+    
+    /// 校验活跃事务的操作，返回缓冲的日志条目加上PREPARE记录
+    ///
+    /// 只复制事务涉及的键到临时存储中应用操作，未设置存储时只检查事务内部的冲突。
+    /// 调用方持有存储的锁直到操作应用完成，校验结果在应用时仍然有效。
+    fn prepare_entries(&self, txn: &Transaction, store: Option<&Store>) -> WalResult<Vec<LogEntry>> {
+        if txn.state != TransactionState::Active {
+            return Err(WalError::InvalidEntry(format!(
+                "事务 {} 状态为 {:?}，不是活跃状态",
+                txn.id, txn.state
+            )));
         }
         
-        // 记录提交到WAL
-        {
-            let mut wal = self.wal.lock().unwrap();
-            wal.commit(txn_id)?;
+        // 过期的键按不存在处理，临时存储中不设置过期时间
+        let mut scratch = Store::new();
+        if let Some(store) = store {
+            for key in txn.operations.iter().map(StoreOperation::key) {
+                if scratch.key_snapshot(key).is_some() || store.key_version(key).is_none() {
+                    continue;
+                }
+                if let Some(snapshot) = store.key_snapshot(key) {
+                    scratch.restore_key(key, Some(KeySnapshot::new(snapshot.value)));
+                }
+            }
+        }
+        for operation in &txn.operations {
+            scratch.try_apply_transaction_operation(operation).map_err(|e| {
+                WalError::InvalidEntry(format!("事务 {} 的操作 {:?} 无法应用: {}", txn.id, operation, e))
+            })?;
         }
         
-        // 更新事务状态
+        let mut batch = self.pending_entries.lock().unwrap().get(&txn.id).cloned().unwrap_or_default();
+        batch.push(LogEntry::new(LogCommand::Prepare, None, None, txn.id).with_timestamp(self.clock.now_secs()));
+        Ok(batch)
+    }
+    
+    /// 提交事务
+    ///
+    /// 只有准备状态的事务可以提交，活跃事务会先自动执行准备阶段。
+    /// 事务缓冲的所有日志条目和PREPARE、COMMIT记录一次写入WAL，只同步一次。
+    /// 设置了存储时，提交后将事务的操作应用到存储。
+    pub fn commit_transaction(&self, txn_id: u64) -> WalResult<bool> {
+        // 持有存储的锁直到操作应用完成，其他写入不能在校验和应用之间修改涉及的键
+        let mut store = self.store.as_ref().map(|store| store.lock().unwrap());
+        self.commit_into(txn_id, 0, store.as_deref_mut())
+    }
+    
+    /// 提交事务并把操作应用到编号为db的数据库的存储，调用方持有存储的锁直到返回
+    ///
+    /// COMMIT记录中保存数据库编号，恢复时事务的操作重放到同一个数据库。
+    pub fn commit_transaction_in(&self, txn_id: u64, db: usize, store: &mut Store) -> WalResult<bool> {
+        self.commit_into(txn_id, db, Some(store))
+    }
+    
+    fn commit_into(&self, txn_id: u64, db: usize, mut store: Option<&mut Store>) -> WalResult<bool> {
+        let txn_arc = self.active_transaction(txn_id)?;
+        
         {
-            let txns = self.active_transactions.read().unwrap();
-            if let Some(txn) = txns.get(&txn_id) {
-                let mut txn = txn.lock().unwrap();
-                txn.commit();
+            let mut txn = txn_arc.lock().unwrap();
+            let mut batch = match txn.state {
+                TransactionState::Active => self.prepare_entries(&txn, store.as_deref())?,
+                TransactionState::Prepared => Vec::new(),
+                ref state => {
+                    return Err(WalError::InvalidEntry(format!(
+                        "事务 {} 状态为 {:?}，不能提交",
+                        txn_id, state
+                    )));
+                }
+            };
+            let end_time = self.clock.now_secs();
+            let db_key = (db != 0).then(|| db.to_string());
+            batch.push(LogEntry::new(LogCommand::Commit, db_key, None, txn_id).with_timestamp(end_time));
+            
+            // 记录提交到WAL
+            self.wal.lock().unwrap().append_batch(&batch)?;
+            self.pending_entries.lock().unwrap().remove(&txn_id);
+            
+            // 更新事务状态
+            txn.commit_at(end_time);
+            if let Some(store) = store.as_mut() {
+                store.apply_transaction(&txn);
             }
         }
         
@@ -283,10 +505,13 @@ impl TransactionManager {
             }
         }
         
-        // 记录回滚到WAL
+        // 尚未写入WAL的事务直接丢弃缓冲的条目，已写入的事务记录回滚
+        self.pending_entries.lock().unwrap().remove(&txn_id);
         {
             let mut wal = self.wal.lock().unwrap();
-            wal.rollback(txn_id)?;
+            if wal.is_transaction_open(txn_id) {
+                wal.rollback(txn_id)?;
+            }
         }
         
         // 更新事务状态
@@ -294,7 +519,7 @@ impl TransactionManager {
             let txns = self.active_transactions.read().unwrap();
             if let Some(txn) = txns.get(&txn_id) {
                 let mut txn = txn.lock().unwrap();
-                txn.rollback();
+                txn.rollback_at(self.clock.now_secs());
             }
         }
         
@@ -307,6 +532,68 @@ impl TransactionManager {
         Ok(())
     }
     
+    /// 在事务的当前位置创建保存点
+    pub fn savepoint(&self, txn_id: u64, name: &str) -> WalResult<()> {
+        let txn_arc = self.active_transaction(txn_id)?;
+        let mut txn = txn_arc.lock().unwrap();
+        if txn.state != TransactionState::Active {
+            return Err(WalError::InvalidEntry(format!(
+                "事务 {} 状态为 {:?}，不是活跃状态",
+                txn_id, txn.state
+            )));
+        }
+        
+        self.buffer_entry(
+            LogEntry::new(LogCommand::Savepoint, Some(name.to_string()), None, txn_id)
+                .with_timestamp(self.clock.now_secs()),
+        );
+        txn.savepoint(name).map_err(WalError::InvalidEntry)
+    }
+    
+    /// 回滚事务到保存点，保存点之后的操作不会被提交
+    pub fn rollback_to_savepoint(&self, txn_id: u64, name: &str) -> WalResult<()> {
+        let txn_arc = self.active_transaction(txn_id)?;
+        let mut txn = txn_arc.lock().unwrap();
+        if txn.state != TransactionState::Active {
+            return Err(WalError::InvalidEntry(format!(
+                "事务 {} 状态为 {:?}，不是活跃状态",
+                txn_id, txn.state
+            )));
+        }
+        if !txn.savepoints.contains_key(name) {
+            return Err(WalError::InvalidEntry(format!("事务 {} 中不存在保存点 {}", txn_id, name)));
+        }
+        
+        self.buffer_entry(
+            LogEntry::new(LogCommand::RollbackTo, Some(name.to_string()), None, txn_id)
+                .with_timestamp(self.clock.now_secs()),
+        );
+        txn.rollback_to_savepoint(name).map_err(WalError::InvalidEntry)
+    }
+    
+    /// 缓冲事务的日志条目，等待准备或提交时批量写入
+    fn buffer_entry(&self, entry: LogEntry) {
+        self.pending_entries.lock().unwrap().entry(entry.id).or_default().push(entry);
+    }
+    
+    /// 查找活跃事务
+    fn active_transaction(&self, txn_id: u64) -> WalResult<Arc<Mutex<Transaction>>> {
+        self.active_transactions.read().unwrap()
+            .get(&txn_id)
+            .cloned()
+            .ok_or(WalError::TransactionNotFound(txn_id))
+    }
+    
+    /// 在事务中读取字符串值，优先返回事务自己的修改（包括删除），否则读取存储
+    pub fn read_string(&self, txn_id: u64, key: &str) -> WalResult<Option<String>> {
+        let txn_arc = self.active_transaction(txn_id)?;
+        let txn = txn_arc.lock().unwrap();
+        if let Some(local) = txn.read_local(key) {
+            return Ok(local.map(str::to_string));
+        }
+        Ok(self.store.as_ref().and_then(|store| store.lock().unwrap().get_string(key)))
+    }
+    
     /// 执行事务操作
     pub fn execute_operation(&self, txn_id: u64, operation: StoreOperation) -> WalResult<()> {
         // 检查事务是否存在
@@ -369,9 +656,9 @@ impl TransactionManager {
             txn.add_operation(operation.clone()).map_err(WalError::InvalidEntry)?;
         }
         
-        // 记录操作到WAL
+        // 缓冲操作日志，提交时与事务的其他条目一起写入WAL
         {
-            let mut wal = self.wal.lock().unwrap();
+            let timestamp = self.clock.now_secs();
             
             // 优先使用传入的旧值和元数据，或尝试根据操作类型确定默认元数据
             let actual_old_value = old_value;
@@ -402,8 +689,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::Delete(key) => {
                     let entry = LogEntry::new_with_metadata(
@@ -413,8 +700,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::LPush(key, value) => {
                     let entry = LogEntry::new_with_metadata(
@@ -424,8 +711,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::RPush(key, value) => {
                     let entry = LogEntry::new_with_metadata(
@@ -435,8 +722,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::LPop(key) => {
                     let entry = LogEntry::new_with_metadata(
@@ -446,8 +733,8 @@ impl TransactionManager {
                         actual_old_value, // 使用传入的旧值
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::RPop(key) => {
                     let entry = LogEntry::new_with_metadata(
@@ -457,8 +744,8 @@ impl TransactionManager {
                         actual_old_value, // 使用传入的旧值
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 // 处理其他操作类型
                 StoreOperation::LDel(key) => {
@@ -469,8 +756,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::HSet(key, field, value) => {
                     let entry = LogEntry::new_with_metadata(
@@ -480,8 +767,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::HDel(key, field) => {
                     let entry = LogEntry::new_with_metadata(
@@ -491,8 +778,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::HDelKey(key) => {
                     let entry = LogEntry::new_with_metadata(
@@ -502,8 +789,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::SAdd(key, value) => {
                     let entry = LogEntry::new_with_metadata(
@@ -513,8 +800,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 },
                 StoreOperation::SRem(key, value) => {
                     let entry = LogEntry::new_with_metadata(
@@ -524,8 +811,8 @@ impl TransactionManager {
                         actual_old_value,
                         actual_metadata,
                         txn_id
-                    );
-                    wal.append_entry(&entry)?;
+                    ).with_timestamp(timestamp);
+                    self.buffer_entry(entry);
                 }
             }
         }
@@ -553,16 +840,59 @@ impl TransactionManager {
         }
     }
     
+    /// 距指定时间(Unix时间戳，秒)已经过的秒数
+    pub fn elapsed_secs(&self, since: u64) -> u64 {
+        self.clock.now_secs().saturating_sub(since)
+    }
+    
     /// 列出所有活跃事务
     pub fn list_active_transactions(&self) -> Vec<u64> {
         let active_txns = self.active_transactions.read().unwrap();
         active_txns.keys().cloned().collect()
     }
     
-    /// 创建检查点
-    pub fn create_checkpoint(&self, data: HashMap<String, String>) -> WalResult<u64> {
+    /// 创建检查点，未提供数据时以重放日志得到的状态作为检查点数据
+    pub fn create_checkpoint(&self, data: Option<Vec<CheckpointRecord>>) -> WalResult<u64> {
         let mut wal = self.wal.lock().unwrap();
-        wal.create_checkpoint(Some(data))
+        wal.create_checkpoint(data)
+    }
+    
+    /// 设置保留的检查点文件数
+    pub fn set_checkpoint_retention(&self, retention: usize) {
+        self.wal.lock().unwrap().set_checkpoint_retention(retention);
+    }
+    
+    /// 设置检查点目录
+    pub fn set_checkpoint_dir(&self, dir: PathBuf) -> WalResult<()> {
+        self.wal.lock().unwrap().set_checkpoint_dir(dir)
+    }
+    
+    /// 设置WAL同步策略
+    pub fn set_sync_policy(&self, policy: WalSyncPolicy, batch_entries: usize) {
+        self.wal.lock().unwrap().set_sync_policy(policy, batch_entries);
+    }
+    
+    /// 将缓冲的WAL日志写入文件并同步到磁盘
+    pub fn flush_wal(&self) -> WalResult<()> {
+        self.wal.lock().unwrap().flush()
+    }
+    
+    /// 已追加到WAL的位置，之后的写入都在这个位置之后
+    pub fn wal_position(&self) -> u64 {
+        self.wal.lock().unwrap().appended_position()
+    }
+    
+    /// 等待WAL中位置不大于position的条目同步到磁盘，返回已同步的位置
+    ///
+    /// 等待期间不持有WAL的锁，其他连接可以继续写入和同步。
+    pub fn wait_wal_synced(&self, position: u64, timeout: Option<Duration>) -> u64 {
+        let progress = self.wal.lock().unwrap().sync_progress();
+        progress.wait(position, timeout)
+    }
+    
+    /// every_sec策略下同步距上次同步超过1秒的缓冲日志，返回是否执行了同步
+    pub fn sync_wal_if_due(&self) -> WalResult<bool> {
+        self.wal.lock().unwrap().sync_if_due()
     }
     
     /// 从WAL恢复数据
@@ -571,12 +901,57 @@ impl TransactionManager {
         wal.recover()
     }
     
+    /// 从WAL恢复出完整的存储，列表、哈希表和集合按原数据类型重建
+    pub fn recover_store(&self) -> WalResult<super::Store> {
+        self.wal.lock().unwrap().recover_store()
+    }
+    
+    /// 在已有的数据库上重放WAL，日志中有检查点时以检查点替换它们
+    pub fn recover_databases(&self, databases: BTreeMap<usize, Store>) -> WalResult<RecoveredDatabases> {
+        self.wal.lock().unwrap().recover_onto(databases)
+    }
+    
+    /// 在事务之外记录一组键的当前状态，条目多于一个时包在BEGIN和COMMIT之间，恢复时整体生效
+    pub fn log_images(&self, images: &[KeyImage]) -> WalResult<()> {
+        if images.is_empty() {
+            return Ok(());
+        }
+        let id = self.next_id();
+        let timestamp = self.clock.now_secs();
+        let mut batch = Vec::with_capacity(images.len() + 2);
+        for image in images {
+            batch.push(image.to_log_entry(id)?.with_timestamp(timestamp));
+        }
+        if batch.len() > 1 {
+            batch.insert(0, LogEntry::new(LogCommand::Begin, None, None, id).with_timestamp(timestamp));
+            batch.push(LogEntry::new(LogCommand::Commit, None, None, id).with_timestamp(timestamp));
+        }
+        self.wal.lock().unwrap().append_batch(&batch)
+    }
+    
+    /// 恢复到指定序列号时的数据状态
+    pub fn recover_to(&self, seq: u64) -> WalResult<HashMap<String, String>> {
+        let wal = self.wal.lock().unwrap();
+        wal.recover_to(seq)
+    }
+    
+    /// 以records作为完整数据压缩WAL日志，之后的恢复只需要这个检查点和它之后的条目
+    pub fn compact_wal_with(&self, records: Vec<CheckpointRecord>) -> WalResult<()> {
+        self.wal.lock().unwrap().compact_with(Some(records))
+    }
+    
     /// 压缩WAL日志
     pub fn compact_wal(&self) -> WalResult<()> {
         let mut wal = self.wal.lock().unwrap();
         wal.compact()
     }
     
+    /// WAL日志超过threshold字节时压缩，返回是否进行了压缩
+    pub fn compact_wal_if_needed(&self, threshold: u64) -> WalResult<bool> {
+        let mut wal = self.wal.lock().unwrap();
+        wal.compact_if_needed(threshold)
+    }
+    
     /// 获取WAL管理器的可变引用
     pub fn get_wal_manager(&self) -> std::sync::MutexGuard<'_, WriteAheadLog> {
         self.wal.lock().unwrap()
@@ -599,8 +974,8 @@ impl TransactionManager {
         };
         
         if should_checkpoint {
-            // 自动检查点，使用空数据
-            self.create_checkpoint(HashMap::new())?;
+            // 自动检查点，数据来自重放日志
+            self.create_checkpoint(None)?;
         }
         
         Ok(())
@@ -608,10 +983,7 @@ impl TransactionManager {
     
     /// 检查事务超时并自动回滚
     pub fn check_transaction_timeouts(&self, timeout_seconds: u64) -> WalResult<Vec<u64>> {
-        let current_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let current_time = self.clock.now_secs();
             
         let txns = self.active_transactions.read().unwrap();
         let mut timed_out = Vec::new();
@@ -620,12 +992,14 @@ impl TransactionManager {
         for (id, txn_arc) in txns.iter() {
             let txn = txn_arc.lock().unwrap();
             if txn.state == TransactionState::Active {
-                let duration = current_time - txn.start_time;
+                let duration = current_time.saturating_sub(txn.start_time);
                 if duration > timeout_seconds {
                     timed_out.push(*id);
                 }
             }
         }
+        // 回滚需要获取写锁，先释放读锁
+        drop(txns);
         
         // 回滚超时的事务
         for txn_id in &timed_out {
@@ -633,7 +1007,7 @@ impl TransactionManager {
                 Ok(_) => {},
                 Err(e) => {
                     // 记录错误但继续处理其他超时事务
-                    eprintln!("回滚超时事务 {} 失败: {}", txn_id, e);
+                    log::error!("回滚超时事务 {} 失败: {}", txn_id, e);
                 }
             }
         }
@@ -650,7 +1024,7 @@ impl TransactionManager {
         // 先找出所有事务的开始记录
         for entry in &entries {
             if entry.command == LogCommand::Begin {
-                let txn = Transaction::new(entry.id);
+                let txn = Transaction::new_at(entry.id, entry.timestamp);
                 transactions.insert(entry.id, txn);
                 active_ids.insert(entry.id);
             }
@@ -661,27 +1035,28 @@ impl TransactionManager {
             if active_ids.contains(&entry.id) {
                 match entry.command {
                     LogCommand::Put | LogCommand::Delete => {
-                        if let Some(txn) = transactions.get_mut(&entry.id) {
-                            let key = entry.key.clone().unwrap_or_default();
-                            let value = entry.value.clone();
-                            // 简化处理，仅考虑基本操作
-                            let op = match entry.command {
-                                LogCommand::Put => StoreOperation::Set(key, value.unwrap_or_default()),
-                                LogCommand::Delete => StoreOperation::Delete(key),
-                                _ => continue
-                            };
+                        if let (Some(txn), Some(op)) = (transactions.get_mut(&entry.id), StoreOperation::from_log_entry(entry)) {
                             let _ = txn.add_operation(op);
                         }
                     },
+                    LogCommand::Savepoint | LogCommand::RollbackTo => {
+                        if let (Some(txn), Some(name)) = (transactions.get_mut(&entry.id), &entry.key) {
+                            let _ = if entry.command == LogCommand::Savepoint {
+                                txn.savepoint(name)
+                            } else {
+                                txn.rollback_to_savepoint(name)
+                            };
+                        }
+                    },
                     LogCommand::Commit => {
                         if let Some(txn) = transactions.get_mut(&entry.id) {
-                            txn.commit();
+                            txn.commit_at(entry.timestamp);
                         }
                         active_ids.remove(&entry.id);
                     },
                     LogCommand::Rollback => {
                         if let Some(txn) = transactions.get_mut(&entry.id) {
-                            txn.rollback();
+                            txn.rollback_at(entry.timestamp);
                         }
                         active_ids.remove(&entry.id);
                     },
@@ -2,10 +2,17 @@
 use std::fs::{self, File};
 use std::io::{BufWriter, BufRead, Write, BufReader};
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, Weak};
+use std::time::{Duration, Instant};
+use log::info;
+use crate::clock::{system_clock, Clock, SystemClock};
+use crate::config::WalSyncPolicy;
+use crate::store::{DataType, KeySnapshot, Store, StoreOperation, StoreTransactionExt};
+use serde::{Deserialize, Serialize};
 
 /// WAL操作可能的错误
 #[derive(Debug)]
@@ -37,6 +44,114 @@ impl From<std::io::Error> for WalError {
 
 pub type WalResult<T> = std::result::Result<T, WalError>;
 
+/// 检查点文件格式版本，写入文件头用于识别格式变化
+pub const CHECKPOINT_FORMAT_VERSION: u32 = 3;
+
+/// WAL日志行格式版本，写在每行开头，例如 `v2|PUT|...`；
+/// 没有版本字段的行是版本1，字段没有转义
+pub const WAL_ENTRY_FORMAT_VERSION: u32 = 2;
+
+/// 检查点目录中的索引文件名，每行记录一个检查点: `id|timestamp|path`
+pub const CHECKPOINT_INDEX_FILE: &str = "checkpoints.index";
+
+/// 转义字段中的分隔符、换行和反斜杠，使其可以安全地用 `|` 拼接成一行
+fn escape_field(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\p"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 还原 `escape_field` 转义的字段，遇到非法转义序列返回None
+fn unescape_field(field: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'p' => unescaped.push('|'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+/// 计算CRC32(IEEE)校验和，用于检测日志条目损坏
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// 日志行解析失败的原因
+enum EntryParseError {
+    Malformed(String),
+    ChecksumMismatch,
+}
+
+/// WAL校验发现的异常
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalAnomaly {
+    /// 无法解析的日志行
+    Malformed { line: usize, reason: String },
+    /// 校验和与内容不一致
+    ChecksumMismatch { line: usize },
+    /// 没有对应BEGIN的COMMIT
+    OrphanCommit { line: usize, txn_id: u64 },
+    /// 没有对应BEGIN的ROLLBACK
+    OrphanRollback { line: usize, txn_id: u64 },
+    /// 事务未结束时再次BEGIN
+    DuplicateBegin { line: usize, txn_id: u64 },
+    /// 文件末尾的不完整日志行
+    TruncatedTail { line: usize },
+}
+
+impl fmt::Display for WalAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalAnomaly::Malformed { line, reason } => write!(f, "第{}行无法解析: {}", line, reason),
+            WalAnomaly::ChecksumMismatch { line } => write!(f, "第{}行校验和不匹配", line),
+            WalAnomaly::OrphanCommit { line, txn_id } => write!(f, "第{}行提交了未开始的事务{}", line, txn_id),
+            WalAnomaly::OrphanRollback { line, txn_id } => write!(f, "第{}行回滚了未开始的事务{}", line, txn_id),
+            WalAnomaly::DuplicateBegin { line, txn_id } => write!(f, "第{}行重复开始事务{}", line, txn_id),
+            WalAnomaly::TruncatedTail { line } => write!(f, "第{}行不完整，日志尾部被截断", line),
+        }
+    }
+}
+
+/// WAL校验报告
+#[derive(Debug, Clone, Default)]
+pub struct WalValidationReport {
+    pub entries: usize,
+    pub anomalies: Vec<WalAnomaly>,
+}
+
+impl WalValidationReport {
+    /// 是否没有发现异常
+    pub fn is_ok(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
 /// WAL日志支持的命令类型
 #[derive(Debug, Clone, PartialEq)]
 pub enum LogCommand {
@@ -46,6 +161,26 @@ pub enum LogCommand {
     Commit,   // 提交事务
     Rollback, // 回滚事务
     Checkpoint, // 检查点
+    Prepare,  // 事务已通过校验，等待提交(两阶段提交的第一阶段)
+    Savepoint,  // 事务内的保存点，键为保存点名称
+    RollbackTo, // 回滚到事务内的保存点，键为保存点名称
+}
+
+impl LogCommand {
+    /// 日志中使用的命令名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogCommand::Put => "PUT",
+            LogCommand::Delete => "DELETE",
+            LogCommand::Begin => "BEGIN",
+            LogCommand::Commit => "COMMIT",
+            LogCommand::Rollback => "ROLLBACK",
+            LogCommand::Checkpoint => "CHECKPOINT",
+            LogCommand::Prepare => "PREPARE",
+            LogCommand::Savepoint => "SAVEPOINT",
+            LogCommand::RollbackTo => "ROLLBACK_TO",
+        }
+    }
 }
 
 /// WAL日志条目
@@ -63,32 +198,68 @@ pub struct LogEntry {
 impl LogEntry {
     /// 序列化日志条目为字符串
     pub fn serialize(&self) -> String {
-        let cmd = match self.command {
-            LogCommand::Put => "PUT",
-            LogCommand::Delete => "DELETE",
-            LogCommand::Begin => "BEGIN",
-            LogCommand::Commit => "COMMIT",
-            LogCommand::Rollback => "ROLLBACK",
-            LogCommand::Checkpoint => "CHECKPOINT",
-        };
-        // 使用|分隔字段，增加了old_value和metadata字段
-        format!("{}|{}|{}|{}|{}|{}|{}\n", 
+        let cmd = self.command.as_str();
+        // 使用|分隔字段，第一个字段是格式版本，字段内容经过转义，不会包含分隔符和换行；最后一个字段是CRC32校验和
+        let field = |value: &Option<String>| value.as_deref().map(escape_field).unwrap_or_default();
+        let body = format!("v{}|{}|{}|{}|{}|{}|{}|{}",
+            WAL_ENTRY_FORMAT_VERSION,
             cmd, 
-            self.key.clone().unwrap_or_default(), 
-            self.value.clone().unwrap_or_default(), 
+            field(&self.key), 
+            field(&self.value), 
             self.id,
             self.timestamp,
-            self.old_value.clone().unwrap_or_default(),
-            self.metadata.clone().unwrap_or_default()
-        )
+            field(&self.old_value),
+            field(&self.metadata)
+        );
+        format!("{}|{:08x}\n", body, crc32(body.as_bytes()))
     }
     
     /// 从字符串反序列化为日志条目
     pub fn deserialize(line: &str) -> Option<LogEntry> {
-        let parts: Vec<&str> = line.trim().split('|').collect();
+        Self::parse(line).ok()
+    }
+    
+    /// 解析日志行，区分格式错误和校验和错误
+    fn parse(line: &str) -> Result<LogEntry, EntryParseError> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        // 版本1的日志行没有版本字段: PUT|key|value|id|...，命令名不会以v开头
+        let (version, body) = match line.split_once('|') {
+            Some((tag, body)) if tag.starts_with('v') => {
+                let version = tag[1..]
+                    .parse::<u32>()
+                    .map_err(|_| EntryParseError::Malformed(format!("无效的格式版本: {}", tag)))?;
+                (version, body)
+            }
+            _ => (1, line),
+        };
+        if !(1..=WAL_ENTRY_FORMAT_VERSION).contains(&version) {
+            return Err(EntryParseError::Malformed(format!(
+                "不支持的日志格式版本: {} (当前版本 {})",
+                version, WAL_ENTRY_FORMAT_VERSION
+            )));
+        }
+        let parts: Vec<&str> = body.split('|').collect();
         
-        // 支持旧版本日志格式 (没有old_value和metadata字段)
-        if parts.len() < 4 { return None; }
+        // 版本1的日志可能没有时间戳、old_value和metadata字段，也没有校验和；
+        // 版本2起每行都必须带校验和，缺少校验和的行视为损坏
+        let max_fields = if version >= 2 { 8 } else { 7 };
+        if parts.len() < 4 {
+            return Err(EntryParseError::Malformed(format!("字段数{}少于4", parts.len())));
+        }
+        if parts.len() > max_fields {
+            return Err(EntryParseError::Malformed(format!("字段数{}多于{}", parts.len(), max_fields)));
+        }
+        if version >= 2 && parts.len() != 8 {
+            return Err(EntryParseError::Malformed(format!("缺少校验和，字段数{}", parts.len())));
+        }
+        if version >= 2 {
+            let body_len = line.len() - parts[7].len() - 1;
+            let expected = u32::from_str_radix(parts[7], 16)
+                .map_err(|_| EntryParseError::Malformed(format!("无效的校验和: {}", parts[7])))?;
+            if crc32(&line.as_bytes()[..body_len]) != expected {
+                return Err(EntryParseError::ChecksumMismatch);
+            }
+        }
         
         let command = match parts[0] {
             "PUT" => LogCommand::Put,
@@ -97,40 +268,41 @@ impl LogEntry {
             "COMMIT" => LogCommand::Commit,
             "ROLLBACK" => LogCommand::Rollback,
             "CHECKPOINT" => LogCommand::Checkpoint,
-            _ => return None,
+            "PREPARE" => LogCommand::Prepare,
+            "SAVEPOINT" => LogCommand::Savepoint,
+            "ROLLBACK_TO" => LogCommand::RollbackTo,
+            other => return Err(EntryParseError::Malformed(format!("未知命令: {}", other))),
         };
         
         // 处理新增的时间戳字段
         let timestamp = if parts.len() >= 5 {
-            parts[4].parse().unwrap_or_else(|_| {
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs()
-            })
+            parts[4].parse().unwrap_or_else(|_| SystemClock.now_secs())
         } else {
             0 // 默认时间戳
         };
         
-        // 读取old_value字段(如果存在)
-        let old_value = if parts.len() >= 6 && !parts[5].is_empty() {
-            Some(parts[5].to_string())
-        } else {
-            None
+        // 空字段表示None，版本2起非空字段需要还原转义，版本1的字段按原样使用
+        let field = |index: usize| -> Result<Option<String>, EntryParseError> {
+            match parts.get(index) {
+                Some(part) if !part.is_empty() && version == 1 => Ok(Some(part.to_string())),
+                Some(part) if !part.is_empty() => unescape_field(part)
+                    .map(Some)
+                    .ok_or_else(|| EntryParseError::Malformed(format!("第{}个字段包含非法转义", index + 1))),
+                _ => Ok(None),
+            }
         };
         
-        // 读取metadata字段(如果存在)
-        let metadata = if parts.len() >= 7 && !parts[6].is_empty() {
-            Some(parts[6].to_string())
-        } else {
-            None
-        };
+        // old_value和metadata字段在旧版本日志中不存在
+        let old_value = field(5)?;
+        let metadata = field(6)?;
         
-        Some(LogEntry {
+        Ok(LogEntry {
             command,
-            key: if parts[1].is_empty() { None } else { Some(parts[1].to_string()) },
-            value: if parts[2].is_empty() { None } else { Some(parts[2].to_string()) },
-            id: parts[3].parse().ok()?,
+            key: field(1)?,
+            value: field(2)?,
+            id: parts[3]
+                .parse()
+                .map_err(|_| EntryParseError::Malformed(format!("无效的序列号: {}", parts[3])))?,
             timestamp,
             old_value,
             metadata,
@@ -139,10 +311,7 @@ impl LogEntry {
     
     /// 创建带时间戳的新日志条目
     pub fn new(command: LogCommand, key: Option<String>, value: Option<String>, id: u64) -> Self {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let timestamp = SystemClock.now_secs();
             
         LogEntry {
             command,
@@ -164,10 +333,7 @@ impl LogEntry {
         metadata: Option<String>,
         id: u64
     ) -> Self {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let timestamp = SystemClock.now_secs();
             
         LogEntry {
             command,
@@ -179,6 +345,33 @@ impl LogEntry {
             metadata,
         }
     }
+    
+    /// 使用指定的时间戳，用于由注入的时钟生成条目
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+}
+
+/// 检查点中的一个键，值保留数据类型和过期时间
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointRecord {
+    #[serde(default)]
+    pub db: usize,
+    pub key: String,
+    #[serde(flatten)]
+    pub snapshot: KeySnapshot,
+}
+
+impl CheckpointRecord {
+    /// 数据库0中没有过期时间的字符串键，旧版本的检查点按这种方式载入
+    fn string(key: String, value: String) -> Self {
+        CheckpointRecord {
+            db: 0,
+            key,
+            snapshot: KeySnapshot::new(DataType::String(Arc::from(value))),
+        }
+    }
 }
 
 /// 检查点数据结构
@@ -186,7 +379,7 @@ impl LogEntry {
 pub struct Checkpoint {
     pub id: u64,
     pub timestamp: u64,
-    pub data: HashMap<String, String>, // 保存检查点时的完整数据状态
+    pub data: Vec<CheckpointRecord>, // 保存检查点时的完整数据状态
 }
 
 impl Checkpoint {
@@ -195,12 +388,14 @@ impl Checkpoint {
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
         
-        // 写入检查点元数据
-        writeln!(writer, "CHECKPOINT|{}|{}", self.id, self.timestamp)?;
+        // 写入检查点元数据，带格式版本
+        writeln!(writer, "CHECKPOINT|v{}|{}|{}", CHECKPOINT_FORMAT_VERSION, self.id, self.timestamp)?;
         
-        // 写入所有键值对数据
-        for (key, value) in &self.data {
-            writeln!(writer, "{}|{}", key, value)?;
+        // 每个键一行JSON，保留数据类型和过期时间
+        for record in &self.data {
+            let line = serde_json::to_string(record)
+                .map_err(|e| WalError::CheckpointError(format!("无法序列化检查点数据: {}", e)))?;
+            writeln!(writer, "{}", line)?;
         }
         
         writer.flush()?;
@@ -224,18 +419,54 @@ impl Checkpoint {
             return Err(WalError::CheckpointError("无效的检查点格式".to_string()));
         }
         
-        let id = parts[1].parse::<u64>()
+        // 版本1的文件头没有版本字段: CHECKPOINT|id|timestamp
+        let (version, fields) = match parts[1].strip_prefix('v') {
+            Some(version) => {
+                let version = version.parse::<u32>()
+                    .map_err(|_| WalError::CheckpointError(format!("无法解析检查点格式版本: {}", parts[1])))?;
+                (version, &parts[2..])
+            }
+            None => (1, &parts[1..]),
+        };
+        if !(1..=CHECKPOINT_FORMAT_VERSION).contains(&version) {
+            return Err(WalError::CheckpointError(format!(
+                "不支持的检查点格式版本: {} (当前版本 {})",
+                version, CHECKPOINT_FORMAT_VERSION
+            )));
+        }
+        if fields.len() < 2 {
+            return Err(WalError::CheckpointError("无效的检查点格式".to_string()));
+        }
+        
+        let id = fields[0].parse::<u64>()
             .map_err(|_| WalError::CheckpointError("无法解析检查点ID".to_string()))?;
-        let timestamp = parts[2].parse::<u64>()
+        let timestamp = fields[1].parse::<u64>()
             .map_err(|_| WalError::CheckpointError("无法解析检查点时间戳".to_string()))?;
         
-        // 读取所有键值对
-        let mut data = HashMap::new();
+        // 读取所有键，版本1和2只保存字符串键值对，按数据库0中的字符串载入
+        let mut data = Vec::new();
         for line in lines {
             let line = line?;
+            if version >= 3 {
+                if line.is_empty() {
+                    continue;
+                }
+                let record = serde_json::from_str(&line)
+                    .map_err(|_| WalError::CheckpointError(format!("无效的检查点数据行: {}", line)))?;
+                data.push(record);
+                continue;
+            }
             let parts: Vec<&str> = line.split('|').collect();
-            if parts.len() >= 2 {
-                data.insert(parts[0].to_string(), parts[1].to_string());
+            if parts.len() < 2 {
+                continue;
+            }
+            if version == 1 {
+                data.push(CheckpointRecord::string(parts[0].to_string(), parts[1].to_string()));
+            } else {
+                let (Some(key), Some(value)) = (unescape_field(parts[0]), unescape_field(parts[1])) else {
+                    return Err(WalError::CheckpointError(format!("无效的检查点数据行: {}", line)));
+                };
+                data.push(CheckpointRecord::string(key, value));
             }
         }
         
@@ -247,17 +478,223 @@ impl Checkpoint {
     }
 }
 
+/// 检查点索引中的一条记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointInfo {
+    pub id: u64,
+    pub timestamp: u64,
+    pub path: PathBuf,
+}
+
+impl CheckpointInfo {
+    fn serialize(&self) -> String {
+        format!("{}|{}|{}\n", self.id, self.timestamp, escape_field(&self.path.to_string_lossy()))
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(3, '|');
+        let id = parts.next()?.parse().ok()?;
+        let timestamp = parts.next()?.parse().ok()?;
+        let path = PathBuf::from(unescape_field(parts.next()?)?);
+        Some(CheckpointInfo { id, timestamp, path })
+    }
+}
+
+/// 写入检查点索引时持有，进程内的实例依次合并并替换索引文件
+static CHECKPOINT_INDEX_LOCK: Mutex<()> = Mutex::new(());
+/// 检查点索引临时文件名的序号
+static NEXT_INDEX_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// 读取检查点目录中的索引，按ID从旧到新排列，文件不存在时返回空索引，无法解析的行被忽略
+fn load_checkpoint_index(checkpoint_dir: &Path) -> WalResult<Vec<CheckpointInfo>> {
+    let content = match fs::read_to_string(checkpoint_dir.join(CHECKPOINT_INDEX_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut index: Vec<CheckpointInfo> = content.lines().filter_map(CheckpointInfo::parse).collect();
+    index.sort_by_key(|info| info.id);
+    Ok(index)
+}
+
+/// 批量写入时的缓冲区大小，写满后立即写入文件
+const WAL_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// WAL日志文件，记录写入系统调用的次数以便观察批量写入的效果
+#[derive(Debug)]
+struct LogFile {
+    file: File,
+    write_calls: u64,
+}
+
+impl LogFile {
+    fn new(file: File) -> Self {
+        LogFile { file, write_calls: 0 }
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_calls += 1;
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// 已同步到磁盘的日志位置，WAIT命令据此等待写入落盘
+///
+/// 位置是写入端追加过的条目总数，压缩替换文件后继续累加。
+#[derive(Debug, Default)]
+pub struct SyncProgress {
+    synced: Mutex<u64>,
+    advanced: Condvar,
+}
+
+impl SyncProgress {
+    fn advance(&self, position: u64) {
+        let mut synced = self.synced.lock().unwrap();
+        *synced = (*synced).max(position);
+        self.advanced.notify_all();
+    }
+
+    /// 等待位置不大于position的条目都同步到磁盘，返回已同步的位置
+    ///
+    /// timeout为None时一直等待；超时返回时结果可能小于position。
+    pub fn wait(&self, position: u64, timeout: Option<Duration>) -> u64 {
+        let synced = self.synced.lock().unwrap();
+        let synced = match timeout {
+            Some(timeout) => {
+                self.advanced
+                    .wait_timeout_while(synced, timeout, |synced| *synced < position)
+                    .unwrap()
+                    .0
+            }
+            None => self.advanced.wait_while(synced, |synced| *synced < position).unwrap(),
+        };
+        *synced
+    }
+}
+
+/// 日志文件的写入端，进程内打开同一个日志文件的所有WAL实例共用一个
+///
+/// 压缩在持有锁时替换文件并重新打开，之后所有实例都写入新文件，不会有实例继续写入已被替换的旧文件。
+#[derive(Debug)]
+struct LogWriter {
+    writer: BufWriter<LogFile>,
+    unsynced_entries: usize, // 上次同步到磁盘后追加的条目数
+    last_sync: Instant,
+    appended: u64, // 追加过的条目总数
+    progress: Arc<SyncProgress>,
+}
+
+impl LogWriter {
+    fn new(file: File) -> Self {
+        LogWriter {
+            writer: BufWriter::with_capacity(WAL_BUFFER_CAPACITY, LogFile::new(file)),
+            unsynced_entries: 0,
+            last_sync: Instant::now(),
+            appended: 0,
+            progress: Arc::new(SyncProgress::default()),
+        }
+    }
+
+    /// 压缩后改为写入新文件，已追加的位置和同步进度保持不变
+    fn reopen(&mut self, file: File) {
+        self.writer = BufWriter::with_capacity(WAL_BUFFER_CAPACITY, LogFile::new(file));
+        self.unsynced_entries = 0;
+        self.last_sync = Instant::now();
+        self.progress.advance(self.appended);
+    }
+
+    /// 将缓冲的日志写入文件并同步到磁盘
+    fn sync(&mut self) -> WalResult<()> {
+        if self.unsynced_entries == 0 {
+            return Ok(());
+        }
+        self.writer.flush()?;
+        // 执行fsync，确保数据物理写入磁盘
+        self.writer.get_ref().file.sync_all()?; // 同步数据和元数据到磁盘
+        self.unsynced_entries = 0;
+        self.last_sync = Instant::now();
+        self.progress.advance(self.appended);
+        Ok(())
+    }
+}
+
+/// 进程内打开的日志文件写入端，按规范化路径索引，所有实例释放后条目随之失效
+static LOG_WRITERS: OnceLock<Mutex<HashMap<PathBuf, Weak<Mutex<LogWriter>>>>> = OnceLock::new();
+
+/// 打开日志文件的写入端，同一个文件已被其他实例打开时共用它的写入端
+fn open_log_writer(log_file: &Path) -> WalResult<Arc<Mutex<LogWriter>>> {
+    // 文件被删除后重新创建的是另一个文件，不能沿用旧文件的写入端
+    let existed = log_file.exists();
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .create(true)
+        .append(true)
+        .open(log_file)?;
+    let path = fs::canonicalize(log_file)?;
+    
+    let mut writers = LOG_WRITERS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    if existed {
+        if let Some(writer) = writers.get(&path).and_then(Weak::upgrade) {
+            return Ok(writer);
+        }
+    }
+    writers.retain(|_, writer| writer.strong_count() > 0);
+    let writer = Arc::new(Mutex::new(LogWriter::new(file)));
+    writers.insert(path, Arc::downgrade(&writer));
+    Ok(writer)
+}
+
+/// 读取日志文件中的所有条目，无法解析的行被忽略
+fn read_log_entries(log_file: &Path) -> WalResult<Vec<LogEntry>> {
+    let reader = BufReader::new(File::open(log_file)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        if let Some(entry) = LogEntry::deserialize(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// 日志中已开始但尚未提交或回滚的事务
+fn open_transactions(entries: &[LogEntry]) -> Vec<u64> {
+    let mut open = Vec::new();
+    for entry in entries {
+        match entry.command {
+            LogCommand::Begin => open.push(entry.id),
+            LogCommand::Commit | LogCommand::Rollback => open.retain(|&id| id != entry.id),
+            _ => {}
+        }
+    }
+    open
+}
+
 /// 预写式日志实现
 #[derive(Debug)]
 pub struct WriteAheadLog {
     log_file: PathBuf,
-    writer: BufWriter<File>,
+    // 与打开同一日志文件的其他实例共用，追加、检查点和压缩都在持有它的锁时进行
+    writer: Arc<Mutex<LogWriter>>,
     pub last_sequence_number: u64,
     active_transactions: Vec<u64>,
     // 检查点相关字段
     checkpoint_interval: u64, // 多少条日志后创建一个检查点
     entries_since_checkpoint: u64,
     checkpoint_dir: PathBuf,
+    checkpoint_retention: usize, // 保留最近的检查点文件数，0表示全部保留
+    checkpoint_index: Vec<CheckpointInfo>, // 检查点索引的内存副本，按ID从旧到新排列
+    clock: Arc<dyn Clock>, // 日志条目和检查点时间戳的来源
+    sync_policy: WalSyncPolicy,
+    batch_entries: usize,     // 批量写入时每缓冲多少条日志写入一次文件
 }
 
 impl WriteAheadLog {
@@ -268,11 +705,7 @@ impl WriteAheadLog {
             fs::create_dir_all(parent)?;
         }
         
-        let file = std::fs::OpenOptions::new()
-            .read(true)
-            .create(true)
-            .append(true)
-            .open(log_file)?;
+        let writer = open_log_writer(log_file)?;
             
         // 创建检查点目录
         let checkpoint_dir = if let Some(parent) = log_file.parent() {
@@ -286,42 +719,27 @@ impl WriteAheadLog {
             dir
         };
         
-        // 尝试初始化序列号和活动事务
-        let mut last_sequence_number = 0;
-        let mut active_transactions = Vec::new();
+        // 从已有的日志初始化序列号和活动事务，其他实例缓冲的条目先写入文件
+        lock_writer(&writer).writer.flush()?;
+        let entries = read_log_entries(log_file)?;
+        let last_sequence_number = entries.iter().map(|entry| entry.id).max().unwrap_or(0);
+        let active_transactions = open_transactions(&entries);
         
-        let temp_reader = BufReader::new(File::open(log_file)?);
-        
-        for line in temp_reader.lines() {
-            let line = line?;
-            if let Some(entry) = LogEntry::deserialize(&line) {
-                if entry.id > last_sequence_number {
-                    last_sequence_number = entry.id;
-                }
-                
-                // 跟踪活动事务
-                match entry.command {
-                    LogCommand::Begin => {
-                        active_transactions.push(entry.id);
-                    },
-                    LogCommand::Commit | LogCommand::Rollback => {
-                        if let Some(pos) = active_transactions.iter().position(|&id| id == entry.id) {
-                            active_transactions.remove(pos);
-                        }
-                    },
-                    _ => {}
-                }
-            }
-        }
+        let checkpoint_index = load_checkpoint_index(&checkpoint_dir)?;
         
         Ok(WriteAheadLog {
             log_file: log_file.to_path_buf(),
-            writer: BufWriter::new(file),
+            writer,
             last_sequence_number,
             active_transactions,
             checkpoint_interval: 1000, // 默认每1000条日志创建一个检查点
             entries_since_checkpoint: 0,
             checkpoint_dir,
+            checkpoint_retention: 5,
+            checkpoint_index,
+            clock: system_clock(),
+            sync_policy: WalSyncPolicy::Always,
+            batch_entries: 64,
         })
     }
 
@@ -331,95 +749,293 @@ impl WriteAheadLog {
         self
     }
     
+    /// 设置保留的检查点文件数
+    pub fn with_checkpoint_retention(mut self, retention: usize) -> Self {
+        self.checkpoint_retention = retention;
+        self
+    }
+
+    /// 修改保留的检查点文件数
+    pub fn set_checkpoint_retention(&mut self, retention: usize) {
+        self.checkpoint_retention = retention;
+    }
+
+    /// 设置同步策略，batch_entries为批量写入时每次写入文件的条目数
+    pub fn with_sync_policy(mut self, policy: WalSyncPolicy, batch_entries: usize) -> Self {
+        self.set_sync_policy(policy, batch_entries);
+        self
+    }
+    
+    /// 修改同步策略
+    pub fn set_sync_policy(&mut self, policy: WalSyncPolicy, batch_entries: usize) {
+        self.sync_policy = policy;
+        self.batch_entries = batch_entries.max(1);
+    }
+
+    /// 使用指定的时钟生成时间戳
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    /// 修改生成时间戳所用的时钟
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
     /// 设置检查点目录
     pub fn with_checkpoint_dir(mut self, dir: PathBuf) -> WalResult<Self> {
+        self.set_checkpoint_dir(dir)?;
+        Ok(self)
+    }
+    
+    /// 修改检查点目录
+    pub fn set_checkpoint_dir(&mut self, dir: PathBuf) -> WalResult<()> {
         fs::create_dir_all(&dir)?;
+        self.checkpoint_index = load_checkpoint_index(&dir)?;
         self.checkpoint_dir = dir;
-        Ok(self)
+        Ok(())
     }
 
     /// 添加日志条目
     pub fn append_entry(&mut self, entry: &LogEntry) -> WalResult<()> {
-        let line = entry.serialize();
-        self.writer.write_all(line.as_bytes())?;
-        self.writer.flush()?;
-        
-        // 执行fsync，确保数据物理写入磁盘
-        self.writer.get_mut().sync_all()?; // 同步数据和元数据到磁盘
-        
-        self.last_sequence_number = entry.id;
+        self.append_batch(std::slice::from_ref(entry))
+    }
+    
+    /// 批量追加日志条目，所有条目写入后只按同步策略同步一次，达到检查点间隔时创建检查点
+    pub fn append_batch(&mut self, entries: &[LogEntry]) -> WalResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        self.write_entries(entries)?;
         
         // 检查是否需要创建检查点
-        self.entries_since_checkpoint += 1;
         if self.entries_since_checkpoint >= self.checkpoint_interval {
             self.create_checkpoint(None)?;
         }
         
-        // 更新事务状态
-        match entry.command {
-            LogCommand::Begin => {
-                self.active_transactions.push(entry.id);
-            },
-            LogCommand::Commit | LogCommand::Rollback => {
-                if let Some(pos) = self.active_transactions.iter().position(|&id| id == entry.id) {
-                    self.active_transactions.remove(pos);
-                }
-            },
+        Ok(())
+    }
+    
+    /// 写入日志条目并更新序列号和事务状态，不会触发检查点
+    ///
+    /// 创建检查点和压缩时通过这里写入自己的记录，因此检查点不会在创建过程中再次触发检查点或压缩。
+    fn write_entries(&mut self, entries: &[LogEntry]) -> WalResult<()> {
+        let shared = Arc::clone(&self.writer);
+        let mut writer = lock_writer(&shared);
+        self.write_entries_locked(&mut writer, entries)
+    }
+    
+    /// 在已持有写入端锁时写入日志条目
+    fn write_entries_locked(&mut self, writer: &mut LogWriter, entries: &[LogEntry]) -> WalResult<()> {
+        let lines: String = entries.iter().map(LogEntry::serialize).collect();
+        writer.writer.write_all(lines.as_bytes())?;
+        let previous = writer.unsynced_entries;
+        writer.unsynced_entries += entries.len();
+        writer.appended += entries.len() as u64;
+        let batch_full = previous / self.batch_entries != writer.unsynced_entries / self.batch_entries;
+        
+        match self.sync_policy {
+            WalSyncPolicy::Always => writer.sync()?,
+            WalSyncPolicy::EverySec if writer.last_sync.elapsed() >= Duration::from_secs(1) => writer.sync()?,
+            _ if batch_full => writer.writer.flush()?,
             _ => {}
         }
         
+        for entry in entries {
+            self.last_sequence_number = entry.id;
+            // 检查点记录本身不计入检查点间隔
+            if !matches!(entry.command, LogCommand::Checkpoint) {
+                self.entries_since_checkpoint += 1;
+            }
+            
+            // 更新事务状态
+            match entry.command {
+                LogCommand::Begin => {
+                    self.active_transactions.push(entry.id);
+                },
+                LogCommand::Commit | LogCommand::Rollback => {
+                    if let Some(pos) = self.active_transactions.iter().position(|&id| id == entry.id) {
+                        self.active_transactions.remove(pos);
+                    }
+                },
+                _ => {}
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 事务是否已在日志中开始且尚未结束
+    pub fn is_transaction_open(&self, txn_id: u64) -> bool {
+        self.active_transactions.contains(&txn_id)
+    }
+
+    /// 将缓冲的日志写入文件并同步到磁盘
+    pub fn flush(&mut self) -> WalResult<()> {
+        lock_writer(&self.writer).sync()
+    }
+    
+    /// every_sec策略下有未同步的日志且距上次同步超过1秒时同步，返回是否执行了同步
+    ///
+    /// 同步只在追加日志时检查，写入停止后缓冲的日志需要由后台定时调用这里同步。
+    pub fn sync_if_due(&self) -> WalResult<bool> {
+        if self.sync_policy != WalSyncPolicy::EverySec {
+            return Ok(false);
+        }
+        let mut writer = lock_writer(&self.writer);
+        if writer.unsynced_entries == 0 || writer.last_sync.elapsed() < Duration::from_secs(1) {
+            return Ok(false);
+        }
+        writer.sync()?;
+        Ok(true)
+    }
+    
+    /// 已追加的日志位置，同步进度达到这个位置时之前追加的条目都已落盘
+    pub fn appended_position(&self) -> u64 {
+        lock_writer(&self.writer).appended
+    }
+
+    /// 日志的同步进度，等待时不需要持有日志的锁
+    pub fn sync_progress(&self) -> Arc<SyncProgress> {
+        Arc::clone(&lock_writer(&self.writer).progress)
+    }
+
+    /// 将缓冲的日志写入文件但不同步，使读取文件时能看到所有条目
+    fn write_buffered(&self) -> WalResult<()> {
+        lock_writer(&self.writer).writer.flush()?;
         Ok(())
     }
+    
+    /// 写入日志文件的系统调用次数
+    pub fn write_calls(&self) -> u64 {
+        lock_writer(&self.writer).writer.get_ref().write_calls
+    }
 
     /// 加载所有日志条目
     pub fn load_entries(&self) -> WalResult<Vec<LogEntry>> {
-        let file = File::open(&self.log_file)?;
-        let reader = BufReader::new(file);
-        let mut entries = Vec::new();
-        
-        for line in reader.lines() {
-            let line = line?;
-            if let Some(entry) = LogEntry::deserialize(&line) {
-                entries.push(entry);
+        self.write_buffered()?;
+        read_log_entries(&self.log_file)
+    }
+
+    /// 校验整个日志而不应用，报告格式错误、校验和错误、孤立的提交/回滚、重复的BEGIN和被截断的尾部
+    pub fn validate(&self) -> WalResult<WalValidationReport> {
+        self.write_buffered()?;
+        let content = fs::read(&self.log_file)?;
+        let content = String::from_utf8_lossy(&content);
+        let mut report = WalValidationReport::default();
+        let mut open_transactions = std::collections::HashSet::new();
+        
+        let lines: Vec<&str> = content.split('\n').collect();
+        // 以换行结尾时最后一个元素为空串
+        let complete_lines = lines.len() - 1;
+        for (index, line) in lines.iter().enumerate() {
+            let line_number = index + 1;
+            if index == complete_lines {
+                if !line.is_empty() {
+                    report.anomalies.push(WalAnomaly::TruncatedTail { line: line_number });
+                }
+                break;
             }
+            if line.trim().is_empty() {
+                continue;
+            }
+            
+            let entry = match LogEntry::parse(line) {
+                Ok(entry) => entry,
+                Err(EntryParseError::ChecksumMismatch) => {
+                    report.anomalies.push(WalAnomaly::ChecksumMismatch { line: line_number });
+                    continue;
+                }
+                Err(EntryParseError::Malformed(reason)) => {
+                    report.anomalies.push(WalAnomaly::Malformed { line: line_number, reason });
+                    continue;
+                }
+            };
+            report.entries += 1;
+            
+            let anomaly = match entry.command {
+                LogCommand::Begin => (!open_transactions.insert(entry.id))
+                    .then_some(WalAnomaly::DuplicateBegin { line: line_number, txn_id: entry.id }),
+                LogCommand::Commit => (!open_transactions.remove(&entry.id))
+                    .then_some(WalAnomaly::OrphanCommit { line: line_number, txn_id: entry.id }),
+                LogCommand::Rollback => (!open_transactions.remove(&entry.id))
+                    .then_some(WalAnomaly::OrphanRollback { line: line_number, txn_id: entry.id }),
+                _ => None,
+            };
+            report.anomalies.extend(anomaly);
         }
         
-        Ok(entries)
+        Ok(report)
     }
 
     /// 开始事务
     pub fn begin(&mut self, txn_id: u64) -> WalResult<()> {
-        let entry = LogEntry::new(LogCommand::Begin, None, None, txn_id);
+        let entry = LogEntry::new(LogCommand::Begin, None, None, txn_id)
+            .with_timestamp(self.clock.now_secs());
         self.append_entry(&entry)
     }
     
-    /// 提交事务
-    pub fn commit(&mut self, txn_id: u64) -> WalResult<()> {
-        // 检查事务是否存在
+    /// 记录事务已准备好提交
+    pub fn prepare(&mut self, txn_id: u64) -> WalResult<()> {
         if !self.active_transactions.contains(&txn_id) {
             return Err(WalError::TransactionNotFound(txn_id));
         }
         
-        let entry = LogEntry::new(LogCommand::Commit, None, None, txn_id);
+        let entry = LogEntry::new(LogCommand::Prepare, None, None, txn_id)
+            .with_timestamp(self.clock.now_secs());
         self.append_entry(&entry)
     }
     
-    /// 回滚事务
-    pub fn rollback(&mut self, txn_id: u64) -> WalResult<()> {
-        // 检查事务是否存在
+    /// 记录事务内的保存点
+    pub fn savepoint(&mut self, txn_id: u64, name: &str) -> WalResult<()> {
+        self.append_savepoint_entry(LogCommand::Savepoint, txn_id, name)
+    }
+    
+    /// 记录事务回滚到保存点
+    pub fn rollback_to_savepoint(&mut self, txn_id: u64, name: &str) -> WalResult<()> {
+        self.append_savepoint_entry(LogCommand::RollbackTo, txn_id, name)
+    }
+    
+    fn append_savepoint_entry(&mut self, command: LogCommand, txn_id: u64, name: &str) -> WalResult<()> {
         if !self.active_transactions.contains(&txn_id) {
             return Err(WalError::TransactionNotFound(txn_id));
         }
         
-        let entry = LogEntry::new(LogCommand::Rollback, None, None, txn_id);
+        let entry = LogEntry::new(command, Some(name.to_string()), None, txn_id)
+            .with_timestamp(self.clock.now_secs());
         self.append_entry(&entry)
     }
     
-    /// 获取需要回滚的操作
-    pub fn rollback_to(&self, txn_id: u64) -> WalResult<Vec<LogEntry>> {
-        let entries = self.load_entries()?;
-        let mut to_undo = Vec::new();
-        let mut in_txn = false;
+    /// 提交事务
+    pub fn commit(&mut self, txn_id: u64) -> WalResult<()> {
+        // 检查事务是否存在
+        if !self.active_transactions.contains(&txn_id) {
+            return Err(WalError::TransactionNotFound(txn_id));
+        }
+        
+        let entry = LogEntry::new(LogCommand::Commit, None, None, txn_id)
+            .with_timestamp(self.clock.now_secs());
+        self.append_entry(&entry)
+    }
+    
+    /// 回滚事务
+    pub fn rollback(&mut self, txn_id: u64) -> WalResult<()> {
+        // 检查事务是否存在
+        if !self.active_transactions.contains(&txn_id) {
+            return Err(WalError::TransactionNotFound(txn_id));
+        }
+        
+        let entry = LogEntry::new(LogCommand::Rollback, None, None, txn_id)
+            .with_timestamp(self.clock.now_secs());
+        self.append_entry(&entry)
+    }
+    
+    /// 获取需要回滚的操作
+    pub fn rollback_to(&self, txn_id: u64) -> WalResult<Vec<LogEntry>> {
+        let entries = self.load_entries()?;
+        let mut to_undo = Vec::new();
+        let mut in_txn = false;
         
         // 从最新的日志向前查找
         for entry in entries.iter().rev() {
@@ -444,40 +1060,169 @@ impl WriteAheadLog {
     }
 
     /// 创建检查点
-    pub fn create_checkpoint(&mut self, data_snapshot: Option<HashMap<String, String>>) -> WalResult<u64> {
-        let checkpoint_id = self.last_sequence_number + 1;
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+    ///
+    /// 未提供数据时以重放当前日志得到的各数据库状态作为检查点数据。
+    pub fn create_checkpoint(&mut self, data_snapshot: Option<Vec<CheckpointRecord>>) -> WalResult<u64> {
+        let shared = Arc::clone(&self.writer);
+        let mut writer = lock_writer(&shared);
+        self.create_checkpoint_locked(&mut writer, data_snapshot)
+    }
+    
+    /// 在已持有写入端锁时创建检查点，期间其他实例不能追加条目
+    fn create_checkpoint_locked(
+        &mut self,
+        writer: &mut LogWriter,
+        data_snapshot: Option<Vec<CheckpointRecord>>,
+    ) -> WalResult<u64> {
+        writer.writer.flush()?;
+        let entries = read_log_entries(&self.log_file)?;
+        // 检查点ID大于日志、索引和检查点目录中已有的所有ID，包括其他实例写入的条目和检查点
+        let checkpoint_id = entries
+            .iter()
+            .map(|entry| entry.id)
+            .chain(self.checkpoint_index.iter().map(|info| info.id))
+            .chain(self.list_checkpoint_files()?.into_iter().map(|(id, _)| id))
+            .fold(self.last_sequence_number, u64::max)
+            + 1;
+        let timestamp = self.clock.now_secs();
+        
+        // 未提供快照时，以当前日志可恢复的状态作为检查点数据，
+        // 否则检查点之前的条目在恢复和压缩时会丢失
+        let data = match data_snapshot {
+            Some(data) => data,
+            None => replay_entries(&entries, u64::MAX, |_, _| {})?.records(),
+        };
         
         // 创建检查点记录
         let checkpoint = Checkpoint {
             id: checkpoint_id,
             timestamp,
-            data: data_snapshot.unwrap_or_default(),
+            data,
         };
         
         // 创建检查点文件
         let mut checkpoint_file_path = self.checkpoint_dir.clone();
         checkpoint_file_path.push(format!("checkpoint_{}.dat", checkpoint_id));
         checkpoint.serialize_to_file(&checkpoint_file_path)?;
+        self.checkpoint_index.push(CheckpointInfo {
+            id: checkpoint_id,
+            timestamp,
+            path: checkpoint_file_path.clone(),
+        });
+        self.write_checkpoint_index()?;
+        
+        self.entries_since_checkpoint = 0;
         
-        // 添加检查点条目到WAL
+        // 添加检查点条目到WAL，不经过append_entry，写入检查点条目不会再次触发检查点
         let entry = LogEntry::new(
             LogCommand::Checkpoint, 
             Some(checkpoint_file_path.to_string_lossy().to_string()), 
             None, 
             checkpoint_id
-        );
-        self.append_entry(&entry)?;
+        ).with_timestamp(timestamp);
+        self.write_entries_locked(writer, std::slice::from_ref(&entry))?;
+        writer.sync()?;
+        
+        // 新检查点已落盘并记录到WAL后才清理旧检查点
+        self.remove_old_checkpoints()?;
         
-        self.entries_since_checkpoint = 0;
         Ok(checkpoint_id)
     }
     
+    /// 检查点目录中的检查点文件，按ID从新到旧排列
+    fn list_checkpoint_files(&self) -> WalResult<Vec<(u64, PathBuf)>> {
+        let mut files = Vec::new();
+        for dir_entry in fs::read_dir(&self.checkpoint_dir)? {
+            let path = dir_entry?.path();
+            let id = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("checkpoint_"))
+                .and_then(|name| name.strip_suffix(".dat"))
+                .and_then(|id| id.parse::<u64>().ok());
+            if let Some(id) = id {
+                files.push((id, path));
+            }
+        }
+        files.sort_by_key(|(id, _)| std::cmp::Reverse(*id));
+        Ok(files)
+    }
+    
+    /// 按保留策略删除旧的检查点文件，最新的检查点始终保留，索引中同时移除对应的记录
+    fn remove_old_checkpoints(&mut self) -> WalResult<()> {
+        if self.checkpoint_retention == 0 {
+            return Ok(());
+        }
+        let removed: Vec<PathBuf> = self
+            .list_checkpoint_files()?
+            .into_iter()
+            .skip(self.checkpoint_retention)
+            .map(|(_, path)| path)
+            .collect();
+        if removed.is_empty() {
+            return Ok(());
+        }
+        for path in &removed {
+            fs::remove_file(path)?;
+        }
+        self.checkpoint_index.retain(|info| !removed.contains(&info.path));
+        self.write_checkpoint_index()
+    }
+    
+    /// 把索引写入同目录下的临时文件后重命名替换，崩溃时索引要么是旧的完整版本，要么是新的
+    ///
+    /// 同一目录可能被多个实例使用，写入前在锁内重新读取磁盘上的索引并与内存副本合并，
+    /// 只保留文件仍然存在的检查点，其他实例新建或清理的检查点不会被覆盖；临时文件名各不相同。
+    fn write_checkpoint_index(&mut self) -> WalResult<()> {
+        let _guard = CHECKPOINT_INDEX_LOCK.lock().unwrap();
+        let mut merged = load_checkpoint_index(&self.checkpoint_dir)?;
+        for info in self.checkpoint_index.drain(..) {
+            if !merged.iter().any(|existing| existing.id == info.id) {
+                merged.push(info);
+            }
+        }
+        merged.retain(|info| info.path.exists());
+        merged.sort_by_key(|info| info.id);
+        self.checkpoint_index = merged;
+        
+        let index_path = self.checkpoint_dir.join(CHECKPOINT_INDEX_FILE);
+        let temp_path = self.checkpoint_dir.join(format!(
+            "{}.{}.{}.tmp",
+            CHECKPOINT_INDEX_FILE,
+            std::process::id(),
+            NEXT_INDEX_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let content: String = self.checkpoint_index.iter().map(CheckpointInfo::serialize).collect();
+        {
+            let mut file = File::create(&temp_path)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, &index_path)?;
+        Ok(())
+    }
+    
+    /// 索引中的所有检查点，按ID从旧到新排列
+    pub fn checkpoints(&self) -> &[CheckpointInfo] {
+        &self.checkpoint_index
+    }
+    
+    /// 按ID读取索引中的检查点，不存在或文件已被删除时返回None
+    pub fn get_checkpoint(&self, id: u64) -> WalResult<Option<Checkpoint>> {
+        match self.checkpoint_index.iter().find(|info| info.id == id) {
+            Some(info) if info.path.exists() => Ok(Some(Checkpoint::deserialize_from_file(&info.path)?)),
+            _ => Ok(None),
+        }
+    }
+    
     /// 获取最后一个检查点
+    ///
+    /// 优先使用检查点索引，不需要扫描WAL；没有索引的旧检查点目录从最新的日志向前查找。
     pub fn get_latest_checkpoint(&self) -> WalResult<Option<Checkpoint>> {
+        if let Some(info) = self.checkpoint_index.iter().rev().find(|info| info.path.exists()) {
+            return Ok(Some(Checkpoint::deserialize_from_file(&info.path)?));
+        }
+        
         let entries = self.load_entries()?;
         
         // 从最新的日志向前查找检查点
@@ -500,84 +1245,61 @@ impl WriteAheadLog {
         self.get_latest_checkpoint()
     }
 
-    /// 从WAL恢复数据
+    /// 从WAL恢复数据库0的数据，非字符串的值按 `Store::get_all_key_values` 的方式表示
     pub fn recover(&mut self) -> WalResult<HashMap<String, String>> {
-        // 首先尝试从最新的检查点恢复
-        let mut data = if let Some(checkpoint) = self.get_latest_checkpoint()? {
-            println!("从检查点 {} 恢复数据", checkpoint.id);
-            checkpoint.data
-        } else {
-            println!("没有找到检查点，从头开始恢复");
-            HashMap::new()
-        };
-        
-        // 查找检查点之后的日志条目
+        self.recover_to(u64::MAX)
+    }
+    
+    /// 从WAL恢复数据，每处理一条日志调用一次progress(已处理条数, 总条数)
+    pub fn recover_with_progress<F: FnMut(usize, usize)>(&mut self, progress: F) -> WalResult<HashMap<String, String>> {
+        Ok(self.replay(u64::MAX, progress)?.into_store(0).get_all_key_values())
+    }
+    
+    /// 恢复到指定序列号时的数据状态，只应用提交条目序列号不大于seq的事务
+    pub fn recover_to(&self, seq: u64) -> WalResult<HashMap<String, String>> {
+        Ok(self.replay(seq, |_, _| {})?.into_store(0).get_all_key_values())
+    }
+    
+    /// 从WAL恢复出数据库0的完整存储，根据条目的元数据把列表、哈希表和集合操作还原为对应的数据类型
+    pub fn recover_store(&self) -> WalResult<Store> {
+        Ok(self.replay(u64::MAX, |_, _| {})?.into_store(0))
+    }
+    
+    /// 从WAL恢复出所有数据库
+    pub fn recover_databases(&self) -> WalResult<RecoveredDatabases> {
+        self.replay(u64::MAX, |_, _| {})
+    }
+    
+    /// 在已有的数据库上重放日志，日志中有检查点时以检查点替换它们
+    pub fn recover_onto(&self, databases: BTreeMap<usize, Store>) -> WalResult<RecoveredDatabases> {
         let entries = self.load_entries()?;
-        let mut checkpoint_index = 0;
-        
-        // 找到最后一个检查点的位置
-        for (i, entry) in entries.iter().enumerate() {
-            if matches!(entry.command, LogCommand::Checkpoint) {
-                checkpoint_index = i;
-            }
-        }
-        
-        // 重放检查点之后的所有已提交事务
-        let mut txn_ops: HashMap<u64, Vec<LogEntry>> = HashMap::new();
-        
-        for entry in entries.iter().skip(checkpoint_index + 1) {
-            match entry.command {
-                LogCommand::Begin => {
-                    // 开始一个新事务
-                    txn_ops.entry(entry.id).or_default();
-                },
-                LogCommand::Put | LogCommand::Delete => {
-                    // 将操作加入到对应的事务中
-                    if self.active_transactions.contains(&entry.id) {
-                        txn_ops.entry(entry.id).or_default().push(entry.clone());
-                    }
-                },
-                LogCommand::Commit => {
-                    // 提交事务: 应用所有操作
-                    if let Some(ops) = txn_ops.remove(&entry.id) {
-                        for op in ops {
-                            match op.command {
-                                LogCommand::Put => {
-                                    if let (Some(key), Some(value)) = (&op.key, &op.value) {
-                                        data.insert(key.clone(), value.clone());
-                                    }
-                                },
-                                LogCommand::Delete => {
-                                    if let Some(key) = &op.key {
-                                        data.remove(key);
-                                    }
-                                },
-                                _ => {}
-                            }
-                        }
-                    }
-                },
-                LogCommand::Rollback => {
-                    // 回滚事务: 丢弃所有操作
-                    txn_ops.remove(&entry.id);
-                },
-                _ => {}
-            }
-        }
-        
-        // 丢弃未提交的事务
-        for txn_id in &self.active_transactions {
-            txn_ops.remove(txn_id);
-        }
-        
-        Ok(data)
+        replay_entries_onto(RecoveredDatabases::from_stores(databases), &entries, u64::MAX, |_, _| {})
+    }
+    
+    /// 重放日志直到序列号seq，并报告进度
+    fn replay<F: FnMut(usize, usize)>(&self, seq: u64, progress: F) -> WalResult<RecoveredDatabases> {
+        let entries = self.load_entries()?;
+        replay_entries(&entries, seq, progress)
     }
+        
     
     /// 压缩WAL日志
+    ///
+    /// 整个压缩过程持有共享写入端的锁，进程内打开同一日志的其他实例此时不能追加。
+    /// 新文件只包含未结束事务的条目和新检查点的记录，同步后原子地重命名替换旧文件，
+    /// 再在锁内重新打开共享写入端，所有实例之后的追加都写入新文件，不会丢失条目。
     pub fn compact(&mut self) -> WalResult<()> {
+        self.compact_with(None)
+    }
+    
+    /// 以data_snapshot作为新检查点的数据压缩日志，未提供数据时重放当前日志得到检查点数据
+    pub fn compact_with(&mut self, data_snapshot: Option<Vec<CheckpointRecord>>) -> WalResult<()> {
+        let shared = Arc::clone(&self.writer);
+        let mut writer = lock_writer(&shared);
+        
         // 首先创建一个检查点作为压缩基础
-        let checkpoint_id = self.create_checkpoint(None)?;
-        println!("创建检查点 {} 用于WAL压缩", checkpoint_id);
+        let checkpoint_id = self.create_checkpoint_locked(&mut writer, data_snapshot)?;
+        info!("创建检查点 {} 用于WAL压缩", checkpoint_id);
         
         // 获取当前WAL文件的路径
         let current_log_path = self.log_file.clone();
@@ -590,41 +1312,38 @@ impl WriteAheadLog {
         let temp_file = File::create(&temp_log_path)?;
         let mut temp_writer = BufWriter::new(temp_file);
         
-        // 读取当前WAL中的必要条目
-        let entries = self.load_entries()?;
-        let mut needed_entries = Vec::new();
+        // 活跃事务从日志本身计算，包括其他实例开始的事务
+        writer.sync()?;
+        let entries = read_log_entries(&current_log_path)?;
+        let active_transactions = open_transactions(&entries);
         
-        // 只保留检查点之后的条目和活跃事务的所有条目
-        for entry in entries {
-            if entry.id >= checkpoint_id || self.active_transactions.contains(&entry.id) {
-                needed_entries.push(entry);
+        // 只保留活跃事务的所有条目和新检查点的记录
+        for entry in &entries {
+            if entry.id >= checkpoint_id || active_transactions.contains(&entry.id) {
+                temp_writer.write_all(entry.serialize().as_bytes())?;
             }
         }
-        
-        // 将需要保留的条目写入新文件
-        for entry in needed_entries {
-            temp_writer.write_all(entry.serialize().as_bytes())?;
-        }
         temp_writer.flush()?;
         // 确保临时文件数据物理写入磁盘
         temp_writer.get_mut().sync_all()?;
         
-        // 关闭当前的WAL文件
-        self.writer.flush()?;
-        
-        // 替换旧文件
+        // 替换旧文件，并同步目录使重命名本身持久化
         fs::rename(temp_log_path, &current_log_path)?;
+        #[cfg(unix)]
+        if let Some(parent) = current_log_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            File::open(parent)?.sync_all()?;
+        }
         
-        // 重新打开WAL文件
+        // 重新打开WAL文件，共用写入端的实例都写入新文件
         let file = std::fs::OpenOptions::new()
             .read(true)
             .create(true)
             .append(true)
             .open(&current_log_path)?;
-            
-        self.writer = BufWriter::new(file);
+        writer.reopen(file);
+        self.active_transactions = active_transactions;
         
-        println!("WAL压缩完成");
+        info!("WAL压缩完成");
         Ok(())
     }
     
@@ -640,11 +1359,12 @@ impl WriteAheadLog {
     
     /// 获取WAL文件大小
     pub fn get_file_size(&self) -> WalResult<u64> {
+        self.write_buffered()?;
         let metadata = fs::metadata(&self.log_file)?;
         Ok(metadata.len())
     }
     
-    /// 设置日志文件轮换的大小阈值，超过该阈值时自动进行压缩
+    /// 日志文件超过threshold_size字节时进行压缩，返回是否进行了压缩
     pub fn compact_if_needed(&mut self, threshold_size: u64) -> WalResult<bool> {
         let current_size = self.get_file_size()?;
         if current_size > threshold_size {
@@ -655,6 +1375,231 @@ impl WriteAheadLog {
     }
 }
 
+/// 从日志条目重放到序列号seq，并报告进度
+fn replay_entries<F: FnMut(usize, usize)>(entries: &[LogEntry], seq: u64, progress: F) -> WalResult<RecoveredDatabases> {
+    replay_entries_onto(RecoveredDatabases::default(), entries, seq, progress)
+}
+
+/// 在data上重放日志条目
+fn replay_entries_onto<F: FnMut(usize, usize)>(
+    mut data: RecoveredDatabases,
+    entries: &[LogEntry],
+    seq: u64,
+    mut progress: F,
+) -> WalResult<RecoveredDatabases> {
+    let start = Instant::now();
+    let total = entries.len();
+    
+    // 从不晚于seq的最新可用检查点开始恢复
+    let mut checkpoint_index = None;
+    for (i, entry) in entries.iter().enumerate().rev() {
+        if !matches!(entry.command, LogCommand::Checkpoint) || entry.id > seq {
+            continue;
+        }
+        if let Some(checkpoint_path) = &entry.key {
+            let path = PathBuf::from(checkpoint_path);
+            if path.exists() {
+                let checkpoint = Checkpoint::deserialize_from_file(&path)?;
+                info!("从检查点 {} 恢复数据", checkpoint.id);
+                checkpoint_index = Some(i);
+                data.load_checkpoint(checkpoint.data);
+                break;
+            }
+        }
+    }
+    if checkpoint_index.is_none() {
+        info!("没有找到检查点，从头开始恢复");
+    }
+    
+    // 重放检查点之后的所有已提交事务。
+    // 跨越检查点的事务，其BEGIN和操作可能位于检查点之前，因此需要从头收集事务操作，
+    // 但只应用检查点之后提交的事务和检查点之后的非事务操作
+    let mut txn_ops: HashMap<u64, Vec<LogEntry>> = HashMap::new();
+    // 事务保存点: (事务ID, 保存点名称) -> 保存点处的操作数
+    let mut savepoints: HashMap<(u64, String), usize> = HashMap::new();
+    
+    for (i, entry) in entries.iter().enumerate() {
+        let after_checkpoint = checkpoint_index.is_none_or(|index| i > index);
+        match entry.command {
+            LogCommand::Begin => {
+                // 开始一个新事务
+                txn_ops.entry(entry.id).or_default();
+            },
+            LogCommand::Put | LogCommand::Delete => {
+                if let Some(ops) = txn_ops.get_mut(&entry.id) {
+                    // 将操作加入到对应的事务中
+                    ops.push(entry.clone());
+                } else if after_checkpoint && entry.id <= seq {
+                    // 不属于任何事务的操作直接生效
+                    data.apply_entry(0, entry);
+                }
+            },
+            LogCommand::Commit => {
+                // 提交事务: 应用所有操作，COMMIT记录的键是事务所在的数据库编号，没有时为数据库0
+                if let Some(ops) = txn_ops.remove(&entry.id) {
+                    if after_checkpoint && entry.id <= seq {
+                        let db = entry.key.as_deref().and_then(|db| db.parse().ok()).unwrap_or(0);
+                        for op in &ops {
+                            data.apply_entry(db, op);
+                        }
+                    }
+                }
+            },
+            LogCommand::Rollback => {
+                // 回滚事务: 丢弃所有操作
+                txn_ops.remove(&entry.id);
+            },
+            LogCommand::Savepoint => {
+                if let (Some(ops), Some(name)) = (txn_ops.get(&entry.id), &entry.key) {
+                    savepoints.insert((entry.id, name.clone()), ops.len());
+                }
+            },
+            LogCommand::RollbackTo => {
+                // 回滚到保存点: 丢弃保存点之后的操作
+                if let (Some(ops), Some(name)) = (txn_ops.get_mut(&entry.id), &entry.key) {
+                    if let Some(&offset) = savepoints.get(&(entry.id, name.clone())) {
+                        ops.truncate(offset);
+                    }
+                }
+            },
+            _ => {}
+        }
+        progress(i + 1, total);
+    }
+    
+    // 剩余未提交的事务被丢弃
+    info!("WAL恢复完成: 处理 {} 条日志，耗时 {:?}", total, start.elapsed());
+    Ok(data)
+}
+
+/// 锁定共享写入端
+fn lock_writer(writer: &Mutex<LogWriter>) -> MutexGuard<'_, LogWriter> {
+    writer.lock().unwrap()
+}
+
+/// 写命令执行后键的状态，记录到WAL中，恢复时按记录重建数据
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyImage {
+    /// 清空数据库，之后的镜像重建它的全部内容
+    ClearDatabase(usize),
+    /// 键的当前状态，None表示键已不存在
+    Key { db: usize, key: String, snapshot: Option<KeySnapshot> },
+}
+
+impl KeyImage {
+    /// 镜像条目的元数据为`image:<数据库编号>`，键的状态以JSON保存在值中
+    pub fn to_log_entry(&self, id: u64) -> WalResult<LogEntry> {
+        let (command, db, key, value) = match self {
+            KeyImage::ClearDatabase(db) => (LogCommand::Delete, *db, None, None),
+            KeyImage::Key { db, key, snapshot: None } => (LogCommand::Delete, *db, Some(key.clone()), None),
+            KeyImage::Key { db, key, snapshot: Some(snapshot) } => {
+                let value = serde_json::to_string(snapshot)
+                    .map_err(|e| WalError::InvalidEntry(format!("无法序列化键 {} 的状态: {}", key, e)))?;
+                (LogCommand::Put, *db, Some(key.clone()), Some(value))
+            }
+        };
+        Ok(LogEntry::new_with_metadata(command, key, value, None, Some(format!("image:{}", db)), id))
+    }
+    
+    /// 从镜像条目还原，不是镜像的条目返回None
+    fn from_log_entry(entry: &LogEntry) -> Option<KeyImage> {
+        let db = entry.metadata.as_deref()?.strip_prefix("image:")?.parse().ok()?;
+        let Some(key) = entry.key.clone() else {
+            return Some(KeyImage::ClearDatabase(db));
+        };
+        let snapshot = match entry.command {
+            LogCommand::Put => Some(serde_json::from_str(entry.value.as_deref()?).ok()?),
+            _ => None,
+        };
+        Some(KeyImage::Key { db, key, snapshot })
+    }
+}
+
+/// WAL重放得到的各个数据库，恢复时从检查点数据开始依次应用已提交的条目
+#[derive(Debug, Default)]
+pub struct RecoveredDatabases {
+    databases: BTreeMap<usize, Store>,
+}
+
+impl RecoveredDatabases {
+    /// 在已有的各个数据库上重放，例如从数据文件加载的数据；日志中有检查点时以检查点替换它们
+    pub fn from_stores(databases: BTreeMap<usize, Store>) -> Self {
+        RecoveredDatabases { databases }
+    }
+    
+    /// 取出所有数据库的存储
+    pub fn into_databases(self) -> BTreeMap<usize, Store> {
+        self.databases
+    }
+    
+    /// 有数据的数据库编号和对应的存储
+    pub fn databases(&self) -> impl Iterator<Item = (usize, &Store)> {
+        self.databases.iter().map(|(&db, store)| (db, store))
+    }
+    
+    /// 取出一个数据库的存储，没有数据时返回空存储
+    pub fn into_store(mut self, db: usize) -> Store {
+        self.databases.remove(&db).unwrap_or_default()
+    }
+    
+    /// 所有键的检查点记录，按数据库和键排列
+    pub fn records(&self) -> Vec<CheckpointRecord> {
+        let mut records = Vec::new();
+        for (db, store) in self.databases() {
+            let mut keys = store.get_memory_keys();
+            keys.sort();
+            records.extend(keys.into_iter().filter_map(|key| {
+                let snapshot = store.key_snapshot(&key)?;
+                Some(CheckpointRecord { db, key, snapshot })
+            }));
+        }
+        records
+    }
+    
+    /// 新建的数据库沿用已有数据库的配置
+    fn database_mut(&mut self, db: usize) -> &mut Store {
+        if !self.databases.contains_key(&db) {
+            let store = self.databases.values().next().map(Store::empty_like).unwrap_or_default();
+            self.databases.insert(db, store);
+        }
+        self.databases.get_mut(&db).unwrap()
+    }
+    
+    /// 以检查点数据替换所有数据库
+    fn load_checkpoint(&mut self, records: Vec<CheckpointRecord>) {
+        for store in self.databases.values_mut() {
+            *store = store.empty_like();
+        }
+        for record in records {
+            self.database_mut(record.db).restore_key(&record.key, Some(record.snapshot));
+        }
+    }
+    
+    /// 根据元数据还原条目对应的操作，再按数据类型应用到存储；镜像条目使用自己记录的数据库
+    fn apply_entry(&mut self, db: usize, entry: &LogEntry) {
+        if let Some(image) = KeyImage::from_log_entry(entry) {
+            match image {
+                KeyImage::ClearDatabase(db) => {
+                    let store = self.database_mut(db);
+                    *store = store.empty_like();
+                }
+                KeyImage::Key { db, key, snapshot } => self.database_mut(db).restore_key(&key, snapshot),
+            }
+        } else if let Some(operation) = StoreOperation::from_log_entry(entry) {
+            self.database_mut(db).apply_transaction_operation(&operation);
+        }
+    }
+}
+
+impl Drop for WriteAheadLog {
+    fn drop(&mut self) {
+        // no策略不主动同步，缓冲区由BufWriter在释放时写入文件
+        if self.sync_policy != WalSyncPolicy::No {
+            let _ = self.flush();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -783,6 +1728,376 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_checkpoint_without_snapshot_keeps_earlier_entries() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let mut wal = WriteAheadLog::new(&dir.path().join("snapshot_test.wal"))?;
+        for i in 1..=3 {
+            wal.append_entry(&LogEntry::new(LogCommand::Put, Some(format!("key{}", i)), Some(i.to_string()), i))?;
+        }
+        wal.append_entry(&LogEntry::new(LogCommand::Delete, Some("key2".to_string()), None, 4))?;
+
+        // 未提供快照时检查点保存当前日志可恢复的数据，而不是空数据
+        let checkpoint_id = wal.create_checkpoint(None)?;
+        let checkpoint = wal.get_latest_checkpoint()?.unwrap();
+        assert_eq!(checkpoint.id, checkpoint_id);
+        let mut keys: Vec<&str> = checkpoint.data.iter().map(|record| record.key.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["key1", "key3"]);
+
+        // 压缩丢弃检查点之前的条目后数据仍然完整
+        wal.compact()?;
+        let recovered = wal.recover()?;
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(recovered.get("key3"), Some(&"3".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_straddling_checkpoint_is_replayed() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let mut wal = WriteAheadLog::new(&dir.path().join("straddle_test.wal"))?;
+        let put = |key: &str, txn_id: u64| LogEntry::new(LogCommand::Put, Some(key.to_string()), Some(txn_id.to_string()), txn_id);
+
+        // 三个事务都在检查点之前开始并写入了一部分操作
+        for txn_id in [1, 2, 3] {
+            wal.begin(txn_id)?;
+            wal.append_entry(&put(&format!("before{}", txn_id), txn_id))?;
+        }
+        wal.create_checkpoint(None)?;
+        for txn_id in [1, 2, 3] {
+            wal.append_entry(&put(&format!("after{}", txn_id), txn_id))?;
+        }
+        wal.commit(1)?;
+        wal.rollback(2)?;
+
+        // 检查点之后提交的事务完整生效，包括检查点之前的操作；回滚和未提交的事务不生效
+        let mut keys: Vec<String> = wal.recover()?.into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["after1", "before1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_fields_with_delimiters_round_trip() {
+        let mut entry = LogEntry::new(
+            LogCommand::Put,
+            Some("a|b\\c".to_string()),
+            Some("line1\nline2|end".to_string()),
+            7,
+        );
+        entry.metadata = Some("string".to_string());
+
+        let decoded = LogEntry::deserialize(&entry.serialize()).unwrap();
+        assert_eq!(decoded.key.as_deref(), Some("a|b\\c"));
+        assert_eq!(decoded.value.as_deref(), Some("line1\nline2|end"));
+        assert_eq!(decoded.metadata.as_deref(), Some("string"));
+        assert_eq!(decoded.id, 7);
+    }
+
+    #[test]
+    fn test_legacy_entry_fields_are_not_unescaped() {
+        // 版本1的日志行没有版本字段，字段中的反斜杠按原样保留
+        let decoded = LogEntry::deserialize("PUT|C:\\temp\\new|a\\nb|3|100").unwrap();
+        assert_eq!(decoded.key.as_deref(), Some("C:\\temp\\new"));
+        assert_eq!(decoded.value.as_deref(), Some("a\\nb"));
+        assert_eq!(decoded.id, 3);
+        assert_eq!(decoded.timestamp, 100);
+
+        let entry = LogEntry::new(LogCommand::Put, Some("k".to_string()), Some("v".to_string()), 1);
+        assert!(entry.serialize().starts_with(&format!("v{}|PUT|", WAL_ENTRY_FORMAT_VERSION)));
+        assert!(LogEntry::deserialize(&entry.serialize().replacen("v2|", "v9|", 1)).is_none());
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip_with_delimiters() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("checkpoint_1.dat");
+
+        let mut queue = KeySnapshot::new(DataType::List(vec!["a|b".to_string(), "c".to_string()].into()));
+        queue.expire_at = Some(4_000_000_000);
+        let data = vec![
+            CheckpointRecord::string("key|with|pipes".to_string(), "value|with|pipes".to_string()),
+            CheckpointRecord::string("back\\slash".to_string(), "multi\nline".to_string()),
+            CheckpointRecord { db: 2, key: "list:queue".to_string(), snapshot: queue },
+        ];
+        let checkpoint = Checkpoint { id: 1, timestamp: 42, data };
+        checkpoint.serialize_to_file(&path)?;
+
+        let header = fs::read_to_string(&path)?;
+        assert!(header.starts_with(&format!("CHECKPOINT|v{}|1|42", CHECKPOINT_FORMAT_VERSION)));
+
+        let loaded = Checkpoint::deserialize_from_file(&path)?;
+        assert_eq!(loaded.id, 1);
+        assert_eq!(loaded.timestamp, 42);
+        assert_eq!(loaded.data, checkpoint.data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint_reads_legacy_string_pairs() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("checkpoint_2.dat");
+        fs::write(&path, "CHECKPOINT|v2|2|0\nkey\\px|value\n")?;
+
+        let loaded = Checkpoint::deserialize_from_file(&path)?;
+        assert_eq!(loaded.data, vec![CheckpointRecord::string("key|x".to_string(), "value".to_string())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint_rejects_unknown_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("checkpoint_9.dat");
+        fs::write(&path, "CHECKPOINT|v99|9|0\nkey|value\n").unwrap();
+
+        match Checkpoint::deserialize_from_file(&path) {
+            Err(WalError::CheckpointError(msg)) => assert!(msg.contains("99")),
+            other => panic!("expected CheckpointError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_retention() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("retention_test.wal");
+        
+        let mut wal = WriteAheadLog::new(&wal_path)?.with_checkpoint_retention(2);
+        
+        let mut ids = Vec::new();
+        for i in 1..=4 {
+            let entry = LogEntry::new(
+                LogCommand::Put,
+                Some(format!("key{}", i)),
+                Some(format!("value{}", i)),
+                i * 10
+            );
+            wal.append_entry(&entry)?;
+            ids.push(wal.create_checkpoint(None)?);
+        }
+        
+        // 只保留最新的两个检查点文件
+        let remaining: Vec<u64> = wal.list_checkpoint_files()?.into_iter().map(|(id, _)| id).collect();
+        assert_eq!(remaining, vec![ids[3], ids[2]]);
+        
+        // 最新检查点仍然可用，恢复结果完整
+        let checkpoint = wal.get_latest_checkpoint()?.unwrap();
+        assert_eq!(checkpoint.id, ids[3]);
+        let recovered = wal.recover()?;
+        assert_eq!(recovered.len(), 4);
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_timestamps_use_injected_clock() -> WalResult<()> {
+        use crate::clock::MockClock;
+        
+        let dir = tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(1_700_000_000_000));
+        let mut wal = WriteAheadLog::new(&dir.path().join("test.wal"))?.with_clock(clock.clone());
+        
+        wal.begin(1)?;
+        clock.advance(std::time::Duration::from_secs(5));
+        wal.commit(1)?;
+        clock.advance(std::time::Duration::from_secs(5));
+        let checkpoint_id = wal.create_checkpoint(Some(Vec::new()))?;
+        
+        let timestamps: Vec<(LogCommand, u64)> = wal
+            .load_entries()?
+            .into_iter()
+            .map(|entry| (entry.command, entry.timestamp))
+            .collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                (LogCommand::Begin, 1_700_000_000),
+                (LogCommand::Commit, 1_700_000_005),
+                (LogCommand::Checkpoint, 1_700_000_010),
+            ]
+        );
+        
+        let checkpoint_file = dir.path().join("checkpoints").join(format!("checkpoint_{}.dat", checkpoint_id));
+        assert_eq!(Checkpoint::deserialize_from_file(&checkpoint_file)?.timestamp, 1_700_000_010);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover_reports_progress() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let mut wal = WriteAheadLog::new(&dir.path().join("progress_test.wal"))?;
+        for txn_id in 1..=3 {
+            wal.begin(txn_id)?;
+            let entry = LogEntry::new(
+                LogCommand::Put,
+                Some(format!("key{}", txn_id)),
+                Some(format!("value{}", txn_id)),
+                txn_id
+            );
+            wal.append_entry(&entry)?;
+            wal.commit(txn_id)?;
+        }
+        
+        let mut reports = Vec::new();
+        let data = wal.recover_with_progress(|processed, total| reports.push((processed, total)))?;
+        assert_eq!(data.len(), 3);
+        
+        // 每条日志报告一次，已处理条数递增到总条数
+        let expected: Vec<(usize, usize)> = (1..=9).map(|processed| (processed, 9)).collect();
+        assert_eq!(reports, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_batched_appends_use_fewer_writes() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let put = |id: u64| LogEntry::new(LogCommand::Put, Some(format!("key{}", id)), Some("value".to_string()), id);
+        
+        // 每条日志单独写入并同步
+        let mut always = WriteAheadLog::new(&dir.path().join("always.wal"))?;
+        for id in 1..=100 {
+            always.append_entry(&put(id))?;
+        }
+        assert_eq!(always.write_calls(), 100);
+        
+        // 每32条日志写入一次文件
+        let batched_path = dir.path().join("batched.wal");
+        let mut batched = WriteAheadLog::new(&batched_path)?.with_sync_policy(WalSyncPolicy::No, 32);
+        for id in 1..=100 {
+            batched.append_entry(&put(id))?;
+        }
+        assert_eq!(batched.write_calls(), 3);
+        
+        // 读取日志前会写入剩余的缓冲条目
+        assert_eq!(batched.load_entries()?.len(), 100);
+        assert_eq!(batched.write_calls(), 4);
+        
+        // 显式flush后其他实例也能读到全部条目
+        batched.append_entry(&put(101))?;
+        batched.flush()?;
+        assert_eq!(WriteAheadLog::new(&batched_path)?.load_entries()?.len(), 101);
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_sec_syncs_idle_buffer_when_due() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("every_sec.wal");
+        let mut wal = WriteAheadLog::new(&wal_path)?.with_sync_policy(WalSyncPolicy::EverySec, 32);
+        wal.append_entry(&LogEntry::new(LogCommand::Put, Some("key".to_string()), Some("value".to_string()), 1))?;
+        
+        // 距上次同步不到1秒，条目留在缓冲区
+        assert!(!wal.sync_if_due()?);
+        assert_eq!(wal.write_calls(), 0);
+        
+        // 之后没有新的写入，定时检查把缓冲的条目同步到磁盘
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(wal.sync_if_due()?);
+        assert_eq!(wal.write_calls(), 1);
+        assert!(!wal.sync_if_due()?);
+        assert_eq!(fs::read_to_string(&wal_path)?.lines().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover_to_sequence_number() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("pitr_test.wal");
+        
+        let mut wal = WriteAheadLog::new(&wal_path)?;
+        for txn_id in 1..=4 {
+            wal.begin(txn_id)?;
+            let entry = LogEntry::new(
+                LogCommand::Put,
+                Some("counter".to_string()),
+                Some(txn_id.to_string()),
+                txn_id
+            );
+            wal.append_entry(&entry)?;
+            let entry = LogEntry::new(
+                LogCommand::Put,
+                Some(format!("key{}", txn_id)),
+                Some(format!("value{}", txn_id)),
+                txn_id
+            );
+            wal.append_entry(&entry)?;
+            wal.commit(txn_id)?;
+        }
+        
+        // 恢复到第2个事务之后，后续写入都不可见
+        let data = wal.recover_to(2)?;
+        assert_eq!(data.get("counter"), Some(&"2".to_string()));
+        assert!(data.contains_key("key1"));
+        assert!(data.contains_key("key2"));
+        assert!(!data.contains_key("key3"));
+        assert!(!data.contains_key("key4"));
+        
+        // 检查点之后恢复到检查点之前的序列号，不使用更晚的检查点
+        wal.create_checkpoint(None)?;
+        let data = wal.recover_to(1)?;
+        assert_eq!(data.get("counter"), Some(&"1".to_string()));
+        assert_eq!(data.len(), 2);
+        
+        assert_eq!(wal.recover()?.get("counter"), Some(&"4".to_string()));
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_reports_anomalies() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("validate_test.wal");
+        
+        let line = |command: LogCommand, id: u64| LogEntry::new(command, None, None, id).serialize();
+        let mut content = String::new();
+        content.push_str(&line(LogCommand::Begin, 1));
+        content.push_str(&LogEntry::new(LogCommand::Put, Some("k".to_string()), Some("v".to_string()), 1).serialize());
+        content.push_str(&line(LogCommand::Commit, 1));
+        content.push_str(&line(LogCommand::Commit, 2)); // 孤立的提交
+        content.push_str(&line(LogCommand::Begin, 3));
+        content.push_str(&line(LogCommand::Begin, 3)); // 重复的BEGIN
+        content.push_str(&line(LogCommand::Rollback, 3));
+        content.push_str(&line(LogCommand::Put, 4).replace("PUT", "DELETE")); // 校验和不匹配
+        content.push_str("GARBAGE\n");
+        let unchecked = line(LogCommand::Put, 5);
+        let without_crc = &unchecked[..unchecked.trim_end().rfind('|').unwrap()];
+        content.push_str(&format!("{}\n", without_crc)); // 新格式缺少校验和
+        content.push_str("PUT|legacy|value|6|100\n"); // 旧格式没有校验和
+        content.push_str("PUT|partial"); // 被截断的尾部
+        fs::write(&wal_path, content)?;
+        
+        let wal = WriteAheadLog::new(&wal_path)?;
+        let report = wal.validate()?;
+        assert_eq!(report.entries, 8);
+        assert_eq!(report.anomalies, vec![
+            WalAnomaly::OrphanCommit { line: 4, txn_id: 2 },
+            WalAnomaly::DuplicateBegin { line: 6, txn_id: 3 },
+            WalAnomaly::ChecksumMismatch { line: 8 },
+            WalAnomaly::Malformed { line: 9, reason: "字段数1少于4".to_string() },
+            WalAnomaly::Malformed { line: 10, reason: "缺少校验和，字段数7".to_string() },
+            WalAnomaly::TruncatedTail { line: 12 },
+        ]);
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_clean_log() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("clean_test.wal");
+        
+        let mut wal = WriteAheadLog::new(&wal_path)?;
+        wal.begin(1)?;
+        wal.append_entry(&LogEntry::new(LogCommand::Put, Some("k".to_string()), Some("v|1".to_string()), 1))?;
+        wal.commit(1)?;
+        
+        let report = wal.validate()?;
+        assert!(report.is_ok(), "unexpected anomalies: {:?}", report.anomalies);
+        assert_eq!(report.entries, 3);
+        
+        Ok(())
+    }
+
     #[test]
     fn test_compaction() -> WalResult<()> {
         let dir = tempdir().unwrap();
@@ -817,4 +2132,152 @@ mod tests {
         
         Ok(())
     }
+
+    #[test]
+    fn test_latest_checkpoint_comes_from_index() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("index_test.wal");
+        let mut wal = WriteAheadLog::new(&wal_path)?.with_checkpoint_retention(2);
+        
+        let mut ids = Vec::new();
+        for i in 1..=4 {
+            let id = wal.last_sequence_number + 1;
+            wal.append_entry(&LogEntry::new(LogCommand::Put, Some(format!("key{}", i)), Some(i.to_string()), id))?;
+            ids.push(wal.create_checkpoint(None)?);
+        }
+        
+        // 被清理的检查点同时从索引中移除，索引文件与内存中的索引一致
+        let indexed: Vec<u64> = wal.checkpoints().iter().map(|info| info.id).collect();
+        assert_eq!(indexed, ids[2..].to_vec());
+        assert_eq!(load_checkpoint_index(&dir.path().join("checkpoints"))?, wal.checkpoints());
+        assert!(wal.get_checkpoint(ids[0])?.is_none());
+        assert_eq!(wal.get_checkpoint(ids[2])?.unwrap().data.len(), 3);
+        
+        // 清空WAL后扫描日志找不到检查点，仍能通过索引找到最新的检查点
+        fs::write(&wal_path, "")?;
+        let latest = wal.get_latest_checkpoint()?.unwrap();
+        assert_eq!(latest.id, ids[3]);
+        assert_eq!(latest.data.len(), 4);
+        
+        // 重新打开时从索引文件加载
+        drop(wal);
+        let reopened = WriteAheadLog::new(&wal_path)?;
+        assert_eq!(reopened.get_latest_checkpoint()?.unwrap().id, ids[3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint_index_merges_instances_sharing_a_directory() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let mut first = WriteAheadLog::new(&dir.path().join("first.wal"))?.with_checkpoint_retention(0);
+        let mut second = WriteAheadLog::new(&dir.path().join("second.wal"))?.with_checkpoint_retention(0);
+
+        // 两个实例共用检查点目录，各自写入索引时不会丢失对方的检查点
+        let first_id = first.create_checkpoint(Some(Vec::new()))?;
+        let second_id = second.create_checkpoint(Some(Vec::new()))?;
+        let third_id = first.create_checkpoint(Some(Vec::new()))?;
+
+        let indexed: Vec<u64> = load_checkpoint_index(&dir.path().join("checkpoints"))?
+            .iter()
+            .map(|info| info.id)
+            .collect();
+        assert_eq!(indexed, vec![first_id, second_id, third_id]);
+        assert_eq!(first.checkpoints().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint_fires_every_interval_entries() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let mut wal = WriteAheadLog::new(&dir.path().join("interval.wal"))?.with_checkpoint_interval(3);
+
+        let mut checkpoints_after = Vec::new();
+        for i in 1..=9 {
+            let id = wal.last_sequence_number + 1;
+            wal.append_entry(&LogEntry::new(LogCommand::Put, Some(format!("key{}", i)), Some(i.to_string()), id))?;
+            let checkpoints = wal
+                .load_entries()?
+                .iter()
+                .filter(|entry| matches!(entry.command, LogCommand::Checkpoint))
+                .count();
+            checkpoints_after.push(checkpoints);
+        }
+
+        // 每3条数据日志恰好创建一个检查点，检查点记录本身不计数
+        assert_eq!(checkpoints_after, vec![0, 0, 1, 1, 1, 2, 2, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compaction_concurrent_with_appends_loses_nothing() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("concurrent.wal");
+        // 间隔很小，追加和压缩过程中都会频繁创建检查点
+        let wal = Arc::new(std::sync::Mutex::new(WriteAheadLog::new(&wal_path)?.with_checkpoint_interval(2)));
+
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let wal = Arc::clone(&wal);
+                std::thread::spawn(move || {
+                    for i in 0..50 {
+                        let mut wal = wal.lock().unwrap();
+                        let id = wal.last_sequence_number + 1;
+                        let entry = LogEntry::new(
+                            LogCommand::Put,
+                            Some(format!("w{}:{}", writer, i)),
+                            Some(i.to_string()),
+                            id,
+                        );
+                        wal.append_entry(&entry).unwrap();
+                    }
+                })
+            })
+            .collect();
+        let compactor = {
+            let wal = Arc::clone(&wal);
+            std::thread::spawn(move || {
+                for _ in 0..20 {
+                    wal.lock().unwrap().compact().unwrap();
+                    std::thread::yield_now();
+                }
+            })
+        };
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        compactor.join().unwrap();
+
+        // 重新打开日志文件恢复，所有写入都没有丢失
+        let mut wal = Arc::try_unwrap(wal).unwrap().into_inner().unwrap();
+        wal.flush()?;
+        drop(wal);
+        let data = WriteAheadLog::new(&wal_path)?.recover()?;
+        assert_eq!(data.len(), 200);
+        for writer in 0..4 {
+            for i in 0..50 {
+                assert_eq!(data.get(&format!("w{}:{}", writer, i)), Some(&i.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compaction_by_another_instance_keeps_later_appends() -> WalResult<()> {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("shared.wal");
+        let mut first = WriteAheadLog::new(&wal_path)?;
+        let mut second = WriteAheadLog::new(&wal_path)?;
+
+        first.append_entry(&LogEntry::new(LogCommand::Put, Some("k1".to_string()), Some("v1".to_string()), 1))?;
+        // 另一个实例压缩后替换了日志文件，之前打开的实例继续追加也写入新文件
+        second.compact()?;
+        first.append_entry(&LogEntry::new(LogCommand::Put, Some("k2".to_string()), Some("v2".to_string()), 2))?;
+        drop(first);
+        drop(second);
+
+        let data = WriteAheadLog::new(&wal_path)?.recover()?;
+        assert_eq!(data.get("k1"), Some(&"v1".to_string()));
+        assert_eq!(data.get("k2"), Some(&"v2".to_string()));
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use crate::config::WalSyncPolicy;
 use crate::store::{TransactionManager, StoreOperation};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
@@ -16,8 +17,8 @@ impl TransactionCommandHandler {
         let txn_manager = match TransactionManager::new(wal_path) {
             Ok(manager) => Arc::new(manager),
             Err(e) => {
-                // 创建失败，打印错误后使用默认设置
-                eprintln!("创建事务管理器失败: {}", e);
+                // 创建失败，记录错误后使用默认设置
+                log::error!("创建事务管理器失败: {}", e);
                 Arc::new(TransactionManager::new(wal_path).unwrap())
             }
         };
@@ -28,6 +29,26 @@ impl TransactionCommandHandler {
         }
     }
     
+    /// 设置保留的检查点文件数
+    pub fn with_checkpoint_retention(self, retention: usize) -> Self {
+        self.txn_manager.set_checkpoint_retention(retention);
+        self
+    }
+    
+    /// 设置WAL同步策略
+    pub fn with_sync_policy(self, policy: WalSyncPolicy, batch_entries: usize) -> Self {
+        self.txn_manager.set_sync_policy(policy, batch_entries);
+        self
+    }
+    
+    /// 设置检查点目录，失败时保留默认目录
+    pub fn with_checkpoint_dir(self, dir: &Path) -> Self {
+        if let Err(e) = self.txn_manager.set_checkpoint_dir(dir.to_path_buf()) {
+            log::warn!("设置检查点目录 {} 失败: {}", dir.display(), e);
+        }
+        self
+    }
+    
     /// 从现有的事务管理器创建命令处理器
     pub fn from_manager(txn_manager: Arc<TransactionManager>) -> Self {
         TransactionCommandHandler {
@@ -55,12 +76,22 @@ impl TransactionCommandHandler {
     
     /// 提交事务
     pub fn commit(&self) -> Result<String, String> {
+        self.commit_with(|txn_manager, txn_id| {
+            txn_manager.commit_transaction(txn_id).map(|_| ()).map_err(|e| e.to_string())
+        })
+    }
+    
+    /// 由commit执行提交当前事务，例如把事务的操作应用到连接选择的数据库
+    pub fn commit_with<F>(&self, commit: F) -> Result<String, String>
+    where
+        F: FnOnce(&TransactionManager, u64) -> Result<(), String>,
+    {
         let mut current_txn = self.current_transaction_id.lock().unwrap();
         
         match *current_txn {
             Some(txn_id) => {
-                match self.txn_manager.commit_transaction(txn_id) {
-                    Ok(_) => {
+                match commit(&self.txn_manager, txn_id) {
+                    Ok(()) => {
                         *current_txn = None;
                         Ok(format!("事务{}已提交", txn_id))
                     },
@@ -89,11 +120,25 @@ impl TransactionCommandHandler {
         }
     }
     
-    /// 创建检查点
+    /// 在当前事务中创建保存点
+    pub fn savepoint(&self, name: &str) -> Result<String, String> {
+        let txn_id = self.current_transaction_id().ok_or("不在事务中，无法创建保存点")?;
+        self.txn_manager.savepoint(txn_id, name)
+            .map(|_| format!("保存点{}已创建", name))
+            .map_err(|e| format!("创建保存点失败: {}", e))
+    }
+    
+    /// 将当前事务回滚到保存点
+    pub fn rollback_to(&self, name: &str) -> Result<String, String> {
+        let txn_id = self.current_transaction_id().ok_or("不在事务中，无法回滚到保存点")?;
+        self.txn_manager.rollback_to_savepoint(txn_id, name)
+            .map(|_| format!("事务{}已回滚到保存点{}", txn_id, name))
+            .map_err(|e| format!("回滚到保存点失败: {}", e))
+    }
+    
+    /// 创建检查点，检查点数据来自重放当前日志
     pub fn checkpoint(&self) -> Result<String, String> {
-        use std::collections::HashMap;
-        let data = HashMap::new(); // 使用空的HashMap作为默认数据
-        match self.txn_manager.create_checkpoint(data) {
+        match self.txn_manager.create_checkpoint(None) {
             Ok(id) => Ok(format!("检查点{}已创建", id)),
             Err(e) => Err(format!("创建检查点失败: {}", e)),
         }
@@ -107,6 +152,15 @@ impl TransactionCommandHandler {
         }
     }
     
+    /// WAL日志超过threshold字节时压缩
+    pub fn compact_if_needed(&self, threshold: u64) -> Result<String, String> {
+        match self.txn_manager.compact_wal_if_needed(threshold) {
+            Ok(true) => Ok("WAL日志已压缩".to_string()),
+            Ok(false) => Ok(format!("WAL日志未超过{}字节，无需压缩", threshold)),
+            Err(e) => Err(format!("压缩WAL日志失败: {}", e)),
+        }
+    }
+    
     /// 列出活跃事务
     pub fn list_transactions(&self) -> Result<String, String> {
         let txns = self.txn_manager.list_active_transactions();
@@ -140,6 +194,74 @@ impl TransactionCommandHandler {
         Ok(result)
     }
     
+    /// 活跃事务数
+    pub fn active_transaction_count(&self) -> usize {
+        self.txn_manager.list_active_transactions().len()
+    }
+
+    /// 查看事务的状态、开始时间、操作数和已运行时间(秒)
+    pub fn txinfo(&self, txn_id: u64) -> Result<String, String> {
+        let txn = self.txn_manager.get_transaction(txn_id)
+            .map_err(|e| format!("查询事务失败: {}", e))?;
+
+        Ok(format!(
+            "id: {}\nstate: {:?}\nstart_time: {}\noperations: {}\nelapsed_seconds: {}",
+            txn.id,
+            txn.state,
+            txn.start_time,
+            txn.operations.len(),
+            self.txn_manager.elapsed_secs(txn.start_time)
+        ))
+    }
+
+    /// 按文件顺序输出WAL日志，只包含序列号不小于from_seq的条目
+    pub fn wal_dump(&self, from_seq: Option<u64>) -> Result<String, String> {
+        let entries = self.txn_manager.get_wal_manager().load_entries()
+            .map_err(|e| format!("读取WAL日志失败: {}", e))?;
+
+        let lines: Vec<String> = entries
+            .iter()
+            .filter(|entry| from_seq.is_none_or(|seq| entry.id >= seq))
+            .map(|entry| {
+                let mut line = format!("seq={} ts={} {}", entry.id, entry.timestamp, entry.command.as_str());
+                if let Some(key) = &entry.key {
+                    line.push_str(&format!(" key={}", key));
+                }
+                if let Some(value) = &entry.value {
+                    line.push_str(&format!(" value={}", value));
+                }
+                if let Some(metadata) = &entry.metadata {
+                    line.push_str(&format!(" ({})", metadata));
+                }
+                line
+            })
+            .collect();
+
+        if lines.is_empty() {
+            Ok("(empty list)".to_string())
+        } else {
+            Ok(lines.join("\n"))
+        }
+    }
+
+    /// WAL文件大小、最后序列号和未完成的事务
+    pub fn wal_info(&self) -> Result<String, String> {
+        let wal = self.txn_manager.get_wal_manager();
+        let file_size = wal.get_file_size()
+            .map_err(|e| format!("读取WAL文件大小失败: {}", e))?;
+        let pending = wal.list_pending_transactions();
+        let pending = if pending.is_empty() {
+            "(none)".to_string()
+        } else {
+            pending.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(",")
+        };
+
+        Ok(format!(
+            "file_size: {}\nlast_sequence_number: {}\npending_transactions: {}",
+            file_size, wal.last_sequence_number, pending
+        ))
+    }
+
     /// 执行存储操作
     pub fn execute_operation(&self, operation: StoreOperation) -> Result<(), String> {
         let current_txn = self.current_transaction_id.lock().unwrap();
@@ -170,6 +292,21 @@ impl TransactionCommandHandler {
         }
     }
     
+    /// 把写操作加入当前事务，操作和日志在提交时才生效
+    pub fn queue_operation(&self, operation: StoreOperation) -> Result<(), String> {
+        let txn_id = self.current_transaction_id().ok_or("不在事务中")?;
+        self.txn_manager
+            .execute_operation_with_old_value(txn_id, operation, None, None)
+            .map_err(|e| format!("执行操作失败: {}", e))
+    }
+    
+    /// 读取当前事务对键的修改，外层None表示不在事务中或事务没有修改该键
+    pub fn read_local(&self, key: &str) -> Option<Option<String>> {
+        let txn_id = self.current_transaction_id()?;
+        let txn = self.txn_manager.get_transaction(txn_id).ok()?;
+        txn.read_local(key).map(|value| value.map(str::to_string))
+    }
+    
     /// 获取事务管理器
     pub fn get_transaction_manager(&self) -> Arc<TransactionManager> {
         self.txn_manager.clone()
@@ -189,10 +326,10 @@ impl TransactionCommandHandler {
     
     /// 恢复系统并返回数据
     pub fn recover_system(&self) -> Result<String, String> {
-        match self.txn_manager.recover() {
-            Ok(data) => {
-                let count = data.len();
-                Ok(format!("恢复了{}个键值对", count))
+        match self.txn_manager.recover_store() {
+            Ok(store) => {
+                let count = store.get_all_keys().len();
+                Ok(format!("恢复了{}个键", count))
             },
             Err(e) => Err(format!("恢复系统失败: {}", e)),
         }
@@ -1,5 +1,5 @@
-use kv_common::command::{Command, CommandHandler};
-use kv_common::store::StoreManager;
+use kv_common::command::{Command, CommandHandler, CommandResult};
+use kv_common::store::{DataType, StoreManager};
 
 #[test]
 fn test_command_parsing() {
@@ -18,7 +18,7 @@ fn test_command_parsing() {
     assert!(matches!(cmd, Command::Get(k) if k == "key1"));
     
     let cmd = handler.parse_command("del key1");
-    assert!(matches!(cmd, Command::Del(k) if k == "key1"));
+    assert!(matches!(cmd, Command::Del(keys) if keys == ["key1"]));
     
     // 测试列表命令解析
     let cmd = handler.parse_command("lpush list1 item1");
@@ -67,7 +67,7 @@ fn test_command_parsing() {
     
     // 测试无效命令
     let cmd = handler.parse_command("invalid command");
-    assert!(matches!(cmd, Command::Invalid(_)));
+    assert!(matches!(cmd, Command::Unknown(name, args) if name == "invalid" && args == ["command"]));
     
     // 测试空命令
     let cmd = handler.parse_command("");
@@ -93,7 +93,7 @@ fn test_command_execution() {
     let result = handler.execute_command(Command::Get("nonexistent".to_string()));
     assert_eq!(result, "(nil)");
     
-    let result = handler.execute_command(Command::Del("key1".to_string()));
+    let result = handler.execute_command(Command::Del(vec!["key1".to_string()]));
     assert_eq!(result, "1");
     
     // 测试列表命令处理
@@ -144,6 +144,18 @@ fn test_command_execution() {
     assert!(result.contains("ERROR"));
 }
 
+#[test]
+fn test_set_replies_ok_instead_of_value() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+
+    // SET返回OK而不是写入的值，覆盖已有的键和看起来像OK的值也一样
+    assert_eq!(handler.execute_typed(handler.parse_command("set greeting hello")), CommandResult::Ok);
+    assert_eq!(handler.execute_command(handler.parse_command("set greeting world")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("set status ERROR")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("get greeting")), "world");
+    assert_eq!(handler.execute_command(handler.parse_command("get status")), "ERROR");
+}
+
 #[test]
 fn test_command_help_functions() {
     // 创建临时数据文件
@@ -213,8 +225,1103 @@ fn test_command_parsing_edge_cases() {
     let cmd = handler.parse_command("get key1 extra");
     assert!(matches!(cmd, Command::Invalid(_)));
     
-    // 测试带引号的参数 - 注意：当前解析器实现不支持引号处理
-    // 所以实际输出会包含引号，修改断言以匹配实际行为
+    // 测试带引号的参数
     let cmd = handler.parse_command("set key1 \"value with spaces\"");
-    assert!(matches!(cmd, Command::Set(k, v) if k == "key1" && v == "\"value with spaces\""));
-}
\ No newline at end of file
+    assert!(matches!(cmd, Command::Set(k, v) if k == "key1" && v == "value with spaces"));
+}
+
+#[test]
+fn test_quoted_arguments() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager, "data/test_parsing_storage.dat".to_string());
+
+    // 引号内的连续空格和首尾空格原样保留
+    let cmd = handler.parse_command("set key \"a  b\"");
+    assert!(matches!(cmd, Command::Set(k, v) if k == "key" && v == "a  b"));
+
+    let cmd = handler.parse_command("set key ' padded '");
+    assert!(matches!(cmd, Command::Set(k, v) if k == "key" && v == " padded "));
+
+    let cmd = handler.parse_command("hset \"my hash\" field ''");
+    assert!(matches!(cmd, Command::HSet(k, f, v) if k == "my hash" && f == "field" && v.is_empty()));
+
+    // 转义的引号和反斜杠
+    let cmd = handler.parse_command(r#"set key "say \"hi\" \\ bye""#);
+    assert!(matches!(cmd, Command::Set(_, v) if v == r#"say "hi" \ bye"#));
+
+    let cmd = handler.parse_command(r"set key 'it\'s'");
+    assert!(matches!(cmd, Command::Set(_, v) if v == "it's"));
+
+    // EX选项
+    let cmd = handler.parse_command("set key \"a  b\" EX 10");
+    assert!(matches!(cmd, Command::SetEx(k, v, 10) if k == "key" && v == "a  b"));
+
+    // 带引号的EX是值的一部分而不是选项
+    let cmd = handler.parse_command("set key value \"EX\" 10");
+    assert!(matches!(cmd, Command::Set(_, v) if v == "value EX 10"));
+
+    // 未闭合的引号
+    let cmd = handler.parse_command("set key \"unterminated");
+    assert!(matches!(cmd, Command::Invalid(e) if e.contains("Unterminated quote")));
+
+    let cmd = handler.parse_command(r#"set key "ends with escape\"#);
+    assert!(matches!(cmd, Command::Invalid(e) if e.contains("Unterminated quote")));
+
+    // 执行后值完全保留
+    let cmd = handler.parse_command("set spaced \"  a  b  \"");
+    assert_eq!(handler.execute_command(cmd), "OK");
+    assert_eq!(handler.execute_command(Command::Get("spaced".to_string())), "  a  b  ");
+
+    let cmd = handler.parse_command("set temp value EX 100");
+    assert_eq!(handler.execute_command(cmd), "OK");
+    assert_eq!(handler.execute_command(Command::Get("temp".to_string())), "value");
+    assert!(handler.execute_command(Command::DDL("temp".to_string())).starts_with("TTL: "));
+}
+#[test]
+fn test_error_codes() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager, "data/test_error_storage.dat".to_string());
+
+    handler.execute_command(Command::Set("str_key".to_string(), "value".to_string()));
+
+    // 对字符串键执行列表操作返回 WRONGTYPE
+    let result = handler.execute_command(Command::Len("str_key".to_string()));
+    assert!(result.starts_with("ERROR: WRONGTYPE "), "unexpected: {}", result);
+
+    let result = handler.execute_command(Command::LPop("str_key".to_string()));
+    assert!(result.starts_with("ERROR: WRONGTYPE "), "unexpected: {}", result);
+
+    let result = handler.execute_command(Command::HGet("str_key".to_string(), "field".to_string()));
+    assert!(result.starts_with("ERROR: WRONGTYPE "), "unexpected: {}", result);
+
+    // 不是来自存储的错误同样以错误码开头
+    let result = handler.execute_command(handler.parse_command("slowlog get"));
+    assert_eq!(result, "ERROR: DISABLED slowlog is disabled");
+    let result = handler.execute_command(handler.parse_command("stats commands"));
+    assert_eq!(result, "ERROR: DISABLED command metrics are disabled");
+    let result = handler.execute_command(handler.parse_command("bgsave status 999"));
+    assert_eq!(result, "ERROR: ERR unknown background save job 999");
+}
+
+#[test]
+fn test_unknown_command_policy() {
+    // 默认返回与Redis格式一致的错误，列出命令的参数
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_error_storage.dat".to_string());
+    assert_eq!(
+        handler.execute_command(handler.parse_command("frobnicate key 10")),
+        "ERROR: ERR unknown command 'frobnicate', with args beginning with: 'key' '10'"
+    );
+    assert_eq!(
+        handler.execute_command(handler.parse_command("frobnicate")),
+        "ERROR: ERR unknown command 'frobnicate', with args beginning with:"
+    );
+
+    // lenient模式下确认收到但不执行，已知命令的参数错误仍然返回错误
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(&config_path, "[server]\nunknown_commands = \"lenient\"\n").unwrap();
+    let settings = std::sync::Arc::new(kv_common::config::Settings::from_path(&config_path).unwrap());
+    let store_manager = StoreManager::new().with_settings(settings.clone());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_error_storage.dat".to_string())
+        .with_settings(settings);
+    assert_eq!(handler.execute_command(handler.parse_command("frobnicate key 10")), "OK");
+    assert!(!store_manager.exists("key"));
+    assert!(handler.execute_command(handler.parse_command("get")).starts_with("ERROR"));
+}
+
+#[test]
+fn test_get_on_non_string_returns_wrongtype() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager, "data/test_error_storage.dat".to_string());
+
+    handler.execute_command(Command::RPush("list_key".to_string(), "a".to_string()));
+    handler.execute_command(Command::SAdd("set_key".to_string(), vec!["x".to_string()]));
+
+    let result = handler.execute_command(Command::Get("list_key".to_string()));
+    assert!(result.starts_with("ERROR: WRONGTYPE "), "unexpected: {}", result);
+    assert!(result.contains("list"), "unexpected: {}", result);
+    let result = handler.execute_command(Command::Get("set_key".to_string()));
+    assert!(result.starts_with("ERROR: WRONGTYPE "), "unexpected: {}", result);
+
+    // 不存在的键仍返回(nil)
+    assert_eq!(handler.execute_command(Command::Get("missing".to_string())), "(nil)");
+}
+
+#[test]
+fn test_help_covers_all_command_groups() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager, "data/test_help_storage.dat".to_string());
+
+    for command in ["set", "sadd", "expire", "smembers", "ddl", "bgsave", "multi", "commit", "slowlog"] {
+        let result = handler.execute_command(Command::HelpCommand(command.to_string()));
+        assert!(!result.contains("Unknown command"), "help {} 缺少说明", command);
+    }
+
+    let result = handler.execute_command(Command::Help);
+    for group in [
+        "字符串类型命令",
+        "双向链表类型命令",
+        "哈希类型命令",
+        "集合类型命令",
+        "过期命令",
+        "持久化命令",
+        "事务命令",
+        "监控命令",
+        "其他命令",
+    ] {
+        assert!(result.contains(group), "help 缺少分组 {}", group);
+    }
+}
+
+#[test]
+fn test_srem_multiple_members_deletes_emptied_set() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_set_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("sadd queue a b c");
+    // 只统计实际移除的成员，不存在的成员和重复参数不计入
+    assert_eq!(run("srem queue a missing a"), "1");
+    assert!(store_manager.exists("queue"));
+
+    // 移除最后的成员后键被删除
+    assert_eq!(run("srem queue b c"), "2");
+    assert!(!store_manager.exists("queue"));
+    assert!(!store_manager.get_memory_keys().contains(&"queue".to_string()));
+    assert_eq!(run("srem queue a"), "0");
+
+    // SPOP弹出最后的成员同样删除键
+    use kv_common::store::{SetOperations, StoreOperations};
+    let mut store = kv_common::Store::new();
+    store.sadd("single".to_string(), vec!["x".to_string()]).unwrap();
+    store.spop("single", None).unwrap();
+    assert!(!store.exists("single"));
+
+    assert!(matches!(handler.parse_command("srem queue"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_spop_count_edge_cases() {
+    use kv_common::store::{SetOperations, StoreOperations};
+    let mut store = kv_common::Store::new();
+    let members: Vec<String> = ["a", "b", "c", "d"].iter().map(|m| m.to_string()).collect();
+    store.sadd("set".to_string(), members.clone()).unwrap();
+
+    // 数量为0时不弹出任何成员，未指定数量时弹出一个
+    assert!(store.spop("set", Some(0)).unwrap().is_empty());
+    assert_eq!(store.smembers("set").unwrap().len(), 4);
+    let single = store.spop("set", None).unwrap();
+    assert_eq!(single.len(), 1);
+    assert!(!store.sismember("set", &single[0]).unwrap());
+
+    // 数量大于集合大小时弹出全部成员并删除键
+    let rest = store.spop("set", Some(10)).unwrap();
+    assert_eq!(rest.len(), 3);
+    assert!(!store.exists("set"));
+
+    // 数量等于集合大小时同样清空并删除键
+    store.sadd("set".to_string(), members.clone()).unwrap();
+    let mut popped = store.spop("set", Some(4)).unwrap();
+    popped.sort();
+    assert_eq!(popped, members);
+    assert!(!store.exists("set"));
+    assert!(store.spop("set", None).unwrap().is_empty());
+}
+
+#[test]
+fn test_popping_last_element_deletes_list_and_hash() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_empty_containers.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    // 链表：LPOP、RPOP和LMPOP弹出最后的元素后键被删除
+    run("rpush empty:list a");
+    run("rpush empty:list b");
+    assert_eq!(run("lpop empty:list"), "a");
+    assert!(store_manager.exists("empty:list"));
+    assert_eq!(run("rpop empty:list"), "b");
+    assert!(!store_manager.exists("empty:list"));
+    assert!(!store_manager.get_memory_keys().contains(&"empty:list".to_string()));
+    run("rpush empty:list a");
+    run("lmpop 1 empty:list LEFT");
+    assert!(!store_manager.exists("empty:list"));
+
+    // 哈希：删除最后一个字段后键被删除
+    run("hset empty:hash f1 v1");
+    run("hset empty:hash f2 v2");
+    run("hdel empty:hash f1");
+    assert!(store_manager.exists("empty:hash"));
+    run("hdel empty:hash f2");
+    assert!(!store_manager.exists("empty:hash"));
+    assert!(!store_manager.get_memory_keys().contains(&"empty:hash".to_string()));
+
+    // 直接调用Store的方法同样删除空容器
+    use kv_common::store::{HashOperations, ListOperations, StoreOperations};
+    let mut store = kv_common::Store::new();
+    store.rpush("list".to_string(), "x".to_string()).unwrap();
+    store.rpop("list").unwrap();
+    assert!(!store.exists("list"));
+    store.hset("hash".to_string(), "field".to_string(), "value".to_string()).unwrap();
+    store.hdel("hash", "field").unwrap();
+    assert!(!store.exists("hash"));
+    assert_eq!(store.memory_usage(), 0);
+}
+
+#[test]
+fn test_sadd_ch_returns_new_members() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager, "data/test_set_storage.dat".to_string());
+
+    let cmd = handler.parse_command("sadd tags CH a b");
+    assert!(matches!(&cmd, Command::SAddCh(k, v) if k == "tags" && v == &["a", "b"]));
+    assert_eq!(handler.execute_command(cmd), "a\nb");
+
+    // 重叠插入只返回新成员，重复参数只计一次
+    let cmd = handler.parse_command("sadd tags ch b c d c");
+    assert_eq!(handler.execute_command(cmd), "c\nd");
+
+    let cmd = handler.parse_command("sadd tags ch a b");
+    assert_eq!(handler.execute_command(cmd), "(empty list)");
+
+    // 不带CH时仍返回新增数量
+    let cmd = handler.parse_command("sadd tags d e e");
+    assert_eq!(handler.execute_command(cmd), "1");
+
+    // 带引号的CH是普通成员
+    let cmd = handler.parse_command("sadd tags \"CH\" f");
+    assert!(matches!(&cmd, Command::SAdd(_, v) if v == &["CH", "f"]));
+    assert_eq!(handler.execute_command(cmd), "2");
+
+    // 已有其他类型的值时报错，不覆盖原来的值
+    handler.execute_command(handler.parse_command("set name alice"));
+    for command in ["sadd name a", "sadd name ch a"] {
+        let reply = handler.execute_command(handler.parse_command(command));
+        assert!(reply.starts_with("ERROR: WRONGTYPE"), "{}", reply);
+    }
+    assert_eq!(handler.execute_command(handler.parse_command("get name")), "alice");
+}
+
+#[test]
+fn test_wal_dump_and_info() {
+    use kv_common::store::{LogCommand, LogEntry, WriteAheadLog};
+
+    let dir = tempfile::tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat");
+    {
+        let mut wal = WriteAheadLog::new(&dir.path().join("wal.log")).unwrap();
+        wal.begin(1).unwrap();
+        wal.append_entry(&LogEntry::new(LogCommand::Put, Some("k1".to_string()), Some("v1".to_string()), 1)).unwrap();
+        wal.commit(1).unwrap();
+        wal.append_entry(&LogEntry::new(LogCommand::Put, Some("k2".to_string()), Some("v2".to_string()), 2)).unwrap();
+        wal.append_entry(&LogEntry::new(LogCommand::Delete, Some("k1".to_string()), None, 3)).unwrap();
+        wal.begin(4).unwrap();
+    }
+
+    let handler = CommandHandler::new(StoreManager::new(), data_file.to_string_lossy().to_string());
+
+    let dump = handler.execute_command(handler.parse_command("wal dump"));
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 6, "unexpected dump: {}", dump);
+    assert!(lines[0].starts_with("seq=1 ") && lines[0].ends_with("BEGIN"));
+    assert!(lines[1].contains("PUT key=k1 value=v1"));
+    assert!(lines[2].starts_with("seq=1 ") && lines[2].ends_with("COMMIT"));
+    assert!(lines[3].contains("PUT key=k2 value=v2"));
+    assert!(lines[4].starts_with("seq=3 ") && lines[4].ends_with("DELETE key=k1"));
+    assert!(lines[5].starts_with("seq=4 ") && lines[5].ends_with("BEGIN"));
+
+    // 按起始序列号过滤
+    let dump = handler.execute_command(handler.parse_command("wal dump 3"));
+    assert_eq!(dump.lines().count(), 2);
+    assert!(dump.lines().next().unwrap().contains("DELETE key=k1"));
+    assert_eq!(handler.execute_command(Command::WalDump(Some(100))), "(empty list)");
+
+    let info = handler.execute_command(Command::WalInfo);
+    assert!(info.contains("last_sequence_number: 4"), "unexpected info: {}", info);
+    assert!(info.contains("pending_transactions: 4"), "unexpected info: {}", info);
+    assert!(!info.contains("file_size: 0"));
+}
+
+#[test]
+fn test_compactwal_threshold() {
+    use kv_common::store::{LogCommand, LogEntry, WriteAheadLog};
+
+    let dir = tempfile::tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    {
+        let mut wal = WriteAheadLog::new(&wal_path).unwrap();
+        for txn_id in 1..=5 {
+            wal.begin(txn_id).unwrap();
+            let entry = LogEntry::new(LogCommand::Put, Some(format!("k{}", txn_id)), Some("v".to_string()), txn_id);
+            wal.append_entry(&entry).unwrap();
+            wal.commit(txn_id).unwrap();
+        }
+    }
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let handler = CommandHandler::new(StoreManager::new(), data_file);
+    let wal_size = || std::fs::metadata(&wal_path).unwrap().len();
+    let initial_size = wal_size();
+
+    // 文件未超过阈值时不压缩
+    let reply = handler.execute_command(handler.parse_command("compactwal 1000000"));
+    assert!(reply.contains("无需压缩"), "unexpected reply: {}", reply);
+    assert_eq!(wal_size(), initial_size);
+
+    // 超过阈值时压缩，已提交的事务从日志中移除
+    let reply = handler.execute_command(handler.parse_command("compactwal 10"));
+    assert!(reply.contains("已压缩"), "unexpected reply: {}", reply);
+    assert!(wal_size() < initial_size);
+
+    assert!(matches!(handler.parse_command("compactwal"), Command::CompactWal(None)));
+    assert!(matches!(handler.parse_command("compactwal big"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_bgcompactwal_reports_status_in_wal_info() {
+    use kv_common::store::{LogCommand, LogEntry, WalCompactionState, WriteAheadLog};
+
+    let dir = tempfile::tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    {
+        let mut wal = WriteAheadLog::new(&wal_path).unwrap();
+        for txn_id in 1..=5 {
+            wal.begin(txn_id).unwrap();
+            let entry = LogEntry::new(LogCommand::Put, Some(format!("k{}", txn_id)), Some("v".to_string()), txn_id);
+            wal.append_entry(&entry).unwrap();
+            wal.commit(txn_id).unwrap();
+        }
+    }
+    let initial_size = std::fs::metadata(&wal_path).unwrap().len();
+    let store_manager = StoreManager::new();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let handler = CommandHandler::new(store_manager.clone(), data_file);
+    assert!(handler.execute_command(Command::WalInfo).contains("last_bg_compaction: (none)"));
+
+    // 命令立即返回，压缩在后台线程中完成
+    let reply = handler.execute_command(handler.parse_command("bgcompactwal"));
+    assert_eq!(reply, "Background WAL compaction started");
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while store_manager.wal_compaction_state() == Some(WalCompactionState::Running)
+        && std::time::Instant::now() < deadline
+    {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let info = handler.execute_command(Command::WalInfo);
+    assert!(info.contains(&format!("last_bg_compaction: completed: {} -> ", initial_size)), "unexpected info: {}", info);
+    assert!(std::fs::metadata(&wal_path).unwrap().len() < initial_size);
+}
+
+#[test]
+fn test_memory_usage() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    handler.execute_command(handler.parse_command("set small v"));
+    for i in 0..1000 {
+        handler.execute_command(handler.parse_command(&format!("rpush biglist element_{}", i)));
+    }
+
+    let small: usize = handler
+        .execute_command(handler.parse_command("memory usage small"))
+        .parse()
+        .unwrap();
+    let big: usize = handler
+        .execute_command(handler.parse_command("MEMORY USAGE biglist"))
+        .parse()
+        .unwrap();
+    // 至少包含键和值本身的长度
+    assert!(small >= "small".len() + "v".len());
+    assert!(big > small * 100, "small={} big={}", small, big);
+
+    assert_eq!(handler.execute_command(handler.parse_command("memory usage missing")), "(nil)");
+    assert!(matches!(handler.parse_command("memory usage"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_interned_values_share_memory() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(&config_path, "[memory]\nintern_values = true\n").unwrap();
+    let settings = std::sync::Arc::new(kv_common::config::Settings::from_path(&config_path).unwrap());
+    let store_manager = StoreManager::new().with_settings(settings);
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+
+    let value = "enabled".repeat(100);
+    let mut key_bytes = 0;
+    for i in 0..1000 {
+        let key = format!("flag:{}", i);
+        key_bytes += key.len();
+        handler.execute_command(handler.parse_command(&format!("set {} {}", key, value)));
+    }
+
+    // 1000个键共享同一个值，整体内存接近一份值的大小
+    let total = store_manager.get_memory_usage();
+    assert!(total <= key_bytes + value.len(), "total={}", total);
+
+    // 单个键只分摊共享值的一部分
+    let single: usize = handler
+        .execute_command(handler.parse_command("memory usage flag:0"))
+        .parse()
+        .unwrap();
+    assert!(single < value.len() / 10, "single={}", single);
+
+    // 修改其中一个键不影响其他键
+    store_manager.append("flag:0", "!").unwrap();
+    assert_eq!(handler.execute_command(handler.parse_command("get flag:0")), format!("{}!", value));
+    assert_eq!(handler.execute_command(handler.parse_command("get flag:1")), value);
+
+    // 未开启时每个键各自保存一份值
+    let plain = StoreManager::new();
+    for i in 0..1000 {
+        plain.set_string(format!("flag:{}", i), value.clone()).unwrap();
+    }
+    assert!(plain.get_memory_usage() >= 1000 * value.len());
+}
+
+#[test]
+fn test_sintercard() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    handler.execute_command(handler.parse_command("sadd s1 a b c d e"));
+    handler.execute_command(handler.parse_command("sadd s2 b c d e f"));
+    handler.execute_command(handler.parse_command("sadd s3 c d e f g"));
+
+    assert_eq!(handler.execute_command(handler.parse_command("sintercard 2 s1 s2")), "4");
+    assert_eq!(handler.execute_command(handler.parse_command("sintercard 3 s1 s2 s3")), "3");
+    // LIMIT达到后提前停止，LIMIT 0表示不限制
+    assert_eq!(handler.execute_command(handler.parse_command("sintercard 3 s1 s2 s3 LIMIT 2")), "2");
+    assert_eq!(handler.execute_command(handler.parse_command("sintercard 3 s1 s2 s3 limit 10")), "3");
+    assert_eq!(handler.execute_command(handler.parse_command("sintercard 2 s1 s2 LIMIT 0")), "4");
+    assert_eq!(handler.execute_command(handler.parse_command("sintercard 2 s1 missing")), "0");
+
+    assert!(matches!(handler.parse_command("sintercard 3 s1 s2"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("sintercard 0 s1"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("sintercard 1 s1 LIMIT x"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_result_cache_invalidated_by_mutation() {
+    let cache = std::sync::Arc::new(kv_common::result_cache::ResultCache::new(16));
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string())
+        .with_result_cache(cache.clone());
+    handler.execute_command(handler.parse_command("sadd s1 a b c d"));
+    handler.execute_command(handler.parse_command("sadd s2 b c d e"));
+
+    assert_eq!(handler.execute_command(handler.parse_command("sinter s1 s2")), "b\nc\nd");
+    assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+    // 重复执行由缓存返回，其他连接共享同一个缓存
+    let other = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string())
+        .with_result_cache(cache.clone());
+    assert_eq!(handler.execute_command(handler.parse_command("sinter s1 s2")), "b\nc\nd");
+    assert_eq!(other.execute_command(other.parse_command("sinter s1 s2")), "b\nc\nd");
+    assert_eq!(cache.hits(), 2);
+
+    // 修改源集合后缓存失效，重新计算
+    other.execute_command(other.parse_command("srem s2 c"));
+    assert_eq!(handler.execute_command(handler.parse_command("sinter s1 s2")), "b\nd");
+    assert_eq!((cache.hits(), cache.misses()), (2, 2));
+    assert_eq!(handler.execute_command(handler.parse_command("sintercard 2 s1 s2")), "2");
+
+    // 删除后重新创建的键同样使缓存失效
+    handler.execute_command(handler.parse_command("del s1"));
+    assert_eq!(handler.execute_command(handler.parse_command("sinter s1 s2")), "(empty set)");
+    handler.execute_command(handler.parse_command("sadd s1 e"));
+    assert_eq!(handler.execute_command(handler.parse_command("sinter s1 s2")), "e");
+    assert_eq!(cache.hits(), 2);
+}
+
+#[test]
+fn test_command_docs() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    assert!(matches!(handler.parse_command("COMMAND DOCS"), Command::CommandList));
+    assert!(matches!(handler.parse_command("command"), Command::Invalid(_)));
+
+    let output = handler.execute_command(Command::CommandList);
+    let docs: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let find = |name: &str| docs.iter().find(|doc| doc["name"] == name).unwrap().clone();
+
+    let set = find("set");
+    assert!(set["min_arity"].as_u64().unwrap() >= 3);
+    assert_eq!(set["kind"], "write");
+    let get = find("get");
+    assert_eq!((get["min_arity"].as_u64(), get["max_arity"].as_u64()), (Some(2), Some(2)));
+    assert_eq!(get["kind"], "read");
+    assert_eq!(find("sinter")["max_arity"], serde_json::Value::Null);
+
+    // 每个列出的命令都有帮助说明
+    for doc in &docs {
+        let name = doc["name"].as_str().unwrap();
+        let help = handler.execute_command(Command::HelpCommand(name.to_string()));
+        assert!(!help.contains("Unknown command"), "{} 没有帮助说明", name);
+    }
+}
+
+#[test]
+fn test_wait_returns_after_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let store_manager = StoreManager::new();
+    let writer = CommandHandler::new(store_manager.clone(), data_file.clone());
+    let saver = CommandHandler::new(store_manager, data_file);
+
+    // 没有未确认的写入时立即返回
+    assert_eq!(writer.execute_command(writer.parse_command("wait 0")), "0");
+
+    writer.execute_command(writer.parse_command("set k1 v1"));
+    writer.execute_command(writer.parse_command("rpush list a"));
+    // 只读命令和失败的写入不计入
+    writer.execute_command(writer.parse_command("get k1"));
+    writer.execute_command(writer.parse_command("lpop k1"));
+
+    // 没有保存时超时返回0
+    assert_eq!(writer.execute_command(writer.parse_command("wait 50")), "0");
+
+    let save_thread = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        saver.execute_command(saver.parse_command("save"))
+    });
+    let start = std::time::Instant::now();
+    assert_eq!(writer.execute_command(writer.parse_command("wait 0")), "2");
+    assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+    assert!(save_thread.join().unwrap().starts_with("Saved"));
+
+    // 已确认的写入不会被重复计算
+    assert_eq!(writer.execute_command(writer.parse_command("wait 10")), "0");
+    assert!(matches!(writer.parse_command("wait"), Command::Invalid(_)));
+    assert!(matches!(writer.parse_command("wait -1"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_wait_returns_after_wal_sync() {
+    use kv_common::config::WalSyncPolicy;
+    use kv_common::TransactionManager;
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let manager = Arc::new(TransactionManager::new(&dir.path().join("wal.log")).unwrap());
+    manager.set_sync_policy(WalSyncPolicy::No, 1000);
+    let store_manager = StoreManager::new().with_transaction_manager(Arc::clone(&manager));
+    let writer = CommandHandler::new(store_manager, data_file).with_transaction_manager(Arc::clone(&manager));
+
+    writer.execute_command(writer.parse_command("set k1 v1"));
+    writer.execute_command(writer.parse_command("rpush list a"));
+    // WAL只写入缓冲区，没有同步到磁盘时超时返回0
+    assert_eq!(writer.execute_command(writer.parse_command("wait 50")), "0");
+
+    // WAL同步到磁盘后返回，不需要保存数据文件
+    let flusher = Arc::clone(&manager);
+    let flush_thread = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        flusher.flush_wal().unwrap();
+    });
+    let start = std::time::Instant::now();
+    assert_eq!(writer.execute_command(writer.parse_command("wait 0")), "2");
+    assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+    flush_thread.join().unwrap();
+
+    // always策略下写入返回前已同步，WAIT立即返回
+    manager.set_sync_policy(WalSyncPolicy::Always, 1);
+    writer.execute_command(writer.parse_command("set k2 v2"));
+    assert_eq!(writer.execute_command(writer.parse_command("wait 0")), "1");
+}
+
+#[test]
+fn test_command_write_classification() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    let writes = [
+        "set k v", "setbin k dg==", "del k", "lpush l a", "rpop l", "ldel l",
+        "hset h f v", "hdel h f", "sadd s a", "srem s a", "expire k 10", "flushdb", "commit",
+    ];
+    let reads = [
+        "get k", "getbin k", "range l 0 -1", "len l", "hget h f", "smembers s",
+        "sintercard 1 s", "ddl k", "save", "lastsave", "wait 0", "begin", "rollback",
+        "checkpoint", "stats commands", "memory usage k", "ping", "help", "nosuchcommand",
+    ];
+    for input in writes {
+        assert!(handler.parse_command(input).is_write(), "{} should be a write", input);
+    }
+    for input in reads {
+        assert!(!handler.parse_command(input).is_write(), "{} should not be a write", input);
+    }
+}
+
+#[test]
+fn test_delpattern() {
+    let dir = tempfile::tempdir().unwrap();
+    let offload_dir = dir.path().join("low_freq");
+    let store_manager = StoreManager::new()
+        .with_memory_optimization(true, u64::MAX, 0, 0, &offload_dir.to_string_lossy());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+
+    // a:cold被转移到磁盘，删除时其磁盘文件也应被删除
+    handler.execute_command(handler.parse_command("set a:cold v"));
+    assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 1);
+    assert_eq!(std::fs::read_dir(&offload_dir).unwrap().count(), 1);
+
+    handler.execute_command(handler.parse_command("set a:1 v"));
+    handler.execute_command(handler.parse_command("rpush a:2 x"));
+    handler.execute_command(handler.parse_command("set b:1 v"));
+
+    assert_eq!(handler.execute_command(handler.parse_command("delpattern a:*")), "3");
+    assert_eq!(store_manager.get_all_keys(), vec!["b:1".to_string()]);
+    assert_eq!(std::fs::read_dir(&offload_dir).unwrap().count(), 0);
+    assert_eq!(handler.execute_command(handler.parse_command("delpattern a:*")), "0");
+    assert!(matches!(handler.parse_command("delpattern"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_rename() {
+    let dir = tempfile::tempdir().unwrap();
+    let offload_dir = dir.path().join("low_freq");
+    let store_manager = StoreManager::new()
+        .with_memory_optimization(true, u64::MAX, 0, 0, &offload_dir.to_string_lossy());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    // 源键已转移到磁盘，重命名后的键可以读取，旧的磁盘文件被删除
+    run("set old v");
+    assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 1);
+    assert_eq!(run("rename old new"), "OK");
+    assert_eq!(run("get new"), "v");
+    assert_eq!(run("get old"), "(nil)");
+    assert_eq!(std::fs::read_dir(&offload_dir).unwrap().count(), 0);
+
+    assert!(run("rename missing other").starts_with("ERROR: NOKEY"));
+    assert!(matches!(handler.parse_command("rename new"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_glob_match() {
+    use kv_common::store::glob_match;
+
+    assert!(glob_match("session:*", "session:42"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("h?llo", "hello"));
+    assert!(!glob_match("h?llo", "hllo"));
+    assert!(glob_match("h[ae]llo", "hallo"));
+    assert!(!glob_match("h[^e]llo", "hello"));
+    assert!(glob_match("key[0-9]", "key7"));
+    assert!(glob_match("a*b*c", "axxbyyc"));
+    assert!(!glob_match("a*b*c", "axxbyy"));
+    assert!(glob_match("literal\\*", "literal*"));
+    assert!(!glob_match("literal\\*", "literalx"));
+    assert!(glob_match("[unclosed", "[unclosed"));
+}
+
+#[test]
+fn test_hrandfield() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    for (field, value) in [("f1", "v1"), ("f2", "v2"), ("f3", "v3")] {
+        handler.execute_command(handler.parse_command(&format!("hset h {} {}", field, value)));
+    }
+    let fields = ["f1", "f2", "f3"];
+
+    let single = handler.execute_command(handler.parse_command("hrandfield h"));
+    assert!(fields.contains(&single.as_str()));
+
+    // 正数count返回不重复的字段，最多返回全部字段
+    let reply = handler.execute_command(handler.parse_command("hrandfield h 2"));
+    let sampled: Vec<&str> = reply.lines().collect();
+    assert_eq!(sampled.len(), 2);
+    assert_ne!(sampled[0], sampled[1]);
+    let mut all: Vec<String> = handler
+        .execute_command(handler.parse_command("hrandfield h 10"))
+        .lines()
+        .map(String::from)
+        .collect();
+    all.sort();
+    assert_eq!(all, fields);
+
+    // 负数count返回指定数量的字段，可能重复
+    let reply = handler.execute_command(handler.parse_command("hrandfield h -10"));
+    let sampled: Vec<&str> = reply.lines().collect();
+    assert_eq!(sampled.len(), 10);
+    assert!(sampled.iter().all(|field| fields.contains(field)));
+
+    // WITHVALUES按字段、值交替返回
+    let reply = handler.execute_command(handler.parse_command("hrandfield h -4 withvalues"));
+    let lines: Vec<&str> = reply.lines().collect();
+    assert_eq!(lines.len(), 8);
+    for pair in lines.chunks(2) {
+        assert_eq!(pair[1], pair[0].replace('f', "v"));
+    }
+
+    assert_eq!(handler.execute_command(handler.parse_command("hrandfield missing")), "(nil)");
+    assert_eq!(handler.execute_command(handler.parse_command("hrandfield missing -3")), "(empty list)");
+    assert!(matches!(handler.parse_command("hrandfield h x"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("hrandfield h 1 values"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_lmpop() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    for value in ["a", "b", "c"] {
+        handler.execute_command(handler.parse_command(&format!("rpush lmpop:second {}", value)));
+    }
+
+    // 跳过不存在的列表，从第一个非空列表弹出
+    let reply = handler.execute_command(handler.parse_command("lmpop 2 lmpop:first lmpop:second LEFT"));
+    assert_eq!(reply, "lmpop:second\na");
+    let reply = handler.execute_command(handler.parse_command("lmpop 2 lmpop:first lmpop:second right count 5"));
+    assert_eq!(reply, "lmpop:second\nc\nb");
+
+    // 所有列表都为空时返回nil
+    let reply = handler.execute_command(handler.parse_command("lmpop 2 lmpop:first lmpop:second LEFT"));
+    assert_eq!(reply, "(nil)");
+
+    // 非列表类型的键返回错误
+    handler.execute_command(handler.parse_command("set lmpop:string value"));
+    let reply = handler.execute_command(handler.parse_command("lmpop 1 lmpop:string LEFT"));
+    assert!(reply.starts_with("ERROR:"));
+
+    assert!(matches!(handler.parse_command("lmpop 0 k LEFT"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("lmpop 2 k LEFT"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("lmpop 1 k UP"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("lmpop 1 k LEFT COUNT 0"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_blpop_waits_for_push_from_another_connection() {
+    let store_manager = StoreManager::new();
+    let worker = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let producer = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+
+    // 列表非空时立即弹出
+    producer.execute_command(producer.parse_command("rpush jobs:low ready"));
+    assert_eq!(worker.execute_command(worker.parse_command("blpop jobs:high jobs:low 1")), "jobs:low\nready");
+
+    // 所有列表都为空时阻塞，直到另一个连接推入元素
+    let blocked = std::thread::spawn(move || {
+        let start = std::time::Instant::now();
+        let reply = worker.execute_command(worker.parse_command("blpop jobs:high jobs:low 0"));
+        (reply, start.elapsed())
+    });
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert_eq!(producer.execute_command(producer.parse_command("rpush jobs:high job1")), "1");
+    let (reply, elapsed) = blocked.join().unwrap();
+    assert_eq!(reply, "jobs:high\njob1");
+    assert!(elapsed >= std::time::Duration::from_millis(200), "{:?}", elapsed);
+    assert!(!store_manager.exists("jobs:high"));
+
+    // 超时返回nil
+    let start = std::time::Instant::now();
+    assert_eq!(producer.execute_command(producer.parse_command("brpop jobs:high 0.1")), "(nil)");
+    assert!(start.elapsed() >= std::time::Duration::from_millis(100));
+
+    assert!(matches!(producer.parse_command("blpop jobs"), Command::Invalid(_)));
+    assert!(matches!(producer.parse_command("blpop jobs -1"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_blpop_wakes_for_lists_created_without_push() {
+    let store_manager = StoreManager::new();
+    let producer = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+
+    // 在后台连接上执行阻塞弹出，返回对应的线程
+    let block_on = |command: &'static str| {
+        let worker = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+        let blocked = std::thread::spawn(move || worker.execute_command(worker.parse_command(command)));
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        blocked
+    };
+
+    // RENAME把已有列表改名为等待的键
+    producer.execute_command(producer.parse_command("rpush staging job1"));
+    let blocked = block_on("blpop jobs 0");
+    assert_eq!(producer.execute_command(producer.parse_command("rename staging jobs")), "OK");
+    assert_eq!(blocked.join().unwrap(), "jobs\njob1");
+
+    // SWAPDB把另一个数据库中的列表换到等待的数据库
+    producer.execute_command(producer.parse_command("select 1"));
+    producer.execute_command(producer.parse_command("rpush jobs job2"));
+    let blocked = block_on("blpop jobs 0");
+    assert_eq!(producer.execute_command(producer.parse_command("swapdb 0 1")), "OK");
+    assert_eq!(blocked.join().unwrap(), "jobs\njob2");
+
+    // 服务器关闭时唤醒所有等待的连接，之后的阻塞弹出立即返回
+    let blocked = block_on("brpop idle 0");
+    store_manager.wake_blocked_pops();
+    assert_eq!(blocked.join().unwrap(), "(nil)");
+    assert_eq!(producer.execute_command(producer.parse_command("blpop idle 0")), "(nil)");
+}
+
+#[test]
+fn test_debug_object() {
+    let dir = tempfile::tempdir().unwrap();
+    let offload_dir = dir.path().join("offload");
+    let store_manager = StoreManager::new().with_memory_optimization(
+        true,
+        5,
+        3600,
+        1000,
+        offload_dir.to_str().unwrap(),
+    );
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    for value in ["a", "b", "c"] {
+        handler.execute_command(handler.parse_command(&format!("rpush dbg:list {}", value)));
+    }
+
+    let reply = handler.execute_command(handler.parse_command("debug object dbg:list"));
+    assert!(reply.starts_with("type:list length:3 "), "{}", reply);
+    assert!(reply.ends_with("offloaded:0"), "{}", reply);
+
+    // 转移到磁盘后仍能查看，且不会被重新加载到内存
+    assert_eq!(store_manager.offload_keys_to_disk(&["dbg:list".to_string()]).unwrap(), 1);
+    let reply = handler.execute_command(handler.parse_command("DEBUG OBJECT dbg:list"));
+    assert!(reply.starts_with("type:list length:3 "), "{}", reply);
+    assert!(reply.ends_with("offloaded:1"), "{}", reply);
+    assert!(store_manager.get_disk_keys().contains(&"dbg:list".to_string()));
+
+    let reply = handler.execute_command(handler.parse_command("debug object dbg:missing"));
+    assert!(reply.starts_with("ERROR: NOKEY"), "{}", reply);
+    assert!(matches!(handler.parse_command("debug object"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_debug_list_reports_element_stats() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    let elements = ["a", "bcd", "efghijkl", "mn"];
+    for element in elements {
+        handler.execute_command(handler.parse_command(&format!("rpush dbg:stats {}", element)));
+    }
+
+    // 总大小与estimated_size一致：元素字节数加每个元素8字节的开销
+    let expected = DataType::List(elements.iter().map(|element| element.to_string()).collect());
+    assert_eq!(expected.estimated_size(), 14 + 4 * 8);
+    assert_eq!(
+        handler.execute_command(handler.parse_command("debug list dbg:stats")),
+        format!(
+            "length:4 total_bytes:{} avg_element_bytes:3.50 largest_element_bytes:8",
+            expected.estimated_size()
+        )
+    );
+
+    handler.execute_command(handler.parse_command("set dbg:string value"));
+    let reply = handler.execute_command(handler.parse_command("debug list dbg:string"));
+    assert!(reply.starts_with("ERROR: WRONGTYPE"), "{}", reply);
+    let reply = handler.execute_command(handler.parse_command("debug list dbg:missing"));
+    assert!(reply.starts_with("ERROR: NOKEY"), "{}", reply);
+    assert!(matches!(handler.parse_command("debug list"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_unwritable_offload_dir_disables_offload() {
+    let dir = tempfile::tempdir().unwrap();
+    // 父路径是普通文件，低频数据目录无法创建
+    let blocker = dir.path().join("blocker");
+    std::fs::write(&blocker, "").unwrap();
+    let offload_dir = blocker.join("low_freq");
+    let store_manager = StoreManager::new()
+        .with_memory_optimization(true, u64::MAX, 0, 0, &offload_dir.to_string_lossy());
+    assert!(!store_manager.offload_enabled());
+
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    for i in 0..3 {
+        assert_eq!(handler.execute_command(handler.parse_command(&format!("set k{} v", i))), "OK");
+        assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 0);
+    }
+    assert_eq!(store_manager.get_memory_keys().len(), 3);
+    assert!(store_manager.get_disk_keys().is_empty());
+
+    // 目录在运行中变得不可写时，第一次写入失败后停止转移
+    let offload_dir = dir.path().join("removed");
+    let store_manager = StoreManager::new()
+        .with_memory_optimization(true, u64::MAX, 0, 0, &offload_dir.to_string_lossy());
+    assert!(store_manager.offload_enabled());
+    std::fs::remove_dir(&offload_dir).unwrap();
+    std::fs::write(&offload_dir, "").unwrap();
+
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    handler.execute_command(handler.parse_command("set a v"));
+    handler.execute_command(handler.parse_command("set b v"));
+    assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 0);
+    assert!(!store_manager.offload_enabled());
+    assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 0);
+    assert_eq!(handler.execute_command(handler.parse_command("get a")), "v");
+    assert!(store_manager.get_disk_keys().is_empty());
+
+    // 目录仍不可写时重新检查失败，恢复可写后重新开始转移
+    assert!(!store_manager.retry_offload());
+    std::fs::remove_file(&offload_dir).unwrap();
+    std::fs::create_dir(&offload_dir).unwrap();
+    assert!(store_manager.retry_offload());
+    assert!(store_manager.offload_enabled());
+    assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 2);
+    assert_eq!(store_manager.get_disk_keys().len(), 2);
+    assert_eq!(handler.execute_command(handler.parse_command("get a")), "v");
+}
+
+#[test]
+fn test_select_and_swapdb() {
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    // 另一个连接在交换前已选择1号数据库
+    let other = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    assert_eq!(other.execute_command(other.parse_command("select 1")), "OK");
+
+    handler.execute_command(handler.parse_command("set greeting hello"));
+    assert_eq!(other.execute_command(other.parse_command("get greeting")), "(nil)");
+
+    assert_eq!(handler.execute_command(handler.parse_command("swapdb 0 1")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("get greeting")), "(nil)");
+    assert_eq!(other.execute_command(other.parse_command("get greeting")), "hello");
+    assert_eq!(handler.execute_command(handler.parse_command("select 1")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("get greeting")), "hello");
+
+    // 选择数据库只影响当前连接
+    let fresh = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    assert_eq!(fresh.execute_command(fresh.parse_command("get greeting")), "(nil)");
+
+    let reply = handler.execute_command(handler.parse_command("select 16"));
+    assert!(reply.starts_with("ERROR: ERR"), "{}", reply);
+    let reply = handler.execute_command(handler.parse_command("swapdb 0 16"));
+    assert!(reply.starts_with("ERROR: ERR"), "{}", reply);
+    assert!(matches!(handler.parse_command("select -1"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("swapdb 0"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_health_reports_loading_then_ok() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), data_file.clone());
+
+    // 模拟服务器启动时的恢复过程
+    store_manager.set_loading(true);
+    let reply = handler.execute_command(handler.parse_command("health"));
+    assert!(reply.starts_with("LOADING\n"), "{}", reply);
+    assert_eq!(handler.execute_command(Command::Ping), "PONG");
+    // 恢复期间其他命令返回LOADING错误，不读写不完整的数据
+    assert_eq!(handler.execute_command(handler.parse_command("set key value")), "ERROR: LOADING server is loading the dataset");
+    assert_eq!(handler.execute_command(handler.parse_command("get key")), "ERROR: LOADING server is loading the dataset");
+
+    store_manager.load_from_file(&data_file).unwrap();
+    store_manager.set_loading(false);
+    assert_eq!(handler.execute_command(handler.parse_command("get key")), "(nil)");
+    assert_eq!(
+        handler.execute_command(handler.parse_command("health")),
+        "OK\nloading: 0\npersistence_ok: 1\nactive_transactions: 0"
+    );
+
+    handler.execute_command(handler.parse_command("begin"));
+    let reply = handler.execute_command(handler.parse_command("healthcheck"));
+    assert!(reply.ends_with("active_transactions: 1"), "{}", reply);
+}
+
+#[test]
+fn test_execute_typed_returns_structured_results() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_typed(handler.parse_command(line));
+
+    assert_eq!(run("set greeting hello"), CommandResult::Ok);
+    assert_eq!(run("get greeting"), CommandResult::Bulk(Some("hello".to_string())));
+    assert_eq!(run("get missing"), CommandResult::Bulk(None));
+    assert_eq!(run("rpush list a"), CommandResult::Integer(1));
+    assert_eq!(run("rpush list b"), CommandResult::Integer(2));
+    assert_eq!(run("len list"), CommandResult::Integer(2));
+    assert_eq!(run("len missing"), CommandResult::Integer(0));
+    assert_eq!(run("range list 0 -1"), CommandResult::Array(vec!["a".to_string(), "b".to_string()]));
+    assert_eq!(run("smembers missing"), CommandResult::Array(vec![]));
+    assert!(matches!(run("len greeting"), CommandResult::Error(e) if e.starts_with("WRONGTYPE")));
+
+    // 文本响应由结构化结果渲染得到
+    assert_eq!(run("get missing").to_string(), "(nil)");
+    assert_eq!(run("range list 0 -1").to_string(), "a\nb");
+    assert_eq!(handler.execute_command(handler.parse_command("smembers missing")), "(empty set)");
+    assert!(run("len greeting").to_string().starts_with("ERROR: WRONGTYPE"));
+}
+
+#[test]
+fn test_del_multiple_keys_counts_removed() {
+    let clock = std::sync::Arc::new(kv_common::clock::MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("set a 1");
+    run("rpush b x");
+    run("sadd c m");
+    run("set temp value EX 5");
+    clock.advance(std::time::Duration::from_secs(10));
+
+    // 不存在、已过期和重复的键不计入删除数量
+    assert_eq!(run("del a missing b temp a c"), "3");
+    assert_eq!(run("get a"), "(nil)");
+    assert_eq!(run("len b"), "0");
+    assert_eq!(run("ddl temp"), "Key does not exist");
+    assert_eq!(run("del a b"), "0");
+    assert!(run("del").starts_with("ERROR:"));
+}
+
+#[test]
+fn test_scan_type_filter_returns_only_matching_keys() {
+    let clock = std::sync::Arc::new(kv_common::clock::MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = CommandHandler::new(store_manager, "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    for i in 0..5 {
+        run(&format!("set str:{} v", i));
+        run(&format!("rpush list:{} v", i));
+    }
+    run("rpush list:expired v");
+    run("expire list:expired 5");
+    clock.advance(std::time::Duration::from_secs(10));
+
+    // 每次只检查3个键，按游标遍历到0为止
+    let mut cursor = "0".to_string();
+    let mut found = Vec::new();
+    loop {
+        let reply = run(&format!("scan {} COUNT 3 TYPE list", cursor));
+        let mut lines = reply.lines().map(str::to_string);
+        cursor = lines.next().unwrap();
+        found.extend(lines);
+        if cursor == "0" {
+            break;
+        }
+    }
+    found.sort();
+    assert_eq!(found, (0..5).map(|i| format!("list:{}", i)).collect::<Vec<_>>());
+
+    assert_eq!(run("scan 0 COUNT 100 MATCH str:[0-1] TYPE string"), "0\nstr:0\nstr:1");
+    assert_eq!(run("scan 0 COUNT 100 TYPE hash"), "0");
+    assert!(run("scan 0 TYPE").starts_with("ERROR:"));
+    assert!(run("scan x").starts_with("ERROR:"));
+}
+
+#[test]
+fn test_scan_cursor_is_stable_while_keys_change() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    for i in 0..40 {
+        run(&format!("set old:{} v", i));
+        run(&format!("set gone:{} v", i));
+    }
+
+    // 遍历期间新增和删除键，一直存在的键仍然每个都被返回
+    let mut cursor = "0".to_string();
+    let mut found = Vec::new();
+    let mut batches = 0;
+    loop {
+        let reply = run(&format!("scan {} COUNT 7 MATCH old:*", cursor));
+        let mut lines = reply.lines().map(str::to_string);
+        cursor = lines.next().unwrap();
+        found.extend(lines);
+        run(&format!("set new:{} v", batches));
+        run(&format!("del gone:{}", batches));
+        batches += 1;
+        if cursor == "0" {
+            break;
+        }
+    }
+    found.sort();
+    found.dedup();
+    let mut expected: Vec<String> = (0..40).map(|i| format!("old:{}", i)).collect();
+    expected.sort();
+    assert_eq!(found, expected);
+}
@@ -0,0 +1,257 @@
+use kv_common::config::{render_params, DataPaths, PersistenceMode, Settings, WalSyncPolicy};
+use kv_common::{Command, CommandHandler, StoreManager};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+#[test]
+fn test_load_settings_from_custom_path() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("custom.toml");
+    fs::write(
+        &config_path,
+        r#"[server]
+host = "0.0.0.0"
+port = 7001
+
+[persistence]
+data_file = "custom/storage.dat"
+mode = "none"
+interval_seconds = 60
+"#,
+    )
+    .unwrap();
+
+    let settings = Settings::from_path(&config_path).unwrap();
+    assert_eq!(settings.server.host, "0.0.0.0");
+    assert_eq!(settings.server.port, 7001);
+    assert_eq!(settings.persistence.data_file, "custom/storage.dat");
+    assert!(matches!(settings.persistence.mode, PersistenceMode::None));
+
+    // 文件中未出现的配置项使用内置默认值
+    assert!(!settings.server.read_only);
+    assert_eq!(settings.persistence.wal_sync, WalSyncPolicy::Always);
+    assert!(!settings.storage.enable_default_expiry);
+    assert_eq!(settings.storage.ttl_jitter_percent, 0);
+    assert_eq!(settings.memory.max_memory_keys, 1000);
+    assert_eq!(settings.logging.level, "info");
+}
+
+#[test]
+fn test_load_settings_from_missing_path() {
+    let dir = tempdir().unwrap();
+    let result = Settings::from_path(dir.path().join("missing.toml"));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_data_dir_roots_all_data_paths() {
+    let dir = tempdir().unwrap();
+    let data_dir = dir.path().join("instance1");
+    let config_path = dir.path().join("instance1.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[persistence]\ndata_dir = '{}'\ndata_file = \"storage.dat\"\n",
+            data_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let paths = settings.data_paths();
+    assert_eq!(paths.data_file, data_dir.join("storage.dat"));
+    assert_eq!(paths.wal_file, data_dir.join("wal.log"));
+    assert_eq!(paths.checkpoint_dir, data_dir.join("checkpoints"));
+    assert_eq!(paths.offload_dir, data_dir.join("low_freq"));
+
+    // WAL和检查点文件落在数据根目录下
+    let data_file = paths.data_file.to_string_lossy().to_string();
+    let handler = CommandHandler::new(StoreManager::new(), data_file).with_settings(Arc::clone(&settings));
+    let result = handler.execute_command(Command::Checkpoint);
+    assert!(!result.starts_with("ERROR"), "unexpected: {}", result);
+    assert!(paths.wal_file.exists());
+    let checkpoint_files = fs::read_dir(&paths.checkpoint_dir)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().starts_with("checkpoint_"))
+        .count();
+    assert_eq!(checkpoint_files, 1);
+    assert!(paths.checkpoint_dir.join("checkpoints.index").exists());
+
+    // 低频数据转移到数据根目录下
+    let store_manager = StoreManager::new()
+        .with_settings(Arc::clone(&settings))
+        .with_memory_optimization(true, u64::MAX, 0, 0, &paths.offload_dir.to_string_lossy());
+    store_manager.set_string("cold".to_string(), "value".to_string()).unwrap();
+    assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 1);
+    assert_eq!(fs::read_dir(&paths.offload_dir).unwrap().count(), 1);
+}
+
+#[test]
+fn test_data_paths_overrides() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("override.toml");
+    fs::write(
+        &config_path,
+        r#"[persistence]
+data_dir = "instance2"
+data_file = "storage.dat"
+wal_file = "/var/log/kv/wal.log"
+"#,
+    )
+    .unwrap();
+
+    let paths = Settings::from_path(&config_path).unwrap().data_paths();
+    assert_eq!(paths.data_file, Path::new("instance2/storage.dat"));
+    assert_eq!(paths.wal_file, Path::new("/var/log/kv/wal.log"));
+    assert_eq!(paths.checkpoint_dir, Path::new("instance2/checkpoints"));
+
+    // 未设置data_dir时沿用数据文件所在目录
+    let paths = DataPaths::from_data_file("data/storage.dat");
+    assert_eq!(paths.wal_file, Path::new("data/wal.log"));
+    assert_eq!(paths.offload_dir, Path::new("data/low_freq"));
+}
+
+#[test]
+fn test_read_only_rejects_writes() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("standby.toml");
+    fs::write(&config_path, "[server]\nread_only = true\n").unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+
+    let store_manager = StoreManager::new();
+    store_manager.set_string("key".to_string(), "value".to_string()).unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let handler = CommandHandler::new(store_manager, data_file).with_settings(settings);
+
+    for command in ["set key other", "del key", "rpush list a", "expire key 10", "flushdb"] {
+        assert_eq!(
+            handler.execute_command(handler.parse_command(command)),
+            "ERROR: READONLY server is read-only",
+            "{}",
+            command
+        );
+    }
+    assert_eq!(handler.execute_command(handler.parse_command("get key")), "value");
+    assert_eq!(handler.execute_command(handler.parse_command("ping")), "PONG");
+    assert!(handler.execute_command(handler.parse_command("memory usage key")).parse::<usize>().is_ok());
+}
+
+#[test]
+fn test_command_aliases_resolve_to_builtin_commands() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("aliases.toml");
+    fs::write(&config_path, "[server]\ncommand_aliases = { put = \"set\", Fetch = \"GET\" }\n").unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let handler = CommandHandler::new(StoreManager::new(), data_file).with_settings(settings);
+
+    assert!(matches!(handler.parse_command("put greeting hello"), Command::Set(k, v) if k == "greeting" && v == "hello"));
+    assert_eq!(handler.execute_command(handler.parse_command("PUT greeting hello")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("fetch greeting")), "hello");
+    assert_eq!(handler.execute_command(handler.parse_command("help put")), handler.execute_command(handler.parse_command("help set")));
+    // 内置别名仍然可用
+    assert!(matches!(handler.parse_command("multi"), Command::Begin));
+
+    // 别名的目标必须是内置命令，与内置命令重名需要显式允许
+    fs::write(&config_path, "[server]\ncommand_aliases = { put = \"missing\" }\n").unwrap();
+    assert!(Settings::from_path(&config_path).is_err());
+    fs::write(&config_path, "[server]\ncommand_aliases = { get = \"del\" }\n").unwrap();
+    assert!(Settings::from_path(&config_path).is_err());
+    fs::write(
+        &config_path,
+        "[server]\nallow_alias_override = true\ncommand_aliases = { get = \"del\" }\n",
+    )
+    .unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string()).with_settings(settings);
+    assert!(matches!(handler.parse_command("get key"), Command::Del(_)));
+}
+
+#[test]
+fn test_config_set_updates_runtime_settings() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("runtime.toml");
+    fs::write(&config_path, "[server]\nport = 7002\nenable_admin_commands = true\n").unwrap();
+    let settings = Settings::from_path(&config_path).unwrap();
+    let runtime_config = Arc::new(std::sync::RwLock::new(settings.clone()));
+    let store_manager = StoreManager::new().with_settings(Arc::new(settings.clone()));
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string())
+        .with_settings(Arc::new(settings))
+        .with_runtime_config(Arc::clone(&runtime_config));
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    assert_eq!(run("config get logging.level"), "info");
+    assert_eq!(run("config set logging.level debug"), "OK");
+    assert_eq!(run("CONFIG GET logging.level"), "debug");
+    assert_eq!(log::max_level(), log::LevelFilter::Debug);
+    assert_eq!(runtime_config.read().unwrap().logging.level, "debug");
+
+    // 默认过期时间对之后的写入立即生效
+    assert_eq!(run("config set storage.enable_default_expiry true"), "OK");
+    assert_eq!(run("config set storage.default_expiry_seconds 120"), "OK");
+    run("set session data");
+    assert_eq!(store_manager.ttl("session").unwrap(), 120);
+
+    // 不可修改、不存在的配置项和无效的值都返回错误，配置保持不变
+    assert_eq!(run("config get server.port"), "7002");
+    assert_eq!(
+        run("config set server.port 7003"),
+        "ERROR: ERR config parameter server.port cannot be changed at runtime"
+    );
+    assert!(run("config get server.missing").starts_with("ERROR: ERR unknown config parameter"));
+    assert!(run("config set logging.level loud").starts_with("ERROR:"));
+    assert!(run("config set storage.default_expiry_seconds soon").starts_with("ERROR:"));
+    assert_eq!(run("config get logging.level"), "debug");
+    assert_eq!(run("config get storage.default_expiry_seconds"), "120");
+}
+
+#[test]
+fn test_config_get_requires_admin_commands() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("locked.toml");
+    fs::write(&config_path, "[server]\nenable_admin_commands = false\n").unwrap();
+    let settings = Settings::from_path(&config_path).unwrap();
+    let runtime_config = Arc::new(std::sync::RwLock::new(settings.clone()));
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string())
+        .with_settings(Arc::new(settings))
+        .with_runtime_config(runtime_config);
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    assert_eq!(run("config get logging.level"), "ERROR: NOPERM admin commands are disabled");
+    assert_eq!(run("config set logging.level debug"), "ERROR: NOPERM admin commands are disabled");
+}
+
+#[test]
+fn test_config_dump_renders_effective_settings() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("dump.toml");
+    fs::write(&config_path, "[server]\nport = 7004\n\n[persistence]\ndata_file = \"custom.dat\"\n").unwrap();
+    let settings = Settings::from_path(&config_path).unwrap();
+    let runtime_config = Arc::new(std::sync::RwLock::new(settings.clone()));
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string())
+        .with_runtime_config(runtime_config);
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    // 运行时的修改同样体现在输出中
+    assert_eq!(run("config set storage.default_expiry_seconds 90"), "OK");
+    let dump = run("config dump");
+    let lines: Vec<&str> = dump.lines().collect();
+    assert!(lines.contains(&"server.port=7004"), "{}", dump);
+    assert!(lines.contains(&"persistence.data_file=custom.dat"), "{}", dump);
+    assert!(lines.contains(&"storage.default_expiry_seconds=90"), "{}", dump);
+    for section in ["server", "persistence", "logging", "storage", "memory"] {
+        assert!(lines.iter().any(|line| line.starts_with(&format!("{}.", section))), "{}", dump);
+    }
+    assert!(matches!(handler.parse_command("config dump all"), Command::Invalid(_)));
+
+    // 密码等敏感的值被隐藏，未设置的保持为空
+    let rendered = render_params(&serde_json::json!({
+        "server": { "port": 7004, "requirepass": "hunter2", "masterauth_password": null }
+    }));
+    assert_eq!(
+        rendered,
+        vec!["server.masterauth_password=", "server.port=7004", "server.requirepass=******"]
+    );
+}
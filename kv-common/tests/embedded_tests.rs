@@ -0,0 +1,26 @@
+use kv_common::EmbeddedStore;
+use tempfile::tempdir;
+
+#[test]
+fn test_embedded_store_executes_command_lines() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("data.json");
+    let store = EmbeddedStore::new(data_file.to_string_lossy());
+
+    assert_eq!(store.execute("set greeting hello"), "OK");
+    assert_eq!(store.execute("get greeting"), "hello");
+    assert_eq!(store.execute("lpush queue a"), "1");
+    assert_eq!(store.execute("lpush queue b"), "2");
+    assert_eq!(store.execute("range queue 0 -1"), "b\na");
+    assert!(store.execute("get").starts_with("ERROR:"));
+
+    // 命令和类型化接口操作同一份数据
+    assert_eq!(store.store_manager().get_string("greeting").unwrap(), Some("hello".to_string()));
+
+    // 保存后由新的实例加载
+    store.save().unwrap();
+    let reopened = EmbeddedStore::new(data_file.to_string_lossy());
+    reopened.load().unwrap();
+    assert_eq!(reopened.execute("get greeting"), "hello");
+    assert_eq!(reopened.execute("range queue 0 -1"), "b\na");
+}
@@ -0,0 +1,24 @@
+// 环境变量对整个进程生效，单独放在一个测试文件中，避免影响其他读取配置的测试
+use kv_common::config::{Settings, ENV_PREFIX};
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_env_vars_override_file_and_default_values() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("env.toml");
+    fs::write(&config_path, "[server]\nport = 7001\nhost = \"0.0.0.0\"\n").unwrap();
+
+    env::set_var(format!("{}_SERVER__PORT", ENV_PREFIX), "7100");
+    env::set_var(format!("{}_MEMORY__MAX_MEMORY_KEYS", ENV_PREFIX), "42");
+    env::set_var(format!("{}_LOGGING__LEVEL", ENV_PREFIX), "debug");
+    let settings = Settings::from_path(&config_path).unwrap();
+
+    // 环境变量覆盖配置文件中的值和内置默认值，未设置环境变量的配置项不受影响
+    assert_eq!(settings.server.port, 7100);
+    assert_eq!(settings.memory.max_memory_keys, 42);
+    assert_eq!(settings.logging.level, "debug");
+    assert_eq!(settings.server.host, "0.0.0.0");
+    assert_eq!(settings.persistence.data_file, "data/storage.dat");
+}
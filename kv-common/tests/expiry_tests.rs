@@ -0,0 +1,294 @@
+use kv_common::clock::MockClock;
+use kv_common::config::Settings;
+use kv_common::store::ExpiryManager;
+use kv_common::{Command, CommandHandler, StoreManager};
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::tempdir;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+#[test]
+fn test_ttl_jitter_spreads_expiry_times() {
+    let mut expiry_manager = ExpiryManager::new().with_jitter(10);
+    let start = now();
+    for i in 0..1000 {
+        expiry_manager.set_expire(&format!("key{}", i), 1000).unwrap();
+    }
+    let end = now();
+
+    let expire_times = expiry_manager.export_expire_times();
+    assert_eq!(expire_times.len(), 1000);
+    // 所有过期时间都在 1000s ±10% 范围内，且分散在多个不同的秒上
+    for &expire_time in expire_times.values() {
+        assert!(expire_time >= start + 900 && expire_time <= end + 1100, "{}", expire_time);
+    }
+    let distinct: HashSet<u64> = expire_times.values().copied().collect();
+    assert!(distinct.len() > 100, "只有 {} 个不同的过期时间", distinct.len());
+
+    // 未开启抖动时所有键在同一秒过期
+    let mut expiry_manager = ExpiryManager::new();
+    for i in 0..1000 {
+        expiry_manager.set_expire(&format!("key{}", i), 1000).unwrap();
+    }
+    let distinct: HashSet<u64> = expiry_manager.export_expire_times().values().copied().collect();
+    assert!(distinct.len() <= 2);
+}
+
+#[test]
+fn test_ttl_jitter_from_settings() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(&config_path, "[storage]\nttl_jitter_percent = 50\n").unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let store_manager = StoreManager::new().with_settings(settings);
+
+    let mut ttls = HashSet::new();
+    for i in 0..200 {
+        let key = format!("key{}", i);
+        store_manager.set_string(key.clone(), "value".to_string()).unwrap();
+        store_manager.expire(&key, 100).unwrap();
+        let ttl = store_manager.ttl(&key).unwrap();
+        assert!((49..=150).contains(&ttl), "ttl {} 超出抖动范围", ttl);
+        ttls.insert(ttl);
+    }
+    assert!(ttls.len() > 10);
+}
+
+#[test]
+fn test_default_expiry_exempt_prefixes() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        "[storage]\nenable_default_expiry = true\ndefault_expiry_seconds = 600\ndefault_expiry_exempt_prefixes = [\"config:\", \"perm:\"]\n",
+    )
+    .unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let store_manager = StoreManager::new().with_settings(settings);
+
+    store_manager.set_string("session:1".to_string(), "a".to_string()).unwrap();
+    store_manager.set_string("config:timeout".to_string(), "30".to_string()).unwrap();
+    store_manager.rpush("perm:list".to_string(), "x".to_string()).unwrap();
+
+    let ttl = store_manager.ttl("session:1").unwrap();
+    assert!(ttl > 0 && ttl <= 600, "ttl {}", ttl);
+    assert_eq!(store_manager.ttl("config:timeout").unwrap(), -1);
+    assert_eq!(store_manager.ttl("perm:list").unwrap(), -1);
+}
+
+#[test]
+fn test_default_expiry_not_reset_by_mutations() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(&config_path, "[storage]\nenable_default_expiry = true\ndefault_expiry_seconds = 100\n").unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_settings(settings).with_clock(clock.clone());
+
+    store_manager.rpush("queue".to_string(), "a".to_string()).unwrap();
+    assert_eq!(store_manager.ttl("queue").unwrap(), 100);
+
+    clock.advance(Duration::from_secs(30));
+    store_manager.rpush("queue".to_string(), "b".to_string()).unwrap();
+    store_manager.lpush("queue".to_string(), "c".to_string()).unwrap();
+
+    // 修改不会重置过期时间，原有TTL继续倒计时
+    assert_eq!(store_manager.ttl("queue").unwrap(), 70);
+}
+
+#[test]
+fn test_append_applies_default_expiry_only_on_creation() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(&config_path, "[storage]\nenable_default_expiry = true\ndefault_expiry_seconds = 100\n").unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_settings(settings).with_clock(clock.clone());
+
+    // 追加到不存在的键会新建键并应用默认过期时间
+    assert_eq!(store_manager.append("log", "a").unwrap(), 1);
+    assert_eq!(store_manager.ttl("log").unwrap(), 100);
+
+    // 追加到已有的键不会改变其TTL
+    clock.advance(Duration::from_secs(40));
+    assert_eq!(store_manager.append("log", "bc").unwrap(), 3);
+    assert_eq!(store_manager.ttl("log").unwrap(), 60);
+
+    // 手动取消过期时间后追加也不会重新应用默认值
+    store_manager.persist_key("log").unwrap();
+    store_manager.append("log", "d").unwrap();
+    assert_eq!(store_manager.ttl("log").unwrap(), -1);
+    assert_eq!(store_manager.get_string("log").unwrap(), Some("abcd".to_string()));
+
+    // 类型错误的写入不会给已有的键设置过期时间
+    store_manager.rpush("list".to_string(), "x".to_string()).unwrap();
+    store_manager.persist_key("list").unwrap();
+    assert!(store_manager.append("list", "y").is_err());
+    assert_eq!(store_manager.ttl("list").unwrap(), -1);
+}
+
+#[test]
+fn test_mock_clock_expires_keys_without_sleeping() {
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+
+    store_manager.set_string("session".to_string(), "data".to_string()).unwrap();
+    store_manager.expire("session", 10).unwrap();
+    assert_eq!(store_manager.ttl("session").unwrap(), 10);
+
+    clock.advance(Duration::from_secs(9));
+    assert_eq!(store_manager.ttl("session").unwrap(), 1);
+    assert_eq!(store_manager.get_string("session").unwrap(), Some("data".to_string()));
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(store_manager.get_string("session").unwrap(), None);
+    assert_eq!(store_manager.ttl("session").unwrap(), -2);
+}
+
+#[test]
+fn test_debug_set_active_expire() {
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+
+    handler.execute_command(handler.parse_command("set temp value EX 5"));
+    assert_eq!(handler.execute_command(handler.parse_command("debug set-active-expire 0")), "OK");
+    clock.advance(Duration::from_secs(10));
+
+    // 关闭主动清理后，过期键只是不可见，仍留在内存中
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    assert!(store_manager.get_memory_keys().contains(&"temp".to_string()));
+    assert_eq!(handler.execute_command(handler.parse_command("get temp")), "(nil)");
+
+    assert_eq!(handler.execute_command(handler.parse_command("DEBUG SET-ACTIVE-EXPIRE 1")), "OK");
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    assert!(!store_manager.get_memory_keys().contains(&"temp".to_string()));
+
+    assert!(matches!(handler.parse_command("debug set-active-expire 2"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_hexpire_expires_only_the_given_fields() {
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("hset user name alice");
+    run("hset user token secret");
+    run("hset user session abc");
+    assert_eq!(run("hexpire user 10 FIELDS 3 token session missing"), "1\n1\n-2");
+    assert_eq!(run("httl user fields 3 token name missing"), "10\n-1\n-2");
+
+    clock.advance(Duration::from_secs(9));
+    assert_eq!(run("hget user token"), "secret");
+    assert_eq!(run("httl user FIELDS 1 session"), "1");
+
+    // 字段过期后不可见，其他字段不受影响，键本身没有过期时间
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(run("hget user token"), "(nil)");
+    assert_eq!(run("hget user name"), "alice");
+    assert_eq!(
+        store_manager.hrandfield("user", Some(10)).unwrap(),
+        vec![("name".to_string(), "alice".to_string())]
+    );
+    assert_eq!(run("httl user FIELDS 1 token"), "-2");
+    assert_eq!(store_manager.ttl("user").unwrap(), -1);
+
+    // 主动清理真正删除过期字段，重新设置的字段没有过期时间
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    assert_eq!(store_manager.used_memory_bytes(), "user".len() + "name".len() + "alice".len() + 16);
+    run("hset user token renewed");
+    assert_eq!(run("httl user FIELDS 1 token"), "-1");
+
+    // 秒数为0时立即删除字段，全部字段过期后删除整个键
+    assert_eq!(run("hexpire user 0 FIELDS 1 token"), "2");
+    assert_eq!(run("hexpire user 5 FIELDS 1 name"), "1");
+    clock.advance(Duration::from_secs(5));
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    assert!(!store_manager.get_memory_keys().contains(&"user".to_string()));
+
+    assert!(matches!(handler.parse_command("hexpire user 10 FIELDS 2 name"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("httl user name"), Command::Invalid(_)));
+    run("set plain value");
+    assert!(run("hexpire plain 10 FIELDS 1 name").starts_with("ERROR: WRONGTYPE"));
+}
+
+#[test]
+fn test_hash_with_every_field_expired_is_not_reported() {
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("hset session token abc");
+    run("hset session user alice");
+    assert_eq!(run("hexpire session 10 FIELDS 2 token user"), "1\n1");
+    clock.advance(Duration::from_secs(10));
+
+    // 清理之前字段仍在内存中，但读取和存在性检查都不再看到这个哈希
+    assert_eq!(run("hget session token"), "(nil)");
+    assert!(store_manager.hrandfield("session", Some(10)).unwrap().is_empty());
+    assert!(!store_manager.exists("session"));
+    assert!(store_manager.scan(0, None, 10, Some("hash")).unwrap().1.is_empty());
+}
+
+#[test]
+fn test_getex_sets_or_clears_ttl_while_reading() {
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("set lease holder EX 10");
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(run("getex lease"), "holder");
+    assert_eq!(store_manager.ttl("lease").unwrap(), 5);
+
+    // EX 续期，PERSIST 移除过期时间
+    assert_eq!(run("getex lease EX 100"), "holder");
+    assert_eq!(store_manager.ttl("lease").unwrap(), 100);
+    assert_eq!(run("GETEX lease persist"), "holder");
+    assert_eq!(store_manager.ttl("lease").unwrap(), -1);
+
+    // 不存在的键返回(nil)，不会创建键或过期时间
+    assert_eq!(run("getex missing EX 100"), "(nil)");
+    assert!(!store_manager.exists("missing"));
+    assert_eq!(store_manager.ttl("missing").unwrap(), -2);
+
+    assert!(matches!(handler.parse_command("getex lease EX"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("getex lease EX ten"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("getex lease PERSIST now"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_expiring_soon_lists_keys_by_remaining_ttl() {
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("set later v EX 50");
+    run("set soon v EX 10");
+    run("set sooner v EX 5");
+    run("set distant v EX 1000");
+    run("set persistent v");
+
+    // 只返回时间窗口内过期的键，剩余时间短的在前
+    assert_eq!(store_manager.expiring_soon(60), vec!["sooner", "soon", "later"]);
+    assert_eq!(run("expiringsoon 10"), "sooner\nsoon");
+    assert_eq!(run("keyswithttl"), "distant\nlater\nsoon\nsooner");
+
+    // 已过期的键不再出现
+    clock.advance(Duration::from_secs(6));
+    assert_eq!(run("expiringsoon 10"), "soon");
+    assert_eq!(store_manager.keys_with_ttl(), vec!["distant", "later", "soon"]);
+
+    assert!(matches!(handler.parse_command("expiringsoon"), Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("expiringsoon soon"), Command::Invalid(_)));
+}
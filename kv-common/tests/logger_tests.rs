@@ -0,0 +1,77 @@
+use kv_common::logger;
+use kv_common::store::WriteAheadLog;
+use kv_common::{CommandHandler, StoreManager, TransactionManager};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::thread;
+use tempfile::{tempdir, TempDir};
+
+// 日志系统是全局的，每个测试进程只能初始化一次，本文件的测试共用同一个日志文件
+fn log_file() -> PathBuf {
+    static LOG_DIR: OnceLock<TempDir> = OnceLock::new();
+    let dir = LOG_DIR.get_or_init(|| {
+        let dir = tempdir().unwrap();
+        let log_file = dir.path().join("logs").join("server.log");
+        logger::init_logger(&log_file.to_string_lossy(), "info").unwrap();
+        dir
+    });
+    dir.path().join("logs").join("server.log")
+}
+
+#[test]
+fn test_wal_messages_go_to_configured_log() {
+    let log_file = log_file();
+    let dir = tempdir().unwrap();
+
+    let wal_path = dir.path().join("wal.log");
+    let manager = TransactionManager::new(&wal_path).unwrap();
+    let txn_id = manager.begin_transaction().unwrap();
+    manager.commit_transaction(txn_id).unwrap();
+    WriteAheadLog::new(&wal_path).unwrap().recover().unwrap();
+    // debug级别的消息低于配置的级别，不会被写入
+    log::debug!("debug message should be filtered");
+
+    let contents = fs::read_to_string(&log_file).unwrap();
+    let recovery_line = contents
+        .lines()
+        .find(|line| line.contains("WAL恢复完成"))
+        .unwrap_or_else(|| panic!("恢复日志未写入: {}", contents));
+    assert!(recovery_line.contains("[INFO]"), "{}", recovery_line);
+    assert!(!contents.contains("debug message should be filtered"));
+}
+
+#[test]
+fn test_concurrent_connections_log_distinct_ids() {
+    let log_file = log_file();
+
+    let store_manager = StoreManager::new();
+    let handles: Vec<_> = [101u64, 102u64]
+        .into_iter()
+        .map(|conn_id| {
+            let store_manager = store_manager.clone();
+            thread::spawn(move || {
+                let handler = CommandHandler::new(store_manager, "data/test_storage.dat".to_string())
+                    .with_connection_id(conn_id);
+                for i in 0..5 {
+                    let command = handler.parse_command(&format!("set key{}_{} value", conn_id, i));
+                    assert_eq!(handler.execute_command(command), "OK");
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let contents = fs::read_to_string(&log_file).unwrap();
+    for conn_id in [101u64, 102u64] {
+        let tag = format!("[conn {}]", conn_id);
+        let tagged: Vec<&str> = contents.lines().filter(|line| line.contains(&tag)).collect();
+        assert_eq!(tagged.len(), 5, "连接 {} 的日志行数不正确", conn_id);
+        // 每行只能属于当前连接
+        for line in tagged {
+            assert!(line.contains(&format!("key{}_", conn_id)));
+        }
+    }
+}
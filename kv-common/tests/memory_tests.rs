@@ -0,0 +1,480 @@
+use kv_common::clock::MockClock;
+use kv_common::config::Settings;
+use kv_common::store::{
+    HashOperations, ListOperations, SetOperations, StoreError, StoreOperations, StringOperations,
+};
+use kv_common::{CommandHandler, Store, StoreManager, TransactionManager};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+// 以指定的内存上限和策略创建存储管理器
+fn store_with_memory_limit(dir: &Path, max_memory_bytes: usize, policy: &str) -> StoreManager {
+    let config_path = dir.join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[persistence]\ndata_dir = {:?}\n\n[memory]\nmax_memory_bytes = {}\nmax_memory_policy = \"{}\"\n",
+            dir.join("data").to_string_lossy(),
+            max_memory_bytes,
+            policy
+        ),
+    )
+    .unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    StoreManager::new().with_settings(settings)
+}
+
+#[test]
+fn test_max_memory_bytes_rejects_writes_when_exceeded() {
+    let dir = tempdir().unwrap();
+    let store_manager = store_with_memory_limit(dir.path(), 1000, "reject");
+    let large_value = "x".repeat(450);
+
+    // 加上写入的数据后不超过上限的写入照常执行，之后会超过上限的写入被拒绝
+    store_manager.set_string("first".to_string(), large_value.clone()).unwrap();
+    store_manager.set_string("second".to_string(), large_value.clone()).unwrap();
+    assert!(store_manager.used_memory_bytes() <= 1000);
+    assert!(matches!(
+        store_manager.set_string("third".to_string(), large_value.clone()),
+        Err(StoreError::OutOfMemory)
+    ));
+    assert!(matches!(
+        store_manager.rpush("list".to_string(), "item".repeat(50)),
+        Err(StoreError::OutOfMemory)
+    ));
+    assert_eq!(store_manager.get_string("third").unwrap(), None);
+
+    // 读取和删除不受影响，删除后用量降到上限以下又可以写入
+    assert_eq!(store_manager.get_string("first").unwrap(), Some(large_value.clone()));
+    assert!(store_manager.delete_key("first").unwrap());
+    assert_eq!(store_manager.used_memory_bytes(), "second".len() + large_value.len());
+    store_manager.set_string("third".to_string(), large_value).unwrap();
+}
+
+#[test]
+fn test_max_memory_bytes_offloads_least_accessed_keys() {
+    let dir = tempdir().unwrap();
+    let store_manager = store_with_memory_limit(dir.path(), 1000, "offload");
+    let large_value = "x".repeat(400);
+
+    store_manager.set_string("cold".to_string(), large_value.clone()).unwrap();
+    store_manager.set_string("hot".to_string(), large_value.clone()).unwrap();
+    for _ in 0..5 {
+        store_manager.get_string("hot").unwrap();
+    }
+
+    // 会超过上限的写入先把访问最少的键转移到磁盘
+    store_manager.set_string("new".to_string(), large_value.clone()).unwrap();
+    assert_eq!(store_manager.get_disk_keys(), vec!["cold".to_string()]);
+    assert!(store_manager.get_memory_keys().contains(&"hot".to_string()));
+
+    // 转移的键仍然可以读取
+    assert_eq!(store_manager.get_string("cold").unwrap(), Some(large_value));
+}
+
+#[test]
+fn test_incremental_memory_usage_matches_full_recalculation() {
+    let mut store = Store::new();
+    let check = |store: &Store| assert_eq!(store.memory_usage(), store.recalculate_memory_usage());
+
+    store.set("name".to_string(), "value".repeat(10)).unwrap();
+    store.set("name".to_string(), "short".to_string()).unwrap();
+    store.append("name", " appended").unwrap();
+    check(&store);
+
+    for i in 0..20 {
+        store.rpush("list".to_string(), format!("item{}", i)).unwrap();
+    }
+    store.lpop("list").unwrap();
+    store.rpop("list").unwrap();
+    store.lset("list", 0, "replaced value".to_string()).unwrap();
+    check(&store);
+
+    store.hset("hash".to_string(), "field".to_string(), "v1".to_string()).unwrap();
+    store.hset("hash".to_string(), "other".to_string(), "v2".to_string()).unwrap();
+    store.hdel("hash", "field").unwrap();
+    store.sadd("set".to_string(), vec!["a".to_string(), "b".to_string(), "c".to_string()]).unwrap();
+    store.srem("set", "b").unwrap();
+    store.spop("set", Some(1)).unwrap();
+    check(&store);
+
+    // 覆盖为其他类型、删除以及弹出最后一个元素后统计同样保持一致
+    store.set("hash".to_string(), "now a string".to_string()).unwrap();
+    store.delete("name").unwrap();
+    store.delete("missing").unwrap();
+    while store.lpop("list").unwrap().is_some() {}
+    check(&store);
+
+    store.delete("list").unwrap();
+    store.delete("hash").unwrap();
+    store.delete("set").unwrap();
+    check(&store);
+    assert_eq!(store.memory_usage(), 0);
+}
+
+#[test]
+fn test_eviction_candidate_follows_accesses_and_writes() {
+    let mut store = Store::new();
+    let candidate = |store: &Store, volatile: bool| store.eviction_candidate(volatile).map(|(_, key)| key);
+    assert_eq!(candidate(&store, false), None);
+
+    store.set("a".to_string(), "1".to_string()).unwrap();
+    store.set("b".to_string(), "2".to_string()).unwrap();
+    for _ in 0..3 {
+        store.touch("a");
+    }
+    assert_eq!(candidate(&store, false), Some("b".to_string()));
+
+    // 访问和写入增量调整顺序，删除和转移到磁盘的键不再是候选
+    for _ in 0..5 {
+        store.touch("b");
+    }
+    assert_eq!(candidate(&store, false), Some("a".to_string()));
+    store.mark_as_disk_stored("a");
+    assert_eq!(candidate(&store, false), Some("b".to_string()));
+    store.delete("b").unwrap();
+    assert_eq!(candidate(&store, false), None);
+
+    // volatile只考虑设置了过期时间的键
+    store.set("c".to_string(), "3".to_string()).unwrap();
+    store.set("d".to_string(), "4".to_string()).unwrap();
+    store.touch("d");
+    store.set_expire("d", 100).unwrap();
+    assert_eq!(candidate(&store, false), Some("c".to_string()));
+    assert_eq!(candidate(&store, true), Some("d".to_string()));
+}
+
+#[test]
+fn test_all_keys_lfu_evicts_least_accessed_keys() {
+    let dir = tempdir().unwrap();
+    let store_manager = store_with_memory_limit(dir.path(), 1000, "all_keys_lfu");
+    let value = "x".repeat(200);
+
+    store_manager.set_string("hot".to_string(), value.clone()).unwrap();
+    for _ in 0..10 {
+        store_manager.get_string("hot").unwrap();
+    }
+    store_manager.set_string("cold1".to_string(), value.clone()).unwrap();
+    store_manager.set_string("cold2".to_string(), value.clone()).unwrap();
+    store_manager.set_string("warm".to_string(), value.clone()).unwrap();
+    for _ in 0..3 {
+        store_manager.get_string("warm").unwrap();
+    }
+
+    // 会超过上限的写入删除访问最少的键，而不是转移到磁盘
+    store_manager.set_string("new".to_string(), value.clone()).unwrap();
+    let cold_left = ["cold1", "cold2"]
+        .iter()
+        .filter(|key| store_manager.get_string(key).unwrap().is_some())
+        .count();
+    assert_eq!(cold_left, 1);
+    assert_eq!(store_manager.get_string("hot").unwrap(), Some(value.clone()));
+    assert_eq!(store_manager.get_string("warm").unwrap(), Some(value));
+    assert!(store_manager.get_disk_keys().is_empty());
+}
+
+#[test]
+fn test_volatile_lfu_only_evicts_keys_with_ttl() {
+    let dir = tempdir().unwrap();
+    let store_manager = store_with_memory_limit(dir.path(), 1000, "volatile_lfu");
+    let value = "x".repeat(300);
+
+    store_manager.set_string("persistent".to_string(), value.clone()).unwrap();
+    store_manager.set_string("session".to_string(), value.clone()).unwrap();
+    store_manager.expire("session", 100).unwrap();
+    for _ in 0..10 {
+        store_manager.get_string("session").unwrap();
+    }
+    store_manager.set_string("third".to_string(), value.clone()).unwrap();
+
+    // 只有设置了过期时间的键会被删除，即使它被访问得更多
+    store_manager.set_string("fourth".to_string(), value.clone()).unwrap();
+    assert_eq!(store_manager.get_string("session").unwrap(), None);
+    assert_eq!(store_manager.get_string("persistent").unwrap(), Some(value.clone()));
+
+    // 没有可删除的键时拒绝写入
+    assert!(matches!(
+        store_manager.set_string("fifth".to_string(), value),
+        Err(StoreError::OutOfMemory)
+    ));
+}
+
+#[test]
+fn test_lfu_decay_halves_access_counts_each_period() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[persistence]\ndata_dir = {:?}\n\n[memory]\nlfu_decay_seconds = 10\n",
+            dir.path().join("data").to_string_lossy()
+        ),
+    )
+    .unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_settings(settings).with_clock(clock.clone());
+    let access_count = || store_manager.debug_object("key").unwrap().unwrap().access_count;
+
+    store_manager.set_string("key".to_string(), "value".to_string()).unwrap();
+    for _ in 0..15 {
+        store_manager.get_string("key").unwrap();
+    }
+    let initial = access_count();
+    assert!(initial >= 16);
+
+    // 第一次检查只开始计时，之后每个完整周期减半一次
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    clock.advance(Duration::from_secs(9));
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    assert_eq!(access_count(), initial);
+    clock.advance(Duration::from_secs(1));
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    assert_eq!(access_count(), initial / 2);
+    clock.advance(Duration::from_secs(25));
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+    assert_eq!(access_count(), initial / 2 / 4);
+}
+
+// 目录下(包括子目录)所有转移到磁盘的键文件
+fn offloaded_files(dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(offloaded_files(&path));
+        } else if path.to_string_lossy().ends_with(".json") {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+    files
+}
+
+#[test]
+fn test_lfu_evicts_across_databases_and_decays_counts() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[persistence]\ndata_dir = {:?}\n\n[memory]\nmax_memory_bytes = 1000\nmax_memory_policy = \"all_keys_lfu\"\nlfu_decay_seconds = 10\n",
+            dir.path().join("data").to_string_lossy()
+        ),
+    )
+    .unwrap();
+    let settings = Arc::new(Settings::from_path(&config_path).unwrap());
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_settings(settings).with_clock(clock.clone());
+    let value = "x".repeat(300);
+    store_manager.check_and_offload_low_frequency_data().unwrap();
+
+    // 3号数据库中访问最少的键转移到磁盘后又被读回内存，磁盘文件仍然保留
+    store_manager.select(3).unwrap();
+    store_manager.set_string("cold".to_string(), value.clone()).unwrap();
+    assert_eq!(store_manager.offload_keys_to_disk(&["cold".to_string()]).unwrap(), 1);
+    assert_eq!(store_manager.get_string("cold").unwrap(), Some(value.clone()));
+    assert_eq!(offloaded_files(dir.path()).len(), 1);
+
+    store_manager.select(0).unwrap();
+    store_manager.set_string("hot".to_string(), value.clone()).unwrap();
+    store_manager.set_string("warm".to_string(), value.clone()).unwrap();
+    for _ in 0..15 {
+        store_manager.get_string("hot").unwrap();
+    }
+    for _ in 0..3 {
+        store_manager.get_string("warm").unwrap();
+    }
+    let hot_count = store_manager.debug_object("hot").unwrap().unwrap().access_count;
+
+    // 超过上限时淘汰所有数据库中访问最少的键及其磁盘文件，淘汰前先衰减访问次数
+    clock.advance(Duration::from_secs(10));
+    store_manager.set_string("new".to_string(), value.clone()).unwrap();
+    assert_eq!(store_manager.debug_object("hot").unwrap().unwrap().access_count, hot_count / 2);
+    assert_eq!(store_manager.get_string("warm").unwrap(), Some(value));
+    store_manager.select(3).unwrap();
+    assert_eq!(store_manager.get_string("cold").unwrap(), None);
+    assert!(offloaded_files(dir.path()).is_empty());
+}
+
+#[test]
+fn test_lfu_eviction_survives_restart() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let value = "x".repeat(400);
+
+    let manager = Arc::new(TransactionManager::new(&wal_path).unwrap());
+    let store_manager = store_with_memory_limit(dir.path(), 1000, "all_keys_lfu")
+        .with_transaction_manager(Arc::clone(&manager));
+    store_manager.recover_from_wal().unwrap();
+    let handler = CommandHandler::new(store_manager.clone(), data_file).with_transaction_manager(Arc::clone(&manager));
+    let run = |command: &str| handler.execute_command(handler.parse_command(command));
+    assert_eq!(run(&format!("set cold {}", value)), "OK");
+    assert_eq!(run(&format!("set hot {}", value)), "OK");
+    for _ in 0..5 {
+        run("get hot");
+    }
+    // 写入new时淘汰cold
+    assert_eq!(run(&format!("set new {}", value)), "OK");
+    assert_eq!(run("get cold"), "(nil)");
+    // 其他写命令触发的淘汰同样写入WAL
+    for _ in 0..2 {
+        run("get hot");
+    }
+    assert_eq!(run(&format!("hset user name {}", value)), "1");
+    assert_eq!(run("get new"), "(nil)");
+    drop(handler);
+    drop(store_manager);
+    drop(manager);
+
+    // 重启后从检查点和WAL恢复，被淘汰的键不会回来
+    let manager = Arc::new(TransactionManager::new(&wal_path).unwrap());
+    let store_manager = StoreManager::new().with_transaction_manager(Arc::clone(&manager));
+    store_manager.recover_from_wal().unwrap();
+    assert_eq!(store_manager.get_string("cold").unwrap(), None);
+    assert_eq!(store_manager.get_string("hot").unwrap(), Some(value.clone()));
+    assert_eq!(store_manager.get_string("new").unwrap(), None);
+    assert_eq!(store_manager.hget("user", "name").unwrap(), Some(value));
+}
+
+#[test]
+fn test_rename_loads_offloaded_keys() {
+    let dir = tempdir().unwrap();
+    let store_manager = store_with_memory_limit(dir.path(), 0, "reject");
+
+    store_manager.set_string("cold".to_string(), "value".to_string()).unwrap();
+    store_manager.expire("cold", 100).unwrap();
+    store_manager.offload_keys_to_disk(&["cold".to_string()]).unwrap();
+    assert_eq!(store_manager.get_disk_keys(), vec!["cold".to_string()]);
+
+    // 已转移到磁盘的源键先加载回内存，值和过期时间随键转移
+    store_manager.rename("cold", "renamed").unwrap();
+    assert_eq!(store_manager.get_string("renamed").unwrap(), Some("value".to_string()));
+    assert_eq!(store_manager.get_string("cold").unwrap(), None);
+    assert!(store_manager.ttl("renamed").unwrap() > 0);
+    assert!(store_manager.get_disk_keys().is_empty());
+
+    // 覆盖已转移到磁盘的目标键，目标键的旧值不会再被加载
+    store_manager.set_string("target".to_string(), "old".to_string()).unwrap();
+    store_manager.offload_keys_to_disk(&["target".to_string()]).unwrap();
+    store_manager.rename("renamed", "target").unwrap();
+    assert_eq!(store_manager.get_string("target").unwrap(), Some("value".to_string()));
+    assert!(store_manager.get_disk_keys().is_empty());
+
+    assert!(matches!(store_manager.rename("missing", "other"), Err(StoreError::KeyNotFound(_))));
+}
+
+#[test]
+fn test_scan_type_filter_leaves_offloaded_keys_on_disk() {
+    let dir = tempdir().unwrap();
+    let store_manager = store_with_memory_limit(dir.path(), 0, "reject");
+    let handler = kv_common::CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("set name value");
+    run("rpush jobs a");
+    run("hset user:1 name alice");
+    store_manager.offload_keys_to_disk(&["name".to_string(), "jobs".to_string()]).unwrap();
+
+    // 类型从磁盘文件开头读取，键仍留在磁盘上
+    assert_eq!(run("scan 0 TYPE list"), "0\njobs");
+    assert_eq!(run("scan 0 TYPE string"), "0\nname");
+    assert_eq!(run("scan 0 TYPE hash"), "0\nuser:1");
+    let mut disk_keys = store_manager.get_disk_keys();
+    disk_keys.sort();
+    assert_eq!(disk_keys, vec!["jobs".to_string(), "name".to_string()]);
+}
+
+#[test]
+fn test_idle_keys_lists_only_keys_idle_past_threshold() {
+    let clock = Arc::new(MockClock::new(1_000_000));
+    let store_manager = StoreManager::new().with_clock(clock.clone());
+    let handler = kv_common::CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+
+    store_manager.set_string("idle1".to_string(), "value".to_string()).unwrap();
+    store_manager.set_string("idle2".to_string(), "value".to_string()).unwrap();
+    store_manager.set_string("active".to_string(), "value".to_string()).unwrap();
+    clock.advance(Duration::from_secs(2));
+
+    // 最近访问过的键不算闲置，查询本身不会改变访问时间
+    store_manager.get_string("active").unwrap();
+    let mut idle = store_manager.idle_keys(1);
+    idle.sort();
+    assert_eq!(idle, vec!["idle1", "idle2"]);
+    assert_eq!(store_manager.idle_keys(1).len(), 2);
+    assert!(store_manager.idle_keys(60).is_empty());
+
+    let reply = handler.execute_command(handler.parse_command("idlekeys 1"));
+    assert!(!reply.contains("active") && reply.contains("idle1"), "unexpected reply: {}", reply);
+    assert!(matches!(handler.parse_command("idlekeys"), kv_common::Command::Invalid(_)));
+}
+
+#[test]
+fn test_flushdb_keeps_settings_and_removes_offloaded_files() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[persistence]\ndata_dir = {:?}\n\n[memory]\nmax_memory_bytes = 1000\nmax_memory_policy = \"offload\"\n\n[storage]\nenable_default_expiry = true\ndefault_expiry_seconds = 100\n",
+            dir.path().join("data").to_string_lossy()
+        ),
+    )
+    .unwrap();
+    let store_manager = StoreManager::new().with_settings(Arc::new(Settings::from_path(&config_path).unwrap()));
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let handler = CommandHandler::new(store_manager.clone(), data_file);
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+    let value = "x".repeat(400);
+
+    for key in ["cold", "hot", "new"] {
+        assert_eq!(run(&format!("set {} {}", key, value)), "OK");
+    }
+    assert_eq!(offloaded_files(dir.path()).len(), 1);
+
+    // 清空后转移到磁盘的文件一并删除
+    assert_eq!(run("flushdb"), "OK");
+    assert!(offloaded_files(dir.path()).is_empty());
+    assert_eq!(run("get cold"), "(nil)");
+
+    // 清空后的数据库仍使用原来的配置
+    assert_eq!(run("set fresh v"), "OK");
+    let ttl = store_manager.ttl("fresh").unwrap();
+    assert!(ttl > 0 && ttl <= 100, "ttl {}", ttl);
+}
+
+#[test]
+fn test_flushdb_type_removes_only_matching_keys() {
+    let dir = tempdir().unwrap();
+    let store_manager = store_with_memory_limit(dir.path(), 0, "reject");
+    let handler = kv_common::CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string());
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("set name value");
+    run("rpush jobs a");
+    run("rpush archived b");
+    run("expire jobs 100");
+    run("sadd tags rust");
+    run("hset user:1 name alice");
+    store_manager.offload_keys_to_disk(&["archived".to_string(), "name".to_string()]).unwrap();
+
+    // 内存中和已转移到磁盘的列表都被删除，过期时间一起清除，其他类型的键保留
+    assert_eq!(run("FLUSHDB TYPE list"), "2");
+    assert!(!store_manager.exists("jobs"));
+    assert!(!store_manager.exists("archived"));
+    assert_eq!(store_manager.ttl("jobs").unwrap(), -2);
+    assert_eq!(store_manager.get_disk_keys(), vec!["name".to_string()]);
+    assert_eq!(store_manager.get_string("name").unwrap(), Some("value".to_string()));
+    assert!(store_manager.exists("tags"));
+    assert!(store_manager.exists("user:1"));
+
+    assert_eq!(run("flushdb type list"), "0");
+    assert!(matches!(handler.parse_command("flushdb type"), kv_common::Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("flushdb all"), kv_common::Command::Invalid(_)));
+    assert!(matches!(handler.parse_command("flushdb type foo"), kv_common::Command::Invalid(_)));
+    assert!(run("flushdb type strings").starts_with("ERROR: Unknown type strings"));
+    assert!(store_manager.exists("tags"));
+}
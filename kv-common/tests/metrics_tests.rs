@@ -0,0 +1,110 @@
+use kv_common::metrics::SlowLog;
+use kv_common::{CommandHandler, CommandMetrics, StoreManager};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_command_metrics_counts() {
+    let metrics = Arc::new(CommandMetrics::new());
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string())
+        .with_metrics(Arc::clone(&metrics));
+
+    for i in 0..5 {
+        let command = handler.parse_command(&format!("set key{} value{}", i, i));
+        handler.execute_command(command);
+    }
+    for i in 0..3 {
+        let command = handler.parse_command(&format!("get key{}", i));
+        handler.execute_command(command);
+    }
+
+    assert_eq!(metrics.get("set").unwrap().count, 5);
+    assert_eq!(metrics.get("get").unwrap().count, 3);
+    assert!(metrics.get("del").is_none());
+
+    let output = handler.execute_command(handler.parse_command("stats commands"));
+    assert!(output.contains("set: calls=5"));
+    assert!(output.contains("get: calls=3"));
+}
+
+#[test]
+fn test_command_metrics_disabled() {
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string());
+    let output = handler.execute_command(handler.parse_command("stats commands"));
+    assert!(output.starts_with("ERROR"));
+}
+
+#[test]
+fn test_latency_percentiles() {
+    let metrics = CommandMetrics::new();
+    for _ in 0..99 {
+        metrics.record("get", Duration::from_micros(5));
+    }
+    metrics.record("get", Duration::from_millis(200));
+
+    let stat = metrics.get("get").unwrap();
+    assert_eq!(stat.count, 100);
+    assert_eq!(stat.p99_micros(), 10);
+    assert_eq!(stat.max_micros, 200_000);
+    assert!(stat.average_micros() > 5.0);
+}
+
+#[test]
+fn test_slowlog_records_slow_commands() {
+    let slowlog = Arc::new(SlowLog::new(Duration::from_millis(10), 16));
+    let handler = CommandHandler::new(StoreManager::new(), "data/test_storage.dat".to_string())
+        .with_slowlog(Arc::clone(&slowlog));
+
+    handler.execute_command(handler.parse_command("set fast value"));
+    assert!(slowlog.is_empty());
+
+    assert_eq!(handler.execute_command(handler.parse_command("debug sleep 0.05")), "OK");
+    assert_eq!(slowlog.len(), 1);
+    let entry = &slowlog.get(1)[0];
+    assert!(entry.duration_micros >= 50_000);
+    assert!(entry.command.contains("DebugSleep"));
+
+    let output = handler.execute_command(handler.parse_command("slowlog get 5"));
+    assert!(output.contains("DebugSleep"));
+
+    assert_eq!(handler.execute_command(handler.parse_command("slowlog reset")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("slowlog get")), "(empty list)");
+}
+
+#[test]
+fn test_slowlog_is_bounded() {
+    let slowlog = SlowLog::new(Duration::ZERO, 3);
+    for i in 0..5 {
+        slowlog.record(format!("cmd{}", i), Duration::from_millis(1));
+    }
+    let entries = slowlog.get(10);
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].command, "cmd4");
+    assert_eq!(entries[2].command, "cmd2");
+}
+
+#[test]
+fn test_reset_stats_clears_key_and_command_counters() {
+    let metrics = Arc::new(CommandMetrics::new());
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), "data/test_storage.dat".to_string())
+        .with_metrics(Arc::clone(&metrics));
+    let run = |line: &str| handler.execute_command(handler.parse_command(line));
+
+    run("set hot value");
+    for _ in 0..5 {
+        run("get hot");
+    }
+    let access_count = |key: &str| store_manager.debug_object(key).unwrap().unwrap().access_count;
+    assert!(access_count("hot") >= 5);
+
+    assert_eq!(run("resetstats hot"), "1");
+    assert_eq!(access_count("hot"), 0);
+    assert_eq!(run("resetstats missing"), "0");
+
+    // 不带参数时清空命令执行指标
+    assert!(metrics.get("get").is_some());
+    assert_eq!(run("resetstats"), "OK");
+    assert!(metrics.get("get").is_none());
+    assert!(run("resetstats a b").starts_with("ERROR:"));
+}
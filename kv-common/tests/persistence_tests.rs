@@ -0,0 +1,421 @@
+use kv_common::config::Settings;
+use kv_common::{Command, CommandHandler, StoreManager};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tempfile::tempdir;
+
+// 以指定的持久化配置加载设置
+fn settings_with(dir: &Path, persistence: &str) -> Arc<Settings> {
+    let config_path = dir.join("config.toml");
+    fs::write(&config_path, format!("[persistence]\n{}\n", persistence)).unwrap();
+    Arc::new(Settings::from_path(&config_path).unwrap())
+}
+
+#[test]
+fn test_corrupt_data_file_fails_by_default() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat");
+    fs::write(&data_file, "{not valid json").unwrap();
+
+    let store_manager = StoreManager::new().with_settings(settings_with(dir.path(), ""));
+    assert!(store_manager.load_from_file(&data_file.to_string_lossy()).is_err());
+
+    // 默认不修改损坏的文件
+    assert_eq!(fs::read_to_string(&data_file).unwrap(), "{not valid json");
+}
+
+#[test]
+fn test_corrupt_data_file_is_quarantined() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat");
+    fs::write(&data_file, "{not valid json").unwrap();
+
+    let settings = settings_with(dir.path(), "on_corrupt_data_file = \"quarantine\"");
+    let store_manager = StoreManager::new().with_settings(settings);
+    store_manager.load_from_file(&data_file.to_string_lossy()).unwrap();
+
+    // 损坏的文件被改名隔离，存储为空且可以继续使用
+    assert!(!data_file.exists());
+    let quarantined: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("storage.dat.corrupt-"))
+        .collect();
+    assert_eq!(quarantined.len(), 1);
+    assert_eq!(
+        fs::read_to_string(dir.path().join(&quarantined[0])).unwrap(),
+        "{not valid json"
+    );
+    assert_eq!(store_manager.get_string("any").unwrap(), None);
+
+    store_manager.set_string("key".to_string(), "value".to_string()).unwrap();
+    store_manager.save_to_file(&data_file.to_string_lossy()).unwrap();
+    assert!(data_file.exists());
+}
+
+#[test]
+fn test_interrupted_save_keeps_previous_snapshot() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat");
+    let data_path = data_file.to_string_lossy().to_string();
+
+    let store_manager = StoreManager::new();
+    store_manager.set_string("key".to_string(), "v1".to_string()).unwrap();
+    store_manager.save_to_file(&data_path).unwrap();
+    let snapshot = fs::read_to_string(&data_file).unwrap();
+
+    // 模拟写入新快照时崩溃：只留下写了一半的临时文件
+    store_manager.set_string("key".to_string(), "v2".to_string()).unwrap();
+    let temp_file = dir.path().join("storage.dat.1.0.tmp");
+    fs::write(&temp_file, &snapshot[..snapshot.len() / 2]).unwrap();
+
+    // 数据文件仍是上一个完整快照
+    let recovered = StoreManager::new();
+    recovered.load_from_file(&data_path).unwrap();
+    assert_eq!(recovered.get_string("key").unwrap(), Some("v1".to_string()));
+
+    // 下一次保存使用自己的临时文件替换数据文件，成功后不留下新的临时文件
+    store_manager.save_to_file(&data_path).unwrap();
+    let temp_files: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+        .collect();
+    assert_eq!(temp_files, vec![temp_file]);
+    let recovered = StoreManager::new();
+    recovered.load_from_file(&data_path).unwrap();
+    assert_eq!(recovered.get_string("key").unwrap(), Some("v2".to_string()));
+}
+
+#[test]
+fn test_concurrent_saves_to_one_file_use_separate_temp_files() {
+    let dir = tempdir().unwrap();
+    let data_path = dir.path().join("storage.dat").to_string_lossy().to_string();
+
+    // 不同的存储同时保存到同一个数据文件，临时文件互不覆盖，每次保存都成功
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let data_path = data_path.clone();
+            thread::spawn(move || {
+                let store_manager = StoreManager::new();
+                store_manager.set_string("key".to_string(), "v".repeat(10_000 + i)).unwrap();
+                for _ in 0..20 {
+                    store_manager.save_to_file(&data_path).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let recovered = StoreManager::new();
+    recovered.load_from_file(&data_path).unwrap();
+    assert!(recovered.get_string("key").unwrap().is_some_and(|value| value.len() >= 10_000));
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_per_database_files_restore_independently() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat");
+    let data_path = data_file.to_string_lossy().to_string();
+    let settings = settings_with(dir.path(), "per_database_files = true");
+
+    let store_manager = StoreManager::new().with_settings(Arc::clone(&settings));
+    store_manager.set_string("zero".to_string(), "0".to_string()).unwrap();
+    store_manager.select(1).unwrap();
+    store_manager.set_string("one".to_string(), "1".to_string()).unwrap();
+    store_manager.save_to_file(&data_path).unwrap();
+
+    // 每个数据库一个文件，不再写入合并的数据文件
+    assert!(dir.path().join("storage.0.dat").exists());
+    assert!(dir.path().join("storage.1.dat").exists());
+    assert!(!data_file.exists());
+
+    // 删除0号数据库的文件后重新加载，只有1号数据库的数据保留
+    fs::remove_file(dir.path().join("storage.0.dat")).unwrap();
+    let reloaded = StoreManager::new().with_settings(settings);
+    reloaded.load_from_file(&data_path).unwrap();
+    assert_eq!(reloaded.get_string("zero").unwrap(), None);
+    reloaded.select(1).unwrap();
+    assert_eq!(reloaded.get_string("one").unwrap(), Some("1".to_string()));
+    assert_eq!(reloaded.get_string("zero").unwrap(), None);
+}
+
+#[test]
+fn test_single_data_file_keeps_every_database() {
+    let dir = tempdir().unwrap();
+    let data_path = dir.path().join("storage.dat").to_string_lossy().to_string();
+
+    let store_manager = StoreManager::new();
+    store_manager.set_string("zero".to_string(), "0".to_string()).unwrap();
+    store_manager.select(2).unwrap();
+    store_manager.set_string("two".to_string(), "2".to_string()).unwrap();
+    store_manager.expire("two", 100).unwrap();
+    store_manager.swap_db(2, 3).unwrap();
+    store_manager.save_to_file(&data_path).unwrap();
+
+    // 默认只写一个数据文件，重新加载后每个数据库的内容和过期时间都保留
+    assert!(!dir.path().join("storage.0.dat").exists());
+    let reloaded = StoreManager::new();
+    reloaded.load_from_file(&data_path).unwrap();
+    assert_eq!(reloaded.get_string("zero").unwrap(), Some("0".to_string()));
+    reloaded.select(2).unwrap();
+    assert_eq!(reloaded.get_string("two").unwrap(), None);
+    reloaded.select(3).unwrap();
+    assert_eq!(reloaded.get_string("two").unwrap(), Some("2".to_string()));
+    assert!((99..=100).contains(&reloaded.get_ttl("two").unwrap()));
+}
+
+#[test]
+fn test_save_reports_size_and_bgsave_job_status() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat");
+    let handler = CommandHandler::new(StoreManager::new(), data_file.to_string_lossy().to_string());
+    handler.execute_command(Command::Set("key".to_string(), "value".to_string()));
+
+    // SAVE返回的字节数与磁盘上的文件大小一致
+    let result = handler.execute_command(Command::Save);
+    let bytes: u64 = result
+        .strip_prefix("Saved ")
+        .and_then(|rest| rest.split(' ').next())
+        .and_then(|bytes| bytes.parse().ok())
+        .unwrap_or_else(|| panic!("unexpected: {}", result));
+    assert!(result.ends_with("ms"));
+    assert_eq!(bytes, fs::metadata(&data_file).unwrap().len());
+
+    // BGSAVE返回任务ID，可以查询完成状态
+    let result = handler.execute_command(Command::BgSave);
+    let job_id: u64 = result.rsplit(' ').next().unwrap().parse().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let status = loop {
+        let status = handler.execute_command(handler.parse_command(&format!("bgsave status {}", job_id)));
+        if status != "running" || Instant::now() > deadline {
+            break status;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(
+        status.split(" in ").next().unwrap(),
+        format!("completed: saved {} bytes", bytes)
+    );
+
+    let result = handler.execute_command(Command::BgSaveStatus(job_id + 100));
+    assert!(result.starts_with("ERROR: ERR unknown background save job"));
+}
+
+#[test]
+fn test_lastsave_advances_after_bgsave() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat");
+    let handler = CommandHandler::new(StoreManager::new(), data_file.to_string_lossy().to_string());
+
+    assert_eq!(handler.execute_command(handler.parse_command("lastsave")), "0");
+
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    handler.execute_command(Command::BgSave);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while handler.execute_command(Command::LastSave) == "0" && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let last_save: u64 = handler.execute_command(Command::LastSave).parse().unwrap();
+    assert!(last_save >= before);
+    assert!(data_file.exists());
+}
+
+#[test]
+fn test_failed_bgsave_is_flagged() {
+    let dir = tempdir().unwrap();
+    // 数据文件的父路径是普通文件，保存必然失败
+    let blocker = dir.path().join("blocker");
+    fs::write(&blocker, "").unwrap();
+    let data_file = blocker.join("storage.dat");
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), data_file.to_string_lossy().to_string());
+
+    handler.execute_command(Command::BgSave);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !store_manager.last_bgsave_failed() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(store_manager.last_bgsave_failed());
+    assert_eq!(store_manager.last_save_time(), None);
+    assert_eq!(
+        handler.execute_command(Command::LastSave),
+        "0 (last background save failed)"
+    );
+}
+
+#[test]
+fn test_binary_value_round_trip() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let handler = CommandHandler::new(StoreManager::new(), data_file.clone());
+
+    // 0xDE 0xAD 0xBE 0xEF 0x00 0xFF 不是合法的UTF-8
+    let blob: Vec<u8> = vec![0xde, 0xad, 0xbe, 0xef, 0x00, 0xff];
+    let encoded = "3q2+7wD/";
+    assert!(matches!(handler.parse_command(&format!("setbin blob {}", encoded)), Command::SetBin(_, ref value) if *value == blob));
+    assert_eq!(handler.execute_command(handler.parse_command(&format!("setbin blob {}", encoded))), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("getbin blob")), encoded);
+    assert!(handler.execute_command(handler.parse_command("get blob")).starts_with("ERROR: WRONGTYPE"));
+
+    // UTF-8数据仍按字符串保存
+    handler.execute_command(handler.parse_command("setbin text aGVsbG8="));
+    assert_eq!(handler.execute_command(handler.parse_command("get text")), "hello");
+    assert!(matches!(handler.parse_command("setbin blob not-base64!"), Command::Invalid(_)));
+
+    // 保存后重新加载，字节内容不变
+    handler.execute_command(Command::Save);
+    let reloaded = StoreManager::new();
+    reloaded.load_from_file(&data_file).unwrap();
+    assert_eq!(reloaded.get_bytes("blob").unwrap(), Some(blob));
+    assert_eq!(reloaded.get_string("text").unwrap(), Some("hello".to_string()));
+}
+
+#[test]
+fn test_compressed_offload_round_trip() {
+    let dir = tempdir().unwrap();
+    let large_value = "compressible value ".repeat(2000);
+
+    // 分别以未压缩和压缩方式转移同一个值
+    let mut file_sizes = Vec::new();
+    for (name, compress) in [("plain", false), ("compressed", true)] {
+        let data_dir = dir.path().join(name);
+        let settings = settings_with(
+            dir.path(),
+            &format!(
+                "data_dir = {:?}\n\n[memory]\ncompress_offload = {}",
+                data_dir.to_string_lossy(),
+                compress
+            ),
+        );
+        let offload_dir = settings.data_paths().offload_dir;
+        let store_manager = StoreManager::new()
+            .with_settings(Arc::clone(&settings))
+            .with_memory_optimization(true, u64::MAX, 0, 0, &offload_dir.to_string_lossy());
+        store_manager.set_string("big".to_string(), large_value.clone()).unwrap();
+        assert_eq!(store_manager.check_and_offload_low_frequency_data().unwrap(), 1);
+
+        let files: Vec<_> = fs::read_dir(&offload_dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(files.len(), 1);
+        let file_name = files[0].to_string_lossy().to_string();
+        assert_eq!(file_name.ends_with(".json.gz"), compress, "{}", file_name);
+        file_sizes.push(fs::metadata(&files[0]).unwrap().len());
+
+        assert_eq!(store_manager.get_string("big").unwrap(), Some(large_value.clone()));
+    }
+    assert!(file_sizes[1] < file_sizes[0], "{:?}", file_sizes);
+}
+
+#[test]
+fn test_export_import_round_trip() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let export_file = dir.path().join("export.jsonl").to_string_lossy().to_string();
+    let source = CommandHandler::new(StoreManager::new(), data_file.clone());
+    for command in [
+        "set greeting \"hello world\"",
+        "setbin blob 3q2+7wD/",
+        "rpush queue a",
+        "rpush queue b",
+        "hset user name alice",
+        "sadd tags x",
+        "sadd tags y",
+        "expire greeting 100",
+    ] {
+        assert!(!source.execute_command(source.parse_command(command)).starts_with("ERROR"), "{}", command);
+    }
+
+    // 每个键一行，值保留原有的数据类型
+    let exported = source.execute_command(source.parse_command("export"));
+    let lines: Vec<&str> = exported.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", exported);
+    assert!(lines[1].starts_with("{\"key\":\"greeting\",\"value\":{\"String\":\"hello world\"},\"ttl\":"), "{}", lines[1]);
+    assert_eq!(lines[2], "{\"key\":\"queue\",\"value\":{\"List\":[\"a\",\"b\"]}}");
+    assert_eq!(source.execute_command(source.parse_command(&format!("export {}", export_file))), "5");
+
+    let target_manager = StoreManager::new();
+    let target = CommandHandler::new(target_manager.clone(), data_file);
+    target.execute_command(target.parse_command("set queue stale"));
+    assert_eq!(target.execute_command(target.parse_command(&format!("import {}", export_file))), "5");
+
+    assert_eq!(target.execute_command(target.parse_command("get greeting")), "hello world");
+    assert_eq!(target.execute_command(target.parse_command("getbin blob")), "3q2+7wD/");
+    assert_eq!(target.execute_command(target.parse_command("range queue 0 -1")), "a\nb");
+    assert_eq!(target.execute_command(target.parse_command("hget user name")), "alice");
+    assert_eq!(target.execute_command(target.parse_command("sismember tags y")), "1");
+    let ttl = target_manager.get_ttl("greeting").unwrap();
+    assert!((99..=100).contains(&ttl), "{}", ttl);
+    assert_eq!(target_manager.get_ttl("queue").unwrap(), -1);
+    assert_eq!(target.execute_command(target.parse_command("export")).lines().count(), 5);
+
+    // 格式错误的文件不会导入任何数据
+    fs::write(&export_file, "{\"key\":\"a\",\"value\":{\"String\":\"1\"}}\nnot json\n").unwrap();
+    assert!(target.execute_command(target.parse_command(&format!("import {}", export_file))).starts_with("ERROR:"));
+    assert_eq!(target.execute_command(target.parse_command("get a")), "(nil)");
+}
+
+#[test]
+fn test_debug_reload_round_trips_values_and_ttl() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), data_file.clone());
+    for command in [
+        "set greeting \"hello world\"",
+        "set counter 42",
+        "rpush queue a",
+        "rpush queue b",
+        "rpush queue c",
+        "set session token",
+        "expire session 100",
+    ] {
+        assert!(!handler.execute_command(handler.parse_command(command)).starts_with("ERROR"), "{}", command);
+    }
+
+    assert_eq!(handler.execute_command(handler.parse_command("debug reload")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("get greeting")), "hello world");
+    assert_eq!(handler.execute_command(handler.parse_command("get counter")), "42");
+    assert_eq!(handler.execute_command(handler.parse_command("range queue 0 -1")), "a\nb\nc");
+    assert_eq!(handler.execute_command(handler.parse_command("get session")), "token");
+    let ttl = store_manager.get_ttl("session").unwrap();
+    assert!((99..=100).contains(&ttl), "{}", ttl);
+    assert_eq!(store_manager.get_ttl("greeting").unwrap(), -1);
+
+    // 数据文件本身也保存了过期时间，重启后加载同样保留
+    let restarted = StoreManager::new();
+    restarted.load_from_file(&data_file).unwrap();
+    assert_eq!(restarted.get_string("session").unwrap(), Some("token".to_string()));
+    assert!((99..=100).contains(&restarted.get_ttl("session").unwrap()));
+
+    assert!(matches!(handler.parse_command("debug reload now"), Command::Invalid(_)));
+}
+
+#[test]
+fn test_debug_reload_covers_every_database() {
+    let dir = tempdir().unwrap();
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let store_manager = StoreManager::new();
+    let handler = CommandHandler::new(store_manager.clone(), data_file.clone());
+    for command in ["set zero 0", "select 4", "set four 4", "rpush queue a", "select 0"] {
+        assert!(!handler.execute_command(handler.parse_command(command)).starts_with("ERROR"), "{}", command);
+    }
+
+    assert_eq!(handler.execute_command(handler.parse_command("debug reload")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("select 4")), "OK");
+    assert_eq!(handler.execute_command(handler.parse_command("get four")), "4");
+    assert_eq!(handler.execute_command(handler.parse_command("range queue 0 -1")), "a");
+
+    // 保存的数据文件包含4号数据库，重启后同样可以加载
+    let restarted = StoreManager::new();
+    restarted.load_from_file(&data_file).unwrap();
+    restarted.select(4).unwrap();
+    assert_eq!(restarted.get_string("four").unwrap(), Some("4".to_string()));
+}
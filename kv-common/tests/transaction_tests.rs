@@ -0,0 +1,431 @@
+use kv_common::clock::MockClock;
+use kv_common::config::WalSyncPolicy;
+use kv_common::store::{HashOperations, ListOperations, LogCommand, SetOperations, StoreOperation, TransactionState, WriteAheadLog};
+use kv_common::{CommandHandler, Store, StoreManager, TransactionCommandHandler, TransactionManager};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[test]
+fn test_transaction_timeout_with_mock_clock() {
+    let dir = tempdir().unwrap();
+    let clock = Arc::new(MockClock::new(1_700_000_000_000));
+    let manager = TransactionManager::new(&dir.path().join("wal.log"))
+        .unwrap()
+        .with_clock(clock.clone());
+
+    let stale = manager.begin_transaction().unwrap();
+    clock.advance(Duration::from_secs(20));
+    let fresh = manager.begin_transaction().unwrap();
+
+    // 未超过超时时间的事务不会被回滚
+    clock.advance(Duration::from_secs(20));
+    assert_eq!(manager.check_transaction_timeouts(30).unwrap(), vec![stale]);
+    assert_eq!(manager.get_transaction_state(fresh), Some(TransactionState::Active));
+
+    clock.advance(Duration::from_secs(11));
+    assert_eq!(manager.check_transaction_timeouts(30).unwrap(), vec![fresh]);
+}
+
+#[test]
+fn test_transaction_timestamps_with_mock_clock() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let clock = Arc::new(MockClock::new(1_700_000_000_000));
+    let manager = TransactionManager::new(&wal_path).unwrap().with_clock(clock.clone());
+
+    let txn_id = manager.begin_transaction().unwrap();
+    assert_eq!(manager.get_transaction(txn_id).unwrap().start_time, 1_700_000_000);
+
+    clock.advance(Duration::from_secs(3));
+    manager
+        .execute_operation_with_old_value(
+            txn_id,
+            StoreOperation::Set("key".to_string(), "value".to_string()),
+            None,
+            None,
+        )
+        .unwrap();
+    clock.advance(Duration::from_secs(4));
+    manager.commit_transaction(txn_id).unwrap();
+
+    let entries: Vec<(LogCommand, u64)> = WriteAheadLog::new(&wal_path)
+        .unwrap()
+        .load_entries()
+        .unwrap()
+        .into_iter()
+        .filter(|entry| entry.id == txn_id)
+        .map(|entry| (entry.command, entry.timestamp))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (LogCommand::Begin, 1_700_000_000),
+            (LogCommand::Put, 1_700_000_003),
+            (LogCommand::Prepare, 1_700_000_007),
+            (LogCommand::Commit, 1_700_000_007),
+        ]
+    );
+
+    // 从日志重建的已完成事务使用日志中的时间戳
+    let completed = manager.get_completed_transactions().unwrap();
+    let txn = completed.iter().find(|txn| txn.id == txn_id).unwrap();
+    assert_eq!(txn.start_time, 1_700_000_000);
+    assert_eq!(txn.end_time, Some(1_700_000_007));
+}
+
+#[test]
+fn test_txinfo_reports_active_transaction() {
+    let dir = tempdir().unwrap();
+    let clock = Arc::new(MockClock::new(1_700_000_000_000));
+    let manager = TransactionManager::new(&dir.path().join("wal.log"))
+        .unwrap()
+        .with_clock(clock.clone());
+    let handler = TransactionCommandHandler::from_manager(Arc::new(manager));
+
+    handler.begin().unwrap();
+    let txn_id = handler.current_transaction_id().unwrap();
+    handler.execute_operation(StoreOperation::Set("a".to_string(), "1".to_string())).unwrap();
+    handler.execute_operation(StoreOperation::Delete("b".to_string())).unwrap();
+    clock.advance(Duration::from_secs(5));
+
+    assert_eq!(
+        handler.txinfo(txn_id).unwrap(),
+        format!(
+            "id: {}\nstate: Active\nstart_time: 1700000000\noperations: 2\nelapsed_seconds: 5",
+            txn_id
+        )
+    );
+    assert!(handler.txinfo(txn_id + 1).is_err());
+
+    // 同一连接的后续命令能查到之前开始的事务
+    let data_file = dir.path().join("storage.dat").to_string_lossy().to_string();
+    let command_handler = CommandHandler::new(StoreManager::new(), data_file);
+    let reply = command_handler.execute_command(command_handler.parse_command("begin"));
+    let txn_id: String = reply.chars().filter(|c| c.is_ascii_digit()).collect();
+    let info = command_handler.execute_command(command_handler.parse_command(&format!("txinfo {}", txn_id)));
+    assert!(info.contains("state: Active"), "{}", info);
+    assert!(info.contains("operations: 0"), "{}", info);
+    assert_eq!(command_handler.execute_command(command_handler.parse_command("rollback")), format!("事务{}已回滚", txn_id));
+    assert!(command_handler.execute_command(command_handler.parse_command(&format!("txinfo {}", txn_id))).starts_with("ERROR:"));
+}
+
+#[test]
+fn test_transaction_ids_continue_from_wal() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+
+    // 空日志从1开始分配
+    let manager = TransactionManager::new(&wal_path).unwrap();
+    assert_eq!(manager.begin_transaction().unwrap(), 1);
+    drop(manager);
+
+    {
+        let mut wal = WriteAheadLog::new(&wal_path).unwrap();
+        for txn_id in [7, 41] {
+            wal.begin(txn_id).unwrap();
+            wal.commit(txn_id).unwrap();
+        }
+    }
+
+    // 重新打开后下一个ID恰好是日志中最大ID加一，与启动时间无关
+    let manager = TransactionManager::new(&wal_path).unwrap();
+    assert_eq!(manager.begin_transaction().unwrap(), 42);
+    assert_eq!(manager.begin_transaction().unwrap(), 43);
+    // BEGIN记录在提交时才写入WAL，只有写入过日志的事务ID会在重启后被跳过
+    manager.commit_transaction(43).unwrap();
+    drop(manager);
+
+    let manager = TransactionManager::new(&wal_path).unwrap();
+    assert_eq!(manager.begin_transaction().unwrap(), 44);
+}
+
+#[test]
+fn test_completed_transactions_include_collection_operations() {
+    let dir = tempdir().unwrap();
+    let manager = TransactionManager::new(&dir.path().join("wal.log")).unwrap();
+
+    let txn_id = manager.begin_transaction().unwrap();
+    let operations = vec![
+        StoreOperation::Set("plain".to_string(), "1".to_string()),
+        StoreOperation::LPush("queue".to_string(), "job".to_string()),
+        StoreOperation::HSet("user:1".to_string(), "name".to_string(), "alice".to_string()),
+        StoreOperation::SAdd("tags".to_string(), "red".to_string()),
+        StoreOperation::HDel("user:1".to_string(), "age".to_string()),
+        StoreOperation::RPop("queue".to_string()),
+    ];
+    for operation in &operations {
+        manager.execute_operation_with_old_value(txn_id, operation.clone(), None, None).unwrap();
+    }
+    manager.commit_transaction(txn_id).unwrap();
+
+    // 从WAL重建的事务按日志顺序保留列表、哈希和集合操作，键中的冒号不影响解析
+    let completed = manager.get_completed_transactions().unwrap();
+    let txn = completed.iter().find(|txn| txn.id == txn_id).unwrap();
+    assert_eq!(txn.operations, operations);
+}
+
+#[test]
+fn test_recover_store_rebuilds_lists_and_hashes() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let manager = TransactionManager::new(&wal_path).unwrap();
+
+    let txn_id = manager.begin_transaction().unwrap();
+    for operation in [
+        StoreOperation::RPush("queue".to_string(), "a".to_string()),
+        StoreOperation::RPush("queue".to_string(), "b".to_string()),
+        StoreOperation::LPush("queue".to_string(), "c".to_string()),
+        StoreOperation::HSet("user:1".to_string(), "name".to_string(), "alice".to_string()),
+        StoreOperation::HSet("user:1".to_string(), "age".to_string(), "30".to_string()),
+        StoreOperation::HDel("user:1".to_string(), "age".to_string()),
+        StoreOperation::SAdd("tags".to_string(), "red".to_string()),
+        StoreOperation::Set("plain".to_string(), "1".to_string()),
+    ] {
+        manager.execute_operation_with_old_value(txn_id, operation, None, None).unwrap();
+    }
+    manager.commit_transaction(txn_id).unwrap();
+
+    // 回滚的事务不会被重放
+    let txn_id = manager.begin_transaction().unwrap();
+    manager
+        .execute_operation_with_old_value(txn_id, StoreOperation::RPush("queue".to_string(), "x".to_string()), None, None)
+        .unwrap();
+    manager.rollback_transaction(txn_id).unwrap();
+    drop(manager);
+
+    // 恢复结果按数据类型重建，而不是list:queue这样的字符串键
+    let store = WriteAheadLog::new(&wal_path).unwrap().recover_store().unwrap();
+    let mut keys = store.get_all_keys();
+    keys.sort();
+    assert_eq!(keys, vec!["plain", "queue", "tags", "user:1"]);
+    assert_eq!(store.range("queue", 0, -1), vec!["c", "a", "b"]);
+    assert_eq!(store.hget("user:1", "name").unwrap(), Some("alice".to_string()));
+    assert_eq!(store.hget("user:1", "age").unwrap(), None);
+    assert!(store.sismember("tags", "red").unwrap());
+    assert_eq!(store.get_string("plain"), Some("1".to_string()));
+}
+
+#[test]
+fn test_recover_store_keeps_types_after_checkpoint_and_compaction() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let manager = TransactionManager::new(&wal_path).unwrap();
+
+    let txn_id = manager.begin_transaction().unwrap();
+    for operation in [
+        StoreOperation::RPush("queue".to_string(), "a".to_string()),
+        StoreOperation::RPush("queue".to_string(), "b".to_string()),
+        StoreOperation::HSet("user:1".to_string(), "name".to_string(), "alice".to_string()),
+        StoreOperation::SAdd("tags".to_string(), "red".to_string()),
+    ] {
+        manager.execute_operation_with_old_value(txn_id, operation, None, None).unwrap();
+    }
+    manager.commit_transaction(txn_id).unwrap();
+    manager.create_checkpoint(None).unwrap();
+
+    // 检查点之后的事务在压缩后仍保留在日志中，恢复时应用在检查点数据之上
+    let txn_id = manager.begin_transaction().unwrap();
+    manager
+        .execute_operation_with_old_value(txn_id, StoreOperation::RPush("queue".to_string(), "c".to_string()), None, None)
+        .unwrap();
+    manager.commit_transaction(txn_id).unwrap();
+    manager.compact_wal().unwrap();
+    drop(manager);
+
+    let store = WriteAheadLog::new(&wal_path).unwrap().recover_store().unwrap();
+    let mut keys = store.get_all_keys();
+    keys.sort();
+    assert_eq!(keys, vec!["queue", "tags", "user:1"]);
+    assert_eq!(store.range("queue", 0, -1), vec!["a", "b", "c"]);
+    assert_eq!(store.hget("user:1", "name").unwrap(), Some("alice".to_string()));
+    assert!(store.sismember("tags", "red").unwrap());
+}
+
+#[test]
+fn test_two_phase_commit() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let store = Arc::new(Mutex::new(Store::new()));
+    let mut manager = TransactionManager::new(&wal_path).unwrap();
+    manager.set_store(Arc::clone(&store));
+
+    let txn_id = manager.begin_transaction().unwrap();
+    manager.execute_operation(txn_id, StoreOperation::Set("a".to_string(), "1".to_string())).unwrap();
+    manager.execute_operation(txn_id, StoreOperation::SAdd("s".to_string(), "x".to_string())).unwrap();
+    manager.prepare_transaction(txn_id).unwrap();
+    assert_eq!(manager.get_transaction_state(txn_id), Some(TransactionState::Prepared));
+
+    // 准备阶段之后不能再添加操作，也不会提前写入存储
+    assert!(manager.execute_operation(txn_id, StoreOperation::Delete("a".to_string())).is_err());
+    assert_eq!(store.lock().unwrap().get_string("a"), None);
+
+    // 重启后准备状态从WAL恢复
+    let reopened = TransactionManager::new(&wal_path).unwrap();
+    assert_eq!(reopened.get_transaction_state(txn_id), Some(TransactionState::Prepared));
+    drop(reopened);
+
+    assert!(manager.commit_transaction(txn_id).unwrap());
+    assert_eq!(store.lock().unwrap().get_string("a"), Some("1".to_string()));
+    assert!(store.lock().unwrap().sismember("s", "x").unwrap());
+
+    // 类型冲突的事务无法通过准备阶段，也不能提交
+    let txn_id = manager.begin_transaction().unwrap();
+    manager.execute_operation(txn_id, StoreOperation::LPop("a".to_string())).unwrap();
+    assert!(manager.prepare_transaction(txn_id).is_err());
+    assert_eq!(manager.get_transaction_state(txn_id), Some(TransactionState::Active));
+    assert!(manager.commit_transaction(txn_id).is_err());
+    manager.rollback_transaction(txn_id).unwrap();
+}
+
+#[test]
+fn test_commit_validates_against_current_store() {
+    let dir = tempdir().unwrap();
+    let store = Arc::new(Mutex::new(Store::new()));
+    let mut manager = TransactionManager::new(&dir.path().join("wal.log")).unwrap();
+    manager.set_store(Arc::clone(&store));
+
+    let txn_id = manager.begin_transaction().unwrap();
+    manager.execute_operation(txn_id, StoreOperation::LPop("queue".to_string())).unwrap();
+    manager.execute_operation(txn_id, StoreOperation::Set("other".to_string(), "1".to_string())).unwrap();
+
+    // 事务开始后其他写入改变了键的类型，提交时按存储中的当前数据校验
+    store.lock().unwrap().set_string("queue".to_string(), "plain".to_string());
+    assert!(manager.commit_transaction(txn_id).is_err());
+    assert_eq!(store.lock().unwrap().get_string("queue"), Some("plain".to_string()));
+    assert_eq!(store.lock().unwrap().get_string("other"), None);
+    manager.rollback_transaction(txn_id).unwrap();
+
+    store.lock().unwrap().del_key("queue");
+    store.lock().unwrap().rpush("queue".to_string(), "x".to_string()).unwrap();
+    let txn_id = manager.begin_transaction().unwrap();
+    manager.execute_operation(txn_id, StoreOperation::RPush("queue".to_string(), "y".to_string())).unwrap();
+    assert!(manager.commit_transaction(txn_id).unwrap());
+    assert_eq!(store.lock().unwrap().range("queue", 0, -1), vec!["x", "y"]);
+}
+
+#[test]
+fn test_rollback_to_savepoint() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let store = Arc::new(Mutex::new(Store::new()));
+    let mut manager = TransactionManager::new(&wal_path).unwrap();
+    manager.set_store(Arc::clone(&store));
+
+    let txn_id = manager.begin_transaction().unwrap();
+    manager
+        .execute_operation_with_old_value(txn_id, StoreOperation::Set("a".to_string(), "1".to_string()), None, None)
+        .unwrap();
+    manager.savepoint(txn_id, "sp").unwrap();
+    manager
+        .execute_operation_with_old_value(txn_id, StoreOperation::Set("b".to_string(), "2".to_string()), None, None)
+        .unwrap();
+    manager.rollback_to_savepoint(txn_id, "sp").unwrap();
+    assert_eq!(manager.get_transaction(txn_id).unwrap().operations.len(), 1);
+    assert!(manager.rollback_to_savepoint(txn_id, "missing").is_err());
+    assert!(manager.commit_transaction(txn_id).unwrap());
+
+    // 只有保存点之前的操作被提交，WAL重放结果一致
+    assert_eq!(store.lock().unwrap().get_string("a"), Some("1".to_string()));
+    assert_eq!(store.lock().unwrap().get_string("b"), None);
+    let recovered = manager.recover().unwrap();
+    assert_eq!(recovered.get("a"), Some(&"1".to_string()));
+    assert!(!recovered.contains_key("b"));
+}
+
+#[test]
+fn test_delete_in_transaction_is_visible_to_later_reads() {
+    let dir = tempdir().unwrap();
+    let store = Arc::new(Mutex::new(Store::new()));
+    store.lock().unwrap().set_string("a".to_string(), "old".to_string());
+    let mut manager = TransactionManager::new(&dir.path().join("wal.log")).unwrap();
+    manager.set_store(Arc::clone(&store));
+
+    let txn_id = manager.begin_transaction().unwrap();
+    assert_eq!(manager.read_string(txn_id, "a").unwrap(), Some("old".to_string()));
+    manager.execute_operation(txn_id, StoreOperation::Set("a".to_string(), "new".to_string())).unwrap();
+    assert_eq!(manager.read_string(txn_id, "a").unwrap(), Some("new".to_string()));
+
+    // 删除记为墓碑，之后的读取看到键不存在，而不是回落到存储中的旧值
+    manager.execute_operation(txn_id, StoreOperation::Delete("a".to_string())).unwrap();
+    assert_eq!(manager.read_string(txn_id, "a").unwrap(), None);
+    assert_eq!(manager.get_transaction(txn_id).unwrap().read_local("a"), Some(None));
+    assert_eq!(store.lock().unwrap().get_string("a"), Some("old".to_string()));
+
+    assert!(manager.commit_transaction(txn_id).unwrap());
+    assert_eq!(store.lock().unwrap().get_string("a"), None);
+    assert!(manager.read_string(txn_id, "a").is_err());
+}
+
+#[test]
+fn test_transaction_wal_writes_are_batched() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let manager = TransactionManager::new(&wal_path).unwrap();
+    manager.set_sync_policy(WalSyncPolicy::Always, 1);
+    let write_calls = || manager.get_wal_manager().write_calls();
+
+    let txn_id = manager.begin_transaction().unwrap();
+    let before = write_calls();
+    for i in 0..100 {
+        manager
+            .execute_operation_with_old_value(txn_id, StoreOperation::Set(format!("k{}", i), i.to_string()), None, None)
+            .unwrap();
+    }
+    // 事务进行中不写入WAL，提交时BEGIN、操作、PREPARE和COMMIT一次写入
+    assert_eq!(write_calls(), before);
+    manager.commit_transaction(txn_id).unwrap();
+    assert_eq!(write_calls(), before + 1);
+
+    let recovered = manager.recover().unwrap();
+    assert_eq!(recovered.len(), 100);
+    assert_eq!(recovered.get("k99"), Some(&"99".to_string()));
+
+    // 未提交的事务回滚时不需要写入WAL
+    let txn_id = manager.begin_transaction().unwrap();
+    manager
+        .execute_operation_with_old_value(txn_id, StoreOperation::Delete("k1".to_string()), None, None)
+        .unwrap();
+    let before = write_calls();
+    manager.rollback_transaction(txn_id).unwrap();
+    assert_eq!(write_calls(), before);
+    assert!(TransactionManager::new(&wal_path).unwrap().list_active_transactions().is_empty());
+}
+
+#[test]
+fn test_logged_writes_are_recovered_on_top_of_a_checkpoint() {
+    let dir = tempdir().unwrap();
+    let wal_path = dir.path().join("wal.log");
+    let data_file = dir.path().join("data.dat").to_string_lossy().to_string();
+    let run = |handler: &CommandHandler, command: &str| handler.execute_command(handler.parse_command(command));
+
+    let manager = Arc::new(TransactionManager::new(&wal_path).unwrap());
+    let store_manager = StoreManager::new().with_transaction_manager(Arc::clone(&manager));
+    store_manager.recover_from_wal().unwrap();
+    let handler = CommandHandler::new(store_manager, data_file.clone()).with_transaction_manager(Arc::clone(&manager));
+    assert_eq!(run(&handler, "set plain value"), "OK");
+    assert_eq!(run(&handler, "select 2"), "OK");
+    assert_eq!(run(&handler, "rpush queue a"), "1");
+    assert_eq!(run(&handler, "rpush queue b"), "2");
+    assert_eq!(run(&handler, "lpop queue"), "a");
+    assert_eq!(run(&handler, "hset user:1 name alice"), "1");
+    assert_eq!(run(&handler, "set gone soon"), "OK");
+    assert_eq!(run(&handler, "del gone"), "1");
+    drop(handler);
+    drop(manager);
+
+    // 没有保存数据文件，所有写入从WAL恢复；恢复后WAL以完整数据的检查点开头
+    for _ in 0..2 {
+        let manager = Arc::new(TransactionManager::new(&wal_path).unwrap());
+        let store_manager = StoreManager::new().with_transaction_manager(Arc::clone(&manager));
+        assert_eq!(store_manager.recover_from_wal().unwrap(), 3);
+        let handler = CommandHandler::new(store_manager, data_file.clone());
+        assert_eq!(run(&handler, "get plain"), "value");
+        assert_eq!(run(&handler, "select 2"), "OK");
+        assert_eq!(run(&handler, "range queue 0 -1"), "b");
+        assert_eq!(run(&handler, "hget user:1 name"), "alice");
+        assert_eq!(run(&handler, "get gone"), "(nil)");
+    }
+    let entries = WriteAheadLog::new(&wal_path).unwrap().load_entries().unwrap();
+    assert!(entries.iter().all(|entry| entry.command == LogCommand::Checkpoint));
+}
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// 当前打开的客户端连接，服务器关闭时据此等待各连接发送关闭通知后再退出
+#[derive(Debug, Default)]
+pub struct ConnectionRegistry {
+    active: Mutex<HashSet<u64>>,
+    closed: Condvar,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个连接，返回的guard被释放（连接处理结束）时自动注销
+    pub fn register(self: &Arc<Self>, conn_id: u64) -> ConnectionGuard {
+        self.active.lock().unwrap().insert(conn_id);
+        ConnectionGuard { registry: Arc::clone(self), conn_id }
+    }
+
+    /// 等待所有连接关闭，最多等待timeout，返回仍未关闭的连接数
+    pub fn wait_until_empty(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let mut active = self.active.lock().unwrap();
+        while !active.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            active = self.closed.wait_timeout(active, remaining).unwrap().0;
+        }
+        active.len()
+    }
+}
+
+/// 已登记的连接，释放时从登记表中移除
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    registry: Arc<ConnectionRegistry>,
+    conn_id: u64,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.registry.active.lock().unwrap().remove(&self.conn_id);
+        self.registry.closed.notify_all();
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Unix域套接字地址的前缀，例如`unix:/tmp/kv.sock`
+pub const UNIX_PREFIX: &str = "unix:";
+
+/// 服务器监听的一个地址：TCP地址或Unix域套接字
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// 绑定地址，`unix:`开头的地址使用Unix域套接字，遗留的套接字文件会被替换
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let Some(path) = addr.strip_prefix(UNIX_PREFIX) else {
+            return TcpListener::bind(addr).map(Listener::Tcp);
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+
+            let path = PathBuf::from(path);
+            // 只删除套接字文件，同名的普通文件保留并让绑定失败
+            if std::fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(&path)?;
+            }
+            UnixListener::bind(&path).map(|listener| Listener::Unix(listener, path))
+        }
+        #[cfg(not(unix))]
+        {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("当前平台不支持Unix域套接字: {}", path),
+            ))
+        }
+    }
+
+    /// 实际监听的地址，端口为0时包含系统分配的端口
+    pub fn local_addr(&self) -> String {
+        match self {
+            Listener::Tcp(listener) => listener
+                .local_addr()
+                .map_or_else(|e| format!("<未知地址: {}>", e), |addr| addr.to_string()),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => format!("{}{}", UNIX_PREFIX, path.display()),
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => listener.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener.set_nonblocking(nonblocking),
+        }
+    }
+
+    /// 接受一个连接，返回连接和对端地址的描述
+    ///
+    /// 部分平台上接受的连接会继承监听器的非阻塞模式，这里统一切换回阻塞模式。
+    pub fn accept(&self) -> io::Result<(Box<dyn ClientConnection>, String)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, addr) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok((Box::new(stream), addr.to_string()))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, path) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok((Box::new(stream), format!("{}{}", UNIX_PREFIX, path.display())))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// 客户端连接，连接处理只依赖读写接口，与监听地址的类型无关
+pub trait ClientConnection: Read + Write + Send {
+    /// 设置读取超时，不支持超时的传输（如内存中的测试流）使用默认的空实现
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<C: ClientConnection + ?Sized> ClientConnection for Box<C> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        (**self).set_read_timeout(timeout)
+    }
+}
+
+impl ClientConnection for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl ClientConnection for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
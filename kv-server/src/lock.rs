@@ -0,0 +1,65 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// 锁文件名，内容为持有锁的进程ID
+pub const LOCK_FILE_NAME: &str = "server.pid";
+
+/// 数据目录锁，防止多个服务器实例同时使用同一个数据目录
+///
+/// 使用操作系统的建议锁，进程崩溃后锁会被自动释放，遗留的锁文件会在下次启动时被覆盖。
+/// 锁文件本身从不删除：删除后等待同一文件的实例和新建文件的实例会同时认为自己持有锁。
+#[derive(Debug)]
+pub struct DataDirLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl DataDirLock {
+    /// 获取数据目录锁，目录已被其他进程使用时返回错误
+    pub fn acquire(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir.join(LOCK_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| format!("无法打开锁文件 {}: {}", path.display(), e))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
+                let pid = pid.trim();
+                return Err(format!(
+                    "ERROR: data directory already in use by pid {}",
+                    if pid.is_empty() { "unknown" } else { pid }
+                ));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(format!("无法锁定数据目录 {}: {}", data_dir.display(), e));
+            }
+        }
+
+        // 覆盖可能由崩溃进程遗留的旧进程ID
+        file.set_len(0)
+            .and_then(|_| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("无法写入锁文件 {}: {}", path.display(), e))?;
+
+        Ok(DataDirLock { file, path })
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        // 只清空进程ID再释放锁，锁文件留给下一个实例使用
+        if let Err(e) = self.file.set_len(0) {
+            log::warn!("无法清空锁文件 {}: {}", self.path.display(), e);
+        }
+        let _ = self.file.unlock();
+    }
+}
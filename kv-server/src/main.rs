@@ -1,17 +1,30 @@
+mod connections;
+mod listener;
+mod lock;
+mod rate_limit;
 mod server;
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use kv_common::config::Settings;
 use kv_common::logger;
 use log::{error, info};
 use server::Server;
 use std::process;
+use std::sync::Arc;
 
 fn main() {
     // 解析命令行参数
     let matches = Command::new("KV Store Server")
         .version("1.1")
         .about("A simple key-value store server")
+        // -h 用于主机地址，帮助信息只保留 --help
+        .disable_help_flag(true)
+        .arg(
+            Arg::new("help")
+                .long("help")
+                .help("打印帮助信息")
+                .action(ArgAction::Help),
+        )
         .arg(
             Arg::new("host")
                 .short('h')
@@ -26,13 +39,26 @@ fn main() {
                 .long("port")
                 .value_name("PORT")
                 .help("服务器端口")
+                .value_parser(clap::value_parser!(u16))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("FILE")
+                .help("配置文件路径")
                 .num_args(1),
         )
         .get_matches();
 
     // 加载配置
-    let settings = match Settings::new() {
-        Ok(s) => s,
+    let settings = match matches.get_one::<String>("config") {
+        Some(path) => Settings::from_path(path),
+        None => Settings::new(),
+    };
+    let settings = match settings {
+        Ok(s) => Arc::new(s),
         Err(e) => {
             eprintln!("加载配置失败: {}", e);
             process::exit(1);
@@ -57,12 +83,18 @@ fn main() {
         .unwrap_or(&settings.server.port);
 
     // 启动服务器
-    run_server(host, port, &settings.persistence.data_file);
+    let data_file = settings.data_paths().data_file.to_string_lossy().to_string();
+    let config_path = matches.get_one::<String>("config");
+    run_server(host, port, &data_file, Arc::clone(&settings), config_path);
 }
 
 // 启动服务器
-fn run_server(host: &str, port: &u16, data_file: &str) {
-    let mut server = Server::new(host.to_string(), *port, data_file.to_string());
+fn run_server(host: &str, port: &u16, data_file: &str, settings: Arc<Settings>, config_path: Option<&String>) {
+    let mut server = Server::new(host.to_string(), *port, data_file.to_string())
+        .with_settings(settings);
+    if let Some(config_path) = config_path {
+        server = server.with_config_path(config_path);
+    }
 
     info!(
         "服务器配置: 主机={}, 端口={}, 数据文件={}",
@@ -1,100 +1,383 @@
+use crate::connections::ConnectionRegistry;
+use crate::listener::{ClientConnection, Listener, UNIX_PREFIX};
+use crate::lock::DataDirLock;
+use crate::rate_limit::RateLimiter;
 use kv_common::command::CommandHandler;
-use kv_common::store::StoreManager;
+use kv_common::config::{DataPaths, Settings, WalSyncPolicy, WireProtocol};
+use kv_common::metrics::{CommandMetrics, SlowLog};
+use kv_common::result_cache::ResultCache;
+use kv_common::protocol::{read_frame, write_frame, LineBuffer, MAX_FRAME_LEN};
+use kv_common::store::{StoreManager, WriteAheadLog};
+use kv_common::{TransactionCommandHandler, TransactionManager};
 use log::{debug, error, info, warn};
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::sync::Arc;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use std::thread;
-use std::time::Duration;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use chrono::Local;
 
+/// 连接读取超时的间隔，超时后检查服务器是否正在关闭
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// 服务器关闭时等待各连接发送关闭通知的最长时间，阻塞中的命令（如BLPOP）不会拖住关闭流程
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(2);
+/// 服务器关闭时发送给仍连接着的客户端的通知
+const SHUTDOWN_NOTICE: &str = "ERROR: SHUTDOWN server is shutting down";
+
 pub struct Server {
     host: String,
     port: u16,
     store_manager: StoreManager,
     data_file: String,
-    wal_path: String,           // WAL日志存储路径
+    paths: DataPaths,           // WAL、检查点等数据路径
     running: Arc<AtomicBool>,
+    settings: Option<Arc<Settings>>,
+    next_connection_id: AtomicU64, // 下一个连接的ID，所有监听地址共用
+    metrics: Option<Arc<CommandMetrics>>, // 所有连接共享的命令指标
+    slowlog: Option<Arc<SlowLog>>,        // 所有连接共享的慢查询日志
+    result_cache: Option<Arc<ResultCache>>, // 所有连接共享的只读命令结果缓存
+    runtime_config: Option<Arc<RwLock<Settings>>>, // 所有连接共享、CONFIG SET可修改的配置
+    connections: Arc<ConnectionRegistry>, // 当前打开的客户端连接
+    transaction_manager: Option<Arc<TransactionManager>>, // 所有连接共享的事务管理器，启动时打开WAL日志后创建
+    config_path: Option<PathBuf>, // 启动时加载的配置文件，收到SIGHUP时重新加载
 }
 
 impl Server {
     pub fn new(host: String, port: u16, data_file: String) -> Self {
-        // 根据数据文件生成WAL路径
-        let wal_path = std::path::Path::new(&data_file)
-            .parent()
-            .unwrap_or(std::path::Path::new("."))
-            .join("wal")
-            .to_string_lossy()
-            .to_string();
+        // 未提供配置时，其他数据路径由数据文件所在目录推导
+        let paths = DataPaths::from_data_file(&data_file);
             
         Server {
             host,
             port,
             store_manager: StoreManager::new(),
             data_file,
-            wal_path,
+            paths,
             running: Arc::new(AtomicBool::new(false)),
+            settings: None,
+            next_connection_id: AtomicU64::new(1),
+            metrics: None,
+            slowlog: None,
+            result_cache: None,
+            runtime_config: None,
+            connections: Arc::new(ConnectionRegistry::new()),
+            transaction_manager: None,
+            config_path: None,
+        }
+    }
+
+    /// 使用配置构建
+    pub fn with_settings(mut self, settings: Arc<Settings>) -> Self {
+        self.paths = settings.data_paths();
+        self.data_file = self.paths.data_file.to_string_lossy().to_string();
+        self.store_manager = self.store_manager.with_settings(Arc::clone(&settings));
+        if settings.monitoring.enable_metrics {
+            self.metrics = Some(Arc::new(CommandMetrics::new()));
+        }
+        if settings.monitoring.slowlog_max_len > 0 {
+            self.slowlog = Some(Arc::new(SlowLog::new(
+                Duration::from_millis(settings.monitoring.slowlog_threshold_ms),
+                settings.monitoring.slowlog_max_len,
+            )));
+        }
+        if settings.memory.result_cache_entries > 0 {
+            self.result_cache = Some(Arc::new(ResultCache::new(settings.memory.result_cache_entries)));
+        }
+        self.runtime_config = Some(Arc::new(RwLock::new((*settings).clone())));
+        self.settings = Some(settings);
+        self
+    }
+
+    /// 记录配置文件路径，收到SIGHUP时从这里重新加载配置，未设置时重新加载默认配置文件
+    pub fn with_config_path(mut self, config_path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(config_path.into());
+        self
+    }
+
+    /// 为新连接创建命令处理器
+    fn create_command_handler(&self, conn_id: u64) -> CommandHandler {
+        let mut command_handler = CommandHandler::new(self.store_manager.clone(), self.data_file.clone());
+        if let Some(settings) = &self.settings {
+            command_handler = command_handler.with_settings(Arc::clone(settings));
+        }
+        if self.settings.as_ref().is_some_and(|settings| settings.logging.log_commands) {
+            command_handler = command_handler.with_connection_id(conn_id);
+        }
+        if let Some(metrics) = &self.metrics {
+            command_handler = command_handler.with_metrics(Arc::clone(metrics));
+        }
+        if let Some(slowlog) = &self.slowlog {
+            command_handler = command_handler.with_slowlog(Arc::clone(slowlog));
+        }
+        if let Some(result_cache) = &self.result_cache {
+            command_handler = command_handler.with_result_cache(Arc::clone(result_cache));
+        }
+        if let Some(runtime_config) = &self.runtime_config {
+            command_handler = command_handler.with_runtime_config(Arc::clone(runtime_config));
+        }
+        if let Some(transaction_manager) = &self.transaction_manager {
+            command_handler = command_handler.with_transaction_manager(Arc::clone(transaction_manager));
+        }
+        command_handler
+    }
+
+    /// 按配置打开WAL日志并创建所有连接共享的事务管理器
+    fn open_transaction_manager(&self) -> Result<Arc<TransactionManager>, String> {
+        let manager = TransactionManager::new(&self.paths.wal_file)
+            .map_err(|e| format!("打开WAL日志失败: {}", e))?;
+        manager.set_checkpoint_dir(self.paths.checkpoint_dir.clone())
+            .map_err(|e| format!("创建检查点目录失败: {}", e))?;
+        if let Some(settings) = &self.settings {
+            manager.set_checkpoint_retention(settings.persistence.checkpoint_retention);
+            manager.set_sync_policy(settings.persistence.wal_sync, settings.persistence.wal_batch_entries);
+        }
+        Ok(Arc::new(manager))
+    }
+
+    /// 校验命令处理器使用的WAL日志，只记录异常不做修改
+    fn validate_wal(&self) {
+        let wal_file = &self.paths.wal_file;
+        if !wal_file.exists() {
+            return;
+        }
+        
+        match WriteAheadLog::new(wal_file).and_then(|wal| wal.validate()) {
+            Ok(report) if report.is_ok() => {
+                info!("WAL校验通过，共{}条日志", report.entries);
+            }
+            Ok(report) => {
+                warn!("WAL校验发现{}个异常，共{}条日志", report.anomalies.len(), report.entries);
+                for anomaly in &report.anomalies {
+                    warn!("WAL异常: {}", anomaly);
+                }
+            }
+            Err(e) => warn!("WAL校验失败: {}", e),
         }
     }
 
     // 启动服务器
     pub fn start(&mut self) -> Result<(), String> {
-        // 初始化WAL
-        let wal_dir = std::path::Path::new(&self.wal_path);
-        if !wal_dir.exists() {
-            std::fs::create_dir_all(wal_dir)
-                .map_err(|e| format!("创建WAL目录失败: {}", e))?;
-        }
+        // 初始化数据目录
+        std::fs::create_dir_all(&self.paths.data_dir)
+            .map_err(|e| format!("创建数据目录失败: {}", e))?;
         
-        // 使用WAL初始化StoreManager
-        self.store_manager = self.store_manager.clone().with_wal(wal_dir);
+        // 锁定数据目录，服务器关闭时释放
+        let _data_dir_lock = DataDirLock::acquire(&self.paths.data_dir)?;
         
-        // 从WAL日志中恢复数据
-        info!("从WAL恢复数据...");
-        if let Err(e) = self.store_manager.recover_from_wal() {
-            warn!("从WAL恢复数据失败: {}", e);
-        }
+        // 恢复前先校验WAL日志，发现异常时记录警告
+        self.validate_wal();
         
-        // 加载持久化数据
-        info!("从数据文件加载数据...");
-        self.store_manager.load_from_file(&self.data_file)
-            .map_err(|e| format!("加载数据文件失败: {}", e))?;
+        // 所有连接共用一个事务管理器，事务ID和WAL写入在连接之间不会冲突，写命令修改的键也通过它记录到WAL
+        let transaction_manager = self.open_transaction_manager()?;
+        self.store_manager = self.store_manager.clone().with_transaction_manager(Arc::clone(&transaction_manager));
+        self.transaction_manager = Some(transaction_manager);
         
-        // 创建 TCP 监听器
-        let addr = format!("{}:{}", self.host, self.port);
-        let listener = TcpListener::bind(&addr)
-            .map_err(|e| format!("无法绑定到地址 {}: {}", addr, e))?;
+        // 先绑定监听地址再恢复数据，恢复期间HEALTH返回LOADING，其他命令返回LOADING错误
+        self.store_manager.set_loading(true);
         
-        info!("服务器在 {} 上启动", addr);
+        // 绑定所有监听地址，任意一个失败都不启动
+        let listeners = self
+            .listen_addresses()
+            .iter()
+            .map(|addr| {
+                let listener = Listener::bind(addr)
+                    .map_err(|e| format!("无法绑定到地址 {}: {}", addr, e))?;
+                listener.set_nonblocking(true)
+                    .map_err(|e| format!("设置非阻塞模式失败: {}", e))?;
+                info!("服务器在 {} 上启动", listener.local_addr());
+                Ok(listener)
+            })
+            .collect::<Result<Vec<Listener>, String>>()?;
         
         // 设置为运行状态
         self.running.store(true, Ordering::SeqCst);
-        let running = Arc::clone(&self.running);
         
-        // 捕获 Ctrl+C 信号
+        // Ctrl+C(SIGINT)和SIGTERM走优雅关闭流程保存数据，SIGHUP重新加载配置文件
+        let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP])
+            .map_err(|e| format!("无法设置信号处理程序: {}", e))?;
         let running_sig = Arc::clone(&self.running);
-        ctrlc::set_handler(move || {
-            info!("接收到终止信号，正在关闭服务器...");
-            running_sig.store(false, Ordering::SeqCst);
-        }).map_err(|e| format!("无法设置信号处理程序: {}", e))?;
+        let config_path = self.config_path.clone();
+        let config_handler = self.create_command_handler(0);
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                if signal == SIGHUP {
+                    Self::reload_config(config_path.as_deref(), &config_handler);
+                } else {
+                    info!("接收到终止信号，正在关闭服务器...");
+                    running_sig.store(false, Ordering::SeqCst);
+                }
+            }
+        });
+        
+        // 每个监听地址一个接受循环，所有连接共用同一个存储
+        let server = &*self;
+        let recovery = thread::scope(|scope| {
+            for listener in &listeners {
+                scope.spawn(move || server.accept_loop(listener));
+            }
+            let recovery = server.recover_data();
+            match &recovery {
+                Ok(()) => {
+                    scope.spawn(move || server.wal_compaction_loop());
+                    scope.spawn(move || server.wal_sync_loop());
+                }
+                // 恢复失败时停止接受连接，不保存不完整的数据
+                Err(_) => server.running.store(false, Ordering::SeqCst),
+            }
+            recovery
+        });
+        drop(listeners);
+        recovery?;
         
-        // 监听连接
-        listener.set_nonblocking(true)
-            .map_err(|e| format!("设置非阻塞模式失败: {}", e))?;
+        // 阻塞在BLPOP/BRPOP上的连接不会检查运行状态，先唤醒它们
+        self.store_manager.wake_blocked_pops();
+        
+        // 各连接发现服务器正在关闭后发送通知并断开，等待它们结束后再保存数据
+        let remaining = self.connections.wait_until_empty(SHUTDOWN_GRACE_PERIOD);
+        if remaining > 0 {
+            warn!("{}个连接未在关闭期限内断开", remaining);
+        }
+        
+        // 优雅关闭：保存数据，再以全部数据创建最后一个WAL检查点并压缩WAL
+        info!("创建WAL检查点和保存数据...");
+        match self.store_manager.save_to_file(&self.data_file) {
+            Ok(_) => info!("数据成功保存到 {}", self.data_file),
+            Err(e) => error!("保存数据失败: {}", e),
+        }
+        match self.store_manager.checkpoint_wal() {
+            Ok(keys) => info!("已创建包含 {} 个键的WAL检查点并压缩WAL", keys),
+            Err(e) => error!("创建WAL检查点失败: {}", e),
+        }
+        
+        info!("服务器已关闭");
+        Ok(())
+    }
+    
+    /// 重新加载配置文件，运行时可调整的配置项立即生效，加载失败时保持当前配置
+    fn reload_config(config_path: Option<&Path>, command_handler: &CommandHandler) {
+        info!("接收到SIGHUP，重新加载配置...");
+        let settings = match config_path {
+            Some(path) => Settings::from_path(path),
+            None => Settings::new(),
+        };
+        let result = settings
+            .map_err(|e| e.to_string())
+            .and_then(|settings| command_handler.reload_config(&settings));
+        match result {
+            Ok(changed) if changed.is_empty() => info!("配置已重新加载，没有需要修改的配置项"),
+            Ok(changed) => info!("配置已重新加载，修改了 {}", changed.join(", ")),
+            Err(e) => error!("重新加载配置失败，继续使用当前配置: {}", e),
+        }
+    }
+    
+    /// 加载数据文件并在此基础上重放WAL日志，完成后结束LOADING状态
+    fn recover_data(&self) -> Result<(), String> {
+        info!("从数据文件加载数据...");
+        self.store_manager.load_from_file(&self.data_file)
+            .map_err(|e| format!("加载数据文件失败: {}", e))?;
         
-        while running.load(Ordering::SeqCst) {
+        // 恢复上次保存之后的写入
+        info!("从WAL恢复数据...");
+        let recovered = self.store_manager.recover_from_wal()
+            .map_err(|e| format!("从WAL恢复数据失败: {}", e))?;
+        info!("WAL恢复完成，共{}个键", recovered);
+        self.store_manager.set_loading(false);
+        info!("数据恢复完成，开始处理命令");
+        Ok(())
+    }
+    
+    /// 需要监听的地址：配置了server.listen时使用该列表，否则使用host:port，另外加上server.unix_socket
+    fn listen_addresses(&self) -> Vec<String> {
+        let mut addresses = match &self.settings {
+            Some(settings) if !settings.server.listen.is_empty() => settings.server.listen.clone(),
+            _ => vec![format!("{}:{}", self.host, self.port)],
+        };
+        if let Some(path) = self.settings.as_ref().and_then(|settings| settings.server.unix_socket.as_ref()) {
+            let addr = format!("{}{}", UNIX_PREFIX, path);
+            if !addresses.contains(&addr) {
+                addresses.push(addr);
+            }
+        }
+        addresses
+    }
+    
+    /// 定期检查WAL日志大小，超过persistence.wal_compact_threshold_bytes时压缩，阈值为0时不启动
+    fn wal_compaction_loop(&self) {
+        let Some(persistence) = self.settings.as_ref().map(|settings| &settings.persistence) else {
+            return;
+        };
+        if persistence.wal_compact_threshold_bytes == 0 {
+            return;
+        }
+        // 与各连接共用同一个事务管理器，压缩和事务写入使用同一个WAL
+        let Some(transaction_manager) = &self.transaction_manager else {
+            return;
+        };
+        let handler = TransactionCommandHandler::from_manager(Arc::clone(transaction_manager));
+        let interval = Duration::from_secs(persistence.wal_compact_check_seconds.max(1));
+        
+        let mut last_check = Instant::now();
+        while self.running.load(Ordering::SeqCst) {
+            // 短暂休眠后再检查运行状态，服务器关闭时不必等待整个检查间隔
+            thread::sleep(Duration::from_millis(100));
+            if last_check.elapsed() < interval {
+                continue;
+            }
+            last_check = Instant::now();
+            match handler.compact_if_needed(persistence.wal_compact_threshold_bytes) {
+                Ok(message) => debug!("{}", message),
+                Err(e) => warn!("{}", e),
+            }
+        }
+    }
+    
+    /// wal_sync为every_sec时定期同步WAL，写入停止后缓冲的日志最多延迟约1秒落盘
+    fn wal_sync_loop(&self) {
+        let every_sec = self.settings.as_ref()
+            .is_some_and(|settings| settings.persistence.wal_sync == WalSyncPolicy::EverySec);
+        let Some(transaction_manager) = self.transaction_manager.as_ref().filter(|_| every_sec) else {
+            return;
+        };
+        while self.running.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(100));
+            if let Err(e) = transaction_manager.sync_wal_if_due() {
+                warn!("同步WAL日志失败: {}", e);
+            }
+        }
+    }
+    
+    // 接受一个监听地址上的连接，直到服务器停止运行
+    fn accept_loop(&self, listener: &Listener) {
+        while self.running.load(Ordering::SeqCst) {
             match listener.accept() {
                 Ok((stream, addr)) => {
-                    info!("新连接: {}", addr);
+                    let conn_id = self.next_connection_id.fetch_add(1, Ordering::SeqCst);
+                    info!("[conn {}] 新连接: {}", conn_id, addr);
                     
                     // 为每个客户端创建一个线程
-                    let store_manager = self.store_manager.clone();
-                    let data_file = self.data_file.clone();
+                    let command_handler = self.create_command_handler(conn_id);
+                    let protocol = self.settings.as_ref()
+                        .map_or(WireProtocol::Line, |settings| settings.server.protocol);
+                    // 每个连接独立限流，0表示不限制
+                    let rate_limiter = self.settings.as_ref()
+                        .map(|settings| settings.server.rate_limit_per_second)
+                        .filter(|&rate| rate > 0)
+                        .map(RateLimiter::new);
+                    let max_command_length = self.settings.as_ref()
+                        .map_or(MAX_FRAME_LEN, |settings| settings.server.max_command_length);
+                    let connection = self.connections.register(conn_id);
+                    let running = Arc::clone(&self.running);
                     
                     thread::spawn(move || {
-                        if let Err(e) = Self::handle_client(stream, addr.to_string(), store_manager, data_file) {
-                            error!("处理客户端 {} 时出错: {}", addr, e);
+                        let result = match protocol {
+                            WireProtocol::Line => Self::handle_client(stream, addr.clone(), conn_id, command_handler, rate_limiter, max_command_length, &running),
+                            WireProtocol::Binary => Self::handle_binary_client(stream, addr.clone(), conn_id, command_handler, rate_limiter, &running),
+                        };
+                        drop(connection);
+                        if let Err(e) = result {
+                            error!("[conn {}] 处理客户端 {} 时出错: {}", conn_id, addr, e);
                         }
                     });
                 }
@@ -103,65 +386,143 @@ impl Server {
                     thread::sleep(Duration::from_millis(100));
                 }
                 Err(e) => {
-                    error!("接受连接时出错: {}", e);
+                    error!("在 {} 上接受连接时出错: {}", listener.local_addr(), e);
                 }
             }
         }
-        
-        // 优雅关闭：创建检查点并保存数据
-        info!("创建WAL检查点和保存数据...");
-        match self.store_manager.save_to_file(&self.data_file) {
-            Ok(_) => info!("数据成功保存到 {}", self.data_file),
-            Err(e) => error!("保存数据失败: {}", e),
-        }
-        
-        info!("服务器已关闭");
-        Ok(())
     }
     
-    // 处理单个客户端连接
-    fn handle_client(
-        mut stream: TcpStream,
+    // 处理单个客户端连接，stream可以是任何实现了ClientConnection的传输
+    fn handle_client<S: ClientConnection>(
+        mut stream: S,
         addr: String,
-        store_manager: StoreManager,
-        data_file: String,
-    ) -> Result<(), String> {
-        // 创建命令处理器
-        let command_handler = CommandHandler::new(store_manager, data_file);
-        
-        // 设置读取超时
-        stream.set_read_timeout(Some(Duration::from_secs(30)))
+        conn_id: u64,
+        command_handler: CommandHandler,
+        mut rate_limiter: Option<RateLimiter>,
+        max_command_length: usize,
+        running: &AtomicBool,
+    ) -> Result<(), String> {        
+        // 设置读取超时，超时后检查服务器是否正在关闭
+        stream.set_read_timeout(Some(SHUTDOWN_POLL_INTERVAL))
             .map_err(|e| format!("设置读取超时失败: {}", e))?;
         
         let mut buffer = [0; 1024];
-        let client_disconnected = Arc::new(AtomicBool::new(false));
+        let mut lines = LineBuffer::new().with_max_line_len(max_command_length);
         
-        while !client_disconnected.load(Ordering::SeqCst) {
+        'connection: loop {
+            // 服务器正在关闭时通知客户端后断开连接
+            if !running.load(Ordering::SeqCst) {
+                let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                let _ = stream.write_all(format!("[{}] {}\n", timestamp, SHUTDOWN_NOTICE).as_bytes());
+                info!("[conn {}] 服务器正在关闭，断开客户端 {}", conn_id, addr);
+                break;
+            }
+            
             // 读取客户端命令
             match stream.read(&mut buffer) {
                 Ok(0) => {
                     // 客户端断开连接
-                    info!("客户端 {} 断开连接", addr);
+                    info!("[conn {}] 客户端 {} 断开连接", conn_id, addr);
                     break;
                 }
                 Ok(n) => {
-                    let command_str = String::from_utf8_lossy(&buffer[..n]).trim().to_string();
-                    debug!("从 {} 接收到命令: {}", addr, command_str);
+                    lines.extend(&buffer[..n]);
                     
-                    if command_str.is_empty() {
-                        continue;
+                    // 一次读取可能包含多条以\n或\r\n结尾的命令，依次执行并按顺序响应
+                    while let Some(line) = lines.next_line() {
+                        let response = match line {
+                            // 超过长度上限的命令行已被丢弃，不解析执行，连接保持可用
+                            Err(e) => {
+                                warn!("[conn {}] 客户端 {} 的命令行超过{}字节，已丢弃", conn_id, addr, max_command_length);
+                                format!("ERROR: {}", e)
+                            }
+                            Ok(line) => {
+                                // 整行到达后才解码，非UTF-8的命令返回错误，连接保持可用
+                                let line = String::from_utf8(line);
+                                let command_str = line.as_deref().map_or("", str::trim);
+                                if line.is_ok() && command_str.is_empty() {
+                                    continue;
+                                }
+                                debug!("[conn {}] 从 {} 接收到命令: {}", conn_id, addr, command_str);
+                                
+                                // 超过限流速率的命令直接拒绝，不解析执行
+                                if rate_limiter.as_mut().is_some_and(|limiter| !limiter.try_acquire()) {
+                                    "ERROR: rate limit exceeded".to_string()
+                                } else if line.is_err() {
+                                    "ERROR: ERR command is not valid UTF-8".to_string()
+                                } else {
+                                    // 解析并执行命令
+                                    let command = command_handler.parse_command(command_str);
+                                    command_handler.execute_command(command)
+                                }
+                            }
+                        };
+                        
+                        // 发送响应
+                        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                        let formatted_response = format!("[{}] {}\n", timestamp, response);
+                        
+                        if let Err(e) = stream.write_all(formatted_response.as_bytes()) {
+                            error!("[conn {}] 向客户端 {} 发送响应时出错: {}", conn_id, addr, e);
+                            break 'connection;
+                        }
                     }
+                }
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    // 超时但客户端仍然连接
+                    continue;
+                }
+                Err(e) => {
+                    error!("[conn {}] 从客户端 {} 读取时出错: {}", conn_id, addr, e);
+                    break;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // 处理使用长度前缀分帧协议的客户端连接，每个命令帧都对应一个响应帧
+    fn handle_binary_client<S: ClientConnection>(
+        mut stream: S,
+        addr: String,
+        conn_id: u64,
+        command_handler: CommandHandler,
+        mut rate_limiter: Option<RateLimiter>,
+        running: &AtomicBool,
+    ) -> Result<(), String> {
+        stream.set_read_timeout(Some(SHUTDOWN_POLL_INTERVAL))
+            .map_err(|e| format!("设置读取超时失败: {}", e))?;
+        
+        loop {
+            // 服务器正在关闭时通知客户端后断开连接
+            if !running.load(Ordering::SeqCst) {
+                let _ = write_frame(&mut stream, SHUTDOWN_NOTICE.as_bytes());
+                info!("[conn {}] 服务器正在关闭，断开客户端 {}", conn_id, addr);
+                break;
+            }
+            
+            match read_frame(&mut stream) {
+                Ok(None) => {
+                    info!("[conn {}] 客户端 {} 断开连接", conn_id, addr);
+                    break;
+                }
+                Ok(Some(payload)) => {
+                    // 帧内容原样作为命令行解析，不做trim，引号内的换行等字符得以保留
+                    let response = match String::from_utf8(payload) {
+                        _ if rate_limiter.as_mut().is_some_and(|limiter| !limiter.try_acquire()) => {
+                            "ERROR: rate limit exceeded".to_string()
+                        }
+                        Ok(command_str) => {
+                            debug!("[conn {}] 从 {} 接收到命令: {:?}", conn_id, addr, command_str);
+                            let command = command_handler.parse_command(&command_str);
+                            command_handler.execute_command(command)
+                        }
+                        Err(_) => "ERROR: ERR command is not valid UTF-8".to_string(),
+                    };
                     
-                    // 解析并执行命令
-                    let command = command_handler.parse_command(&command_str);
-                    let response = command_handler.execute_command(command);
-                    
-                    // 发送响应
-                    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-                    let formatted_response = format!("[{}] {}\n", timestamp, response);
-                    
-                    if let Err(e) = stream.write_all(formatted_response.as_bytes()) {
-                        error!("向客户端 {} 发送响应时出错: {}", addr, e);
+                    if let Err(e) = write_frame(&mut stream, response.as_bytes()) {
+                        error!("[conn {}] 向客户端 {} 发送响应时出错: {}", conn_id, addr, e);
                         break;
                     }
                 }
@@ -170,7 +531,7 @@ impl Server {
                     continue;
                 }
                 Err(e) => {
-                    error!("从客户端 {} 读取时出错: {}", addr, e);
+                    error!("[conn {}] 从客户端 {} 读取帧时出错: {}", conn_id, addr, e);
                     break;
                 }
             }
@@ -178,4 +539,62 @@ impl Server {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read, Write};
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    // 内存中的双工流：从预先准备的输入读取，写入的响应保存在共享缓冲区中
+    struct MemoryStream {
+        input: Cursor<Vec<u8>>,
+        output: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Read for MemoryStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MemoryStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ClientConnection for MemoryStream {}
+
+    #[test]
+    fn test_handle_client_over_memory_stream() {
+        let dir = tempdir().unwrap();
+        let data_file = dir.path().join("data.json").to_string_lossy().to_string();
+        let command_handler = CommandHandler::new(StoreManager::new(), data_file);
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut input = b"set greeting hello\r\n\nget greeting\nget missing\n".to_vec();
+        input.extend_from_slice(&[0xff, 0xfe, b'\n']);
+        input.extend_from_slice(b"ping");
+        let stream = MemoryStream { input: Cursor::new(input), output: Arc::clone(&output) };
+
+        // 输入读完相当于客户端断开连接，未以换行结尾的命令不会执行
+        Server::handle_client(stream, "memory".to_string(), 1, command_handler, None, MAX_FRAME_LEN, &AtomicBool::new(true))
+            .unwrap();
+
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        let responses: Vec<&str> = output
+            .lines()
+            .map(|line| line.split_once("] ").map_or(line, |(_, response)| response))
+            .collect();
+        assert_eq!(
+            responses,
+            vec!["OK", "hello", "(nil)", "ERROR: ERR command is not valid UTF-8"]
+        );
+    }
+}
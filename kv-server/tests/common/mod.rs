@@ -0,0 +1,71 @@
+// 集成测试共用的服务器启动辅助函数，不是每个测试文件都会用到全部函数
+#![allow(dead_code)]
+
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 在临时目录中启动服务器，server_config为追加到[server]段的配置，返回进程和监听端口
+pub fn spawn_server(dir: &Path, server_config: &str) -> (Child, u16) {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let config_path = dir.join("server.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[server]\nport = {}\n{}\n\n[persistence]\ndata_dir = '{}'\ndata_file = \"storage.dat\"\n\n[logging]\nlog_file = '{}'\n",
+            port,
+            server_config,
+            dir.join("data").display(),
+            dir.join("server.log").display()
+        ),
+    )
+    .unwrap();
+
+    // 同一目录可能启动过多次服务器，只等待本次启动的恢复完成日志
+    let log_path = dir.join("server.log");
+    let loaded_before = count_loaded(&log_path);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kv-server"))
+        .arg("--config")
+        .arg(&config_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_until_loaded(&mut child, &log_path, loaded_before);
+    (child, port)
+}
+
+/// 日志中恢复完成的次数
+fn count_loaded(log_path: &Path) -> usize {
+    fs::read_to_string(log_path).map_or(0, |log| log.matches(LOADED_MESSAGE).count())
+}
+
+/// 服务器恢复数据完成后才处理命令，恢复期间除HEALTH外都返回LOADING
+const LOADED_MESSAGE: &str = "数据恢复完成，开始处理命令";
+
+/// 等待服务器写出恢复完成日志，服务器提前退出(如启动失败)时直接返回
+fn wait_until_loaded(child: &mut Child, log_path: &Path, loaded_before: usize) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if count_loaded(log_path) > loaded_before || child.try_wait().unwrap().is_some() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    panic!("服务器未在超时时间内完成数据恢复");
+}
+
+/// 等待服务器开始监听并建立连接
+pub fn connect(port: u16) -> TcpStream {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)) {
+            return stream;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    panic!("服务器未在超时时间内开始监听端口 {}", port);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::{connect, spawn_server};
+use kv_common::protocol::{read_frame, write_frame};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::Child;
+use tempfile::tempdir;
+
+fn request(stream: &mut TcpStream, command: &str) -> String {
+    write_frame(stream, command.as_bytes()).unwrap();
+    let response = read_frame(stream).unwrap().expect("服务器关闭了连接");
+    String::from_utf8(response).unwrap()
+}
+
+// 在数据目录上启动服务器并连接，使用二进制协议以便多行响应保持完整，返回进程、连接和端口
+fn start(dir: &Path) -> (Child, TcpStream, u16) {
+    let (server, port) = spawn_server(dir, "protocol = \"binary\"");
+    (server, connect(port), port)
+}
+
+// 强制结束服务器进程，不经过保存数据的关闭流程
+fn crash(mut server: Child) {
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+// 剩余生存时间在(0, max]范围内
+fn assert_ttl_within(stream: &mut TcpStream, key: &str, max: i64) {
+    let response = request(stream, &format!("ddl {}", key));
+    let ttl: i64 = response
+        .strip_prefix("TTL: ")
+        .and_then(|rest| rest.strip_suffix(" seconds"))
+        .and_then(|ttl| ttl.parse().ok())
+        .unwrap_or_else(|| panic!("{}没有恢复过期时间: {}", key, response));
+    assert!(ttl > 0 && ttl <= max, "{}恢复后的剩余生存时间: {}", key, ttl);
+}
+
+#[test]
+fn test_saved_data_and_ttls_survive_crash() {
+    let dir = tempdir().unwrap();
+    let (server, mut stream, _) = start(dir.path());
+
+    assert_eq!(request(&mut stream, "set plain value"), "OK");
+    assert_eq!(request(&mut stream, "rpush queue job1"), "1");
+    assert_eq!(request(&mut stream, "hset user:1 name alice"), "1");
+    assert_eq!(request(&mut stream, "set session token"), "OK");
+    assert_eq!(request(&mut stream, "expire session 1000"), "1");
+    assert!(!request(&mut stream, "save").starts_with("ERROR"));
+    crash(server);
+
+    // 进程被强制结束后，新的服务器从同一数据目录恢复最近一次保存的数据和过期时间
+    let (server, mut stream, _) = start(dir.path());
+    assert_eq!(request(&mut stream, "get plain"), "value");
+    assert_eq!(request(&mut stream, "range queue 0 -1"), "job1");
+    assert_eq!(request(&mut stream, "hget user:1 name"), "alice");
+    assert_eq!(request(&mut stream, "get session"), "token");
+    assert_ttl_within(&mut stream, "session", 1000);
+
+    // 再次崩溃和重启同样可以恢复，数据目录锁不会阻止启动
+    crash(server);
+    let (server, mut stream, _) = start(dir.path());
+    assert_eq!(request(&mut stream, "get plain"), "value");
+    crash(server);
+}
+
+// 普通写入和已提交的事务都记录在WAL中，没有保存就崩溃时启动时重放WAL恢复它们
+#[test]
+fn test_committed_writes_survive_crash_and_uncommitted_do_not() {
+    let dir = tempdir().unwrap();
+    let (server, mut stream, port) = start(dir.path());
+
+    assert_eq!(request(&mut stream, "set plain value"), "OK");
+    assert_eq!(request(&mut stream, "set session token"), "OK");
+    assert_eq!(request(&mut stream, "expire session 1000"), "1");
+
+    // 已提交的事务
+    request(&mut stream, "begin");
+    request(&mut stream, "set committed yes");
+    request(&mut stream, "set plain updated");
+    assert!(request(&mut stream, "commit").contains("已提交"));
+
+    // 另一个连接上开始但没有提交的事务
+    let mut pending = connect(port);
+    request(&mut pending, "begin");
+    request(&mut pending, "set uncommitted no");
+    crash(server);
+
+    let (server, mut stream, _) = start(dir.path());
+    assert_eq!(request(&mut stream, "get plain"), "updated");
+    assert_eq!(request(&mut stream, "get committed"), "yes");
+    assert_eq!(request(&mut stream, "get uncommitted"), "(nil)");
+    assert_ttl_within(&mut stream, "session", 1000);
+    crash(server);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::{connect, spawn_server};
+use kv_client::client::Client;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+// 按行协议发送命令并返回去掉时间戳前缀的响应
+fn request<S: Read + Write>(reader: &mut BufReader<S>, command: &str) -> String {
+    reader.get_mut().write_all(format!("{}\n", command).as_bytes()).unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let line = line.trim_end();
+    match line.split_once("] ") {
+        Some((_, response)) => response.to_string(),
+        None => line.to_string(),
+    }
+}
+
+// 从服务器日志中读取实际监听的地址，端口为0时由系统分配
+fn wait_for_listen_addresses(log_file: &Path, count: usize) -> Vec<String> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        let log = fs::read_to_string(log_file).unwrap_or_default();
+        let addresses: Vec<String> = log
+            .lines()
+            .filter_map(|line| line.split_once("服务器在 "))
+            .filter_map(|(_, rest)| rest.split_once(" 上启动"))
+            .map(|(addr, _)| addr.to_string())
+            .collect();
+        if addresses.len() >= count {
+            return addresses;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    panic!("服务器未在超时时间内开始监听 {} 个地址", count);
+}
+
+#[test]
+fn test_server_listens_on_multiple_addresses() {
+    let dir = tempdir().unwrap();
+    let socket_path = dir.path().join("kv.sock");
+    let (mut server, _) = spawn_server(
+        dir.path(),
+        &format!("listen = [\"127.0.0.1:0\", \"[::1]:0\", \"unix:{}\"]", socket_path.display()),
+    );
+
+    let addresses = wait_for_listen_addresses(&dir.path().join("server.log"), 3);
+    let ipv4 = addresses.iter().find(|addr| addr.starts_with("127.0.0.1:")).unwrap();
+    let ipv6 = addresses.iter().find(|addr| addr.starts_with("[::1]:")).unwrap();
+    assert!(addresses.contains(&format!("unix:{}", socket_path.display())));
+
+    // 所有监听地址上的连接使用同一个存储
+    let mut v4 = BufReader::new(TcpStream::connect(ipv4.as_str()).unwrap());
+    assert_eq!(request(&mut v4, "set shared hello"), "OK");
+    let mut v6 = BufReader::new(TcpStream::connect(ipv6.as_str()).unwrap());
+    assert_eq!(request(&mut v6, "get shared"), "hello");
+    let mut local = BufReader::new(UnixStream::connect(&socket_path).unwrap());
+    assert_eq!(request(&mut local, "get shared"), "hello");
+    assert_eq!(request(&mut v4, "ping"), "PONG");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn test_client_set_get_over_unix_socket() {
+    let dir = tempdir().unwrap();
+    let socket_path = dir.path().join("kv.sock");
+    let (mut server, port) = spawn_server(dir.path(), &format!("unix_socket = '{}'", socket_path.display()));
+
+    // TCP地址照常监听，Unix域套接字是额外的监听地址
+    let mut tcp = BufReader::new(connect(port));
+    assert_eq!(request(&mut tcp, "set greeting hello"), "OK");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !socket_path.exists() {
+        assert!(Instant::now() < deadline, "Unix域套接字未创建");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let mut client = Client::new("127.0.0.1".to_string(), 0).with_unix_socket(&socket_path);
+    client.connect_for_test().unwrap();
+    let mut send = |command: &str| {
+        let response = client.send_command_with_response(command).unwrap();
+        response.split_once("] ").map_or(response.clone(), |(_, reply)| reply.to_string())
+    };
+    assert_eq!(send("get greeting"), "hello");
+    assert_eq!(send("set local 1"), "OK");
+    assert_eq!(request(&mut tcp, "get local"), "1");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
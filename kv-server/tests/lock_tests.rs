@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+// 写入指向临时数据目录的配置文件
+fn write_config(dir: &Path) -> PathBuf {
+    let config_path = dir.join("server.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[server]\nport = 0\n\n[persistence]\ndata_dir = '{}'\ndata_file = \"storage.dat\"\n\n[logging]\nlog_file = '{}'\n",
+            dir.join("data").display(),
+            dir.join("server.log").display()
+        ),
+    )
+    .unwrap();
+    config_path
+}
+
+fn spawn_server(config_path: &Path) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_kv-server"))
+        .arg("--config")
+        .arg(config_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+// 等待锁文件写入进程ID
+fn wait_for_lock(lock_file: &Path, pid: u32) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if fs::read_to_string(lock_file).is_ok_and(|content| content.trim() == pid.to_string()) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    panic!("服务器 {} 未在超时时间内获取数据目录锁", pid);
+}
+
+#[test]
+fn test_second_server_refuses_locked_data_dir() {
+    let dir = tempdir().unwrap();
+    let config_path = write_config(dir.path());
+    let lock_file = dir.path().join("data").join("server.pid");
+
+    let mut first = spawn_server(&config_path);
+    wait_for_lock(&lock_file, first.id());
+
+    // 第二个实例立即失败并报告持有锁的进程
+    let mut second = spawn_server(&config_path);
+    let status = second.wait().unwrap();
+    let mut stderr = String::new();
+    second.stderr.take().unwrap().read_to_string(&mut stderr).unwrap();
+    assert!(!status.success());
+    assert!(
+        stderr.contains(&format!("ERROR: data directory already in use by pid {}", first.id())),
+        "unexpected stderr: {}",
+        stderr
+    );
+
+    // 崩溃的进程遗留的锁文件不会阻止新实例启动
+    first.kill().unwrap();
+    first.wait().unwrap();
+    assert!(lock_file.exists());
+
+    let mut third = spawn_server(&config_path);
+    wait_for_lock(&lock_file, third.id());
+    third.kill().unwrap();
+    third.wait().unwrap();
+}
+
+#[test]
+fn test_graceful_shutdown_keeps_lock_file() {
+    let dir = tempdir().unwrap();
+    let config_path = write_config(dir.path());
+    let lock_file = dir.path().join("data").join("server.pid");
+
+    let mut first = spawn_server(&config_path);
+    wait_for_lock(&lock_file, first.id());
+    // 等待恢复完成，此时信号处理已经安装
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !fs::read_to_string(dir.path().join("server.log")).is_ok_and(|log| log.contains("数据恢复完成")) {
+        assert!(Instant::now() < deadline, "服务器未在超时时间内完成恢复");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // 正常关闭只清空进程ID并释放锁，不删除锁文件
+    let status = Command::new("kill")
+        .arg("-TERM")
+        .arg(first.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(first.wait().unwrap().success());
+    assert_eq!(fs::read_to_string(&lock_file).unwrap(), "");
+
+    let mut second = spawn_server(&config_path);
+    wait_for_lock(&lock_file, second.id());
+    second.kill().unwrap();
+    second.wait().unwrap();
+}
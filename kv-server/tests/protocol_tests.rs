@@ -0,0 +1,173 @@
+mod common;
+
+use common::{connect, spawn_server};
+use kv_common::protocol::{read_frame, write_frame};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use tempfile::tempdir;
+
+fn request(stream: &mut TcpStream, command: &str) -> String {
+    write_frame(stream, command.as_bytes()).unwrap();
+    let response = read_frame(stream).unwrap().expect("服务器关闭了连接");
+    String::from_utf8(response).unwrap()
+}
+
+#[test]
+fn test_binary_protocol_round_trips_newlines_and_nulls() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "protocol = \"binary\"");
+    let mut stream = connect(port);
+
+    let value = "line1\nline2\r\n\0tail\0";
+    assert_eq!(request(&mut stream, &format!("set blob \"{}\"", value)), "OK");
+    assert_eq!(request(&mut stream, "get blob"), value);
+
+    // 每个命令帧对应一个响应帧，多行响应不会被拆分
+    assert_eq!(request(&mut stream, "rpush list a"), "1");
+    assert_eq!(request(&mut stream, "len list"), "1");
+    assert!(request(&mut stream, "help").contains('\n'));
+
+    // 非UTF-8的命令返回错误，连接保持可用
+    write_frame(&mut stream, &[0xff, 0xfe, b'\n']).unwrap();
+    let response = read_frame(&mut stream).unwrap().unwrap();
+    assert!(response.starts_with(b"ERROR:"));
+    assert_eq!(request(&mut stream, "ping"), "PONG");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+// 读取一行文本协议响应并去掉时间戳前缀
+fn read_response(reader: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let line = line.trim_end();
+    match line.split_once("] ") {
+        Some((_, response)) => response.to_string(),
+        None => line.to_string(),
+    }
+}
+
+#[test]
+fn test_line_protocol_accepts_pipelined_crlf_commands() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "");
+    let mut reader = BufReader::new(connect(port));
+
+    // 一次写入多条CRLF结尾的命令，每条命令按顺序得到一个响应
+    reader.get_mut().write_all(b"set greeting hello\r\nget greeting\r\nping\n").unwrap();
+    assert_eq!(read_response(&mut reader), "OK");
+    assert_eq!(read_response(&mut reader), "hello");
+    assert_eq!(read_response(&mut reader), "PONG");
+
+    // 分多次到达的命令在行结束后才执行
+    reader.get_mut().write_all(b"get gre").unwrap();
+    reader.get_mut().flush().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    reader.get_mut().write_all(b"eting\r\n\r\nping\r\n").unwrap();
+    assert_eq!(read_response(&mut reader), "hello");
+    assert_eq!(read_response(&mut reader), "PONG");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn test_line_protocol_reassembles_multibyte_utf8_split_across_reads() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "");
+    let mut reader = BufReader::new(connect(port));
+    reader.get_mut().set_nodelay(true).unwrap();
+
+    // 逐字节发送，多字节字符会被拆分到多次读取中
+    for byte in "set 用户:1 \"张三 ✓\"\r\n".bytes() {
+        reader.get_mut().write_all(&[byte]).unwrap();
+        reader.get_mut().flush().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    assert_eq!(read_response(&mut reader), "OK");
+
+    reader.get_mut().write_all("get 用户:1\n".as_bytes()).unwrap();
+    assert_eq!(read_response(&mut reader), "张三 ✓");
+
+    // 非UTF-8的命令返回错误，后续命令不受影响
+    reader.get_mut().write_all(&[0xff, 0xfe, b'\n']).unwrap();
+    assert_eq!(read_response(&mut reader), "ERROR: ERR command is not valid UTF-8");
+    reader.get_mut().write_all(b"ping\n").unwrap();
+    assert_eq!(read_response(&mut reader), "PONG");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn test_line_protocol_rejects_over_long_commands() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "max_command_length = 1024");
+    let mut reader = BufReader::new(connect(port));
+
+    // 没有换行的超长命令在超过上限时就被拒绝，不会一直缓冲
+    let long_value = "x".repeat(4096);
+    reader.get_mut().write_all(format!("set big {}", long_value).as_bytes()).unwrap();
+    assert_eq!(read_response(&mut reader), "ERROR: command too long");
+
+    // 该行剩余的部分被丢弃，换行之后的命令正常执行
+    reader.get_mut().write_all(format!("{}\nget big\n", long_value).as_bytes()).unwrap();
+    assert_eq!(read_response(&mut reader), "(nil)");
+
+    // 一次到达的完整超长行同样被拒绝，前后的命令不受影响
+    reader
+        .get_mut()
+        .write_all(format!("set small 1\nset big {}\nget small\n", "y".repeat(2048)).as_bytes())
+        .unwrap();
+    assert_eq!(read_response(&mut reader), "OK");
+    assert_eq!(read_response(&mut reader), "ERROR: command too long");
+    assert_eq!(read_response(&mut reader), "1");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+// 读取counted格式的多值响应：首行为*元素数（带时间戳前缀），每个元素前一行为$字节数
+fn read_counted_array(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+    let header = read_response(reader);
+    let count: usize = header
+        .strip_prefix('*')
+        .and_then(|count| count.parse().ok())
+        .unwrap_or_else(|| panic!("不是多值响应: {}", header));
+    let mut values = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let len: usize = line.trim_end().strip_prefix('$').and_then(|len| len.parse().ok()).unwrap();
+        // 元素内容按字节数读取，之后是换行
+        let mut value = vec![0u8; len + 1];
+        reader.read_exact(&mut value).unwrap();
+        assert_eq!(value.pop(), Some(b'\n'));
+        values.push(String::from_utf8(value).unwrap());
+    }
+    values
+}
+
+#[test]
+fn test_counted_array_replies_round_trip_newlines() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "array_replies = \"counted\"");
+    let mut reader = BufReader::new(connect(port));
+
+    reader.get_mut().write_all(b"rpush list \"first\\nline\"\nrpush list second\n").unwrap();
+    assert_eq!(read_response(&mut reader), "1");
+    assert_eq!(read_response(&mut reader), "2");
+
+    // 含换行的元素按字节数读取，不会被拆成两个元素
+    reader.get_mut().write_all(b"range list 0 -1\n").unwrap();
+    assert_eq!(read_counted_array(&mut reader), vec!["first\nline", "second"]);
+
+    // 空结果为*0，单值响应不受影响
+    reader.get_mut().write_all(b"smembers missing\nget missing\n").unwrap();
+    assert!(read_counted_array(&mut reader).is_empty());
+    assert_eq!(read_response(&mut reader), "(nil)");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{connect, spawn_server};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::tempdir;
+
+// 按行协议发送命令并返回去掉时间戳前缀的响应
+fn request(reader: &mut BufReader<TcpStream>, command: &str) -> String {
+    reader.get_mut().write_all(format!("{}\n", command).as_bytes()).unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let line = line.trim_end();
+    match line.split_once("] ") {
+        Some((_, response)) => response.to_string(),
+        None => line.to_string(),
+    }
+}
+
+#[test]
+fn test_connection_rate_limit() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "rate_limit_per_second = 3");
+    let mut reader = BufReader::new(connect(port));
+
+    // 桶内的令牌用完后命令被拒绝
+    for _ in 0..3 {
+        assert_eq!(request(&mut reader, "ping"), "PONG");
+    }
+    assert_eq!(request(&mut reader, "ping"), "ERROR: rate limit exceeded");
+
+    // 等待令牌恢复后可以继续执行
+    thread::sleep(Duration::from_millis(1000));
+    assert_eq!(request(&mut reader, "ping"), "PONG");
+
+    // 限流按连接独立计算
+    let mut other = BufReader::new(connect(port));
+    assert_eq!(request(&mut other, "ping"), "PONG");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
@@ -0,0 +1,107 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{connect, spawn_server};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::Command;
+use tempfile::tempdir;
+
+#[test]
+fn test_sigterm_saves_data_before_exit() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "");
+    let mut reader = BufReader::new(connect(port));
+    reader.get_mut().write_all(b"set greeting hello\n").unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(line.ends_with("OK\n"));
+
+    // 容器停止时发送的是SIGTERM，服务器应保存数据后正常退出
+    let status = Command::new("kill")
+        .arg("-TERM")
+        .arg(server.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(server.wait().unwrap().success());
+
+    let data = fs::read_to_string(dir.path().join("data").join("storage.dat")).unwrap();
+    assert!(data.contains("greeting"));
+    assert!(data.contains("hello"));
+
+    // 关闭前以全部数据创建检查点，压缩后的WAL只剩这个检查点
+    let wal = fs::read_to_string(dir.path().join("data").join("wal.log")).unwrap();
+    let lines: Vec<&str> = wal.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", wal);
+    assert!(lines[0].contains("|CHECKPOINT|"), "{}", wal);
+    let checkpoints: Vec<String> = fs::read_dir(dir.path().join("data").join("checkpoints"))
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect();
+    assert!(checkpoints.iter().any(|checkpoint| checkpoint.contains("greeting")));
+}
+
+#[test]
+fn test_connected_clients_receive_shutdown_notice() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "");
+    let mut reader = BufReader::new(connect(port));
+    reader.get_mut().write_all(b"ping\n").unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(line.ends_with("PONG\n"));
+
+    let status = Command::new("kill")
+        .arg("-TERM")
+        .arg(server.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // 空闲的客户端先收到关闭通知，随后连接被关闭
+    line.clear();
+    reader.read_line(&mut line).unwrap();
+    assert!(line.ends_with("ERROR: SHUTDOWN server is shutting down\n"), "{}", line);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+    assert!(server.wait().unwrap().success());
+}
+
+#[test]
+fn test_sighup_reloads_config_without_exiting() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "enable_admin_commands = true");
+    let mut reader = BufReader::new(connect(port));
+    let mut request = |command: &str| {
+        reader.get_mut().write_all(format!("{}\n", command).as_bytes()).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line.trim_end().split_once("] ").map_or(line.clone(), |(_, reply)| reply.to_string())
+    };
+    assert_eq!(request("config get storage.default_expiry_seconds"), "3600");
+
+    // 修改配置文件后发送SIGHUP，运行时可调整的配置项立即生效，服务器继续运行
+    let config_path = dir.path().join("server.toml");
+    let config = fs::read_to_string(&config_path).unwrap();
+    fs::write(&config_path, format!("{}\n[storage]\ndefault_expiry_seconds = 42\n", config)).unwrap();
+    let status = Command::new("kill")
+        .arg("-HUP")
+        .arg(server.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while request("config get storage.default_expiry_seconds") != "42" {
+        assert!(std::time::Instant::now() < deadline, "配置未重新加载");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    assert!(server.try_wait().unwrap().is_none());
+    assert_eq!(request("ping"), "PONG");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
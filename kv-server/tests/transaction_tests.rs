@@ -0,0 +1,76 @@
+mod common;
+
+use common::{connect, spawn_server};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use tempfile::tempdir;
+
+fn request(reader: &mut BufReader<TcpStream>, command: &str) -> String {
+    reader.get_mut().write_all(format!("{}\n", command).as_bytes()).unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let line = line.trim_end();
+    match line.split_once("] ") {
+        Some((_, response)) => response.to_string(),
+        None => line.to_string(),
+    }
+}
+
+#[test]
+fn test_concurrent_clients_get_distinct_transaction_ids() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "");
+    let mut first = BufReader::new(connect(port));
+    let mut second = BufReader::new(connect(port));
+
+    // 两个连接同时开启事务，事务ID来自同一个事务管理器，不会重复
+    let first_reply = request(&mut first, "begin");
+    let second_reply = request(&mut second, "begin");
+    assert!(first_reply.contains("已开始"), "{}", first_reply);
+    assert!(second_reply.contains("已开始"), "{}", second_reply);
+    assert_ne!(first_reply, second_reply);
+
+    assert!(request(&mut first, "commit").contains("已提交"));
+    assert!(request(&mut second, "commit").contains("已提交"));
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn test_writes_inside_transaction_apply_on_commit_and_honor_savepoints() {
+    let dir = tempdir().unwrap();
+    let (mut server, port) = spawn_server(dir.path(), "");
+    let mut client = BufReader::new(connect(port));
+    let mut other = BufReader::new(connect(port));
+
+    assert!(request(&mut client, "begin").contains("已开始"));
+    assert_eq!(request(&mut client, "set a 1"), "QUEUED");
+    assert_eq!(request(&mut client, "rpush queue x"), "QUEUED");
+    assert!(request(&mut client, "savepoint sp").contains("已创建"));
+    assert_eq!(request(&mut client, "set b 2"), "QUEUED");
+    assert_eq!(request(&mut client, "set a 3"), "QUEUED");
+
+    // 事务内读取自己的修改，其他连接在提交前看不到
+    assert_eq!(request(&mut client, "get a"), "3");
+    assert_eq!(request(&mut other, "get a"), "(nil)");
+
+    // 回滚到保存点撤销之后的写入
+    assert!(request(&mut client, "rollback to sp").contains("已回滚到保存点"));
+    assert_eq!(request(&mut client, "get a"), "1");
+    assert!(request(&mut client, "import dump.jsonl").starts_with("ERROR: ERR "));
+    assert!(request(&mut client, "commit").contains("已提交"));
+
+    assert_eq!(request(&mut other, "get a"), "1");
+    assert_eq!(request(&mut other, "get b"), "(nil)");
+    assert_eq!(request(&mut other, "range queue 0 -1"), "x");
+
+    // 回滚的事务不修改数据
+    assert!(request(&mut client, "begin").contains("已开始"));
+    assert_eq!(request(&mut client, "set a 9"), "QUEUED");
+    assert!(request(&mut client, "rollback").contains("已回滚"));
+    assert_eq!(request(&mut other, "get a"), "1");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}